@@ -0,0 +1,270 @@
+//! Trilha de auditoria
+//!
+//! Registra quem alterou o quê em `auditoria`, sempre na mesma transação
+//! da alteração: se a alteração for desfeita, o registro também é.
+//!
+//! Uso típico em um comando:
+//!
+//! ```ignore
+//! db.with_transaction(|tx| {
+//!     let captura = auditoria::capturar(tx, "clientes", id)?;
+//!     tx.execute("UPDATE clientes SET ...", params![...])?;
+//!     captura.registrar(tx, &ctx, Modulo::Vendas, "editar")
+//! })?;
+//! ```
+
+use rusqlite::{Connection, params};
+use rusqlite::types::ValueRef;
+use serde_json::{Map, Value};
+
+use crate::error::AppResult;
+use crate::models::Modulo;
+use crate::context::{RequestContext, carimbo};
+
+/// Colunas nunca copiadas para a auditoria
+const CAMPOS_OMITIDOS: &[&str] = &["senha_hash", "token_hash"];
+
+/// Colunas ignoradas ao comparar antes/depois
+const CAMPOS_IGNORADOS: &[&str] = &["updated_at"];
+
+/// Estado de um registro antes da alteração
+pub struct Captura {
+    tabela: &'static str,
+    chave: &'static str,
+    registro_id: String,
+    antes: Option<Value>,
+}
+
+/// Captura o registro identificado pela coluna `id`
+pub fn capturar(conn: &Connection, tabela: &'static str, id: i64) -> AppResult<Captura> {
+    capturar_por(conn, tabela, "id", &id.to_string())
+}
+
+/// Captura o registro identificado por outra coluna-chave (ex.: `codigo`)
+pub fn capturar_por(conn: &Connection, tabela: &'static str, chave: &'static str, valor: &str) -> AppResult<Captura> {
+    Ok(Captura {
+        tabela,
+        chave,
+        registro_id: valor.to_string(),
+        antes: snapshot(conn, tabela, chave, valor)?,
+    })
+}
+
+/// Registro recém-criado (sem estado anterior)
+pub fn novo(tabela: &'static str, id: i64) -> Captura {
+    Captura {
+        tabela,
+        chave: "id",
+        registro_id: id.to_string(),
+        antes: None,
+    }
+}
+
+impl Captura {
+    /// Compara com o estado atual e grava a diferença em `auditoria`
+    ///
+    /// Alterações sem diferença efetiva não geram registro.
+    pub fn registrar(self, conn: &Connection, ctx: &RequestContext, modulo: Modulo, acao: &str) -> AppResult<()> {
+        let depois = snapshot(conn, self.tabela, self.chave, &self.registro_id)?;
+        
+        let (antes, depois) = match (self.antes, depois) {
+            (Some(antes), Some(depois)) => {
+                let (antes, depois) = diff(&antes, &depois);
+                if antes.is_empty() && depois.is_empty() {
+                    return Ok(());
+                }
+                (Some(Value::Object(antes)), Some(Value::Object(depois)))
+            }
+            (None, None) => return Ok(()),
+            (antes, depois) => (antes, depois),
+        };
+        
+        conn.execute(
+            "INSERT INTO auditoria (usuario_id, usuario_nome, modulo, tabela, registro_id, acao, antes, depois, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            params![
+                ctx.usuario.id,
+                ctx.usuario.nome,
+                modulo.to_string(),
+                self.tabela,
+                self.registro_id,
+                acao,
+                antes.map(|v| v.to_string()),
+                depois.map(|v| v.to_string()),
+                carimbo(chrono::Utc::now())
+            ],
+        )?;
+        
+        Ok(())
+    }
+}
+
+/// Registra um evento que não corresponde a uma única linha (ex.: vínculos)
+pub fn registrar_evento(
+    conn: &Connection,
+    ctx: &RequestContext,
+    modulo: Modulo,
+    tabela: &str,
+    registro_id: &str,
+    acao: &str,
+    dados: Value,
+) -> AppResult<()> {
+    conn.execute(
+        "INSERT INTO auditoria (usuario_id, usuario_nome, modulo, tabela, registro_id, acao, antes, depois, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, NULL, ?7, ?8)",
+        params![
+            ctx.usuario.id,
+            ctx.usuario.nome,
+            modulo.to_string(),
+            tabela,
+            registro_id,
+            acao,
+            dados.to_string(),
+            carimbo(chrono::Utc::now())
+        ],
+    )?;
+    
+    Ok(())
+}
+
+/// Lê a linha inteira como objeto JSON (sem as colunas omitidas)
+fn snapshot(conn: &Connection, tabela: &str, chave: &str, valor: &str) -> AppResult<Option<Value>> {
+    // tabela e chave vêm sempre de constantes do código, nunca do usuário
+    let mut stmt = conn.prepare(&format!("SELECT * FROM {} WHERE {} = ?1", tabela, chave))?;
+    let colunas: Vec<String> = stmt.column_names().iter().map(|c| c.to_string()).collect();
+    
+    let mut rows = stmt.query(params![valor])?;
+    let Some(row) = rows.next()? else {
+        return Ok(None);
+    };
+    
+    let mut objeto = Map::new();
+    for (i, coluna) in colunas.iter().enumerate() {
+        if CAMPOS_OMITIDOS.contains(&coluna.as_str()) {
+            continue;
+        }
+        let valor = match row.get_ref(i)? {
+            ValueRef::Null => Value::Null,
+            ValueRef::Integer(n) => Value::from(n),
+            ValueRef::Real(f) => Value::from(f),
+            ValueRef::Text(t) => Value::String(String::from_utf8_lossy(t).into_owned()),
+            ValueRef::Blob(b) => Value::String(format!("<{} bytes>", b.len())),
+        };
+        objeto.insert(coluna.clone(), valor);
+    }
+    
+    Ok(Some(Value::Object(objeto)))
+}
+
+/// Mantém apenas os campos que mudaram
+fn diff(antes: &Value, depois: &Value) -> (Map<String, Value>, Map<String, Value>) {
+    let vazio = Map::new();
+    let antes = antes.as_object().unwrap_or(&vazio);
+    let depois = depois.as_object().unwrap_or(&vazio);
+    
+    let mut a = Map::new();
+    let mut d = Map::new();
+    
+    for (campo, valor) in depois {
+        if CAMPOS_IGNORADOS.contains(&campo.as_str()) {
+            continue;
+        }
+        let anterior = antes.get(campo).unwrap_or(&Value::Null);
+        if anterior != valor {
+            a.insert(campo.clone(), anterior.clone());
+            d.insert(campo.clone(), valor.clone());
+        }
+    }
+    
+    (a, d)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::auditoria::get_auditoria;
+    use crate::database::DatabaseState;
+    use crate::error::AppError;
+    use crate::models::AuditoriaFiltro;
+    use crate::testes::*;
+    
+    fn editar_cliente(db: &DatabaseState, id: i64, sql: &str) -> AppResult<()> {
+        let ctx = RequestContext::resolve(db, TOKEN)?;
+        db.with_transaction(|tx| {
+            let captura = capturar(tx, "clientes", id)?;
+            tx.execute(sql, params![id])?;
+            captura.registrar(tx, &ctx, Modulo::Vendas, "editar")
+        })
+    }
+    
+    #[test]
+    fn registra_so_os_campos_alterados() {
+        let db = banco_teste(TOKEN);
+        let id = cliente(&db);
+        
+        editar_cliente(&db, id, "UPDATE clientes SET nome = 'Cliente B', updated_at = datetime('now', '+1 day') WHERE id = ?1").unwrap();
+        // Só updated_at mudou: nada a registrar
+        editar_cliente(&db, id, "UPDATE clientes SET updated_at = datetime('now', '+2 day') WHERE id = ?1").unwrap();
+        
+        let registros = executar(get_auditoria(db.state(), TOKEN.into(), AuditoriaFiltro {
+            tabela: Some("clientes".into()),
+            ..Default::default()
+        })).unwrap();
+        assert_eq!(registros.total, 1);
+        let registro = &registros.data[0];
+        assert_eq!((registro.usuario_id, registro.acao.as_str()), (Some(1), "editar"));
+        assert_eq!(registro.antes, Some(serde_json::json!({ "nome": "Cliente" })));
+        assert_eq!(registro.depois, Some(serde_json::json!({ "nome": "Cliente B" })));
+    }
+    
+    #[test]
+    fn registro_e_desfeito_com_a_alteracao() {
+        let db = banco_teste(TOKEN);
+        let id = cliente(&db);
+        
+        let ctx = RequestContext::resolve(&db, TOKEN).unwrap();
+        let resultado: AppResult<()> = db.with_transaction(|tx| {
+            let captura = capturar(tx, "clientes", id)?;
+            tx.execute("UPDATE clientes SET nome = 'Cliente B' WHERE id = ?1", params![id])?;
+            captura.registrar(tx, &ctx, Modulo::Vendas, "editar")?;
+            Err(AppError::Validation("falha depois da auditoria".into()))
+        });
+        
+        assert!(resultado.is_err());
+        assert_eq!(contar(&db, "SELECT COUNT(*) FROM auditoria"), 0);
+    }
+    
+    #[test]
+    fn senha_nao_vai_para_a_auditoria_e_registro_e_imutavel() {
+        let db = banco_teste(TOKEN);
+        let ctx = RequestContext::resolve(&db, TOKEN).unwrap();
+        db.with_transaction(|tx| {
+            let captura = capturar(tx, "usuarios", 1)?;
+            tx.execute("UPDATE usuarios SET senha_hash = 'novo', cargo = 'Diretor' WHERE id = 1", [])?;
+            captura.registrar(tx, &ctx, Modulo::Config, "editar")
+        }).unwrap();
+        
+        let depois: String = db.reader().unwrap().query_row("SELECT depois FROM auditoria", [], |row| row.get(0)).unwrap();
+        assert_eq!(depois, r#"{"cargo":"Diretor"}"#);
+        
+        assert!(db.writer().unwrap().execute("DELETE FROM auditoria", []).is_err());
+        assert!(db.writer().unwrap().execute("UPDATE auditoria SET acao = 'outra'", []).is_err());
+    }
+    
+    #[test]
+    fn consulta_filtra_por_modulo_usuario_e_periodo() {
+        let db = banco_teste(TOKEN);
+        let id = cliente(&db);
+        editar_cliente(&db, id, "UPDATE clientes SET nome = 'Cliente B' WHERE id = ?1").unwrap();
+        
+        let total = |filtro: AuditoriaFiltro| executar(get_auditoria(db.state(), TOKEN.into(), filtro)).unwrap().total;
+        let hoje = chrono::Utc::now().format("%Y-%m-%d").to_string();
+        
+        assert_eq!(total(AuditoriaFiltro { modulo: Some(Modulo::Vendas), usuario_id: Some(1), ..Default::default() }), 1);
+        assert_eq!(total(AuditoriaFiltro { modulo: Some(Modulo::Rh), ..Default::default() }), 0);
+        assert_eq!(total(AuditoriaFiltro { registro_id: Some(id.to_string()), ..Default::default() }), 1);
+        assert_eq!(total(AuditoriaFiltro { data_inicio: Some(hoje.clone()), data_fim: Some(hoje), ..Default::default() }), 1);
+        assert_eq!(total(AuditoriaFiltro { data_fim: Some("2000-01-01".into()), ..Default::default() }), 0);
+    }
+}
+
@@ -0,0 +1,570 @@
+//! Comissões de vendedores
+//!
+//! O vendedor de um pedido é o `vendedor_id` do pedido ou, sem ele, o do
+//! cliente. Cada item comissiona pelo percentual da regra mais específica
+//! que o atende (vendedor, categoria do produto e tabela de preço do
+//! cliente), sobre o total do item com o desconto/acréscimo do pedido
+//! rateado.
+//!
+//! O momento da apuração vem da configuração `comissao_momento`:
+//!
+//! - `"faturamento"` (padrão): a comissão inteira, quando o pedido gera as
+//!   contas a receber;
+//! - `"recebimento"`: a cada baixa de conta a receber do pedido, na
+//!   proporção do que já foi recebido.
+//!
+//! A devolução tira da base os itens devolvidos e ajusta o que já foi
+//! apurado com um lançamento negativo do mesmo tipo; o crédito da devolução
+//! não conta como título do pedido no recebimento.
+//!
+//! O cancelamento do pedido ou da nota estorna o que foi apurado com
+//! lançamentos negativos; se a comissão já foi fechada em contas a pagar, o
+//! estorno desconta no extrato seguinte. A nota emitida de novo para o
+//! pedido apura a comissão outra vez.
+
+use chrono::NaiveDate;
+use rusqlite::{Connection, params};
+use log::info;
+
+use crate::auditoria;
+use crate::context::RequestContext;
+use crate::error::{AppError, AppResult};
+use crate::models::{Dinheiro, Modulo};
+
+/// Chave em `configuracoes` do momento da apuração
+pub const CHAVE_MOMENTO: &str = "comissao_momento";
+
+/// Quando a comissão é apurada
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Momento {
+    Faturamento,
+    Recebimento,
+}
+
+/// Comissão do pedido inteiro
+struct Calculo {
+    vendedor_id: i64,
+    base: Dinheiro,
+    valor: Dinheiro,
+}
+
+/// Linha a gravar em `comissoes`
+struct Lancamento<'a> {
+    vendedor_id: i64,
+    pedido_id: i64,
+    conta_receber_id: Option<i64>,
+    tipo: &'a str,
+    base: Dinheiro,
+    valor: Dinheiro,
+    data: NaiveDate,
+    observacao: Option<&'a str>,
+}
+
+/// Momento configurado; sem configuração, no faturamento
+pub fn momento(conn: &Connection) -> AppResult<Momento> {
+    let result = conn.query_row(
+        "SELECT valor FROM configuracoes WHERE chave = ?1",
+        params![CHAVE_MOMENTO],
+        |row| row.get::<_, Option<String>>(0),
+    );
+    
+    let valor = match result {
+        Ok(valor) => valor.unwrap_or_default(),
+        Err(rusqlite::Error::QueryReturnedNoRows) => return Ok(Momento::Faturamento),
+        Err(e) => return Err(AppError::Database(e.to_string())),
+    };
+    
+    // `save_config` grava o valor em JSON ("\"recebimento\"")
+    let valor = serde_json::from_str::<String>(&valor).unwrap_or(valor);
+    match valor.trim() {
+        "" | "faturamento" => Ok(Momento::Faturamento),
+        "recebimento" => Ok(Momento::Recebimento),
+        outro => Err(AppError::Validation(format!("Configuração '{}' inválida: '{}'", CHAVE_MOMENTO, outro))),
+    }
+}
+
+/// Percentual da regra mais específica para o item, se houver
+pub fn percentual(
+    conn: &Connection,
+    vendedor_id: i64,
+    categoria: Option<&str>,
+    tabela_preco_id: Option<i64>,
+) -> AppResult<Option<f64>> {
+    let result = conn.query_row(
+        "SELECT percentual FROM regras_comissao
+         WHERE ativo = 1
+           AND (vendedor_id IS NULL OR vendedor_id = ?1)
+           AND (categoria IS NULL OR categoria = ?2)
+           AND (tabela_preco_id IS NULL OR tabela_preco_id = ?3)
+         ORDER BY (vendedor_id IS NOT NULL) * 4 + (categoria IS NOT NULL) * 2 + (tabela_preco_id IS NOT NULL) DESC,
+                  id DESC
+         LIMIT 1",
+        params![vendedor_id, categoria, tabela_preco_id],
+        |row| row.get(0),
+    );
+    
+    match result {
+        Ok(percentual) => Ok(Some(percentual)),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+        Err(e) => Err(AppError::Database(e.to_string())),
+    }
+}
+
+/// Lança a comissão do pedido faturado, se apurada no faturamento
+pub fn apurar_faturamento(tx: &Connection, ctx: &RequestContext, pedido_id: i64, data: NaiveDate) -> AppResult<()> {
+    if momento(tx)? != Momento::Faturamento || apurado(tx, pedido_id)? {
+        return Ok(());
+    }
+    let Some(calculo) = calcular(tx, pedido_id)? else {
+        return Ok(());
+    };
+    
+    lancar(tx, ctx, Lancamento {
+        vendedor_id: calculo.vendedor_id,
+        pedido_id,
+        conta_receber_id: None,
+        tipo: "faturamento",
+        base: calculo.base,
+        valor: calculo.valor,
+        data,
+        observacao: None,
+    })
+}
+
+/// Lança a comissão proporcional à conta recebida, se apurada no recebimento
+///
+/// Chamar depois de marcar a conta como recebida. O valor é a diferença
+/// entre a comissão do total já recebido e o que já foi lançado, então a
+/// soma dos lançamentos fecha com a comissão do pedido na última parcela.
+pub fn apurar_recebimento(tx: &Connection, ctx: &RequestContext, conta_receber_id: i64, data: NaiveDate) -> AppResult<()> {
+    if momento(tx)? != Momento::Recebimento {
+        return Ok(());
+    }
+    
+    let pedido_id: Option<i64> = tx.query_row(
+        "SELECT pedido_id FROM contas_receber WHERE id = ?1",
+        params![conta_receber_id],
+        |row| row.get(0),
+    )?;
+    let Some(pedido_id) = pedido_id else {
+        return Ok(());
+    };
+    if estornado(tx, pedido_id)? {
+        return Ok(());
+    }
+    let Some(calculo) = calcular(tx, pedido_id)? else {
+        return Ok(());
+    };
+    
+    let Some(fracao) = fracao_recebida(tx, pedido_id)? else {
+        return Ok(());
+    };
+    let (base_lancada, valor_lancado) = lancado(tx, pedido_id, "recebimento")?;
+    
+    let base = calculo.base.vezes(fracao)? - base_lancada;
+    let valor = calculo.valor.vezes(fracao)? - valor_lancado;
+    if valor.is_zero() {
+        return Ok(());
+    }
+    
+    lancar(tx, ctx, Lancamento {
+        vendedor_id: calculo.vendedor_id,
+        pedido_id,
+        conta_receber_id: Some(conta_receber_id),
+        tipo: "recebimento",
+        base,
+        valor,
+        data,
+        observacao: None,
+    })
+}
+
+/// Ajusta a comissão já apurada depois de uma devolução do pedido
+///
+/// Chamar depois de gravar os itens devolvidos. A comissão devida passa a
+/// ser a do valor não devolvido (no recebimento, na proporção do que já foi
+/// recebido); a diferença para o que foi lançado entra com o tipo do
+/// momento configurado. Sem comissão lançada, não há o que ajustar.
+pub fn ajustar_devolucao(tx: &Connection, ctx: &RequestContext, pedido_id: i64, data: NaiveDate, devolucao: &str) -> AppResult<()> {
+    if estornado(tx, pedido_id)? {
+        return Ok(());
+    }
+    
+    let tipo = match momento(tx)? {
+        Momento::Faturamento => "faturamento",
+        Momento::Recebimento => "recebimento",
+    };
+    let vendedor_id: i64 = match tx.query_row(
+        "SELECT vendedor_id FROM comissoes WHERE pedido_id = ?1 AND tipo = ?2 ORDER BY id LIMIT 1",
+        params![pedido_id, tipo],
+        |row| row.get(0),
+    ) {
+        Ok(vendedor_id) => vendedor_id,
+        Err(rusqlite::Error::QueryReturnedNoRows) => return Ok(()),
+        Err(e) => return Err(AppError::Database(e.to_string())),
+    };
+    
+    let fracao = match tipo {
+        "recebimento" => fracao_recebida(tx, pedido_id)?.unwrap_or(0.0),
+        _ => 1.0,
+    };
+    let (base_devida, valor_devido) = match calcular(tx, pedido_id)? {
+        Some(calculo) => (calculo.base.vezes(fracao)?, calculo.valor.vezes(fracao)?),
+        None => (Dinheiro::ZERO, Dinheiro::ZERO),
+    };
+    let (base_lancada, valor_lancado) = lancado(tx, pedido_id, tipo)?;
+    
+    let valor = valor_devido - valor_lancado;
+    if valor.is_zero() {
+        return Ok(());
+    }
+    
+    let observacao = format!("Devolução {}", devolucao);
+    lancar(tx, ctx, Lancamento {
+        vendedor_id,
+        pedido_id,
+        conta_receber_id: None,
+        tipo,
+        base: base_devida - base_lancada,
+        valor,
+        data,
+        observacao: Some(&observacao),
+    })
+}
+
+/// Estorna as comissões lançadas para o pedido
+pub fn estornar_pedido(tx: &Connection, ctx: &RequestContext, pedido_id: i64, data: NaiveDate, motivo: &str) -> AppResult<()> {
+    let saldos: Vec<(i64, Dinheiro, Dinheiro)> = {
+        let mut stmt = tx.prepare(
+            "SELECT vendedor_id, SUM(base), SUM(valor) FROM comissoes
+             WHERE pedido_id = ?1
+             GROUP BY vendedor_id
+             HAVING SUM(valor) <> 0"
+        )?;
+        let linhas = stmt.query_map(params![pedido_id], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?;
+        linhas.collect::<Result<_, _>>()?
+    };
+    
+    for (vendedor_id, base, valor) in saldos {
+        lancar(tx, ctx, Lancamento {
+            vendedor_id,
+            pedido_id,
+            conta_receber_id: None,
+            tipo: "estorno",
+            base: -base,
+            valor: -valor,
+            data,
+            observacao: Some(motivo),
+        })?;
+    }
+    
+    Ok(())
+}
+
+/// Comissão do pedido; `None` sem vendedor ou sem regra que atenda algum item
+fn calcular(conn: &Connection, pedido_id: i64) -> AppResult<Option<Calculo>> {
+    let (vendedor_id, tabela_preco_id, subtotal, total): (Option<i64>, Option<i64>, Dinheiro, Dinheiro) = conn.query_row(
+        "SELECT COALESCE(p.vendedor_id, c.vendedor_id), c.tabela_preco_id, p.subtotal, p.total
+         FROM pedidos_venda p
+         LEFT JOIN clientes c ON c.id = p.cliente_id
+         WHERE p.id = ?1",
+        params![pedido_id],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get::<_, Option<Dinheiro>>(2)?.unwrap_or_default(), row.get::<_, Option<Dinheiro>>(3)?.unwrap_or_default())),
+    )?;
+    let Some(vendedor_id) = vendedor_id else {
+        return Ok(None);
+    };
+    if subtotal.is_zero() || subtotal.is_negative() {
+        return Ok(None);
+    }
+    
+    // Desconto e acréscimo do pedido rateados pelos itens
+    let fator = total.em_centavos() as f64 / subtotal.em_centavos() as f64;
+    
+    // O valor devolvido de cada item já tem o rateio do pedido
+    let itens: Vec<(Dinheiro, Option<String>, Dinheiro)> = {
+        let mut stmt = conn.prepare(
+            "SELECT i.total, p.categoria,
+                    (SELECT COALESCE(SUM(d.valor), 0) FROM devolucao_itens d WHERE d.pedido_item_id = i.id)
+             FROM pedido_itens i
+             LEFT JOIN produtos p ON p.id = i.produto_id
+             WHERE i.pedido_id = ?1"
+        )?;
+        let linhas = stmt.query_map(params![pedido_id], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?;
+        linhas.collect::<Result<_, _>>()?
+    };
+    
+    let mut base = Dinheiro::ZERO;
+    let mut valor = Dinheiro::ZERO;
+    for (total_item, categoria, devolvido) in itens {
+        let base_item = total_item.vezes(fator)? - devolvido;
+        if base_item.is_zero() || base_item.is_negative() {
+            continue;
+        }
+        let Some(percentual) = percentual(conn, vendedor_id, categoria.as_deref(), tabela_preco_id)? else {
+            continue;
+        };
+        base += base_item;
+        valor += base_item.percentual(percentual)?;
+    }
+    
+    if valor.is_zero() {
+        return Ok(None);
+    }
+    Ok(Some(Calculo { vendedor_id, base, valor }))
+}
+
+/// Fração recebida dos títulos do pedido; `None` sem títulos
+///
+/// O crédito de devolução (categoria "devolucoes") e os títulos cancelados
+/// com a nota não são títulos do pedido.
+fn fracao_recebida(conn: &Connection, pedido_id: i64) -> AppResult<Option<f64>> {
+    let (titulos, recebido): (Dinheiro, Dinheiro) = conn.query_row(
+        "SELECT COALESCE(SUM(valor), 0), COALESCE(SUM(CASE WHEN status = 'recebido' THEN valor ELSE 0 END), 0)
+         FROM contas_receber
+         WHERE pedido_id = ?1 AND COALESCE(categoria, '') <> 'devolucoes' AND status <> 'cancelado'",
+        params![pedido_id],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )?;
+    if titulos.is_zero() || titulos.is_negative() {
+        return Ok(None);
+    }
+    Ok(Some((recebido.em_centavos() as f64 / titulos.em_centavos() as f64).min(1.0)))
+}
+
+/// Base e valor já lançados no pedido com o tipo, líquidos dos estornos
+fn lancado(conn: &Connection, pedido_id: i64, tipo: &str) -> AppResult<(Dinheiro, Dinheiro)> {
+    let lancado = conn.query_row(
+        "SELECT COALESCE(SUM(base), 0), COALESCE(SUM(valor), 0) FROM comissoes
+         WHERE pedido_id = ?1 AND tipo IN (?2, 'estorno')",
+        params![pedido_id, tipo],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )?;
+    Ok(lancado)
+}
+
+/// Pedido tem comissão lançada e não estornada
+fn apurado(conn: &Connection, pedido_id: i64) -> AppResult<bool> {
+    let (_, saldo) = saldo(conn, pedido_id)?;
+    Ok(!saldo.is_zero())
+}
+
+/// Comissão do pedido estornada e não apurada de novo
+fn estornado(conn: &Connection, pedido_id: i64) -> AppResult<bool> {
+    let (estornos, saldo) = saldo(conn, pedido_id)?;
+    Ok(estornos > 0 && saldo.is_zero())
+}
+
+/// Quantidade de estornos e soma de todos os lançamentos do pedido
+fn saldo(conn: &Connection, pedido_id: i64) -> AppResult<(i64, Dinheiro)> {
+    let saldo = conn.query_row(
+        "SELECT COALESCE(SUM(tipo = 'estorno'), 0), COALESCE(SUM(valor), 0) FROM comissoes WHERE pedido_id = ?1",
+        params![pedido_id],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )?;
+    Ok(saldo)
+}
+
+fn lancar(tx: &Connection, ctx: &RequestContext, lancamento: Lancamento) -> AppResult<()> {
+    tx.execute(
+        "INSERT INTO comissoes (vendedor_id, pedido_id, conta_receber_id, tipo, base, valor, data_competencia, observacao)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        params![
+            lancamento.vendedor_id, lancamento.pedido_id, lancamento.conta_receber_id, lancamento.tipo,
+            lancamento.base, lancamento.valor, lancamento.data.format("%Y-%m-%d").to_string(), lancamento.observacao
+        ],
+    )?;
+    
+    let acao = if lancamento.tipo == "estorno" { "estornar" } else { "apurar" };
+    auditoria::novo("comissoes", tx.last_insert_rowid()).registrar(tx, ctx, Modulo::Vendas, acao)?;
+    
+    info!(
+        "💼 Comissão ({}) do pedido {}: R$ {} para o vendedor {}",
+        lancamento.tipo, lancamento.pedido_id, lancamento.valor, lancamento.vendedor_id
+    );
+    
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    
+    use super::*;
+    use crate::testes::*;
+    
+    #[test]
+    fn comissao_do_faturamento_e_estornada_com_a_nfe() {
+        use crate::commands::comissoes::{create_regra_comissao, get_extrato_comissoes};
+        use crate::commands::nfe::{cancelar_nfe, emitir_nfe};
+        
+        let db = banco_teste(TOKEN);
+        let id = pedido_aprovavel(&db).id;
+        estoque(&db, 5.0);
+        db.writer().unwrap().execute("UPDATE clientes SET vendedor_id = 1", []).unwrap();
+        
+        // A regra do vendedor prevalece sobre a geral
+        for regra in [serde_json::json!({ "percentual": 5 }), serde_json::json!({ "vendedor_id": 1, "percentual": 10 })] {
+            executar(create_regra_comissao(db.state(), TOKEN.into(), serde_json::from_value(regra).unwrap())).unwrap();
+        }
+        
+        faturar(&db, id);
+        let nfe = executar(emitir_nfe(db.state(), TOKEN.into(), id, "Venda".into(), None)).unwrap();
+        executar(cancelar_nfe(db.state(), TOKEN.into(), nfe.id, "Erro no destinatário da nota".into())).unwrap();
+        
+        let hoje = Utc::now().format("%Y-%m-%d").to_string();
+        let extrato = executar(get_extrato_comissoes(db.state(), TOKEN.into(), 1, hoje.clone(), hoje)).unwrap();
+        let lancamentos: Vec<(&str, i64)> = extrato.lancamentos.iter().map(|c| (c.tipo.as_str(), c.valor.em_centavos())).collect();
+        assert_eq!(lancamentos, vec![("faturamento", 200), ("estorno", -200)]);
+        assert!(extrato.total.is_zero());
+    }
+    
+    #[test]
+    fn nota_reemitida_apura_a_comissao_de_novo() {
+        use crate::commands::comissoes::{create_regra_comissao, get_extrato_comissoes};
+        use crate::commands::devolucoes::create_devolucao;
+        use crate::commands::nfe::{cancelar_nfe, emitir_nfe};
+        
+        let db = banco_teste(TOKEN);
+        let pedido = pedido_aprovavel(&db);
+        estoque(&db, 5.0);
+        db.writer().unwrap().execute("UPDATE pedidos_venda SET vendedor_id = 1", []).unwrap();
+        executar(create_regra_comissao(db.state(), TOKEN.into(), serde_json::from_value(serde_json::json!({
+            "vendedor_id": 1, "percentual": 10
+        })).unwrap())).unwrap();
+        
+        faturar(&db, pedido.id);
+        let nfe = executar(emitir_nfe(db.state(), TOKEN.into(), pedido.id, "Venda".into(), None)).unwrap();
+        executar(cancelar_nfe(db.state(), TOKEN.into(), nfe.id, "Erro no destinatário da nota".into())).unwrap();
+        let nova = executar(emitir_nfe(db.state(), TOKEN.into(), pedido.id, "Venda".into(), None)).unwrap();
+        
+        // Os títulos cancelados com a primeira nota são gerados de novo na segunda
+        let titulos = format!("SELECT COUNT(*) FROM contas_receber WHERE status = 'pendente' AND nota_fiscal_id = {}", nova.id);
+        assert_eq!(contar(&db, &titulos), 1);
+        
+        // A devolução depois da nova nota ajusta a comissão apurada de novo
+        executar(create_devolucao(db.state(), TOKEN.into(), serde_json::from_value(serde_json::json!({
+            "pedido_id": pedido.id,
+            "motivo": "Perfil amassado",
+            "itens": [{ "pedido_item_id": pedido.itens[0].id, "quantidade": 1 }],
+        })).unwrap())).unwrap();
+        
+        let hoje = Utc::now().format("%Y-%m-%d").to_string();
+        let extrato = executar(get_extrato_comissoes(db.state(), TOKEN.into(), 1, hoje.clone(), hoje)).unwrap();
+        let lancamentos: Vec<(&str, i64)> = extrato.lancamentos.iter().map(|c| (c.tipo.as_str(), c.valor.em_centavos())).collect();
+        assert_eq!(lancamentos, vec![("faturamento", 200), ("estorno", -200), ("faturamento", 200), ("faturamento", -100)]);
+        assert_eq!(extrato.total, Dinheiro::centavos(100));
+    }
+    
+    #[test]
+    fn regra_de_comissao_exige_gestao() {
+        use crate::commands::comissoes::create_regra_comissao;
+        
+        let db = banco_teste(TOKEN);
+        let regra = || serde_json::from_value(serde_json::json!({ "vendedor_id": 1, "percentual": 50 })).unwrap();
+        for (email, permissoes) in [
+            ("vendedor@teste", r#"{"modulos": {"vendas": ["ler", "criar", "editar", "excluir"]}}"#),
+            ("financeiro@teste", r#"{"modulos": {"financeiro": ["ler", "editar"]}}"#),
+        ] {
+            sessao(&db, usuario(&db, email, permissoes), email);
+        }
+        
+        assert!(matches!(
+            executar(create_regra_comissao(db.state(), "vendedor@teste".into(), regra())),
+            Err(AppError::Authorization(_))
+        ));
+        executar(create_regra_comissao(db.state(), "financeiro@teste".into(), regra())).unwrap();
+    }
+    
+    #[test]
+    fn comissao_no_recebimento_fecha_em_contas_a_pagar() {
+        use crate::commands::comissoes::{create_regra_comissao, fechar_comissoes, get_extrato_comissoes};
+        use crate::commands::financeiro::baixar_conta_receber;
+        use crate::commands::vendas::update_pedido;
+        
+        let db = banco_teste(TOKEN);
+        let id = pedido_aprovavel(&db).id;
+        estoque(&db, 5.0);
+        {
+            let conn = db.writer().unwrap();
+            conn.execute("UPDATE pedidos_venda SET vendedor_id = 1", []).unwrap();
+            conn.execute("INSERT INTO configuracoes (chave, valor) VALUES ('comissao_momento', '\"recebimento\"')", []).unwrap();
+        }
+        executar(create_regra_comissao(db.state(), TOKEN.into(), serde_json::from_value(serde_json::json!({
+            "vendedor_id": 1, "percentual": 10
+        })).unwrap())).unwrap();
+        
+        executar(update_pedido(db.state(), TOKEN.into(), id, None, Some("30/60".into()), None, None, None, None)).unwrap();
+        faturar(&db, id);
+        assert_eq!(contar(&db, "SELECT COUNT(*) FROM comissoes"), 0);
+        
+        let titulos: Vec<i64> = {
+            let conn = db.reader().unwrap();
+            let mut stmt = conn.prepare("SELECT id FROM contas_receber WHERE pedido_id = ?1 ORDER BY parcela").unwrap();
+            stmt.query_map(params![id], |row| row.get(0)).unwrap().collect::<Result<_, _>>().unwrap()
+        };
+        let hoje = Utc::now().format("%Y-%m-%d").to_string();
+        
+        executar(baixar_conta_receber(db.state(), TOKEN.into(), titulos[0], None, None)).unwrap();
+        let fechado = executar(fechar_comissoes(db.state(), TOKEN.into(), 1, hoje.clone(), hoje.clone(), None)).unwrap();
+        assert_eq!(fechado.total, Dinheiro::centavos(100));
+        assert!(fechado.em_aberto.is_zero());
+        assert_eq!(contar(&db, "SELECT valor FROM contas_pagar WHERE categoria = 'comissoes'"), 100);
+        
+        executar(baixar_conta_receber(db.state(), TOKEN.into(), titulos[1], None, None)).unwrap();
+        let extrato = executar(get_extrato_comissoes(db.state(), TOKEN.into(), 1, hoje.clone(), hoje)).unwrap();
+        assert_eq!(extrato.total, Dinheiro::centavos(200));
+        assert_eq!(extrato.em_aberto, Dinheiro::centavos(100));
+        assert!(executar(baixar_conta_receber(db.state(), TOKEN.into(), titulos[1], None, None)).is_err());
+    }
+    
+    #[test]
+    fn devolucao_parcial_reduz_a_comissao_no_recebimento() {
+        use crate::commands::comissoes::{create_regra_comissao, get_extrato_comissoes};
+        use crate::commands::devolucoes::create_devolucao;
+        use crate::commands::financeiro::baixar_conta_receber;
+        use crate::commands::nfe::emitir_nfe;
+        use crate::commands::vendas::update_pedido;
+        
+        let db = banco_teste(TOKEN);
+        let pedido = pedido_aprovavel(&db);
+        estoque(&db, 5.0);
+        {
+            let conn = db.writer().unwrap();
+            conn.execute("UPDATE pedidos_venda SET vendedor_id = 1", []).unwrap();
+            conn.execute("INSERT INTO configuracoes (chave, valor) VALUES ('comissao_momento', '\"recebimento\"')", []).unwrap();
+        }
+        executar(create_regra_comissao(db.state(), TOKEN.into(), serde_json::from_value(serde_json::json!({
+            "vendedor_id": 1, "percentual": 10
+        })).unwrap())).unwrap();
+        
+        executar(update_pedido(db.state(), TOKEN.into(), pedido.id, None, Some("30/60".into()), None, None, None, None)).unwrap();
+        faturar(&db, pedido.id);
+        executar(emitir_nfe(db.state(), TOKEN.into(), pedido.id, "Venda".into(), None)).unwrap();
+        let titulos: Vec<i64> = {
+            let conn = db.reader().unwrap();
+            let mut stmt = conn.prepare("SELECT id FROM contas_receber WHERE pedido_id = ?1 ORDER BY parcela").unwrap();
+            stmt.query_map(params![pedido.id], |row| row.get(0)).unwrap().collect::<Result<_, _>>().unwrap()
+        };
+        
+        // Metade recebida: 10% de R$ 10,00
+        executar(baixar_conta_receber(db.state(), TOKEN.into(), titulos[0], None, None)).unwrap();
+        
+        // Devolvida uma das duas unidades: a base cai para R$ 10,00 e, com
+        // metade recebida, a comissão devida até aqui passa a R$ 0,50
+        executar(create_devolucao(db.state(), TOKEN.into(), serde_json::from_value(serde_json::json!({
+            "pedido_id": pedido.id,
+            "motivo": "Perfil amassado",
+            "itens": [{ "pedido_item_id": pedido.itens[0].id, "quantidade": 1 }],
+        })).unwrap())).unwrap();
+        
+        // O crédito da devolução não entra na fração recebida
+        executar(baixar_conta_receber(db.state(), TOKEN.into(), titulos[1], None, None)).unwrap();
+        
+        let hoje = Utc::now().format("%Y-%m-%d").to_string();
+        let extrato = executar(get_extrato_comissoes(db.state(), TOKEN.into(), 1, hoje.clone(), hoje)).unwrap();
+        let lancamentos: Vec<(&str, i64)> = extrato.lancamentos.iter().map(|c| (c.tipo.as_str(), c.valor.em_centavos())).collect();
+        assert_eq!(lancamentos, vec![("recebimento", 100), ("recebimento", -50), ("recebimento", 50)]);
+        assert_eq!(extrato.total, Dinheiro::centavos(100));
+    }
+}
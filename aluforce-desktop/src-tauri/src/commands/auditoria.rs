@@ -0,0 +1,80 @@
+//! Comandos de Auditoria
+
+use tauri::State;
+use rusqlite::params;
+
+use crate::error::AppError;
+use crate::models::{RegistroAuditoria, AuditoriaFiltro, PaginatedResponse, Acao};
+use crate::database::DatabaseState;
+use crate::context::RequestContext;
+
+/// Consulta a trilha de auditoria
+///
+/// Sem filtro de módulo a consulta exige administrador; com filtro basta
+/// permissão de leitura no módulo.
+#[tauri::command]
+pub async fn get_auditoria(
+    db: State<'_, DatabaseState>,
+    token: String,
+    filtro: AuditoriaFiltro,
+) -> Result<PaginatedResponse<RegistroAuditoria>, AppError> {
+    let ctx = RequestContext::resolve(&db, &token)?;
+    match filtro.modulo {
+        Some(modulo) => ctx.require(modulo, Acao::Ler)?,
+        None => ctx.require_admin()?,
+    }
+    
+    let conn = db.reader()?;
+    
+    let page = filtro.page.unwrap_or(1);
+    let per_page = filtro.per_page.unwrap_or(50);
+    let offset = (page - 1) * per_page;
+    
+    let modulo = filtro.modulo.map(|m| m.to_string());
+    let condicoes = "(?1 IS NULL OR modulo = ?1)
+          AND (?2 IS NULL OR usuario_id = ?2)
+          AND (?3 IS NULL OR tabela = ?3)
+          AND (?4 IS NULL OR registro_id = ?4)
+          AND (?5 IS NULL OR substr(created_at, 1, 10) >= ?5)
+          AND (?6 IS NULL OR substr(created_at, 1, 10) <= ?6)";
+    
+    let total: i64 = conn.query_row(
+        &format!("SELECT COUNT(*) FROM auditoria WHERE {}", condicoes),
+        params![modulo, filtro.usuario_id, filtro.tabela, filtro.registro_id, filtro.data_inicio, filtro.data_fim],
+        |row| row.get(0),
+    )?;
+    
+    let mut stmt = conn.prepare(&format!(
+        "SELECT id, usuario_id, usuario_nome, modulo, tabela, registro_id, acao, antes, depois, created_at
+         FROM auditoria WHERE {}
+         ORDER BY id DESC LIMIT ?7 OFFSET ?8",
+        condicoes
+    ))?;
+    
+    let registros = stmt.query_map(
+        params![
+            modulo, filtro.usuario_id, filtro.tabela, filtro.registro_id,
+            filtro.data_inicio, filtro.data_fim, per_page, offset
+        ],
+        |row| {
+            let antes: Option<String> = row.get(7)?;
+            let depois: Option<String> = row.get(8)?;
+            Ok(RegistroAuditoria {
+                id: row.get(0)?,
+                usuario_id: row.get(1)?,
+                usuario_nome: row.get(2)?,
+                modulo: row.get(3)?,
+                tabela: row.get(4)?,
+                registro_id: row.get(5)?,
+                acao: row.get(6)?,
+                antes: antes.and_then(|s| serde_json::from_str(&s).ok()),
+                depois: depois.and_then(|s| serde_json::from_str(&s).ok()),
+                created_at: row.get(9)?,
+            })
+        },
+    )?
+    .filter_map(|r| r.ok())
+    .collect();
+    
+    Ok(PaginatedResponse::new(registros, total, page, per_page))
+}
@@ -1,15 +1,17 @@
 //! Comandos de autenticação
 
-use tauri::{AppHandle, State};
+use tauri::State;
 use rusqlite::{Connection, params};
 use uuid::Uuid;
-use chrono::{Utc, Duration};
-use log::info;
-use std::sync::Mutex;
+use chrono::{DateTime, Utc, Duration};
+use log::{info, warn};
+use std::sync::{Mutex, OnceLock};
 
 use crate::error::{AppError, AppResult};
-use crate::models::{Usuario, UsuarioPublico, LoginRequest, LoginResponse};
-use crate::database::get_database_path;
+use crate::models::{Usuario, UsuarioPublico, LoginRequest, LoginResponse, Sessao, Modulo};
+use crate::database::DatabaseState;
+use crate::context::{RequestContext, hash_token, carimbo, SESSAO_INATIVIDADE_HORAS};
+use crate::auditoria;
 
 /// Estado da sessão atual
 pub struct AuthState {
@@ -26,20 +28,43 @@ impl Default for AuthState {
     }
 }
 
+/// Falhas consecutivas que bloqueiam temporariamente o login do e-mail
+const MAX_TENTATIVAS: i64 = 5;
+
+/// Duração do bloqueio temporário
+const BLOQUEIO_MINUTOS: i64 = 15;
+
+/// Atraso da resposta na primeira falha; dobra a cada falha seguinte
+const ATRASO_BASE_MS: u64 = 500;
+const ATRASO_MAXIMO_MS: u64 = 8000;
+
+/// Mensagem única para e-mail inexistente, usuário inativo ou senha errada
+const FALHA_LOGIN: &str = "E-mail ou senha inválidos";
+
 /// Realiza login do usuário
 #[tauri::command]
 pub async fn login(
-    app: AppHandle,
+    db: State<'_, DatabaseState>,
+    auth: State<'_, AuthState>,
     email: String,
     senha: String,
 ) -> Result<LoginResponse, AppError> {
-    let db_path = get_database_path(&app)?;
-    let conn = Connection::open(&db_path)?;
+    let email = email.trim().to_string();
+    let agora = Utc::now();
+    
+    // E-mail bloqueado: recusa sem nem verificar a senha
+    let bloqueio = bloqueado_ate(&*db.reader()?, &email, agora)?;
+    if let Some(ate) = bloqueio {
+        let minutos = ((ate - agora).num_seconds() + 59) / 60;
+        return Ok(falha_login(format!(
+            "Muitas tentativas inválidas. Tente novamente em {} minuto(s)", minutos
+        )));
+    }
     
     // Buscar usuário por email
-    let result: Result<(i64, String, String, Option<String>, Option<String>, Option<String>, String), rusqlite::Error> = 
-        conn.query_row(
-            "SELECT id, nome, senha_hash, cargo, departamento, avatar, permissoes 
+    let result: Result<(i64, String, String, Option<String>, Option<String>, Option<String>, String, bool), rusqlite::Error> = 
+        db.reader()?.query_row(
+            "SELECT id, nome, senha_hash, cargo, departamento, avatar, permissoes, trocar_senha 
              FROM usuarios WHERE email = ?1 AND ativo = 1",
             params![email],
             |row| Ok((
@@ -50,139 +75,281 @@ pub async fn login(
                 row.get(4)?,
                 row.get(5)?,
                 row.get(6)?,
+                row.get(7)?,
             ))
         );
     
-    match result {
-        Ok((id, nome, senha_hash, cargo, departamento, avatar, permissoes_str)) => {
-            // Verificar senha
-            if !bcrypt::verify(&senha, &senha_hash).unwrap_or(false) {
-                return Ok(LoginResponse {
-                    success: false,
-                    token: None,
-                    usuario: None,
-                    message: Some("Senha incorreta".into()),
-                });
-            }
-            
-            // Gerar token de sessão
-            let token = Uuid::new_v4().to_string();
-            let expires_at = (Utc::now() + Duration::hours(24)).to_rfc3339();
-            
-            // Salvar sessão
-            conn.execute(
-                "INSERT INTO sessoes (id, usuario_id, token, expires_at) VALUES (?1, ?2, ?3, ?4)",
-                params![Uuid::new_v4().to_string(), id, token, expires_at],
-            )?;
-            
-            // Atualizar último acesso
-            conn.execute(
-                "UPDATE usuarios SET ultimo_acesso = datetime('now') WHERE id = ?1",
-                params![id],
-            )?;
-            
-            let permissoes: serde_json::Value = serde_json::from_str(&permissoes_str)
-                .unwrap_or(serde_json::json!({}));
-            
-            let usuario = UsuarioPublico {
-                id,
-                nome,
-                email: email.clone(),
-                cargo,
-                departamento,
-                avatar,
-                permissoes,
-            };
-            
-            info!("✅ Login realizado: {}", email);
-            
-            Ok(LoginResponse {
-                success: true,
-                token: Some(token),
-                usuario: Some(usuario),
-                message: None,
-            })
-        },
-        Err(_) => {
-            Ok(LoginResponse {
-                success: false,
-                token: None,
-                usuario: None,
-                message: Some("Usuário não encontrado".into()),
-            })
+    let autenticado = match result {
+        Ok(dados) => bcrypt::verify(&senha, &dados.2).unwrap_or(false).then_some(dados),
+        Err(rusqlite::Error::QueryReturnedNoRows) => {
+            // Mesmo custo de bcrypt para não revelar se o e-mail existe
+            let _ = bcrypt::verify(&senha, hash_ficticio());
+            None
         }
+        Err(e) => return Err(AppError::Database(e.to_string())),
+    };
+    
+    let Some((id, nome, _, cargo, departamento, avatar, permissoes_str, trocar_senha)) = autenticado else {
+        let falhas = registrar_falha(&db, &email, agora)?;
+        tokio::time::sleep(atraso(falhas)).await;
+        return Ok(falha_login(FALHA_LOGIN.into()));
+    };
+    
+    // Gerar token de sessão (gravado apenas em hash)
+    let token = Uuid::new_v4().to_string();
+    let expires_at = carimbo(agora + Duration::hours(SESSAO_INATIVIDADE_HORAS));
+    
+    // Salvar sessão, atualizar último acesso e zerar falhas
+    db.with_transaction(|tx| {
+        tx.execute(
+            "INSERT INTO sessoes (id, usuario_id, token_hash, expires_at, ultimo_uso, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?5)",
+            params![Uuid::new_v4().to_string(), id, hash_token(&token), expires_at, carimbo(agora)],
+        )?;
+        tx.execute(
+            "UPDATE usuarios SET ultimo_acesso = datetime('now') WHERE id = ?1",
+            params![id],
+        )?;
+        tx.execute("DELETE FROM tentativas_login WHERE email = ?1", params![email])?;
+        Ok(())
+    })?;
+    
+    let permissoes: serde_json::Value = serde_json::from_str(&permissoes_str)
+        .unwrap_or(serde_json::json!({}));
+    
+    let usuario = UsuarioPublico {
+        id,
+        nome,
+        email: email.clone(),
+        cargo,
+        departamento,
+        avatar,
+        permissoes,
+        trocar_senha,
+    };
+    
+    if let Ok(mut atual) = auth.current_user.lock() {
+        *atual = Some(usuario.clone());
+    }
+    if let Ok(mut sessao) = auth.session_token.lock() {
+        *sessao = Some(token.clone());
+    }
+    
+    info!("✅ Login realizado: {}", email);
+    
+    Ok(LoginResponse {
+        success: true,
+        token: Some(token),
+        usuario: Some(usuario),
+        message: None,
+    })
+}
+
+fn falha_login(message: String) -> LoginResponse {
+    LoginResponse {
+        success: false,
+        token: None,
+        usuario: None,
+        message: Some(message),
     }
 }
 
+/// Hash usado para verificar senhas de e-mails inexistentes
+fn hash_ficticio() -> &'static str {
+    static HASH: OnceLock<String> = OnceLock::new();
+    HASH.get_or_init(|| bcrypt::hash("aluforce", bcrypt::DEFAULT_COST).unwrap_or_default())
+}
+
+/// Atraso progressivo aplicado à resposta de uma falha
+fn atraso(falhas: i64) -> std::time::Duration {
+    let expoente = (falhas.max(1) - 1).min(16) as u32;
+    std::time::Duration::from_millis((ATRASO_BASE_MS << expoente).min(ATRASO_MAXIMO_MS))
+}
+
+/// Fim do bloqueio vigente do e-mail, se houver
+fn bloqueado_ate(conn: &Connection, email: &str, agora: DateTime<Utc>) -> AppResult<Option<DateTime<Utc>>> {
+    let result = conn.query_row(
+        "SELECT bloqueado_ate FROM tentativas_login WHERE email = ?1",
+        params![email],
+        |row| row.get::<_, Option<String>>(0),
+    );
+    
+    let bloqueado_ate = match result {
+        Ok(valor) => valor,
+        Err(rusqlite::Error::QueryReturnedNoRows) => None,
+        Err(e) => return Err(AppError::Database(e.to_string())),
+    };
+    
+    Ok(bloqueado_ate
+        .and_then(|v| DateTime::parse_from_rfc3339(&v).ok())
+        .map(|d| d.with_timezone(&Utc))
+        .filter(|ate| *ate > agora))
+}
+
+/// Contabiliza uma falha e bloqueia o e-mail ao atingir o limite
+/// 
+/// Retorna o número de falhas consecutivas após esta.
+fn registrar_falha(db: &DatabaseState, email: &str, agora: DateTime<Utc>) -> AppResult<i64> {
+    db.with_transaction(|tx| {
+        let result = tx.query_row(
+            "SELECT falhas, bloqueado_ate FROM tentativas_login WHERE email = ?1",
+            params![email],
+            |row| Ok((row.get::<_, i64>(0)?, row.get::<_, Option<String>>(1)?)),
+        );
+        
+        let (anteriores, bloqueio_anterior) = match result {
+            Ok(r) => r,
+            Err(rusqlite::Error::QueryReturnedNoRows) => (0, None),
+            Err(e) => return Err(AppError::Database(e.to_string())),
+        };
+        
+        // Um bloqueio já expirado recomeça a contagem
+        let vigente = bloqueio_anterior.as_deref()
+            .and_then(|v| DateTime::parse_from_rfc3339(v).ok())
+            .map(|d| d.with_timezone(&Utc) > agora);
+        let falhas = match vigente {
+            Some(false) => 1,
+            _ => anteriores + 1,
+        };
+        
+        // Falhas durante um bloqueio vigente (logins simultâneos) não o prorrogam
+        let novo_bloqueio = vigente != Some(true) && falhas >= MAX_TENTATIVAS;
+        let bloqueio = if novo_bloqueio {
+            Some(carimbo(agora + Duration::minutes(BLOQUEIO_MINUTOS)))
+        } else if vigente == Some(true) {
+            bloqueio_anterior
+        } else {
+            None
+        };
+        
+        tx.execute(
+            "INSERT INTO tentativas_login (email, falhas, ultima_falha, bloqueado_ate)
+             VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT(email) DO UPDATE SET
+                falhas = excluded.falhas,
+                ultima_falha = excluded.ultima_falha,
+                bloqueado_ate = excluded.bloqueado_ate",
+            params![email, falhas, carimbo(agora), bloqueio],
+        )?;
+        
+        if let (true, Some(ate)) = (novo_bloqueio, &bloqueio) {
+            tx.execute(
+                "INSERT INTO eventos_bloqueio (email, tipo, falhas, bloqueado_ate) VALUES (?1, 'bloqueio', ?2, ?3)",
+                params![email, falhas, ate],
+            )?;
+            warn!("🔒 Login bloqueado após {} falhas: {}", falhas, email);
+        }
+        
+        Ok(falhas)
+    })
+}
+
 /// Realiza logout do usuário
 #[tauri::command]
-pub async fn logout(app: AppHandle, token: String) -> Result<bool, AppError> {
-    let db_path = get_database_path(&app)?;
-    let conn = Connection::open(&db_path)?;
+pub async fn logout(
+    db: State<'_, DatabaseState>,
+    auth: State<'_, AuthState>,
+    token: String,
+) -> Result<bool, AppError> {
+    let conn = db.writer()?;
     
-    conn.execute("DELETE FROM sessoes WHERE token = ?1", params![token])?;
+    conn.execute("DELETE FROM sessoes WHERE token_hash = ?1", params![hash_token(&token)])?;
+    
+    if let Ok(mut atual) = auth.current_user.lock() {
+        *atual = None;
+    }
+    if let Ok(mut sessao) = auth.session_token.lock() {
+        *sessao = None;
+    }
     
     info!("👋 Logout realizado");
     
     Ok(true)
 }
 
-/// Obtém usuário atual da sessão
+/// Lista as sessões ativas do usuário atual
 #[tauri::command]
-pub async fn get_current_user(app: AppHandle, token: String) -> Result<Option<UsuarioPublico>, AppError> {
-    let db_path = get_database_path(&app)?;
-    let conn = Connection::open(&db_path)?;
-    
-    let result: Result<(i64, String, String, Option<String>, Option<String>, Option<String>, String), _> = 
-        conn.query_row(
-            "SELECT u.id, u.nome, u.email, u.cargo, u.departamento, u.avatar, u.permissoes
-             FROM usuarios u
-             INNER JOIN sessoes s ON s.usuario_id = u.id
-             WHERE s.token = ?1 AND s.expires_at > datetime('now') AND u.ativo = 1",
-            params![token],
-            |row| Ok((
-                row.get(0)?,
-                row.get(1)?,
-                row.get(2)?,
-                row.get(3)?,
-                row.get(4)?,
-                row.get(5)?,
-                row.get(6)?,
-            ))
-        );
+pub async fn list_sessoes(db: State<'_, DatabaseState>, token: String) -> Result<Vec<Sessao>, AppError> {
+    let ctx = RequestContext::resolve_sessao(&db, &token)?;
+    
+    let conn = db.reader()?;
+    
+    let mut stmt = conn.prepare(
+        "SELECT id, created_at, ultimo_uso, expires_at
+         FROM sessoes WHERE usuario_id = ?1 AND expires_at > ?2
+         ORDER BY ultimo_uso DESC"
+    )?;
+    
+    let sessoes = stmt.query_map(params![ctx.usuario.id, carimbo(Utc::now())], |row| {
+        let id: String = row.get(0)?;
+        Ok(Sessao {
+            atual: id == ctx.sessao_id,
+            id,
+            created_at: row.get(1)?,
+            ultimo_uso: row.get(2)?,
+            expires_at: row.get(3)?,
+        })
+    })?
+    .filter_map(|r| r.ok())
+    .collect();
+    
+    Ok(sessoes)
+}
+
+/// Encerra uma sessão do usuário atual
+#[tauri::command]
+pub async fn revoke_sessao(db: State<'_, DatabaseState>, token: String, sessao_id: String) -> Result<bool, AppError> {
+    let ctx = RequestContext::resolve_sessao(&db, &token)?;
+    
+    let removidas = db.writer()?.execute(
+        "DELETE FROM sessoes WHERE id = ?1 AND usuario_id = ?2",
+        params![sessao_id, ctx.usuario.id],
+    )?;
     
-    match result {
-        Ok((id, nome, email, cargo, departamento, avatar, permissoes_str)) => {
-            let permissoes: serde_json::Value = serde_json::from_str(&permissoes_str)
-                .unwrap_or(serde_json::json!({}));
-            
-            Ok(Some(UsuarioPublico {
-                id,
-                nome,
-                email,
-                cargo,
-                departamento,
-                avatar,
-                permissoes,
-            }))
-        },
-        Err(_) => Ok(None)
+    Ok(removidas > 0)
+}
+
+/// Encerra todas as sessões do usuário atual, exceto a desta requisição
+#[tauri::command]
+pub async fn revoke_outras_sessoes(db: State<'_, DatabaseState>, token: String) -> Result<i64, AppError> {
+    let ctx = RequestContext::resolve_sessao(&db, &token)?;
+    
+    let removidas = db.writer()?.execute(
+        "DELETE FROM sessoes WHERE usuario_id = ?1 AND id <> ?2",
+        params![ctx.usuario.id, ctx.sessao_id],
+    )?;
+    
+    info!("🔒 {} sessões encerradas para usuário ID: {}", removidas, ctx.usuario.id);
+    
+    Ok(removidas as i64)
+}
+
+/// Obtém usuário atual da sessão
+#[tauri::command]
+pub async fn get_current_user(db: State<'_, DatabaseState>, token: String) -> Result<Option<UsuarioPublico>, AppError> {
+    match RequestContext::resolve_sessao(&db, &token) {
+        Ok(ctx) => Ok(Some(ctx.usuario)),
+        Err(AppError::Authentication(_)) => Ok(None),
+        Err(e) => Err(e),
     }
 }
 
 /// Altera senha do usuário
 #[tauri::command]
 pub async fn change_password(
-    app: AppHandle,
-    user_id: i64,
+    db: State<'_, DatabaseState>,
+    token: String,
     senha_atual: String,
     nova_senha: String,
 ) -> Result<bool, AppError> {
-    let db_path = get_database_path(&app)?;
-    let conn = Connection::open(&db_path)?;
+    // Liberado mesmo com troca de senha pendente
+    let ctx = RequestContext::resolve_sessao(&db, &token)?;
+    let user_id = ctx.usuario.id;
+    
+    validar_senha(&nova_senha)?;
     
     // Verificar senha atual
-    let senha_hash: String = conn.query_row(
+    let senha_hash: String = db.reader()?.query_row(
         "SELECT senha_hash FROM usuarios WHERE id = ?1",
         params![user_id],
         |row| row.get(0),
@@ -195,28 +362,146 @@ pub async fn change_password(
     // Hash da nova senha
     let nova_hash = bcrypt::hash(&nova_senha, bcrypt::DEFAULT_COST)?;
     
-    // Atualizar senha
-    conn.execute(
-        "UPDATE usuarios SET senha_hash = ?1, updated_at = datetime('now') WHERE id = ?2",
-        params![nova_hash, user_id],
-    )?;
-    
-    info!("🔐 Senha alterada para usuário ID: {}", user_id);
-    
-    Ok(true)
+    // Atualizar senha (o hash nunca vai para a auditoria, só o evento)
+    db.with_transaction(|tx| {
+        tx.execute(
+            "UPDATE usuarios SET senha_hash = ?1, trocar_senha = 0, updated_at = datetime('now') WHERE id = ?2",
+            params![nova_hash, user_id],
+        )?;
+        auditoria::registrar_evento(
+            tx, &ctx, Modulo::Config, "usuarios", &user_id.to_string(),
+            "alterar_senha", serde_json::json!({}),
+        )?;
+        
+        info!("🔐 Senha alterada para usuário ID: {}", user_id);
+        
+        Ok(true)
+    })
+}
+
+/// Regras mínimas para novas senhas
+pub(crate) fn validar_senha(senha: &str) -> AppResult<()> {
+    if senha.chars().count() < 8 {
+        return Err(AppError::Validation("A senha deve ter pelo menos 8 caracteres".into()));
+    }
+    Ok(())
 }
 
 /// Valida sessão do usuário
 #[tauri::command]
-pub async fn validate_session(app: AppHandle, token: String) -> Result<bool, AppError> {
-    let db_path = get_database_path(&app)?;
-    let conn = Connection::open(&db_path)?;
+pub async fn validate_session(db: State<'_, DatabaseState>, token: String) -> Result<bool, AppError> {
+    match RequestContext::resolve_sessao(&db, &token) {
+        Ok(_) => Ok(true),
+        Err(AppError::Authentication(_)) => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::usuarios::desbloquear_login;
+    use crate::testes::*;
     
-    let count: i32 = conn.query_row(
-        "SELECT COUNT(*) FROM sessoes WHERE token = ?1 AND expires_at > datetime('now')",
-        params![token],
-        |row| row.get(0),
-    ).unwrap_or(0);
+    const EMAIL: &str = "operador@teste";
     
-    Ok(count > 0)
+    fn tentativa(db: &DatabaseState) -> (i64, String, Option<String>) {
+        db.reader().unwrap().query_row(
+            "SELECT falhas, ultima_falha, bloqueado_ate FROM tentativas_login WHERE email = ?1",
+            params![EMAIL],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        ).unwrap()
+    }
+    
+    #[test]
+    fn falhas_consecutivas_bloqueiam_e_recomecam_apos_o_bloqueio() {
+        let db = banco_teste(TOKEN);
+        let agora = Utc::now();
+        
+        for esperado in 1..MAX_TENTATIVAS {
+            assert_eq!(registrar_falha(&db, EMAIL, agora).unwrap(), esperado);
+        }
+        assert!(bloqueado_ate(&db.reader().unwrap(), EMAIL, agora).unwrap().is_none());
+        
+        assert_eq!(registrar_falha(&db, EMAIL, agora).unwrap(), MAX_TENTATIVAS);
+        let fim = agora + Duration::minutes(BLOQUEIO_MINUTOS);
+        assert_eq!(tentativa(&db), (MAX_TENTATIVAS, carimbo(agora), Some(carimbo(fim))));
+        assert!(bloqueado_ate(&db.reader().unwrap(), EMAIL, agora).unwrap().is_some());
+        assert_eq!(contar(&db, "SELECT COUNT(*) FROM eventos_bloqueio WHERE tipo = 'bloqueio'"), 1);
+        
+        // Falha durante o bloqueio não o prorroga
+        registrar_falha(&db, EMAIL, agora + Duration::minutes(1)).unwrap();
+        assert_eq!(tentativa(&db).2, Some(carimbo(fim)));
+        
+        let depois = fim + Duration::minutes(1);
+        assert!(bloqueado_ate(&db.reader().unwrap(), EMAIL, depois).unwrap().is_none());
+        assert_eq!(registrar_falha(&db, EMAIL, depois).unwrap(), 1);
+        assert_eq!(tentativa(&db).2, None);
+    }
+    
+    #[test]
+    fn desbloqueio_libera_o_login() {
+        let db = banco_teste(TOKEN);
+        db.writer().unwrap().execute(
+            "INSERT INTO usuarios (nome, email, senha_hash) VALUES ('Operador', ?1, ?2)",
+            params![EMAIL, bcrypt::hash("segredo", 4).unwrap()],
+        ).unwrap();
+        for _ in 0..MAX_TENTATIVAS {
+            registrar_falha(&db, EMAIL, Utc::now()).unwrap();
+        }
+        
+        // Bloqueado, nem a senha certa entra
+        let recusado = executar(login(db.state(), db.state(), EMAIL.into(), "segredo".into())).unwrap();
+        assert!(!recusado.success);
+        assert!(recusado.message.unwrap().starts_with("Muitas tentativas"));
+        
+        assert!(executar(desbloquear_login(db.state(), TOKEN.into(), EMAIL.into())).unwrap());
+        assert_eq!(contar(&db, "SELECT COUNT(*) FROM tentativas_login"), 0);
+        assert_eq!(contar(&db, "SELECT COUNT(*) FROM eventos_bloqueio WHERE tipo = 'desbloqueio' AND usuario_id = 1"), 1);
+        
+        let aceito = executar(login(db.state(), db.state(), EMAIL.into(), "segredo".into())).unwrap();
+        assert!(aceito.success);
+        assert!(!executar(desbloquear_login(db.state(), TOKEN.into(), EMAIL.into())).unwrap());
+    }
+    
+    #[test]
+    fn login_grava_so_o_hash_do_token() {
+        let db = banco_teste(TOKEN);
+        db.writer().unwrap().execute(
+            "INSERT INTO usuarios (nome, email, senha_hash) VALUES ('Operador', ?1, ?2)",
+            params![EMAIL, bcrypt::hash("segredo", 4).unwrap()],
+        ).unwrap();
+        
+        let resposta = executar(login(db.state(), db.state(), EMAIL.into(), "segredo".into())).unwrap();
+        let token = resposta.token.unwrap();
+        assert_eq!(contar(&db, &format!("SELECT COUNT(*) FROM sessoes WHERE token_hash = '{}'", hash_token(&token))), 1);
+        assert_eq!(contar(&db, &format!("SELECT COUNT(*) FROM sessoes WHERE token_hash = '{}'", token)), 0);
+    }
+    
+    #[test]
+    fn sessoes_listadas_e_revogadas_pelo_proprio_usuario() {
+        let db = banco_teste(TOKEN);
+        sessao(&db, 1, "celular");
+        sessao(&db, 1, "notebook");
+        let outro = usuario(&db, EMAIL, "{}");
+        sessao(&db, outro, "outro");
+        
+        let sessoes = executar(list_sessoes(db.state(), TOKEN.into())).unwrap();
+        assert_eq!(sessoes.len(), 3);
+        assert_eq!(sessoes.iter().filter(|s| s.atual).count(), 1);
+        
+        // Sessão de outro usuário não é revogada
+        let alheia: String = db.reader().unwrap().query_row(
+            "SELECT id FROM sessoes WHERE usuario_id = ?1", params![outro], |row| row.get(0),
+        ).unwrap();
+        assert!(!executar(revoke_sessao(db.state(), TOKEN.into(), alheia)).unwrap());
+        
+        let celular = sessoes.iter().find(|s| !s.atual).unwrap().id.clone();
+        assert!(executar(revoke_sessao(db.state(), TOKEN.into(), celular)).unwrap());
+        assert_eq!(executar(revoke_outras_sessoes(db.state(), TOKEN.into())).unwrap(), 1);
+        
+        assert!(executar(validate_session(db.state(), TOKEN.into())).unwrap());
+        assert!(!executar(validate_session(db.state(), "notebook".into())).unwrap());
+        assert!(executar(validate_session(db.state(), "outro".into())).unwrap());
+    }
 }
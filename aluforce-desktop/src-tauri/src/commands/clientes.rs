@@ -1,23 +1,29 @@
 //! Comandos de Clientes
 
-use tauri::AppHandle;
+use tauri::State;
 use rusqlite::{Connection, params};
 use log::info;
 
 use crate::error::{AppError, AppResult};
-use crate::models::{Cliente, ClienteInput, PaginatedResponse};
-use crate::database::get_database_path;
+use crate::models::{Cliente, ClienteInput, PaginatedResponse, Modulo, Acao, Dinheiro};
+use crate::database::DatabaseState;
+use crate::context::RequestContext;
+use crate::auditoria;
+use crate::precos;
 
 /// Lista clientes com paginação
 #[tauri::command]
 pub async fn get_clientes(
-    app: AppHandle,
+    db: State<'_, DatabaseState>,
+    token: String,
     page: Option<i64>,
     per_page: Option<i64>,
     ativo: Option<bool>,
 ) -> Result<PaginatedResponse<Cliente>, AppError> {
-    let db_path = get_database_path(&app)?;
-    let conn = Connection::open(&db_path)?;
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require_any(&[Modulo::Vendas, Modulo::Financeiro, Modulo::Nfe], Acao::Ler)?;
+    
+    let conn = db.reader()?;
     
     let page = page.unwrap_or(1);
     let per_page = per_page.unwrap_or(20);
@@ -37,7 +43,7 @@ pub async fn get_clientes(
         "SELECT id, nome, razao_social, nome_fantasia, cnpj, cpf, 
                 inscricao_estadual, inscricao_municipal, email, telefone, celular,
                 cep, endereco, numero, complemento, bairro, cidade, estado,
-                observacoes, limite_credito, vendedor_id, ativo, created_at, updated_at
+                observacoes, limite_credito, vendedor_id, ativo, created_at, updated_at, tabela_preco_id
          FROM clientes 
          WHERE ativo = ?1
          ORDER BY nome ASC
@@ -70,6 +76,7 @@ pub async fn get_clientes(
             ativo: row.get(21)?,
             created_at: row.get(22)?,
             updated_at: row.get(23)?,
+            tabela_preco_id: row.get(24)?,
         })
     })?
     .filter_map(|r| r.ok())
@@ -80,15 +87,21 @@ pub async fn get_clientes(
 
 /// Busca cliente por ID
 #[tauri::command]
-pub async fn get_cliente_by_id(app: AppHandle, id: i64) -> Result<Option<Cliente>, AppError> {
-    let db_path = get_database_path(&app)?;
-    let conn = Connection::open(&db_path)?;
+pub async fn get_cliente_by_id(db: State<'_, DatabaseState>, token: String, id: i64) -> Result<Option<Cliente>, AppError> {
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require_any(&[Modulo::Vendas, Modulo::Financeiro, Modulo::Nfe], Acao::Ler)?;
     
+    let conn = db.reader()?;
+    fetch_cliente(&conn, id)
+}
+
+/// Carrega cliente por ID na conexão informada
+pub(crate) fn fetch_cliente(conn: &Connection, id: i64) -> AppResult<Option<Cliente>> {
     let result = conn.query_row(
         "SELECT id, nome, razao_social, nome_fantasia, cnpj, cpf, 
                 inscricao_estadual, inscricao_municipal, email, telefone, celular,
                 cep, endereco, numero, complemento, bairro, cidade, estado,
-                observacoes, limite_credito, vendedor_id, ativo, created_at, updated_at
+                observacoes, limite_credito, vendedor_id, ativo, created_at, updated_at, tabela_preco_id
          FROM clientes WHERE id = ?1",
         params![id],
         |row| {
@@ -117,6 +130,7 @@ pub async fn get_cliente_by_id(app: AppHandle, id: i64) -> Result<Option<Cliente
                 ativo: row.get(21)?,
                 created_at: row.get(22)?,
                 updated_at: row.get(23)?,
+                tabela_preco_id: row.get(24)?,
             })
         },
     );
@@ -129,82 +143,132 @@ pub async fn get_cliente_by_id(app: AppHandle, id: i64) -> Result<Option<Cliente
 }
 
 /// Cria novo cliente
+/// 
+/// Limite de crédito diferente de zero exige aprovação no financeiro.
 #[tauri::command]
-pub async fn create_cliente(app: AppHandle, input: ClienteInput) -> Result<Cliente, AppError> {
-    let db_path = get_database_path(&app)?;
-    let conn = Connection::open(&db_path)?;
-    
-    conn.execute(
-        "INSERT INTO clientes (nome, razao_social, nome_fantasia, cnpj, cpf,
-            inscricao_estadual, inscricao_municipal, email, telefone, celular,
-            cep, endereco, numero, complemento, bairro, cidade, estado,
-            observacoes, limite_credito, vendedor_id)
-         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20)",
-        params![
-            input.nome, input.razao_social, input.nome_fantasia, input.cnpj, input.cpf,
-            input.inscricao_estadual, input.inscricao_municipal, input.email, input.telefone, input.celular,
-            input.cep, input.endereco, input.numero, input.complemento, input.bairro, input.cidade, input.estado,
-            input.observacoes, input.limite_credito.unwrap_or(0.0), input.vendedor_id
-        ],
-    )?;
-    
-    let id = conn.last_insert_rowid();
-    info!("✅ Cliente criado: {} (ID: {})", input.nome, id);
+pub async fn create_cliente(db: State<'_, DatabaseState>, token: String, input: ClienteInput) -> Result<Cliente, AppError> {
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require(Modulo::Vendas, Acao::Criar)?;
+    if input.limite_credito.is_some_and(|limite| !limite.is_zero()) {
+        ctx.require(Modulo::Financeiro, Acao::Aprovar)?;
+    }
     
-    get_cliente_by_id(app, id).await?.ok_or(AppError::NotFound("Cliente não encontrado".into()))
+    db.with_transaction(|tx| {
+        if let Some(tabela_id) = input.tabela_preco_id {
+            precos::tabela(tx, tabela_id)?;
+        }
+        
+        tx.execute(
+            "INSERT INTO clientes (nome, razao_social, nome_fantasia, cnpj, cpf,
+                inscricao_estadual, inscricao_municipal, email, telefone, celular,
+                cep, endereco, numero, complemento, bairro, cidade, estado,
+                observacoes, limite_credito, vendedor_id, tabela_preco_id)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21)",
+            params![
+                input.nome, input.razao_social, input.nome_fantasia, input.cnpj, input.cpf,
+                input.inscricao_estadual, input.inscricao_municipal, input.email, input.telefone, input.celular,
+                input.cep, input.endereco, input.numero, input.complemento, input.bairro, input.cidade, input.estado,
+                input.observacoes, input.limite_credito.unwrap_or_default(), input.vendedor_id, input.tabela_preco_id
+            ],
+        )?;
+        
+        let id = tx.last_insert_rowid();
+        auditoria::novo("clientes", id).registrar(tx, &ctx, Modulo::Vendas, "criar")?;
+        
+        info!("✅ Cliente criado: {} (ID: {})", input.nome, id);
+        
+        fetch_cliente(tx, id)?.ok_or(AppError::NotFound("Cliente não encontrado".into()))
+    })
 }
 
 /// Atualiza cliente existente
+/// 
+/// `limite_credito` e `tabela_preco_id` omitidos mantêm o valor gravado.
+/// Mudar o limite de crédito exige aprovação no financeiro.
 #[tauri::command]
-pub async fn update_cliente(app: AppHandle, id: i64, input: ClienteInput) -> Result<Cliente, AppError> {
-    let db_path = get_database_path(&app)?;
-    let conn = Connection::open(&db_path)?;
-    
-    conn.execute(
-        "UPDATE clientes SET 
-            nome = ?1, razao_social = ?2, nome_fantasia = ?3, cnpj = ?4, cpf = ?5,
-            inscricao_estadual = ?6, inscricao_municipal = ?7, email = ?8, telefone = ?9, celular = ?10,
-            cep = ?11, endereco = ?12, numero = ?13, complemento = ?14, bairro = ?15, cidade = ?16, estado = ?17,
-            observacoes = ?18, limite_credito = ?19, vendedor_id = ?20, updated_at = datetime('now')
-         WHERE id = ?21",
-        params![
-            input.nome, input.razao_social, input.nome_fantasia, input.cnpj, input.cpf,
-            input.inscricao_estadual, input.inscricao_municipal, input.email, input.telefone, input.celular,
-            input.cep, input.endereco, input.numero, input.complemento, input.bairro, input.cidade, input.estado,
-            input.observacoes, input.limite_credito.unwrap_or(0.0), input.vendedor_id, id
-        ],
-    )?;
-    
-    info!("✅ Cliente atualizado: {} (ID: {})", input.nome, id);
+pub async fn update_cliente(db: State<'_, DatabaseState>, token: String, id: i64, input: ClienteInput) -> Result<Cliente, AppError> {
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require(Modulo::Vendas, Acao::Editar)?;
     
-    get_cliente_by_id(app, id).await?.ok_or(AppError::NotFound("Cliente não encontrado".into()))
+    db.with_transaction(|tx| {
+        if let Some(tabela_id) = input.tabela_preco_id {
+            precos::tabela(tx, tabela_id)?;
+        }
+        
+        let limite_atual: Dinheiro = match tx.query_row(
+            "SELECT COALESCE(limite_credito, 0) FROM clientes WHERE id = ?1",
+            params![id],
+            |row| row.get(0),
+        ) {
+            Ok(limite) => limite,
+            Err(rusqlite::Error::QueryReturnedNoRows) => return Err(AppError::NotFound("Cliente não encontrado".into())),
+            Err(e) => return Err(AppError::Database(e.to_string())),
+        };
+        if input.limite_credito.is_some_and(|limite| limite != limite_atual) {
+            ctx.require(Modulo::Financeiro, Acao::Aprovar)?;
+        }
+        
+        let captura = auditoria::capturar(tx, "clientes", id)?;
+        
+        tx.execute(
+            "UPDATE clientes SET 
+                nome = ?1, razao_social = ?2, nome_fantasia = ?3, cnpj = ?4, cpf = ?5,
+                inscricao_estadual = ?6, inscricao_municipal = ?7, email = ?8, telefone = ?9, celular = ?10,
+                cep = ?11, endereco = ?12, numero = ?13, complemento = ?14, bairro = ?15, cidade = ?16, estado = ?17,
+                observacoes = ?18, limite_credito = COALESCE(?19, limite_credito), vendedor_id = ?20,
+                tabela_preco_id = COALESCE(?21, tabela_preco_id),
+                updated_at = datetime('now')
+             WHERE id = ?22",
+            params![
+                input.nome, input.razao_social, input.nome_fantasia, input.cnpj, input.cpf,
+                input.inscricao_estadual, input.inscricao_municipal, input.email, input.telefone, input.celular,
+                input.cep, input.endereco, input.numero, input.complemento, input.bairro, input.cidade, input.estado,
+                input.observacoes, input.limite_credito, input.vendedor_id, input.tabela_preco_id, id
+            ],
+        )?;
+        
+        captura.registrar(tx, &ctx, Modulo::Vendas, "editar")?;
+        
+        info!("✅ Cliente atualizado: {} (ID: {})", input.nome, id);
+        
+        fetch_cliente(tx, id)?.ok_or(AppError::NotFound("Cliente não encontrado".into()))
+    })
 }
 
 /// Deleta cliente (soft delete)
 #[tauri::command]
-pub async fn delete_cliente(app: AppHandle, id: i64) -> Result<bool, AppError> {
-    let db_path = get_database_path(&app)?;
-    let conn = Connection::open(&db_path)?;
+pub async fn delete_cliente(db: State<'_, DatabaseState>, token: String, id: i64) -> Result<bool, AppError> {
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require(Modulo::Vendas, Acao::Excluir)?;
     
-    conn.execute(
-        "UPDATE clientes SET ativo = 0, updated_at = datetime('now') WHERE id = ?1",
-        params![id],
-    )?;
-    
-    info!("🗑️ Cliente desativado: ID {}", id);
-    
-    Ok(true)
+    db.with_transaction(|tx| {
+        let captura = auditoria::capturar(tx, "clientes", id)?;
+        
+        tx.execute(
+            "UPDATE clientes SET ativo = 0, updated_at = datetime('now') WHERE id = ?1",
+            params![id],
+        )?;
+        
+        captura.registrar(tx, &ctx, Modulo::Vendas, "excluir")?;
+        
+        info!("🗑️ Cliente desativado: ID {}", id);
+        
+        Ok(true)
+    })
 }
 
 /// Busca clientes por termo
 #[tauri::command]
 pub async fn search_clientes(
-    app: AppHandle,
+    db: State<'_, DatabaseState>,
+    token: String,
     termo: String,
     limit: Option<i64>,
 ) -> Result<Vec<Cliente>, AppError> {
-    let db_path = get_database_path(&app)?;
-    let conn = Connection::open(&db_path)?;
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require_any(&[Modulo::Vendas, Modulo::Financeiro, Modulo::Nfe], Acao::Ler)?;
+    
+    let conn = db.reader()?;
     
     let limit = limit.unwrap_or(20);
     let search_term = format!("%{}%", termo);
@@ -213,7 +277,7 @@ pub async fn search_clientes(
         "SELECT id, nome, razao_social, nome_fantasia, cnpj, cpf, 
                 inscricao_estadual, inscricao_municipal, email, telefone, celular,
                 cep, endereco, numero, complemento, bairro, cidade, estado,
-                observacoes, limite_credito, vendedor_id, ativo, created_at, updated_at
+                observacoes, limite_credito, vendedor_id, ativo, created_at, updated_at, tabela_preco_id
          FROM clientes 
          WHERE ativo = 1 AND (
              nome LIKE ?1 OR 
@@ -253,6 +317,7 @@ pub async fn search_clientes(
             ativo: row.get(21)?,
             created_at: row.get(22)?,
             updated_at: row.get(23)?,
+            tabela_preco_id: row.get(24)?,
         })
     })?
     .filter_map(|r| r.ok())
@@ -260,3 +325,43 @@ pub async fn search_clientes(
     
     Ok(clientes)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testes::*;
+    
+    fn input(valor: serde_json::Value) -> ClienteInput {
+        serde_json::from_value(valor).unwrap()
+    }
+    
+    #[test]
+    fn limite_de_credito_exige_aprovacao_do_financeiro() {
+        let db = banco_teste(TOKEN);
+        let vendedor = usuario(&db, "vendedor@teste", r#"{"modulos": {"vendas": ["ler", "criar", "editar"]}}"#);
+        sessao(&db, vendedor, "vendedor");
+        
+        let erro = executar(create_cliente(db.state(), "vendedor".into(), input(serde_json::json!({
+            "nome": "Cliente", "limite_credito": 500
+        })))).unwrap_err();
+        assert!(matches!(erro, AppError::Authorization(_)));
+        
+        let cliente = executar(create_cliente(db.state(), "vendedor".into(), input(serde_json::json!({ "nome": "Cliente" })))).unwrap();
+        let tabela = inserir(&db, "INSERT INTO tabelas_preco (nome) VALUES ('Revenda')");
+        executar(update_cliente(db.state(), TOKEN.into(), cliente.id, input(serde_json::json!({
+            "nome": "Cliente", "limite_credito": 500, "tabela_preco_id": tabela
+        })))).unwrap();
+        
+        // Omitidos, limite e tabela ficam como estão
+        let editado = executar(update_cliente(db.state(), "vendedor".into(), cliente.id, input(serde_json::json!({
+            "nome": "Cliente Editado"
+        })))).unwrap();
+        assert_eq!(editado.limite_credito, Dinheiro::centavos(50000));
+        assert_eq!(editado.tabela_preco_id, Some(tabela));
+        
+        let erro = executar(update_cliente(db.state(), "vendedor".into(), cliente.id, input(serde_json::json!({
+            "nome": "Cliente Editado", "limite_credito": 0
+        })))).unwrap_err();
+        assert!(matches!(erro, AppError::Authorization(_)));
+    }
+}
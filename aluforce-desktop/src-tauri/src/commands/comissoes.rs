@@ -0,0 +1,341 @@
+//! Comandos de Comissões
+//!
+//! A apuração fica em `crate::comissoes`; aqui as regras, o extrato por
+//! vendedor e o fechamento do período em contas a pagar.
+
+use tauri::State;
+use rusqlite::{Connection, params};
+use log::info;
+use chrono::{NaiveDate, Utc};
+
+use crate::error::{AppError, AppResult};
+use crate::models::{RegraComissao, RegraComissaoInput, Comissao, ExtratoComissoes, Modulo, Acao, Dinheiro};
+use crate::database::DatabaseState;
+use crate::context::RequestContext;
+use crate::auditoria;
+use crate::precos;
+
+/// Regras de comissão só mudam com aprovação em vendas ou edição no
+/// financeiro; o vendedor não define o próprio percentual
+fn exigir_gestao(ctx: &RequestContext) -> AppResult<()> {
+    if ctx.permissoes.permite(Modulo::Vendas, Acao::Aprovar) || ctx.permissoes.permite(Modulo::Financeiro, Acao::Editar) {
+        Ok(())
+    } else {
+        Err(AppError::Authorization("Sem permissão para alterar regras de comissão".into()))
+    }
+}
+
+#[tauri::command]
+pub async fn get_regras_comissao(
+    db: State<'_, DatabaseState>,
+    token: String,
+    vendedor_id: Option<i64>,
+) -> Result<Vec<RegraComissao>, AppError> {
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require(Modulo::Vendas, Acao::Ler)?;
+    
+    let conn = db.reader()?;
+    
+    let mut stmt = conn.prepare(
+        "SELECT r.id, r.vendedor_id, u.nome, r.categoria, r.tabela_preco_id, t.nome, r.percentual,
+                r.ativo, r.created_at, r.updated_at
+         FROM regras_comissao r
+         LEFT JOIN usuarios u ON u.id = r.vendedor_id
+         LEFT JOIN tabelas_preco t ON t.id = r.tabela_preco_id
+         WHERE r.ativo = 1 AND (?1 IS NULL OR r.vendedor_id IS NULL OR r.vendedor_id = ?1)
+         ORDER BY u.nome ASC, r.categoria ASC, t.nome ASC"
+    )?;
+    
+    let regras = stmt.query_map(params![vendedor_id], map_regra)?.collect::<Result<_, _>>()?;
+    
+    Ok(regras)
+}
+
+#[tauri::command]
+pub async fn create_regra_comissao(
+    db: State<'_, DatabaseState>,
+    token: String,
+    input: RegraComissaoInput,
+) -> Result<RegraComissao, AppError> {
+    let ctx = RequestContext::resolve(&db, &token)?;
+    exigir_gestao(&ctx)?;
+    
+    db.with_transaction(|tx| {
+        validar(tx, &input, None)?;
+        
+        tx.execute(
+            "INSERT INTO regras_comissao (vendedor_id, categoria, tabela_preco_id, percentual)
+             VALUES (?1, ?2, ?3, ?4)",
+            params![input.vendedor_id, categoria(&input), input.tabela_preco_id, input.percentual],
+        )?;
+        
+        let id = tx.last_insert_rowid();
+        auditoria::novo("regras_comissao", id).registrar(tx, &ctx, Modulo::Vendas, "criar")?;
+        
+        info!("✅ Regra de comissão criada: {}% (ID: {})", input.percentual, id);
+        
+        regra(tx, id)
+    })
+}
+
+/// Altera a regra; comissões já lançadas não são recalculadas
+#[tauri::command]
+pub async fn update_regra_comissao(
+    db: State<'_, DatabaseState>,
+    token: String,
+    id: i64,
+    input: RegraComissaoInput,
+) -> Result<RegraComissao, AppError> {
+    let ctx = RequestContext::resolve(&db, &token)?;
+    exigir_gestao(&ctx)?;
+    
+    db.with_transaction(|tx| {
+        regra(tx, id)?;
+        validar(tx, &input, Some(id))?;
+        
+        let captura = auditoria::capturar(tx, "regras_comissao", id)?;
+        
+        tx.execute(
+            "UPDATE regras_comissao SET vendedor_id = ?1, categoria = ?2, tabela_preco_id = ?3, percentual = ?4,
+                    updated_at = datetime('now')
+             WHERE id = ?5",
+            params![input.vendedor_id, categoria(&input), input.tabela_preco_id, input.percentual, id],
+        )?;
+        
+        captura.registrar(tx, &ctx, Modulo::Vendas, "editar")?;
+        
+        info!("✅ Regra de comissão atualizada: ID {}", id);
+        
+        regra(tx, id)
+    })
+}
+
+/// Desativa a regra (soft delete)
+#[tauri::command]
+pub async fn delete_regra_comissao(db: State<'_, DatabaseState>, token: String, id: i64) -> Result<bool, AppError> {
+    let ctx = RequestContext::resolve(&db, &token)?;
+    exigir_gestao(&ctx)?;
+    
+    db.with_transaction(|tx| {
+        regra(tx, id)?;
+        let captura = auditoria::capturar(tx, "regras_comissao", id)?;
+        
+        tx.execute(
+            "UPDATE regras_comissao SET ativo = 0, updated_at = datetime('now') WHERE id = ?1",
+            params![id],
+        )?;
+        
+        captura.registrar(tx, &ctx, Modulo::Vendas, "excluir")?;
+        
+        info!("🗑️ Regra de comissão desativada: ID {}", id);
+        
+        Ok(true)
+    })
+}
+
+/// Extrato de comissões do vendedor entre `data_inicio` e `data_fim` (AAAA-MM-DD)
+#[tauri::command]
+pub async fn get_extrato_comissoes(
+    db: State<'_, DatabaseState>,
+    token: String,
+    vendedor_id: i64,
+    data_inicio: String,
+    data_fim: String,
+) -> Result<ExtratoComissoes, AppError> {
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require_any(&[Modulo::Vendas, Modulo::Financeiro], Acao::Ler)?;
+    
+    let (inicio, fim) = periodo(&data_inicio, &data_fim)?;
+    
+    let conn = db.reader()?;
+    extrato(&conn, vendedor_id, inicio, fim)
+}
+
+/// Fecha as comissões em aberto do período em uma conta a pagar
+///
+/// A conta vence em `data_vencimento` (hoje, se omitida). Estornos do
+/// período descontam do total; período sem saldo positivo não fecha.
+#[tauri::command]
+pub async fn fechar_comissoes(
+    db: State<'_, DatabaseState>,
+    token: String,
+    vendedor_id: i64,
+    data_inicio: String,
+    data_fim: String,
+    data_vencimento: Option<String>,
+) -> Result<ExtratoComissoes, AppError> {
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require(Modulo::Financeiro, Acao::Criar)?;
+    
+    let (inicio, fim) = periodo(&data_inicio, &data_fim)?;
+    let vencimento = match data_vencimento {
+        Some(data) => parse_data(&data)?,
+        None => Utc::now().date_naive(),
+    };
+    
+    db.with_transaction(|tx| {
+        let atual = extrato(tx, vendedor_id, inicio, fim)?;
+        if atual.em_aberto.is_zero() || atual.em_aberto.is_negative() {
+            return Err(AppError::Validation("Não há comissão a pagar no período".into()));
+        }
+        
+        let descricao = format!(
+            "Comissões {} - {} a {}",
+            atual.vendedor_nome.as_deref().unwrap_or("vendedor"), atual.data_inicio, atual.data_fim
+        );
+        tx.execute(
+            "INSERT INTO contas_pagar (descricao, valor, data_vencimento, categoria, status, parcela, total_parcelas)
+             VALUES (?1, ?2, ?3, 'comissoes', 'pendente', 1, 1)",
+            params![descricao, atual.em_aberto, vencimento.format("%Y-%m-%d").to_string()],
+        )?;
+        
+        let conta_id = tx.last_insert_rowid();
+        auditoria::novo("contas_pagar", conta_id).registrar(tx, &ctx, Modulo::Financeiro, "criar")?;
+        
+        tx.execute(
+            "UPDATE comissoes SET conta_pagar_id = ?1
+             WHERE vendedor_id = ?2 AND data_competencia BETWEEN ?3 AND ?4 AND conta_pagar_id IS NULL",
+            params![conta_id, vendedor_id, atual.data_inicio, atual.data_fim],
+        )?;
+        
+        info!("💼 Comissões fechadas: {} - R$ {} (conta a pagar {})", descricao, atual.em_aberto, conta_id);
+        
+        extrato(tx, vendedor_id, inicio, fim)
+    })
+}
+
+fn extrato(conn: &Connection, vendedor_id: i64, inicio: NaiveDate, fim: NaiveDate) -> AppResult<ExtratoComissoes> {
+    let vendedor_nome: Option<String> = match conn.query_row(
+        "SELECT nome FROM usuarios WHERE id = ?1",
+        params![vendedor_id],
+        |row| row.get(0),
+    ) {
+        Ok(nome) => nome,
+        Err(rusqlite::Error::QueryReturnedNoRows) => return Err(AppError::NotFound("Vendedor não encontrado".into())),
+        Err(e) => return Err(AppError::Database(e.to_string())),
+    };
+    
+    let data_inicio = inicio.format("%Y-%m-%d").to_string();
+    let data_fim = fim.format("%Y-%m-%d").to_string();
+    
+    let mut stmt = conn.prepare(
+        "SELECT c.id, c.vendedor_id, c.pedido_id, p.numero, c.conta_receber_id, c.tipo, c.base, c.valor,
+                c.data_competencia, c.conta_pagar_id, c.observacao, c.created_at
+         FROM comissoes c
+         LEFT JOIN pedidos_venda p ON p.id = c.pedido_id
+         WHERE c.vendedor_id = ?1 AND c.data_competencia BETWEEN ?2 AND ?3
+         ORDER BY c.data_competencia ASC, c.id ASC"
+    )?;
+    let lancamentos: Vec<Comissao> = stmt.query_map(params![vendedor_id, data_inicio, data_fim], |row| {
+        Ok(Comissao {
+            id: row.get(0)?,
+            vendedor_id: row.get(1)?,
+            pedido_id: row.get(2)?,
+            pedido_numero: row.get(3)?,
+            conta_receber_id: row.get(4)?,
+            tipo: row.get(5)?,
+            base: row.get(6)?,
+            valor: row.get(7)?,
+            data_competencia: row.get(8)?,
+            conta_pagar_id: row.get(9)?,
+            observacao: row.get(10)?,
+            created_at: row.get(11)?,
+        })
+    })?.collect::<Result<_, _>>()?;
+    
+    let total = lancamentos.iter().map(|c| c.valor).sum::<Dinheiro>();
+    let em_aberto = lancamentos.iter().filter(|c| c.conta_pagar_id.is_none()).map(|c| c.valor).sum::<Dinheiro>();
+    
+    Ok(ExtratoComissoes {
+        vendedor_id,
+        vendedor_nome,
+        data_inicio,
+        data_fim,
+        lancamentos,
+        total,
+        em_aberto,
+    })
+}
+
+fn regra(conn: &Connection, id: i64) -> AppResult<RegraComissao> {
+    let result = conn.query_row(
+        "SELECT r.id, r.vendedor_id, u.nome, r.categoria, r.tabela_preco_id, t.nome, r.percentual,
+                r.ativo, r.created_at, r.updated_at
+         FROM regras_comissao r
+         LEFT JOIN usuarios u ON u.id = r.vendedor_id
+         LEFT JOIN tabelas_preco t ON t.id = r.tabela_preco_id
+         WHERE r.id = ?1",
+        params![id],
+        map_regra,
+    );
+    
+    match result {
+        Ok(regra) => Ok(regra),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Err(AppError::NotFound("Regra de comissão não encontrada".into())),
+        Err(e) => Err(AppError::Database(e.to_string())),
+    }
+}
+
+fn map_regra(row: &rusqlite::Row) -> rusqlite::Result<RegraComissao> {
+    Ok(RegraComissao {
+        id: row.get(0)?,
+        vendedor_id: row.get(1)?,
+        vendedor_nome: row.get(2)?,
+        categoria: row.get(3)?,
+        tabela_preco_id: row.get(4)?,
+        tabela_preco_nome: row.get(5)?,
+        percentual: row.get(6)?,
+        ativo: row.get(7)?,
+        created_at: row.get(8)?,
+        updated_at: row.get(9)?,
+    })
+}
+
+/// Categoria sem espaços; vazia vale para qualquer categoria
+fn categoria(input: &RegraComissaoInput) -> Option<&str> {
+    input.categoria.as_deref().map(str::trim).filter(|c| !c.is_empty())
+}
+
+fn validar(conn: &Connection, input: &RegraComissaoInput, id: Option<i64>) -> AppResult<()> {
+    if !(0.0..=100.0).contains(&input.percentual) {
+        return Err(AppError::Validation("Percentual de comissão deve ficar entre 0% e 100%".into()));
+    }
+    if let Some(vendedor_id) = input.vendedor_id {
+        let vendedores: i64 = conn.query_row(
+            "SELECT COUNT(*) FROM usuarios WHERE id = ?1",
+            params![vendedor_id],
+            |row| row.get(0),
+        )?;
+        if vendedores == 0 {
+            return Err(AppError::Validation(format!("Vendedor {} não encontrado", vendedor_id)));
+        }
+    }
+    if let Some(tabela_id) = input.tabela_preco_id {
+        precos::tabela(conn, tabela_id)?;
+    }
+    
+    let repetidas: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM regras_comissao
+         WHERE ativo = 1 AND vendedor_id IS ?1 AND categoria IS ?2 AND tabela_preco_id IS ?3 AND id <> ?4",
+        params![input.vendedor_id, categoria(input), input.tabela_preco_id, id.unwrap_or(0)],
+        |row| row.get(0),
+    )?;
+    if repetidas > 0 {
+        return Err(AppError::Validation("Já existe uma regra de comissão para essa combinação".into()));
+    }
+    Ok(())
+}
+
+fn periodo(data_inicio: &str, data_fim: &str) -> AppResult<(NaiveDate, NaiveDate)> {
+    let inicio = parse_data(data_inicio)?;
+    let fim = parse_data(data_fim)?;
+    if fim < inicio {
+        return Err(AppError::Validation("Fim do período é anterior ao início".into()));
+    }
+    Ok((inicio, fim))
+}
+
+fn parse_data(data: &str) -> AppResult<NaiveDate> {
+    NaiveDate::parse_from_str(data, "%Y-%m-%d")
+        .map_err(|_| AppError::Validation(format!("Data inválida: '{}'", data)))
+}
@@ -1,11 +1,37 @@
 //! Comandos de Compras
+//!
+//! Fluxo: a requisição aprovada é cotada com um ou mais fornecedores (cada
+//! cotação leva os itens da requisição e recebe o preço de cada um); a
+//! aprovação da cotação vencedora gera o pedido de compra, que também passa
+//! por aprovação antes de ser recebido.
+//!
+//! - Requisição: pendente → aprovada → em_cotacao → atendida; rejeitada ou
+//!   cancelada antes de ser atendida.
+//! - Cotação: pendente → respondida → aprovada; as demais cotações da
+//!   requisição ficam recusadas. Cancelada antes da aprovação.
+//! - Pedido: pendente → aprovado → recebido_parcial → recebido; cancelado
+//!   antes do primeiro recebimento.
+//!
+//! O mapa de cotação compara os fornecedores item a item; a requisição pode
+//! ser adjudicada por inteiro a uma cotação ou dividida entre várias, com um
+//! pedido de compra por fornecedor.
+//!
+//! Cada recebimento dá entrada no estoque e gera as contas a pagar do valor
+//! recebido pela condição de pagamento do pedido.
 
-use tauri::AppHandle;
+use tauri::State;
 use rusqlite::{Connection, params};
 use log::info;
+use chrono::{NaiveDate, Utc};
 
-use crate::error::AppError;
-use crate::database::get_database_path;
+use crate::error::{AppError, AppResult};
+use crate::database::DatabaseState;
+use crate::context::RequestContext;
+use crate::auditoria;
+use crate::sequencias;
+use crate::parcelamento::{self, PRAZO_MAXIMO_DIAS};
+use crate::estoque;
+use crate::models::{Modulo, Acao, TipoDocumento, Dinheiro};
 
 #[derive(serde::Serialize, serde::Deserialize)]
 pub struct Requisicao {
@@ -17,6 +43,37 @@ pub struct Requisicao {
     pub data_necessidade: Option<String>,
     pub status: String,
     pub observacoes: Option<String>,
+    /// Motivo da rejeição ou do cancelamento
+    pub motivo: Option<String>,
+    pub aprovado_por: Option<i64>,
+    pub aprovado_em: Option<String>,
+    pub itens: Vec<RequisicaoItem>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct RequisicaoItem {
+    pub id: i64,
+    pub produto_id: i64,
+    pub produto_nome: Option<String>,
+    pub quantidade: f64,
+    pub observacoes: Option<String>,
+}
+
+/// Dados de uma requisição e dos seus itens
+#[derive(serde::Deserialize)]
+pub struct RequisicaoInput {
+    pub solicitante: Option<String>,
+    pub departamento: Option<String>,
+    pub data_necessidade: Option<String>,
+    pub observacoes: Option<String>,
+    pub itens: Vec<RequisicaoItemInput>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct RequisicaoItemInput {
+    pub produto_id: i64,
+    pub quantidade: f64,
+    pub observacoes: Option<String>,
 }
 
 #[derive(serde::Serialize, serde::Deserialize)]
@@ -25,10 +82,52 @@ pub struct Cotacao {
     pub numero: String,
     pub requisicao_id: Option<i64>,
     pub fornecedor_id: Option<i64>,
+    pub fornecedor_nome: Option<String>,
     pub data_cotacao: String,
     pub validade: Option<String>,
-    pub valor_total: f64,
+    /// Soma dos itens cotados, sem o frete
+    pub valor_total: Dinheiro,
     pub status: String,
+    pub condicao_pagamento_id: Option<i64>,
+    pub prazo_entrega_dias: Option<i64>,
+    pub frete: Dinheiro,
+    pub observacoes: Option<String>,
+    pub motivo: Option<String>,
+    pub aprovado_por: Option<i64>,
+    pub aprovado_em: Option<String>,
+    pub itens: Vec<CotacaoItem>,
+}
+
+/// Item da requisição na cotação; sem preço, o fornecedor não cotou
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct CotacaoItem {
+    pub id: i64,
+    pub requisicao_item_id: i64,
+    pub produto_id: i64,
+    pub produto_nome: Option<String>,
+    pub quantidade: f64,
+    pub preco_unitario: Option<Dinheiro>,
+    pub total: Option<Dinheiro>,
+}
+
+/// Resposta do fornecedor a uma cotação
+///
+/// Itens fora de `precos` mantêm o preço já informado.
+#[derive(serde::Deserialize)]
+pub struct RespostaCotacaoInput {
+    pub validade: Option<String>,
+    pub condicao_pagamento_id: Option<i64>,
+    pub prazo_entrega_dias: Option<i64>,
+    pub frete: Option<Dinheiro>,
+    pub observacoes: Option<String>,
+    pub precos: Vec<PrecoCotacaoInput>,
+}
+
+/// Preço de um item da cotação; `None` marca o item como não cotado
+#[derive(serde::Deserialize)]
+pub struct PrecoCotacaoInput {
+    pub item_id: i64,
+    pub preco_unitario: Option<Dinheiro>,
 }
 
 #[derive(serde::Serialize, serde::Deserialize)]
@@ -37,233 +136,1676 @@ pub struct PedidoCompra {
     pub numero: String,
     pub fornecedor_id: Option<i64>,
     pub fornecedor_nome: Option<String>,
+    pub cotacao_id: Option<i64>,
+    pub requisicao_id: Option<i64>,
     pub data_pedido: String,
     pub data_entrega: Option<String>,
-    pub valor_total: f64,
+    pub data_recebimento: Option<String>,
+    /// Itens mais frete
+    pub valor_total: Dinheiro,
+    pub frete: Dinheiro,
     pub status: String,
+    pub condicao_pagamento_id: Option<i64>,
+    pub observacoes: Option<String>,
+    pub motivo: Option<String>,
+    pub aprovado_por: Option<i64>,
+    pub aprovado_em: Option<String>,
+    pub itens: Vec<PedidoCompraItem>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct PedidoCompraItem {
+    pub id: i64,
+    pub cotacao_item_id: Option<i64>,
+    pub produto_id: i64,
+    pub produto_nome: Option<String>,
+    pub quantidade: f64,
+    pub quantidade_recebida: f64,
+    pub preco_unitario: Dinheiro,
+    pub total: Dinheiro,
+}
+
+/// Pedido de compra avulso, sem cotação
+#[derive(serde::Deserialize)]
+pub struct PedidoCompraInput {
+    pub fornecedor_id: i64,
+    pub data_entrega: Option<String>,
+    pub condicao_pagamento_id: Option<i64>,
+    pub frete: Option<Dinheiro>,
+    pub observacoes: Option<String>,
+    pub itens: Vec<PedidoCompraItemInput>,
 }
 
+#[derive(serde::Deserialize)]
+pub struct PedidoCompraItemInput {
+    pub produto_id: i64,
+    pub quantidade: f64,
+    pub preco_unitario: Dinheiro,
+}
+
+#[derive(serde::Deserialize)]
+pub struct RecebimentoItemInput {
+    pub item_id: i64,
+    pub quantidade: f64,
+}
+
+/// Mapa de cotação: fornecedores × itens da requisição
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct MapaCotacao {
+    pub requisicao_id: i64,
+    pub requisicao_numero: String,
+    pub requisicao_status: String,
+    /// Colunas do mapa, uma por cotação
+    pub fornecedores: Vec<MapaFornecedor>,
+    /// Linhas do mapa; `precos` segue a ordem de `fornecedores`
+    pub itens: Vec<MapaItem>,
+    /// Cotação completa de menor total, frete incluído
+    pub melhor_cotacao_id: Option<i64>,
+    /// Total comprando cada item pelo melhor preço, mais o frete de cada
+    /// fornecedor envolvido; `None` se algum item não tem preço válido
+    pub total_melhor_por_item: Option<Dinheiro>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct MapaFornecedor {
+    pub cotacao_id: i64,
+    pub cotacao_numero: String,
+    pub fornecedor_id: Option<i64>,
+    pub fornecedor_nome: Option<String>,
+    pub status: String,
+    pub validade: Option<String>,
+    pub vencida: bool,
+    pub prazo_entrega_dias: Option<i64>,
+    pub condicao_pagamento_id: Option<i64>,
+    pub condicao_pagamento: Option<String>,
+    pub frete: Dinheiro,
+    pub itens_cotados: usize,
+    /// Itens mais frete; `None` se a cotação não cobre todos os itens
+    pub valor_total: Option<Dinheiro>,
+    pub melhor_total: bool,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct MapaItem {
+    pub requisicao_item_id: i64,
+    pub produto_id: i64,
+    pub produto_nome: Option<String>,
+    pub quantidade: f64,
+    pub precos: Vec<MapaPreco>,
+    pub melhor_cotacao_id: Option<i64>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct MapaPreco {
+    pub cotacao_id: i64,
+    pub cotacao_item_id: Option<i64>,
+    pub preco_unitario: Option<Dinheiro>,
+    pub total: Option<Dinheiro>,
+    pub melhor: bool,
+}
+
+/// Item da requisição e a cotação que vai atendê-lo
+#[derive(serde::Deserialize)]
+pub struct AdjudicacaoInput {
+    pub requisicao_item_id: i64,
+    pub cotacao_id: i64,
+}
+
+/// Documento da cadeia de compras
+#[derive(Clone, Copy)]
+enum Documento {
+    Requisicao,
+    Cotacao,
+    Pedido,
+}
+
+impl Documento {
+    fn tabela(self) -> &'static str {
+        match self {
+            Documento::Requisicao => "requisicoes_compra",
+            Documento::Cotacao => "cotacoes_compra",
+            Documento::Pedido => "pedidos_compra",
+        }
+    }
+    
+    fn nome(self) -> &'static str {
+        match self {
+            Documento::Requisicao => "Requisição",
+            Documento::Cotacao => "Cotação",
+            Documento::Pedido => "Pedido de compra",
+        }
+    }
+    
+    fn nao_encontrado(self) -> AppError {
+        match self {
+            Documento::Pedido => AppError::NotFound("Pedido de compra não encontrado".into()),
+            _ => AppError::NotFound(format!("{} não encontrada", self.nome())),
+        }
+    }
+}
+
+/// Mudança de situação permitida
+struct Transicao {
+    documento: Documento,
+    de: &'static [&'static str],
+    para: &'static str,
+    /// Ação registrada na auditoria
+    acao: &'static str,
+}
+
+const APROVAR_REQUISICAO: Transicao = Transicao {
+    documento: Documento::Requisicao, de: &["pendente"], para: "aprovada", acao: "aprovar",
+};
+const REJEITAR_REQUISICAO: Transicao = Transicao {
+    documento: Documento::Requisicao, de: &["pendente"], para: "rejeitada", acao: "rejeitar",
+};
+const COTAR_REQUISICAO: Transicao = Transicao {
+    documento: Documento::Requisicao, de: &["aprovada"], para: "em_cotacao", acao: "cotar",
+};
+const ATENDER_REQUISICAO: Transicao = Transicao {
+    documento: Documento::Requisicao, de: &["em_cotacao"], para: "atendida", acao: "atender",
+};
+const CANCELAR_REQUISICAO: Transicao = Transicao {
+    documento: Documento::Requisicao, de: &["pendente", "aprovada", "em_cotacao"], para: "cancelada", acao: "cancelar",
+};
+const RESPONDER_COTACAO: Transicao = Transicao {
+    documento: Documento::Cotacao, de: &["pendente", "respondida"], para: "respondida", acao: "responder",
+};
+const APROVAR_COTACAO: Transicao = Transicao {
+    documento: Documento::Cotacao, de: &["respondida"], para: "aprovada", acao: "aprovar",
+};
+const RECUSAR_COTACAO: Transicao = Transicao {
+    documento: Documento::Cotacao, de: &["pendente", "respondida"], para: "recusada", acao: "recusar",
+};
+const CANCELAR_COTACAO: Transicao = Transicao {
+    documento: Documento::Cotacao, de: &["pendente", "respondida"], para: "cancelada", acao: "cancelar",
+};
+const APROVAR_PEDIDO: Transicao = Transicao {
+    documento: Documento::Pedido, de: &["pendente"], para: "aprovado", acao: "aprovar",
+};
+const RECEBER_PARTE_PEDIDO: Transicao = Transicao {
+    documento: Documento::Pedido, de: &["aprovado", "recebido_parcial"], para: "recebido_parcial", acao: "receber",
+};
+const RECEBER_PEDIDO: Transicao = Transicao {
+    documento: Documento::Pedido, de: &["aprovado", "recebido_parcial"], para: "recebido", acao: "receber",
+};
+const CANCELAR_PEDIDO: Transicao = Transicao {
+    documento: Documento::Pedido, de: &["pendente", "aprovado"], para: "cancelado", acao: "cancelar",
+};
+
+const SELECT_REQUISICAO: &str =
+    "SELECT id, numero, solicitante, departamento, data_solicitacao, data_necessidade, status, observacoes,
+            motivo, aprovado_por, aprovado_em
+     FROM requisicoes_compra";
+
+const SELECT_COTACAO: &str =
+    "SELECT c.id, c.numero, c.requisicao_id, c.fornecedor_id, f.nome, c.data_cotacao, c.validade, c.valor_total,
+            c.status, c.condicao_pagamento_id, c.prazo_entrega_dias, c.observacoes, c.motivo, c.aprovado_por,
+            c.aprovado_em, c.frete
+     FROM cotacoes_compra c
+     LEFT JOIN fornecedores f ON f.id = c.fornecedor_id";
+
+const SELECT_PEDIDO: &str =
+    "SELECT pc.id, pc.numero, pc.fornecedor_id, f.nome, pc.cotacao_id, pc.requisicao_id, pc.data_pedido,
+            pc.data_entrega, pc.data_recebimento, pc.valor_total, pc.status, pc.condicao_pagamento_id,
+            pc.observacoes, pc.motivo, pc.aprovado_por, pc.aprovado_em, pc.frete
+     FROM pedidos_compra pc
+     LEFT JOIN fornecedores f ON f.id = pc.fornecedor_id";
+
 #[tauri::command]
-pub async fn get_requisicoes(app: AppHandle) -> Result<Vec<Requisicao>, AppError> {
-    let db_path = get_database_path(&app)?;
-    let conn = Connection::open(&db_path)?;
-    
-    // Criar tabela se não existir
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS requisicoes_compra (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            numero TEXT UNIQUE,
-            solicitante TEXT,
-            departamento TEXT,
-            data_solicitacao TEXT DEFAULT (date('now')),
-            data_necessidade TEXT,
-            status TEXT DEFAULT 'pendente',
-            observacoes TEXT,
-            created_at TEXT DEFAULT (datetime('now'))
-        )", []
-    )?;
+pub async fn get_requisicoes(
+    db: State<'_, DatabaseState>,
+    token: String,
+    status: Option<String>,
+) -> Result<Vec<Requisicao>, AppError> {
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require(Modulo::Compras, Acao::Ler)?;
     
-    let mut stmt = conn.prepare(
-        "SELECT id, numero, solicitante, departamento, data_solicitacao, data_necessidade, status, observacoes
-         FROM requisicoes_compra ORDER BY created_at DESC"
-    )?;
+    let conn = db.reader()?;
     
-    let requisicoes = stmt.query_map([], |row| {
-        Ok(Requisicao {
-            id: row.get(0)?,
-            numero: row.get(1)?,
-            solicitante: row.get(2)?,
-            departamento: row.get(3)?,
-            data_solicitacao: row.get(4)?,
-            data_necessidade: row.get(5)?,
-            status: row.get(6)?,
-            observacoes: row.get(7)?,
-        })
-    })?.filter_map(|r| r.ok()).collect();
+    let mut stmt = conn.prepare(&format!(
+        "{} WHERE (?1 IS NULL OR status = ?1) ORDER BY created_at DESC",
+        SELECT_REQUISICAO
+    ))?;
+    let mut requisicoes: Vec<Requisicao> = stmt.query_map(params![status], map_requisicao)?
+        .collect::<Result<_, _>>()?;
+    
+    for requisicao in &mut requisicoes {
+        requisicao.itens = itens_requisicao(&conn, requisicao.id)?;
+    }
     
     Ok(requisicoes)
 }
 
+#[tauri::command]
+pub async fn get_requisicao(db: State<'_, DatabaseState>, token: String, id: i64) -> Result<Requisicao, AppError> {
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require(Modulo::Compras, Acao::Ler)?;
+    
+    let conn = db.reader()?;
+    fetch_requisicao(&conn, id)
+}
+
 #[tauri::command]
 pub async fn create_requisicao(
-    app: AppHandle,
-    solicitante: Option<String>,
-    departamento: Option<String>,
-    data_necessidade: Option<String>,
-    observacoes: Option<String>,
+    db: State<'_, DatabaseState>,
+    token: String,
+    input: RequisicaoInput,
 ) -> Result<Requisicao, AppError> {
-    let db_path = get_database_path(&app)?;
-    let conn = Connection::open(&db_path)?;
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require(Modulo::Compras, Acao::Criar)?;
     
-    let count: i64 = conn.query_row("SELECT COUNT(*) + 1 FROM requisicoes_compra", [], |row| row.get(0))?;
-    let numero = format!("RC{:06}", count);
+    db.with_transaction(|tx| {
+        let numero = sequencias::proximo(tx, TipoDocumento::RequisicaoCompra, "")?.formatado;
+        
+        tx.execute(
+            "INSERT INTO requisicoes_compra (numero, solicitante, departamento, data_necessidade, observacoes)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![numero, input.solicitante, input.departamento, input.data_necessidade, input.observacoes],
+        )?;
+        
+        let id = tx.last_insert_rowid();
+        gravar_itens_requisicao(tx, id, &input.itens)?;
+        auditoria::novo("requisicoes_compra", id).registrar(tx, &ctx, Modulo::Compras, "criar")?;
+        
+        info!("✅ Requisição de compra criada: {} (ID: {})", numero, id);
+        
+        fetch_requisicao(tx, id)
+    })
+}
+
+/// Altera a requisição ainda pendente, substituindo os itens
+#[tauri::command]
+pub async fn update_requisicao(
+    db: State<'_, DatabaseState>,
+    token: String,
+    id: i64,
+    input: RequisicaoInput,
+) -> Result<Requisicao, AppError> {
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require(Modulo::Compras, Acao::Editar)?;
     
-    conn.execute(
-        "INSERT INTO requisicoes_compra (numero, solicitante, departamento, data_necessidade, observacoes)
-         VALUES (?1, ?2, ?3, ?4, ?5)",
-        params![numero, solicitante, departamento, data_necessidade, observacoes],
-    )?;
+    db.with_transaction(|tx| {
+        let atual = status(tx, Documento::Requisicao, id)?;
+        if atual != "pendente" {
+            return Err(AppError::Validation(format!("Requisição na situação '{}' não pode ser alterada", atual)));
+        }
+        
+        let captura = auditoria::capturar(tx, "requisicoes_compra", id)?;
+        
+        tx.execute(
+            "UPDATE requisicoes_compra SET solicitante = ?1, departamento = ?2, data_necessidade = ?3,
+                    observacoes = ?4, updated_at = datetime('now')
+             WHERE id = ?5",
+            params![input.solicitante, input.departamento, input.data_necessidade, input.observacoes, id],
+        )?;
+        tx.execute("DELETE FROM requisicao_itens WHERE requisicao_id = ?1", params![id])?;
+        gravar_itens_requisicao(tx, id, &input.itens)?;
+        
+        captura.registrar(tx, &ctx, Modulo::Compras, "editar")?;
+        auditoria::registrar_evento(
+            tx, &ctx, Modulo::Compras, "requisicao_itens", &id.to_string(), "substituir",
+            serde_json::to_value(&input.itens)?,
+        )?;
+        
+        fetch_requisicao(tx, id)
+    })
+}
+
+#[tauri::command]
+pub async fn aprovar_requisicao(db: State<'_, DatabaseState>, token: String, id: i64) -> Result<Requisicao, AppError> {
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require(Modulo::Compras, Acao::Aprovar)?;
     
-    let id = conn.last_insert_rowid();
+    db.with_transaction(|tx| {
+        transicionar(tx, &ctx, id, &APROVAR_REQUISICAO, || registrar_aprovacao(tx, &ctx, Documento::Requisicao, id))?;
+        fetch_requisicao(tx, id)
+    })
+}
+
+#[tauri::command]
+pub async fn rejeitar_requisicao(
+    db: State<'_, DatabaseState>,
+    token: String,
+    id: i64,
+    motivo: String,
+) -> Result<Requisicao, AppError> {
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require(Modulo::Compras, Acao::Aprovar)?;
     
-    Ok(Requisicao {
-        id,
-        numero,
-        solicitante,
-        departamento,
-        data_solicitacao: chrono::Utc::now().format("%Y-%m-%d").to_string(),
-        data_necessidade,
-        status: "pendente".to_string(),
-        observacoes,
+    let motivo = exigir_motivo(&motivo)?;
+    db.with_transaction(|tx| {
+        transicionar(tx, &ctx, id, &REJEITAR_REQUISICAO, || gravar_motivo(tx, Documento::Requisicao, id, motivo))?;
+        fetch_requisicao(tx, id)
     })
 }
 
+/// Cancela a requisição e as cotações dela ainda em andamento
 #[tauri::command]
-pub async fn get_cotacoes(app: AppHandle) -> Result<Vec<Cotacao>, AppError> {
-    let db_path = get_database_path(&app)?;
-    let conn = Connection::open(&db_path)?;
-    
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS cotacoes_compra (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            numero TEXT UNIQUE,
-            requisicao_id INTEGER,
-            fornecedor_id INTEGER,
-            data_cotacao TEXT DEFAULT (date('now')),
-            validade TEXT,
-            valor_total REAL DEFAULT 0,
-            status TEXT DEFAULT 'pendente',
-            created_at TEXT DEFAULT (datetime('now'))
-        )", []
-    )?;
+pub async fn cancelar_requisicao(
+    db: State<'_, DatabaseState>,
+    token: String,
+    id: i64,
+    motivo: String,
+) -> Result<Requisicao, AppError> {
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require(Modulo::Compras, Acao::Editar)?;
     
-    let mut stmt = conn.prepare(
-        "SELECT id, numero, requisicao_id, fornecedor_id, data_cotacao, validade, valor_total, status
-         FROM cotacoes_compra ORDER BY created_at DESC"
-    )?;
+    let motivo = exigir_motivo(&motivo)?;
+    db.with_transaction(|tx| {
+        transicionar(tx, &ctx, id, &CANCELAR_REQUISICAO, || {
+            for cotacao_id in cotacoes_em_andamento(tx, id)? {
+                transicionar(tx, &ctx, cotacao_id, &CANCELAR_COTACAO, || {
+                    gravar_motivo(tx, Documento::Cotacao, cotacao_id, "Requisição cancelada")
+                })?;
+            }
+            gravar_motivo(tx, Documento::Requisicao, id, motivo)
+        })?;
+        fetch_requisicao(tx, id)
+    })
+}
+
+#[tauri::command]
+pub async fn get_cotacoes(
+    db: State<'_, DatabaseState>,
+    token: String,
+    requisicao_id: Option<i64>,
+) -> Result<Vec<Cotacao>, AppError> {
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require(Modulo::Compras, Acao::Ler)?;
     
-    let cotacoes = stmt.query_map([], |row| {
-        Ok(Cotacao {
-            id: row.get(0)?,
-            numero: row.get(1)?,
-            requisicao_id: row.get(2)?,
-            fornecedor_id: row.get(3)?,
-            data_cotacao: row.get(4)?,
-            validade: row.get(5)?,
-            valor_total: row.get(6)?,
-            status: row.get(7)?,
-        })
-    })?.filter_map(|r| r.ok()).collect();
+    let conn = db.reader()?;
+    
+    let mut stmt = conn.prepare(&format!(
+        "{} WHERE (?1 IS NULL OR c.requisicao_id = ?1) ORDER BY c.created_at DESC",
+        SELECT_COTACAO
+    ))?;
+    let mut cotacoes: Vec<Cotacao> = stmt.query_map(params![requisicao_id], map_cotacao)?
+        .collect::<Result<_, _>>()?;
+    
+    for cotacao in &mut cotacoes {
+        cotacao.itens = itens_cotacao(&conn, cotacao.id)?;
+    }
     
     Ok(cotacoes)
 }
 
+#[tauri::command]
+pub async fn get_cotacao(db: State<'_, DatabaseState>, token: String, id: i64) -> Result<Cotacao, AppError> {
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require(Modulo::Compras, Acao::Ler)?;
+    
+    let conn = db.reader()?;
+    fetch_cotacao(&conn, id)
+}
+
+/// Abre a cotação da requisição aprovada com um fornecedor
+///
+/// A cotação leva todos os itens da requisição, ainda sem preço. Cada
+/// fornecedor tem uma cotação por requisição.
 #[tauri::command]
 pub async fn create_cotacao(
-    app: AppHandle,
-    requisicao_id: Option<i64>,
-    fornecedor_id: Option<i64>,
-    valor_total: Option<f64>,
+    db: State<'_, DatabaseState>,
+    token: String,
+    requisicao_id: i64,
+    fornecedor_id: i64,
+    validade: Option<String>,
+) -> Result<Cotacao, AppError> {
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require(Modulo::Compras, Acao::Criar)?;
+    
+    let validade = validade.as_deref().map(|data| parse_data(data, "Validade")).transpose()?;
+    
+    db.with_transaction(|tx| {
+        let situacao = status(tx, Documento::Requisicao, requisicao_id)?;
+        if situacao != "aprovada" && situacao != "em_cotacao" {
+            return Err(AppError::Validation(format!(
+                "Requisição na situação '{}' não pode ser cotada; aprove a requisição antes", situacao
+            )));
+        }
+        
+        exigir_fornecedor_ativo(tx, fornecedor_id)?;
+        
+        let existentes: i64 = tx.query_row(
+            "SELECT COUNT(*) FROM cotacoes_compra
+             WHERE requisicao_id = ?1 AND fornecedor_id = ?2 AND status <> 'cancelada'",
+            params![requisicao_id, fornecedor_id],
+            |row| row.get(0),
+        )?;
+        if existentes > 0 {
+            return Err(AppError::Validation("O fornecedor já tem cotação para esta requisição".into()));
+        }
+        
+        let numero = sequencias::proximo(tx, TipoDocumento::CotacaoCompra, "")?.formatado;
+        
+        tx.execute(
+            "INSERT INTO cotacoes_compra (numero, requisicao_id, fornecedor_id, validade, valor_total)
+             VALUES (?1, ?2, ?3, ?4, 0)",
+            params![numero, requisicao_id, fornecedor_id, validade.map(|d| d.format("%Y-%m-%d").to_string())],
+        )?;
+        
+        let id = tx.last_insert_rowid();
+        tx.execute(
+            "INSERT INTO cotacao_itens (cotacao_id, requisicao_item_id, produto_id, quantidade)
+             SELECT ?1, id, produto_id, quantidade FROM requisicao_itens WHERE requisicao_id = ?2 ORDER BY id",
+            params![id, requisicao_id],
+        )?;
+        
+        auditoria::novo("cotacoes_compra", id).registrar(tx, &ctx, Modulo::Compras, "criar")?;
+        
+        if situacao == "aprovada" {
+            transicionar(tx, &ctx, requisicao_id, &COTAR_REQUISICAO, || Ok(()))?;
+        }
+        
+        info!("✅ Cotação criada: {} (ID: {}) para a requisição {}", numero, id, requisicao_id);
+        
+        fetch_cotacao(tx, id)
+    })
+}
+
+/// Registra a resposta do fornecedor: preços, prazo e condição de pagamento
+#[tauri::command]
+pub async fn responder_cotacao(
+    db: State<'_, DatabaseState>,
+    token: String,
+    id: i64,
+    input: RespostaCotacaoInput,
+) -> Result<Cotacao, AppError> {
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require(Modulo::Compras, Acao::Editar)?;
+    
+    let validade = input.validade.as_deref().map(|data| parse_data(data, "Validade")).transpose()?;
+    if input.prazo_entrega_dias.is_some_and(|dias| !(0..=PRAZO_MAXIMO_DIAS).contains(&dias)) {
+        return Err(AppError::Validation(format!("Prazo de entrega deve ficar entre 0 e {} dias", PRAZO_MAXIMO_DIAS)));
+    }
+    if input.frete.is_some_and(|frete| frete.is_negative()) {
+        return Err(AppError::Validation("Frete não pode ser negativo".into()));
+    }
+    if input.precos.iter().any(|p| p.preco_unitario.is_some_and(|preco| preco.is_negative())) {
+        return Err(AppError::Validation("Preço unitário não pode ser negativo".into()));
+    }
+    
+    db.with_transaction(|tx| {
+        if let Some(condicao_id) = input.condicao_pagamento_id {
+            parcelamento::condicao_ativa(tx, condicao_id)?;
+        }
+        
+        let itens = itens_cotacao(tx, id)?;
+        
+        transicionar(tx, &ctx, id, &RESPONDER_COTACAO, || {
+            for preco in &input.precos {
+                let item = itens.iter().find(|i| i.id == preco.item_id)
+                    .ok_or_else(|| AppError::Validation(format!("Item {} não pertence à cotação", preco.item_id)))?;
+                let total = preco.preco_unitario.map(|p| p.vezes(item.quantidade)).transpose()?;
+                tx.execute(
+                    "UPDATE cotacao_itens SET preco_unitario = ?1, total = ?2 WHERE id = ?3",
+                    params![preco.preco_unitario, total, item.id],
+                )?;
+            }
+            
+            tx.execute(
+                "UPDATE cotacoes_compra SET
+                    validade = COALESCE(?1, validade), condicao_pagamento_id = ?2, prazo_entrega_dias = ?3,
+                    frete = ?4, observacoes = ?5,
+                    valor_total = (SELECT COALESCE(SUM(total), 0) FROM cotacao_itens WHERE cotacao_id = ?6)
+                 WHERE id = ?6",
+                params![
+                    validade.map(|d| d.format("%Y-%m-%d").to_string()), input.condicao_pagamento_id,
+                    input.prazo_entrega_dias, input.frete.unwrap_or_default(), input.observacoes, id
+                ],
+            )?;
+            Ok(())
+        })?;
+        
+        fetch_cotacao(tx, id)
+    })
+}
+
+/// Aprova a cotação vencedora e gera o pedido de compra
+///
+/// Todos os itens precisam estar cotados e a cotação dentro da validade.
+/// As demais cotações da requisição são recusadas e a requisição fica
+/// atendida. O pedido nasce pendente de aprovação.
+#[tauri::command]
+pub async fn aprovar_cotacao(db: State<'_, DatabaseState>, token: String, id: i64) -> Result<PedidoCompra, AppError> {
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require(Modulo::Compras, Acao::Aprovar)?;
+    
+    db.with_transaction(|tx| {
+        let cotacao = fetch_cotacao(tx, id)?;
+        if cotacao.itens.is_empty() {
+            return Err(AppError::Validation("Cotação sem itens".into()));
+        }
+        if cotacao.itens.iter().any(|i| i.preco_unitario.is_none()) {
+            return Err(AppError::Validation("Cotação tem itens sem preço; só cotações completas podem ser aprovadas".into()));
+        }
+        
+        let itens: Vec<&CotacaoItem> = cotacao.itens.iter().collect();
+        let pedido_id = adjudicar(tx, &ctx, &cotacao, &itens)?;
+        
+        if let Some(requisicao_id) = cotacao.requisicao_id {
+            encerrar_cotacoes(tx, &ctx, requisicao_id)?;
+        }
+        
+        fetch_pedido(tx, pedido_id)
+    })
+}
+
+#[tauri::command]
+pub async fn cancelar_cotacao(
+    db: State<'_, DatabaseState>,
+    token: String,
+    id: i64,
+    motivo: String,
 ) -> Result<Cotacao, AppError> {
-    let db_path = get_database_path(&app)?;
-    let conn = Connection::open(&db_path)?;
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require(Modulo::Compras, Acao::Editar)?;
+    
+    let motivo = exigir_motivo(&motivo)?;
+    db.with_transaction(|tx| {
+        transicionar(tx, &ctx, id, &CANCELAR_COTACAO, || gravar_motivo(tx, Documento::Cotacao, id, motivo))?;
+        fetch_cotacao(tx, id)
+    })
+}
+
+/// Mapa de cotação da requisição: fornecedores × itens
+///
+/// Traz todas as cotações não canceladas. Os destaques de melhor preço por
+/// item e de melhor total consideram só as cotações respondidas e dentro da
+/// validade; no empate, vence o menor prazo de entrega.
+#[tauri::command]
+pub async fn get_mapa_cotacao(
+    db: State<'_, DatabaseState>,
+    token: String,
+    requisicao_id: i64,
+) -> Result<MapaCotacao, AppError> {
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require(Modulo::Compras, Acao::Ler)?;
     
-    let count: i64 = conn.query_row("SELECT COUNT(*) + 1 FROM cotacoes_compra", [], |row| row.get(0))?;
-    let numero = format!("COT{:06}", count);
+    let conn = db.reader()?;
+    mapa(&conn, requisicao_id)
+}
+
+/// Adjudica cada item da requisição a um fornecedor (compra dividida)
+///
+/// Todos os itens da requisição precisam ser adjudicados, cada um a uma
+/// única cotação. Cada cotação escolhida é aprovada e gera um pedido de
+/// compra só com os seus itens e com o frete dela; as demais são recusadas
+/// e a requisição fica atendida.
+#[tauri::command]
+pub async fn adjudicar_cotacoes(
+    db: State<'_, DatabaseState>,
+    token: String,
+    requisicao_id: i64,
+    itens: Vec<AdjudicacaoInput>,
+) -> Result<Vec<PedidoCompra>, AppError> {
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require(Modulo::Compras, Acao::Aprovar)?;
     
-    conn.execute(
-        "INSERT INTO cotacoes_compra (numero, requisicao_id, fornecedor_id, valor_total)
-         VALUES (?1, ?2, ?3, ?4)",
-        params![numero, requisicao_id, fornecedor_id, valor_total.unwrap_or(0.0)],
+    db.with_transaction(|tx| {
+        let situacao = status(tx, Documento::Requisicao, requisicao_id)?;
+        if situacao != "em_cotacao" {
+            return Err(AppError::Validation(format!("Requisição na situação '{}' não pode ser adjudicada", situacao)));
+        }
+        
+        let requisicao_itens = itens_requisicao(tx, requisicao_id)?;
+        for item in &requisicao_itens {
+            if itens.iter().filter(|a| a.requisicao_item_id == item.id).count() != 1 {
+                return Err(AppError::Validation(format!(
+                    "Item {} da requisição deve ser adjudicado a exatamente um fornecedor", item.id
+                )));
+            }
+        }
+        if itens.len() != requisicao_itens.len() {
+            return Err(AppError::Validation("Adjudicação tem itens que não são da requisição".into()));
+        }
+        
+        let mut cotacao_ids: Vec<i64> = Vec::new();
+        for adjudicacao in &itens {
+            if !cotacao_ids.contains(&adjudicacao.cotacao_id) {
+                cotacao_ids.push(adjudicacao.cotacao_id);
+            }
+        }
+        
+        let mut pedidos = Vec::with_capacity(cotacao_ids.len());
+        for cotacao_id in cotacao_ids {
+            let cotacao = fetch_cotacao(tx, cotacao_id)?;
+            if cotacao.requisicao_id != Some(requisicao_id) {
+                return Err(AppError::Validation(format!("Cotação {} não é desta requisição", cotacao.numero)));
+            }
+            
+            let escolhidos = itens.iter()
+                .filter(|a| a.cotacao_id == cotacao_id)
+                .map(|a| {
+                    cotacao.itens.iter()
+                        .find(|i| i.requisicao_item_id == a.requisicao_item_id && i.preco_unitario.is_some())
+                        .ok_or_else(|| AppError::Validation(format!(
+                            "Cotação {} não tem preço para o item {} da requisição", cotacao.numero, a.requisicao_item_id
+                        )))
+                })
+                .collect::<AppResult<Vec<_>>>()?;
+            
+            pedidos.push(adjudicar(tx, &ctx, &cotacao, &escolhidos)?);
+        }
+        
+        encerrar_cotacoes(tx, &ctx, requisicao_id)?;
+        
+        info!("✅ Requisição {} adjudicada em {} pedido(s) de compra", requisicao_id, pedidos.len());
+        
+        pedidos.into_iter().map(|id| fetch_pedido(tx, id)).collect()
+    })
+}
+
+#[tauri::command]
+pub async fn get_pedidos_compra(
+    db: State<'_, DatabaseState>,
+    token: String,
+    status: Option<String>,
+) -> Result<Vec<PedidoCompra>, AppError> {
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require(Modulo::Compras, Acao::Ler)?;
+    
+    let conn = db.reader()?;
+    
+    let mut stmt = conn.prepare(&format!(
+        "{} WHERE (?1 IS NULL OR pc.status = ?1) ORDER BY pc.created_at DESC",
+        SELECT_PEDIDO
+    ))?;
+    let mut pedidos: Vec<PedidoCompra> = stmt.query_map(params![status], map_pedido)?
+        .collect::<Result<_, _>>()?;
+    
+    for pedido in &mut pedidos {
+        pedido.itens = itens_pedido(&conn, pedido.id)?;
+    }
+    
+    Ok(pedidos)
+}
+
+#[tauri::command]
+pub async fn get_pedido_compra(db: State<'_, DatabaseState>, token: String, id: i64) -> Result<PedidoCompra, AppError> {
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require(Modulo::Compras, Acao::Ler)?;
+    
+    let conn = db.reader()?;
+    fetch_pedido(&conn, id)
+}
+
+/// Pedido de compra avulso, sem cotação, pendente de aprovação
+#[tauri::command]
+pub async fn create_pedido_compra(
+    db: State<'_, DatabaseState>,
+    token: String,
+    pedido: PedidoCompraInput,
+) -> Result<PedidoCompra, AppError> {
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require(Modulo::Compras, Acao::Criar)?;
+    
+    let data_entrega = pedido.data_entrega.as_deref().map(|data| parse_data(data, "Data de entrega")).transpose()?;
+    let frete = pedido.frete.unwrap_or_default();
+    if frete.is_negative() {
+        return Err(AppError::Validation("Frete não pode ser negativo".into()));
+    }
+    if pedido.itens.is_empty() {
+        return Err(AppError::Validation("Pedido de compra sem itens".into()));
+    }
+    for item in &pedido.itens {
+        if !item.quantidade.is_finite() || item.quantidade <= 0.0 {
+            return Err(AppError::Validation("Quantidade deve ser maior que zero".into()));
+        }
+        if item.preco_unitario.is_negative() {
+            return Err(AppError::Validation("Preço unitário não pode ser negativo".into()));
+        }
+    }
+    
+    db.with_transaction(|tx| {
+        exigir_fornecedor_ativo(tx, pedido.fornecedor_id)?;
+        if let Some(id) = pedido.condicao_pagamento_id {
+            parcelamento::condicao_ativa(tx, id)?;
+        }
+        for item in &pedido.itens {
+            exigir_produto_ativo(tx, item.produto_id)?;
+        }
+        
+        let numero = sequencias::proximo(tx, TipoDocumento::PedidoCompra, "")?.formatado;
+        let total = pedido.itens.iter().try_fold(frete, |total, i| total.somar(i.preco_unitario.vezes(i.quantidade)?))?;
+        
+        tx.execute(
+            "INSERT INTO pedidos_compra (numero, fornecedor_id, data_entrega, valor_total, frete, condicao_pagamento_id,
+                                         observacoes, status)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, 'pendente')",
+            params![
+                numero, pedido.fornecedor_id, data_entrega.map(|d| d.format("%Y-%m-%d").to_string()), total, frete,
+                pedido.condicao_pagamento_id, pedido.observacoes
+            ],
+        )?;
+        
+        let id = tx.last_insert_rowid();
+        for item in &pedido.itens {
+            inserir_item_pedido(tx, id, None, item.produto_id, item.quantidade, item.preco_unitario)?;
+        }
+        
+        auditoria::novo("pedidos_compra", id).registrar(tx, &ctx, Modulo::Compras, "criar")?;
+        
+        info!("✅ Pedido de compra avulso criado: {} (ID: {}) - R$ {}", numero, id, total);
+        
+        fetch_pedido(tx, id)
+    })
+}
+
+#[tauri::command]
+pub async fn aprovar_pedido_compra(db: State<'_, DatabaseState>, token: String, id: i64) -> Result<PedidoCompra, AppError> {
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require(Modulo::Compras, Acao::Aprovar)?;
+    
+    db.with_transaction(|tx| {
+        transicionar(tx, &ctx, id, &APROVAR_PEDIDO, || registrar_aprovacao(tx, &ctx, Documento::Pedido, id))?;
+        fetch_pedido(tx, id)
+    })
+}
+
+/// Recebe itens do pedido aprovado em `data_recebimento` (hoje, se omitida)
+///
+/// Sem `itens`, recebe tudo o que falta. Os produtos entram no estoque pelo
+/// preço do pedido e o valor recebido (com o frete, no primeiro recebimento)
+/// vira contas a pagar, parceladas pela condição de pagamento a partir da
+/// data do recebimento.
+#[tauri::command]
+pub async fn receber_pedido_compra(
+    db: State<'_, DatabaseState>,
+    token: String,
+    id: i64,
+    itens: Option<Vec<RecebimentoItemInput>>,
+    data_recebimento: Option<String>,
+) -> Result<PedidoCompra, AppError> {
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require(Modulo::Compras, Acao::Editar)?;
+    
+    let data = match data_recebimento {
+        Some(data) => parse_data(&data, "Data de recebimento")?,
+        None => Utc::now().date_naive(),
+    };
+    
+    db.with_transaction(|tx| {
+        let pedido = fetch_pedido(tx, id)?;
+        if pedido.itens.is_empty() {
+            return Err(AppError::Validation("Pedido de compra sem itens para receber".into()));
+        }
+        
+        let recebimentos: Vec<(&PedidoCompraItem, f64)> = match &itens {
+            None => pedido.itens.iter()
+                .map(|item| (item, item.quantidade - item.quantidade_recebida))
+                .filter(|(_, quantidade)| *quantidade > estoque::TOLERANCIA_QUANTIDADE)
+                .collect(),
+            Some(itens) => itens.iter()
+                .map(|recebido| {
+                    let item = pedido.itens.iter().find(|i| i.id == recebido.item_id)
+                        .ok_or_else(|| AppError::Validation(format!("Item {} não pertence ao pedido", recebido.item_id)))?;
+                    if !recebido.quantidade.is_finite() || recebido.quantidade <= 0.0 {
+                        return Err(AppError::Validation("Quantidade recebida deve ser maior que zero".into()));
+                    }
+                    Ok((item, recebido.quantidade))
+                })
+                .collect::<AppResult<_>>()?,
+        };
+        if recebimentos.is_empty() {
+            return Err(AppError::Validation("Nada a receber neste pedido".into()));
+        }
+        for (item, quantidade) in &recebimentos {
+            let recebida = item.quantidade_recebida
+                + recebimentos.iter().filter(|(i, _)| i.id == item.id).map(|(_, q)| q).sum::<f64>();
+            if recebida > item.quantidade + estoque::TOLERANCIA_QUANTIDADE {
+                return Err(AppError::Validation(format!(
+                    "Item {}: recebimento de {} excede o saldo a receber ({})",
+                    item.id, quantidade, item.quantidade - item.quantidade_recebida
+                )));
+            }
+        }
+        
+        let completo = pedido.itens.iter().all(|item| {
+            let agora: f64 = recebimentos.iter().filter(|(i, _)| i.id == item.id).map(|(_, q)| q).sum();
+            item.quantidade_recebida + agora >= item.quantidade - estoque::TOLERANCIA_QUANTIDADE
+        });
+        let transicao = if completo { &RECEBER_PEDIDO } else { &RECEBER_PARTE_PEDIDO };
+        
+        // O frete é cobrado junto com o primeiro recebimento
+        let primeiro = pedido.itens.iter().all(|item| item.quantidade_recebida == 0.0);
+        
+        transicionar(tx, &ctx, id, transicao, || {
+            let mut valor = if primeiro { pedido.frete } else { Dinheiro::ZERO };
+            for (item, quantidade) in &recebimentos {
+                tx.execute(
+                    "UPDATE pedido_compra_itens SET quantidade_recebida = quantidade_recebida + ?1 WHERE id = ?2",
+                    params![quantidade, item.id],
+                )?;
+                
+                let codigo: Option<String> = tx.query_row(
+                    "SELECT codigo FROM produtos WHERE id = ?1",
+                    params![item.produto_id],
+                    |row| row.get(0),
+                )?;
+                if let Some(codigo) = codigo.filter(|c| !c.is_empty()) {
+                    estoque::entrada_compra(tx, &codigo, *quantidade, item.preco_unitario)?;
+                }
+                
+                valor = valor.somar(item.preco_unitario.vezes(*quantidade)?)?;
+            }
+            
+            tx.execute(
+                "UPDATE pedidos_compra SET data_recebimento = ?1 WHERE id = ?2",
+                params![data.format("%Y-%m-%d").to_string(), id],
+            )?;
+            
+            gerar_contas_pagar(tx, &ctx, &pedido, valor, data)
+        })?;
+        
+        fetch_pedido(tx, id)
+    })
+}
+
+#[tauri::command]
+pub async fn cancelar_pedido_compra(
+    db: State<'_, DatabaseState>,
+    token: String,
+    id: i64,
+    motivo: String,
+) -> Result<PedidoCompra, AppError> {
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require(Modulo::Compras, Acao::Editar)?;
+    
+    let motivo = exigir_motivo(&motivo)?;
+    db.with_transaction(|tx| {
+        transicionar(tx, &ctx, id, &CANCELAR_PEDIDO, || gravar_motivo(tx, Documento::Pedido, id, motivo))?;
+        fetch_pedido(tx, id)
+    })
+}
+
+/// Monta o mapa de cotação da requisição
+fn mapa(conn: &Connection, requisicao_id: i64) -> AppResult<MapaCotacao> {
+    let requisicao = fetch_requisicao(conn, requisicao_id)?;
+    let hoje = Utc::now().format("%Y-%m-%d").to_string();
+    
+    let mut stmt = conn.prepare(
+        "SELECT id FROM cotacoes_compra WHERE requisicao_id = ?1 AND status <> 'cancelada' ORDER BY id"
     )?;
+    let ids: Vec<i64> = stmt.query_map(params![requisicao_id], |row| row.get(0))?.collect::<Result<_, _>>()?;
+    let cotacoes = ids.into_iter().map(|id| fetch_cotacao(conn, id)).collect::<AppResult<Vec<_>>>()?;
     
-    let id = conn.last_insert_rowid();
+    let vencida = |cotacao: &Cotacao| cotacao.validade.as_ref().is_some_and(|v| *v < hoje);
+    // Só as respondidas e dentro da validade concorrem aos destaques
+    let concorre = |cotacao: &Cotacao| cotacao.status == "respondida" && !vencida(cotacao);
+    let prazo = |cotacao: &Cotacao| cotacao.prazo_entrega_dias.unwrap_or(i64::MAX);
     
-    Ok(Cotacao {
-        id,
-        numero,
+    let mut fornecedores = Vec::with_capacity(cotacoes.len());
+    for cotacao in &cotacoes {
+        let condicao_pagamento: Option<String> = conn.query_row(
+            "SELECT (SELECT nome FROM condicoes_pagamento WHERE id = ?1)",
+            params![cotacao.condicao_pagamento_id],
+            |row| row.get(0),
+        )?;
+        let itens_cotados = cotacao.itens.iter().filter(|i| i.preco_unitario.is_some()).count();
+        let completa = !requisicao.itens.is_empty() && requisicao.itens.iter()
+            .all(|r| cotacao.itens.iter().any(|i| i.requisicao_item_id == r.id && i.preco_unitario.is_some()));
+        
+        fornecedores.push(MapaFornecedor {
+            cotacao_id: cotacao.id,
+            cotacao_numero: cotacao.numero.clone(),
+            fornecedor_id: cotacao.fornecedor_id,
+            fornecedor_nome: cotacao.fornecedor_nome.clone(),
+            status: cotacao.status.clone(),
+            validade: cotacao.validade.clone(),
+            vencida: vencida(cotacao),
+            prazo_entrega_dias: cotacao.prazo_entrega_dias,
+            condicao_pagamento_id: cotacao.condicao_pagamento_id,
+            condicao_pagamento,
+            frete: cotacao.frete,
+            itens_cotados,
+            valor_total: completa.then(|| cotacao.valor_total + cotacao.frete),
+            melhor_total: false,
+        });
+    }
+    
+    let melhor_total = cotacoes.iter().zip(&fornecedores)
+        .filter(|(cotacao, coluna)| concorre(cotacao) && coluna.valor_total.is_some())
+        .min_by_key(|(cotacao, coluna)| (coluna.valor_total, prazo(cotacao), cotacao.id))
+        .map(|(cotacao, _)| cotacao.id);
+    for coluna in &mut fornecedores {
+        coluna.melhor_total = Some(coluna.cotacao_id) == melhor_total;
+    }
+    
+    let mut itens = Vec::with_capacity(requisicao.itens.len());
+    for requisicao_item in &requisicao.itens {
+        let cotados: Vec<(&Cotacao, Option<&CotacaoItem>)> = cotacoes.iter()
+            .map(|c| (c, c.itens.iter().find(|i| i.requisicao_item_id == requisicao_item.id)))
+            .collect();
+        
+        let melhor = cotados.iter()
+            .filter(|(cotacao, _)| concorre(cotacao))
+            .filter_map(|(cotacao, item)| Some((*cotacao, (*item)?.preco_unitario?)))
+            .min_by_key(|(cotacao, preco)| (*preco, prazo(cotacao), cotacao.id))
+            .map(|(cotacao, _)| cotacao.id);
+        
+        itens.push(MapaItem {
+            requisicao_item_id: requisicao_item.id,
+            produto_id: requisicao_item.produto_id,
+            produto_nome: requisicao_item.produto_nome.clone(),
+            quantidade: requisicao_item.quantidade,
+            precos: cotados.iter()
+                .map(|(cotacao, item)| MapaPreco {
+                    cotacao_id: cotacao.id,
+                    cotacao_item_id: item.map(|i| i.id),
+                    preco_unitario: item.and_then(|i| i.preco_unitario),
+                    total: item.and_then(|i| i.total),
+                    melhor: Some(cotacao.id) == melhor,
+                })
+                .collect(),
+            melhor_cotacao_id: melhor,
+        });
+    }
+    
+    let total_melhor_por_item = if !itens.is_empty() && itens.iter().all(|i| i.melhor_cotacao_id.is_some()) {
+        let mut envolvidas: Vec<i64> = Vec::new();
+        let mut total = Dinheiro::ZERO;
+        for item in &itens {
+            let preco = item.precos.iter().find(|p| p.melhor).and_then(|p| p.total).unwrap_or_default();
+            total += preco;
+            if let Some(cotacao_id) = item.melhor_cotacao_id.filter(|id| !envolvidas.contains(id)) {
+                envolvidas.push(cotacao_id);
+            }
+        }
+        let frete: Dinheiro = cotacoes.iter().filter(|c| envolvidas.contains(&c.id)).map(|c| c.frete).sum();
+        Some(total + frete)
+    } else {
+        None
+    };
+    
+    Ok(MapaCotacao {
         requisicao_id,
-        fornecedor_id,
-        data_cotacao: chrono::Utc::now().format("%Y-%m-%d").to_string(),
-        validade: None,
-        valor_total: valor_total.unwrap_or(0.0),
-        status: "pendente".to_string(),
+        requisicao_numero: requisicao.numero,
+        requisicao_status: requisicao.status,
+        fornecedores,
+        itens,
+        melhor_cotacao_id: melhor_total,
+        total_melhor_por_item,
     })
 }
 
-#[tauri::command]
-pub async fn get_pedidos_compra(app: AppHandle) -> Result<Vec<PedidoCompra>, AppError> {
-    let db_path = get_database_path(&app)?;
-    let conn = Connection::open(&db_path)?;
-    
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS pedidos_compra (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            numero TEXT UNIQUE,
-            fornecedor_id INTEGER,
-            cotacao_id INTEGER,
-            data_pedido TEXT DEFAULT (date('now')),
-            data_entrega TEXT,
-            valor_total REAL DEFAULT 0,
-            status TEXT DEFAULT 'pendente',
-            observacoes TEXT,
-            created_at TEXT DEFAULT (datetime('now')),
-            FOREIGN KEY (fornecedor_id) REFERENCES fornecedores(id)
-        )", []
+/// Aprova a cotação e gera o pedido de compra com os itens escolhidos
+fn adjudicar(tx: &Connection, ctx: &RequestContext, cotacao: &Cotacao, itens: &[&CotacaoItem]) -> AppResult<i64> {
+    exigir_validade(cotacao)?;
+    transicionar(tx, ctx, cotacao.id, &APROVAR_COTACAO, || registrar_aprovacao(tx, ctx, Documento::Cotacao, cotacao.id))?;
+    gerar_pedido(tx, ctx, cotacao, itens)
+}
+
+/// Gera o pedido de compra, pendente de aprovação, com os itens da cotação
+fn gerar_pedido(tx: &Connection, ctx: &RequestContext, cotacao: &Cotacao, itens: &[&CotacaoItem]) -> AppResult<i64> {
+    let numero = sequencias::proximo(tx, TipoDocumento::PedidoCompra, "")?.formatado;
+    let data_entrega = cotacao.prazo_entrega_dias
+        .map(|dias| parcelamento::somar_dias(Utc::now().date_naive(), dias))
+        .transpose()?
+        .map(|data| data.format("%Y-%m-%d").to_string());
+    let total = itens.iter().filter_map(|i| i.total).sum::<Dinheiro>() + cotacao.frete;
+    
+    tx.execute(
+        "INSERT INTO pedidos_compra (numero, fornecedor_id, cotacao_id, requisicao_id, data_entrega, valor_total,
+                                     frete, condicao_pagamento_id, status)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, 'pendente')",
+        params![
+            numero, cotacao.fornecedor_id, cotacao.id, cotacao.requisicao_id, data_entrega, total, cotacao.frete,
+            cotacao.condicao_pagamento_id
+        ],
+    )?;
+    let id = tx.last_insert_rowid();
+    
+    for item in itens {
+        let Some(preco_unitario) = item.preco_unitario else {
+            return Err(AppError::Validation(format!("Item {} da cotação sem preço", item.id)));
+        };
+        inserir_item_pedido(tx, id, Some(item.id), item.produto_id, item.quantidade, preco_unitario)?;
+    }
+    
+    auditoria::novo("pedidos_compra", id).registrar(tx, ctx, Modulo::Compras, "criar")?;
+    
+    info!("✅ Pedido de compra {} gerado da cotação {} - R$ {}", numero, cotacao.numero, total);
+    
+    Ok(id)
+}
+
+fn inserir_item_pedido(
+    tx: &Connection,
+    pedido_id: i64,
+    cotacao_item_id: Option<i64>,
+    produto_id: i64,
+    quantidade: f64,
+    preco_unitario: Dinheiro,
+) -> AppResult<()> {
+    let total = preco_unitario.vezes(quantidade)?;
+    tx.execute(
+        "INSERT INTO pedido_compra_itens (pedido_id, cotacao_item_id, produto_id, quantidade, preco_unitario, total)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![pedido_id, cotacao_item_id, produto_id, quantidade, preco_unitario, total],
+    )?;
+    Ok(())
+}
+
+/// Recusa as cotações ainda em andamento e dá a requisição por atendida
+fn encerrar_cotacoes(tx: &Connection, ctx: &RequestContext, requisicao_id: i64) -> AppResult<()> {
+    for cotacao_id in cotacoes_em_andamento(tx, requisicao_id)? {
+        transicionar(tx, ctx, cotacao_id, &RECUSAR_COTACAO, || Ok(()))?;
+    }
+    if status(tx, Documento::Requisicao, requisicao_id)? == "em_cotacao" {
+        transicionar(tx, ctx, requisicao_id, &ATENDER_REQUISICAO, || Ok(()))?;
+    }
+    Ok(())
+}
+
+/// Contas a pagar do valor recebido, pela condição de pagamento do pedido
+fn gerar_contas_pagar(
+    tx: &Connection,
+    ctx: &RequestContext,
+    pedido: &PedidoCompra,
+    valor: Dinheiro,
+    data_base: NaiveDate,
+) -> AppResult<()> {
+    if valor.is_zero() {
+        return Ok(());
+    }
+    
+    let parcelas = parcelamento::plano(tx, pedido.condicao_pagamento_id, None)?.parcelar(valor, data_base)?;
+    let total_parcelas = parcelas.len();
+    let documento = format!("Pedido de compra {}", pedido.numero);
+    
+    for parcela in &parcelas {
+        tx.execute(
+            "INSERT INTO contas_pagar (descricao, fornecedor_id, pedido_compra_id, valor, data_vencimento, categoria,
+                                       status, parcela, total_parcelas)
+             VALUES (?1, ?2, ?3, ?4, ?5, 'compras', 'pendente', ?6, ?7)",
+            params![
+                parcela.descricao(&documento, total_parcelas), pedido.fornecedor_id, pedido.id, parcela.valor,
+                parcela.vencimento.format("%Y-%m-%d").to_string(), parcela.numero, total_parcelas as i64
+            ],
+        )?;
+        auditoria::novo("contas_pagar", tx.last_insert_rowid()).registrar(tx, ctx, Modulo::Financeiro, "criar")?;
+    }
+    
+    info!("✅ Contas a pagar do {}: {} parcela(s), total R$ {}", documento, total_parcelas, valor);
+    
+    Ok(())
+}
+
+/// Leva o documento à situação de destino da transição
+///
+/// `efeitos` roda antes da mudança, na mesma transação; a auditoria
+/// registra o documento antes e depois de tudo.
+fn transicionar<F>(tx: &Connection, ctx: &RequestContext, id: i64, transicao: &Transicao, efeitos: F) -> AppResult<()>
+where
+    F: FnOnce() -> AppResult<()>,
+{
+    let documento = transicao.documento;
+    let atual = status(tx, documento, id)?;
+    if !transicao.de.contains(&atual.as_str()) {
+        return Err(AppError::Validation(format!(
+            "{} na situação '{}' não pode passar para '{}'", documento.nome(), atual, transicao.para
+        )));
+    }
+    
+    let captura = auditoria::capturar(tx, documento.tabela(), id)?;
+    
+    efeitos()?;
+    
+    tx.execute(
+        &format!("UPDATE {} SET status = ?1, updated_at = datetime('now') WHERE id = ?2", documento.tabela()),
+        params![transicao.para, id],
     )?;
     
+    captura.registrar(tx, ctx, Modulo::Compras, transicao.acao)?;
+    
+    info!("🔄 {} {}: {} → {}", documento.nome(), id, atual, transicao.para);
+    
+    Ok(())
+}
+
+fn status(conn: &Connection, documento: Documento, id: i64) -> AppResult<String> {
+    let result = conn.query_row(
+        &format!("SELECT status FROM {} WHERE id = ?1", documento.tabela()),
+        params![id],
+        |row| row.get::<_, Option<String>>(0),
+    );
+    
+    match result {
+        Ok(status) => Ok(status.unwrap_or_default()),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Err(documento.nao_encontrado()),
+        Err(e) => Err(AppError::Database(e.to_string())),
+    }
+}
+
+fn registrar_aprovacao(tx: &Connection, ctx: &RequestContext, documento: Documento, id: i64) -> AppResult<()> {
+    tx.execute(
+        &format!("UPDATE {} SET aprovado_por = ?1, aprovado_em = datetime('now') WHERE id = ?2", documento.tabela()),
+        params![ctx.usuario.id, id],
+    )?;
+    Ok(())
+}
+
+fn gravar_motivo(tx: &Connection, documento: Documento, id: i64, motivo: &str) -> AppResult<()> {
+    tx.execute(
+        &format!("UPDATE {} SET motivo = ?1 WHERE id = ?2", documento.tabela()),
+        params![motivo, id],
+    )?;
+    Ok(())
+}
+
+fn exigir_motivo(motivo: &str) -> AppResult<&str> {
+    let motivo = motivo.trim();
+    if motivo.is_empty() {
+        return Err(AppError::Validation("Informe o motivo".into()));
+    }
+    Ok(motivo)
+}
+
+fn exigir_validade(cotacao: &Cotacao) -> AppResult<()> {
+    if let Some(validade) = &cotacao.validade {
+        if *validade < Utc::now().format("%Y-%m-%d").to_string() {
+            return Err(AppError::Validation(format!("Cotação {} vencida em {}", cotacao.numero, validade)));
+        }
+    }
+    Ok(())
+}
+
+fn exigir_fornecedor_ativo(conn: &Connection, fornecedor_id: i64) -> AppResult<()> {
+    let fornecedores: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM fornecedores WHERE id = ?1 AND ativo = 1",
+        params![fornecedor_id],
+        |row| row.get(0),
+    )?;
+    if fornecedores == 0 {
+        return Err(AppError::Validation(format!("Fornecedor {} não encontrado ou inativo", fornecedor_id)));
+    }
+    Ok(())
+}
+
+fn exigir_produto_ativo(conn: &Connection, produto_id: i64) -> AppResult<()> {
+    let produtos: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM produtos WHERE id = ?1 AND ativo = 1",
+        params![produto_id],
+        |row| row.get(0),
+    )?;
+    if produtos == 0 {
+        return Err(AppError::Validation(format!("Produto {} não encontrado ou inativo", produto_id)));
+    }
+    Ok(())
+}
+
+fn parse_data(data: &str, campo: &str) -> AppResult<NaiveDate> {
+    NaiveDate::parse_from_str(data, "%Y-%m-%d")
+        .map_err(|_| AppError::Validation(format!("{} inválida: '{}'", campo, data)))
+}
+
+fn cotacoes_em_andamento(conn: &Connection, requisicao_id: i64) -> AppResult<Vec<i64>> {
     let mut stmt = conn.prepare(
-        "SELECT pc.id, pc.numero, pc.fornecedor_id, f.nome, pc.data_pedido, pc.data_entrega, pc.valor_total, pc.status
-         FROM pedidos_compra pc
-         LEFT JOIN fornecedores f ON f.id = pc.fornecedor_id
-         ORDER BY pc.created_at DESC"
+        "SELECT id FROM cotacoes_compra WHERE requisicao_id = ?1 AND status IN ('pendente', 'respondida') ORDER BY id"
     )?;
+    let ids = stmt.query_map(params![requisicao_id], |row| row.get(0))?.collect::<Result<_, _>>()?;
+    Ok(ids)
+}
+
+fn gravar_itens_requisicao(tx: &Connection, requisicao_id: i64, itens: &[RequisicaoItemInput]) -> AppResult<()> {
+    if itens.is_empty() {
+        return Err(AppError::Validation("Requisição sem itens".into()));
+    }
+    
+    for item in itens {
+        if !item.quantidade.is_finite() || item.quantidade <= 0.0 {
+            return Err(AppError::Validation("Quantidade deve ser maior que zero".into()));
+        }
+        exigir_produto_ativo(tx, item.produto_id)?;
+        
+        tx.execute(
+            "INSERT INTO requisicao_itens (requisicao_id, produto_id, quantidade, observacoes) VALUES (?1, ?2, ?3, ?4)",
+            params![requisicao_id, item.produto_id, item.quantidade, item.observacoes],
+        )?;
+    }
+    
+    Ok(())
+}
+
+fn fetch_requisicao(conn: &Connection, id: i64) -> AppResult<Requisicao> {
+    let result = conn.query_row(&format!("{} WHERE id = ?1", SELECT_REQUISICAO), params![id], map_requisicao);
+    
+    let mut requisicao = match result {
+        Ok(requisicao) => requisicao,
+        Err(rusqlite::Error::QueryReturnedNoRows) => return Err(Documento::Requisicao.nao_encontrado()),
+        Err(e) => return Err(AppError::Database(e.to_string())),
+    };
     
-    let pedidos = stmt.query_map([], |row| {
-        Ok(PedidoCompra {
+    requisicao.itens = itens_requisicao(conn, id)?;
+    Ok(requisicao)
+}
+
+fn map_requisicao(row: &rusqlite::Row) -> rusqlite::Result<Requisicao> {
+    Ok(Requisicao {
+        id: row.get(0)?,
+        numero: row.get(1)?,
+        solicitante: row.get(2)?,
+        departamento: row.get(3)?,
+        data_solicitacao: row.get(4)?,
+        data_necessidade: row.get(5)?,
+        status: row.get(6)?,
+        observacoes: row.get(7)?,
+        motivo: row.get(8)?,
+        aprovado_por: row.get(9)?,
+        aprovado_em: row.get(10)?,
+        itens: Vec::new(),
+    })
+}
+
+fn itens_requisicao(conn: &Connection, requisicao_id: i64) -> AppResult<Vec<RequisicaoItem>> {
+    let mut stmt = conn.prepare(
+        "SELECT i.id, i.produto_id, p.nome, i.quantidade, i.observacoes
+         FROM requisicao_itens i
+         LEFT JOIN produtos p ON p.id = i.produto_id
+         WHERE i.requisicao_id = ?1
+         ORDER BY i.id ASC"
+    )?;
+    let itens = stmt.query_map(params![requisicao_id], |row| {
+        Ok(RequisicaoItem {
             id: row.get(0)?,
-            numero: row.get(1)?,
-            fornecedor_id: row.get(2)?,
-            fornecedor_nome: row.get(3)?,
-            data_pedido: row.get(4)?,
-            data_entrega: row.get(5)?,
-            valor_total: row.get(6)?,
-            status: row.get(7)?,
+            produto_id: row.get(1)?,
+            produto_nome: row.get(2)?,
+            quantidade: row.get(3)?,
+            observacoes: row.get(4)?,
         })
-    })?.filter_map(|r| r.ok()).collect();
+    })?.collect::<Result<_, _>>()?;
     
-    Ok(pedidos)
+    Ok(itens)
 }
 
-#[tauri::command]
-pub async fn create_pedido_compra(
-    app: AppHandle,
-    fornecedor_id: Option<i64>,
-    data_entrega: Option<String>,
-    valor_total: Option<f64>,
-) -> Result<PedidoCompra, AppError> {
-    let db_path = get_database_path(&app)?;
-    let conn = Connection::open(&db_path)?;
+fn fetch_cotacao(conn: &Connection, id: i64) -> AppResult<Cotacao> {
+    let result = conn.query_row(&format!("{} WHERE c.id = ?1", SELECT_COTACAO), params![id], map_cotacao);
     
-    let count: i64 = conn.query_row("SELECT COUNT(*) + 1 FROM pedidos_compra", [], |row| row.get(0))?;
-    let numero = format!("PC{:06}", count);
+    let mut cotacao = match result {
+        Ok(cotacao) => cotacao,
+        Err(rusqlite::Error::QueryReturnedNoRows) => return Err(Documento::Cotacao.nao_encontrado()),
+        Err(e) => return Err(AppError::Database(e.to_string())),
+    };
     
-    conn.execute(
-        "INSERT INTO pedidos_compra (numero, fornecedor_id, data_entrega, valor_total)
-         VALUES (?1, ?2, ?3, ?4)",
-        params![numero, fornecedor_id, data_entrega, valor_total.unwrap_or(0.0)],
+    cotacao.itens = itens_cotacao(conn, id)?;
+    Ok(cotacao)
+}
+
+fn map_cotacao(row: &rusqlite::Row) -> rusqlite::Result<Cotacao> {
+    Ok(Cotacao {
+        id: row.get(0)?,
+        numero: row.get(1)?,
+        requisicao_id: row.get(2)?,
+        fornecedor_id: row.get(3)?,
+        fornecedor_nome: row.get(4)?,
+        data_cotacao: row.get(5)?,
+        validade: row.get(6)?,
+        valor_total: row.get(7)?,
+        status: row.get(8)?,
+        condicao_pagamento_id: row.get(9)?,
+        prazo_entrega_dias: row.get(10)?,
+        frete: row.get(15)?,
+        observacoes: row.get(11)?,
+        motivo: row.get(12)?,
+        aprovado_por: row.get(13)?,
+        aprovado_em: row.get(14)?,
+        itens: Vec::new(),
+    })
+}
+
+fn itens_cotacao(conn: &Connection, cotacao_id: i64) -> AppResult<Vec<CotacaoItem>> {
+    let mut stmt = conn.prepare(
+        "SELECT i.id, i.requisicao_item_id, i.produto_id, p.nome, i.quantidade, i.preco_unitario, i.total
+         FROM cotacao_itens i
+         LEFT JOIN produtos p ON p.id = i.produto_id
+         WHERE i.cotacao_id = ?1
+         ORDER BY i.requisicao_item_id ASC"
     )?;
+    let itens = stmt.query_map(params![cotacao_id], |row| {
+        Ok(CotacaoItem {
+            id: row.get(0)?,
+            requisicao_item_id: row.get(1)?,
+            produto_id: row.get(2)?,
+            produto_nome: row.get(3)?,
+            quantidade: row.get(4)?,
+            preco_unitario: row.get(5)?,
+            total: row.get(6)?,
+        })
+    })?.collect::<Result<_, _>>()?;
+    
+    Ok(itens)
+}
+
+fn fetch_pedido(conn: &Connection, id: i64) -> AppResult<PedidoCompra> {
+    let result = conn.query_row(&format!("{} WHERE pc.id = ?1", SELECT_PEDIDO), params![id], map_pedido);
     
-    let id = conn.last_insert_rowid();
+    let mut pedido = match result {
+        Ok(pedido) => pedido,
+        Err(rusqlite::Error::QueryReturnedNoRows) => return Err(Documento::Pedido.nao_encontrado()),
+        Err(e) => return Err(AppError::Database(e.to_string())),
+    };
     
+    pedido.itens = itens_pedido(conn, id)?;
+    Ok(pedido)
+}
+
+fn map_pedido(row: &rusqlite::Row) -> rusqlite::Result<PedidoCompra> {
     Ok(PedidoCompra {
-        id,
-        numero,
-        fornecedor_id,
-        fornecedor_nome: None,
-        data_pedido: chrono::Utc::now().format("%Y-%m-%d").to_string(),
-        data_entrega,
-        valor_total: valor_total.unwrap_or(0.0),
-        status: "pendente".to_string(),
+        id: row.get(0)?,
+        numero: row.get(1)?,
+        fornecedor_id: row.get(2)?,
+        fornecedor_nome: row.get(3)?,
+        cotacao_id: row.get(4)?,
+        requisicao_id: row.get(5)?,
+        data_pedido: row.get(6)?,
+        data_entrega: row.get(7)?,
+        data_recebimento: row.get(8)?,
+        valor_total: row.get(9)?,
+        frete: row.get(16)?,
+        status: row.get(10)?,
+        condicao_pagamento_id: row.get(11)?,
+        observacoes: row.get(12)?,
+        motivo: row.get(13)?,
+        aprovado_por: row.get(14)?,
+        aprovado_em: row.get(15)?,
+        itens: Vec::new(),
     })
 }
+
+fn itens_pedido(conn: &Connection, pedido_id: i64) -> AppResult<Vec<PedidoCompraItem>> {
+    let mut stmt = conn.prepare(
+        "SELECT i.id, i.cotacao_item_id, i.produto_id, p.nome, i.quantidade, i.quantidade_recebida,
+                i.preco_unitario, i.total
+         FROM pedido_compra_itens i
+         LEFT JOIN produtos p ON p.id = i.produto_id
+         WHERE i.pedido_id = ?1
+         ORDER BY i.id ASC"
+    )?;
+    let itens = stmt.query_map(params![pedido_id], |row| {
+        Ok(PedidoCompraItem {
+            id: row.get(0)?,
+            cotacao_item_id: row.get(1)?,
+            produto_id: row.get(2)?,
+            produto_nome: row.get(3)?,
+            quantidade: row.get(4)?,
+            quantidade_recebida: row.get(5)?,
+            preco_unitario: row.get(6)?,
+            total: row.get(7)?,
+        })
+    })?.collect::<Result<_, _>>()?;
+    
+    Ok(itens)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testes::*;
+    
+    /// Requisição aprovada de 10 unidades do produto 'P1'
+    fn requisicao_aprovada(db: &BancoTeste) -> Requisicao {
+        let produto_id = produto(db);
+        let input = RequisicaoInput {
+            solicitante: Some("Produção".into()),
+            departamento: None,
+            data_necessidade: None,
+            observacoes: None,
+            itens: vec![RequisicaoItemInput { produto_id, quantidade: 10.0, observacoes: None }],
+        };
+        let requisicao = executar(create_requisicao(db.state(), TOKEN.into(), input)).unwrap();
+        executar(aprovar_requisicao(db.state(), TOKEN.into(), requisicao.id)).unwrap()
+    }
+    
+    fn responder(db: &BancoTeste, cotacao: &Cotacao, preco: Option<i64>, condicao_pagamento_id: Option<i64>) -> AppResult<Cotacao> {
+        let input = RespostaCotacaoInput {
+            validade: None,
+            condicao_pagamento_id,
+            prazo_entrega_dias: Some(7),
+            frete: None,
+            observacoes: None,
+            precos: cotacao.itens.iter()
+                .map(|i| PrecoCotacaoInput { item_id: i.id, preco_unitario: preco.map(Dinheiro::centavos) })
+                .collect(),
+        };
+        executar(responder_cotacao(db.state(), TOKEN.into(), cotacao.id, input))
+    }
+    
+    #[test]
+    fn cotacao_aprovada_gera_pedido_e_recebimento_da_entrada_e_contas_a_pagar() {
+        let db = banco_teste(TOKEN);
+        let requisicao = requisicao_aprovada(&db);
+        let fornecedor_a = inserir(&db, "INSERT INTO fornecedores (nome) VALUES ('Fornecedor A')");
+        let fornecedor_b = inserir(&db, "INSERT INTO fornecedores (nome) VALUES ('Fornecedor B')");
+        let a = executar(create_cotacao(db.state(), TOKEN.into(), requisicao.id, fornecedor_a, None)).unwrap();
+        let b = executar(create_cotacao(db.state(), TOKEN.into(), requisicao.id, fornecedor_b, None)).unwrap();
+        assert_eq!(a.itens.len(), 1);
+        assert!(a.itens[0].preco_unitario.is_none());
+        
+        // Prazo de entrega fora do limite não é aceito
+        let fora_do_prazo = RespostaCotacaoInput {
+            validade: None,
+            condicao_pagamento_id: None,
+            prazo_entrega_dias: Some(i64::MAX / 2),
+            frete: None,
+            observacoes: None,
+            precos: Vec::new(),
+        };
+        assert!(executar(responder_cotacao(db.state(), TOKEN.into(), a.id, fora_do_prazo)).is_err());
+        
+        // Mesmo fornecedor não cota duas vezes a mesma requisição
+        assert!(executar(create_cotacao(db.state(), TOKEN.into(), requisicao.id, fornecedor_a, None)).is_err());
+        
+        // 30/60/90
+        let a = responder(&db, &a, Some(1200), Some(3)).unwrap();
+        responder(&db, &b, Some(1500), None).unwrap();
+        assert_eq!(a.valor_total, Dinheiro::centavos(12000));
+        
+        let pedido = executar(aprovar_cotacao(db.state(), TOKEN.into(), a.id)).unwrap();
+        assert_eq!(pedido.status, "pendente");
+        assert_eq!(pedido.fornecedor_id, Some(fornecedor_a));
+        assert_eq!(pedido.valor_total, Dinheiro::centavos(12000));
+        assert_eq!(pedido.condicao_pagamento_id, Some(3));
+        assert!(pedido.data_entrega.is_some());
+        assert_eq!(executar(get_cotacao(db.state(), TOKEN.into(), b.id)).unwrap().status, "recusada");
+        assert_eq!(executar(get_requisicao(db.state(), TOKEN.into(), requisicao.id)).unwrap().status, "atendida");
+        
+        // Só recebe depois de aprovado
+        assert!(executar(receber_pedido_compra(db.state(), TOKEN.into(), pedido.id, None, None)).is_err());
+        executar(aprovar_pedido_compra(db.state(), TOKEN.into(), pedido.id)).unwrap();
+        
+        let item_id = pedido.itens[0].id;
+        let excesso = vec![RecebimentoItemInput { item_id, quantidade: 11.0 }];
+        assert!(executar(receber_pedido_compra(db.state(), TOKEN.into(), pedido.id, Some(excesso), None)).is_err());
+        
+        let parte = vec![RecebimentoItemInput { item_id, quantidade: 4.0 }];
+        let pedido = executar(receber_pedido_compra(db.state(), TOKEN.into(), pedido.id, Some(parte), None)).unwrap();
+        assert_eq!(pedido.status, "recebido_parcial");
+        assert_eq!(pedido.itens[0].quantidade_recebida, 4.0);
+        assert_eq!(contar(&db, "SELECT CAST(quantidade_fisica AS INTEGER) FROM estoque_saldos WHERE codigo = 'P1'"), 4);
+        assert_eq!(contar(&db, "SELECT custo_medio FROM estoque_saldos WHERE codigo = 'P1'"), 1200);
+        assert_eq!(contar(&db, "SELECT COUNT(*) FROM contas_pagar WHERE pedido_compra_id IS NOT NULL"), 3);
+        assert_eq!(contar(&db, "SELECT SUM(valor) FROM contas_pagar WHERE categoria = 'compras'"), 4800);
+        
+        // Sem itens, recebe o saldo
+        let pedido = executar(receber_pedido_compra(db.state(), TOKEN.into(), pedido.id, None, None)).unwrap();
+        assert_eq!(pedido.status, "recebido");
+        assert!(pedido.data_recebimento.is_some());
+        assert_eq!(contar(&db, "SELECT CAST(quantidade_fisica AS INTEGER) FROM estoque_saldos WHERE codigo = 'P1'"), 10);
+        assert_eq!(contar(&db, "SELECT SUM(valor) FROM contas_pagar WHERE categoria = 'compras'"), 12000);
+        assert!(executar(cancelar_pedido_compra(db.state(), TOKEN.into(), pedido.id, "Desistência".into())).is_err());
+    }
+    
+    #[test]
+    fn cotacao_exige_requisicao_aprovada_e_todos_os_itens_cotados() {
+        let db = banco_teste(TOKEN);
+        let produto_id = inserir(&db, "INSERT INTO produtos (codigo, nome) VALUES ('P2', 'Chapa')");
+        let fornecedor_id = inserir(&db, "INSERT INTO fornecedores (nome) VALUES ('Fornecedor')");
+        let input = RequisicaoInput {
+            solicitante: None,
+            departamento: None,
+            data_necessidade: None,
+            observacoes: None,
+            itens: vec![RequisicaoItemInput { produto_id, quantidade: 1.0, observacoes: None }],
+        };
+        let requisicao = executar(create_requisicao(db.state(), TOKEN.into(), input)).unwrap();
+        assert!(executar(create_cotacao(db.state(), TOKEN.into(), requisicao.id, fornecedor_id, None)).is_err());
+        
+        executar(aprovar_requisicao(db.state(), TOKEN.into(), requisicao.id)).unwrap();
+        let cotacao = executar(create_cotacao(db.state(), TOKEN.into(), requisicao.id, fornecedor_id, None)).unwrap();
+        assert_eq!(executar(get_requisicao(db.state(), TOKEN.into(), requisicao.id)).unwrap().status, "em_cotacao");
+        
+        responder(&db, &cotacao, None, None).unwrap();
+        assert!(executar(aprovar_cotacao(db.state(), TOKEN.into(), cotacao.id)).is_err());
+        assert_eq!(contar(&db, "SELECT COUNT(*) FROM pedidos_compra"), 0);
+        
+        // Cancelar a requisição cancela as cotações em andamento
+        executar(cancelar_requisicao(db.state(), TOKEN.into(), requisicao.id, "Sem demanda".into())).unwrap();
+        assert_eq!(executar(get_cotacao(db.state(), TOKEN.into(), cotacao.id)).unwrap().status, "cancelada");
+    }
+    
+    #[test]
+    fn mapa_destaca_melhores_e_adjudicacao_dividida_gera_um_pedido_por_fornecedor() {
+        let db = banco_teste(TOKEN);
+        let perfil = inserir(&db, "INSERT INTO produtos (codigo, nome) VALUES ('P1', 'Perfil')");
+        let chapa = inserir(&db, "INSERT INTO produtos (codigo, nome) VALUES ('P2', 'Chapa')");
+        let input = RequisicaoInput {
+            solicitante: None,
+            departamento: None,
+            data_necessidade: None,
+            observacoes: None,
+            itens: vec![
+                RequisicaoItemInput { produto_id: perfil, quantidade: 10.0, observacoes: None },
+                RequisicaoItemInput { produto_id: chapa, quantidade: 2.0, observacoes: None },
+            ],
+        };
+        let requisicao = executar(create_requisicao(db.state(), TOKEN.into(), input)).unwrap();
+        executar(aprovar_requisicao(db.state(), TOKEN.into(), requisicao.id)).unwrap();
+        let (item_perfil, item_chapa) = (requisicao.itens[0].id, requisicao.itens[1].id);
+        
+        let mut cotacoes = Vec::new();
+        for nome in ["A", "B", "C"] {
+            let fornecedor_id = inserir(&db, &format!("INSERT INTO fornecedores (nome) VALUES ('{}')", nome));
+            cotacoes.push(executar(create_cotacao(db.state(), TOKEN.into(), requisicao.id, fornecedor_id, None)).unwrap());
+        }
+        let responder_com = |cotacao: &Cotacao, perfil: Option<i64>, chapa: Option<i64>, frete: i64| {
+            let input = RespostaCotacaoInput {
+                validade: None,
+                condicao_pagamento_id: None,
+                prazo_entrega_dias: Some(10),
+                frete: Some(Dinheiro::centavos(frete)),
+                observacoes: None,
+                precos: vec![
+                    PrecoCotacaoInput { item_id: cotacao.itens[0].id, preco_unitario: perfil.map(Dinheiro::centavos) },
+                    PrecoCotacaoInput { item_id: cotacao.itens[1].id, preco_unitario: chapa.map(Dinheiro::centavos) },
+                ],
+            };
+            executar(responder_cotacao(db.state(), TOKEN.into(), cotacao.id, input)).unwrap()
+        };
+        // A: 100,00 + 100,00 + 5,00 = 205,00; B: 120,00 + 60,00 + 10,00 = 190,00; C só cota o perfil
+        let a = responder_com(&cotacoes[0], Some(1000), Some(5000), 500);
+        let b = responder_com(&cotacoes[1], Some(1200), Some(3000), 1000);
+        let c = responder_com(&cotacoes[2], Some(900), None, 0);
+        
+        let mapa = executar(get_mapa_cotacao(db.state(), TOKEN.into(), requisicao.id)).unwrap();
+        assert_eq!(mapa.fornecedores.len(), 3);
+        assert_eq!(mapa.fornecedores[0].valor_total, Some(Dinheiro::centavos(20500)));
+        assert_eq!(mapa.fornecedores[2].valor_total, None);
+        assert_eq!(mapa.fornecedores[2].itens_cotados, 1);
+        assert_eq!(mapa.melhor_cotacao_id, Some(b.id));
+        assert!(mapa.fornecedores[1].melhor_total);
+        assert_eq!(mapa.itens[0].melhor_cotacao_id, Some(c.id));
+        assert_eq!(mapa.itens[1].melhor_cotacao_id, Some(b.id));
+        assert!(mapa.itens[0].precos[2].melhor);
+        assert_eq!(mapa.itens[1].precos[2].preco_unitario, None);
+        // 90,00 + 60,00 + frete de B 10,00
+        assert_eq!(mapa.total_melhor_por_item, Some(Dinheiro::centavos(16000)));
+        
+        // C não cotou a chapa; item repetido ou faltando também não passa
+        let sem_preco = vec![
+            AdjudicacaoInput { requisicao_item_id: item_perfil, cotacao_id: c.id },
+            AdjudicacaoInput { requisicao_item_id: item_chapa, cotacao_id: c.id },
+        ];
+        assert!(executar(adjudicar_cotacoes(db.state(), TOKEN.into(), requisicao.id, sem_preco)).is_err());
+        let incompleta = vec![AdjudicacaoInput { requisicao_item_id: item_perfil, cotacao_id: c.id }];
+        assert!(executar(adjudicar_cotacoes(db.state(), TOKEN.into(), requisicao.id, incompleta)).is_err());
+        assert_eq!(contar(&db, "SELECT COUNT(*) FROM pedidos_compra"), 0);
+        
+        let divisao = vec![
+            AdjudicacaoInput { requisicao_item_id: item_perfil, cotacao_id: c.id },
+            AdjudicacaoInput { requisicao_item_id: item_chapa, cotacao_id: b.id },
+        ];
+        let pedidos = executar(adjudicar_cotacoes(db.state(), TOKEN.into(), requisicao.id, divisao)).unwrap();
+        assert_eq!(pedidos.len(), 2);
+        assert_eq!(pedidos[0].cotacao_id, Some(c.id));
+        assert_eq!(pedidos[0].valor_total, Dinheiro::centavos(9000));
+        assert_eq!(pedidos[1].itens.len(), 1);
+        assert_eq!(pedidos[1].itens[0].produto_id, chapa);
+        assert_eq!(pedidos[1].frete, Dinheiro::centavos(1000));
+        assert_eq!(pedidos[1].valor_total, Dinheiro::centavos(7000));
+        
+        assert_eq!(executar(get_cotacao(db.state(), TOKEN.into(), a.id)).unwrap().status, "recusada");
+        assert_eq!(executar(get_cotacao(db.state(), TOKEN.into(), b.id)).unwrap().status, "aprovada");
+        assert_eq!(executar(get_requisicao(db.state(), TOKEN.into(), requisicao.id)).unwrap().status, "atendida");
+        
+        // O frete vai para as contas a pagar do primeiro recebimento
+        executar(aprovar_pedido_compra(db.state(), TOKEN.into(), pedidos[1].id)).unwrap();
+        let parte = vec![RecebimentoItemInput { item_id: pedidos[1].itens[0].id, quantidade: 1.0 }];
+        executar(receber_pedido_compra(db.state(), TOKEN.into(), pedidos[1].id, Some(parte), None)).unwrap();
+        executar(receber_pedido_compra(db.state(), TOKEN.into(), pedidos[1].id, None, None)).unwrap();
+        assert_eq!(contar(&db, &format!("SELECT SUM(valor) FROM contas_pagar WHERE pedido_compra_id = {}", pedidos[1].id)), 7000);
+    }
+    
+    #[test]
+    fn pedido_avulso_exige_itens_e_pode_ser_recebido() {
+        let db = banco_teste(TOKEN);
+        let produto_id = inserir(&db, "INSERT INTO produtos (codigo, nome) VALUES ('P1', 'Perfil')");
+        let fornecedor_id = inserir(&db, "INSERT INTO fornecedores (nome) VALUES ('Fornecedor')");
+        let pedido = |itens: Vec<PedidoCompraItemInput>| PedidoCompraInput {
+            fornecedor_id,
+            data_entrega: None,
+            condicao_pagamento_id: None,
+            frete: Some(Dinheiro::centavos(300)),
+            observacoes: None,
+            itens,
+        };
+        
+        assert!(executar(create_pedido_compra(db.state(), TOKEN.into(), pedido(Vec::new()))).is_err());
+        
+        let itens = vec![PedidoCompraItemInput { produto_id, quantidade: 3.0, preco_unitario: Dinheiro::centavos(250) }];
+        let criado = executar(create_pedido_compra(db.state(), TOKEN.into(), pedido(itens))).unwrap();
+        assert_eq!(criado.status, "pendente");
+        assert_eq!(criado.itens.len(), 1);
+        assert_eq!(criado.valor_total, Dinheiro::centavos(1050));
+        
+        executar(aprovar_pedido_compra(db.state(), TOKEN.into(), criado.id)).unwrap();
+        let recebido = executar(receber_pedido_compra(db.state(), TOKEN.into(), criado.id, None, None)).unwrap();
+        assert_eq!(recebido.status, "recebido");
+        assert_eq!(contar(&db, "SELECT CAST(quantidade_fisica AS INTEGER) FROM estoque_saldos WHERE codigo = 'P1'"), 3);
+        assert_eq!(contar(&db, "SELECT SUM(valor) FROM contas_pagar"), 1050);
+    }
+    
+    #[test]
+    fn recebimento_fracionado_fecha_o_item() {
+        let db = banco_teste(TOKEN);
+        let produto_id = inserir(&db, "INSERT INTO produtos (codigo, nome) VALUES ('P1', 'Perfil')");
+        let fornecedor_id = inserir(&db, "INSERT INTO fornecedores (nome) VALUES ('Fornecedor')");
+        let criado = executar(create_pedido_compra(db.state(), TOKEN.into(), PedidoCompraInput {
+            fornecedor_id,
+            data_entrega: None,
+            condicao_pagamento_id: None,
+            frete: None,
+            observacoes: None,
+            itens: vec![PedidoCompraItemInput { produto_id, quantidade: 0.3, preco_unitario: Dinheiro::centavos(1000) }],
+        })).unwrap();
+        executar(aprovar_pedido_compra(db.state(), TOKEN.into(), criado.id)).unwrap();
+        
+        // 0,1 + 0,2 passa de 0,3 em ponto flutuante
+        let item_id = criado.itens[0].id;
+        for quantidade in [0.1, 0.2] {
+            let parte = vec![RecebimentoItemInput { item_id, quantidade }];
+            executar(receber_pedido_compra(db.state(), TOKEN.into(), criado.id, Some(parte), None)).unwrap();
+        }
+        assert_eq!(executar(get_pedido_compra(db.state(), TOKEN.into(), criado.id)).unwrap().status, "recebido");
+    }
+}
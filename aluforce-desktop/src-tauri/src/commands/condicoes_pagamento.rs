@@ -0,0 +1,206 @@
+//! Comandos de Condições de Pagamento
+//!
+//! O cálculo das parcelas fica em `crate::parcelamento`; aqui só o cadastro.
+
+use tauri::State;
+use rusqlite::{Connection, params};
+use log::info;
+use chrono::{NaiveDate, Utc};
+
+use crate::error::{AppError, AppResult};
+use crate::models::{CondicaoPagamento, CondicaoPagamentoInput, Modulo, Acao, Dinheiro};
+use crate::database::DatabaseState;
+use crate::context::RequestContext;
+use crate::auditoria;
+use crate::parcelamento::{self, Parcela, Plano, PRAZO_MAXIMO_DIAS};
+
+/// Módulos que usam condições de pagamento nos seus documentos
+const MODULOS_USUARIOS: &[Modulo] = &[Modulo::Vendas, Modulo::Compras, Modulo::Financeiro];
+
+#[tauri::command]
+pub async fn get_condicoes_pagamento(
+    db: State<'_, DatabaseState>,
+    token: String,
+    incluir_inativas: Option<bool>,
+) -> Result<Vec<CondicaoPagamento>, AppError> {
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require_any(MODULOS_USUARIOS, Acao::Ler)?;
+    
+    let conn = db.reader()?;
+    
+    let mut stmt = conn.prepare(
+        "SELECT id, nome, parcelas, intervalo_dias, primeiro_vencimento_dias, entrada_percentual,
+                ajuste_percentual, ativo, created_at, updated_at
+         FROM condicoes_pagamento
+         WHERE ativo = 1 OR ?1
+         ORDER BY nome ASC"
+    )?;
+    
+    let condicoes = stmt.query_map(params![incluir_inativas.unwrap_or(false)], parcelamento::map_condicao)?
+        .collect::<Result<_, _>>()?;
+    
+    Ok(condicoes)
+}
+
+#[tauri::command]
+pub async fn create_condicao_pagamento(
+    db: State<'_, DatabaseState>,
+    token: String,
+    input: CondicaoPagamentoInput,
+) -> Result<CondicaoPagamento, AppError> {
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require(Modulo::Financeiro, Acao::Criar)?;
+    
+    validar(&input)?;
+    
+    db.with_transaction(|tx| {
+        exigir_nome_livre(tx, &input.nome, None)?;
+        
+        tx.execute(
+            "INSERT INTO condicoes_pagamento (nome, parcelas, intervalo_dias, primeiro_vencimento_dias,
+                                              entrada_percentual, ajuste_percentual)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                input.nome.trim(), input.parcelas, input.intervalo_dias.unwrap_or(30),
+                input.primeiro_vencimento_dias.unwrap_or(0), input.entrada_percentual,
+                input.ajuste_percentual.unwrap_or(0.0)
+            ],
+        )?;
+        
+        let id = tx.last_insert_rowid();
+        auditoria::novo("condicoes_pagamento", id).registrar(tx, &ctx, Modulo::Financeiro, "criar")?;
+        
+        info!("✅ Condição de pagamento criada: {} (ID: {})", input.nome.trim(), id);
+        
+        parcelamento::condicao(tx, id)
+    })
+}
+
+/// Altera a condição; documentos já faturados não mudam
+#[tauri::command]
+pub async fn update_condicao_pagamento(
+    db: State<'_, DatabaseState>,
+    token: String,
+    id: i64,
+    input: CondicaoPagamentoInput,
+) -> Result<CondicaoPagamento, AppError> {
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require(Modulo::Financeiro, Acao::Editar)?;
+    
+    validar(&input)?;
+    
+    db.with_transaction(|tx| {
+        parcelamento::condicao(tx, id)?;
+        exigir_nome_livre(tx, &input.nome, Some(id))?;
+        
+        let captura = auditoria::capturar(tx, "condicoes_pagamento", id)?;
+        
+        tx.execute(
+            "UPDATE condicoes_pagamento SET
+                nome = ?1, parcelas = ?2, intervalo_dias = ?3, primeiro_vencimento_dias = ?4,
+                entrada_percentual = ?5, ajuste_percentual = ?6, updated_at = datetime('now')
+             WHERE id = ?7",
+            params![
+                input.nome.trim(), input.parcelas, input.intervalo_dias.unwrap_or(30),
+                input.primeiro_vencimento_dias.unwrap_or(0), input.entrada_percentual,
+                input.ajuste_percentual.unwrap_or(0.0), id
+            ],
+        )?;
+        
+        // O nome também é o texto da condição nos pedidos abertos
+        tx.execute(
+            "UPDATE pedidos_venda SET condicao_pagamento = ?1
+             WHERE condicao_pagamento_id = ?2 AND status NOT IN ('faturado', 'entregue', 'cancelado')",
+            params![input.nome.trim(), id],
+        )?;
+        
+        captura.registrar(tx, &ctx, Modulo::Financeiro, "editar")?;
+        
+        info!("✅ Condição de pagamento atualizada: {} (ID: {})", input.nome.trim(), id);
+        
+        parcelamento::condicao(tx, id)
+    })
+}
+
+/// Desativa a condição (soft delete); documentos que já a usam continuam valendo
+#[tauri::command]
+pub async fn delete_condicao_pagamento(db: State<'_, DatabaseState>, token: String, id: i64) -> Result<bool, AppError> {
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require(Modulo::Financeiro, Acao::Excluir)?;
+    
+    db.with_transaction(|tx| {
+        parcelamento::condicao(tx, id)?;
+        let captura = auditoria::capturar(tx, "condicoes_pagamento", id)?;
+        
+        tx.execute(
+            "UPDATE condicoes_pagamento SET ativo = 0, updated_at = datetime('now') WHERE id = ?1",
+            params![id],
+        )?;
+        
+        captura.registrar(tx, &ctx, Modulo::Financeiro, "excluir")?;
+        
+        info!("🗑️ Condição de pagamento desativada: ID {}", id);
+        
+        Ok(true)
+    })
+}
+
+/// Parcelas que a condição gera para `valor` a partir de `data_base` (hoje, se omitida)
+#[tauri::command]
+pub async fn simular_condicao_pagamento(
+    db: State<'_, DatabaseState>,
+    token: String,
+    id: i64,
+    valor: Dinheiro,
+    data_base: Option<String>,
+) -> Result<Vec<Parcela>, AppError> {
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require_any(MODULOS_USUARIOS, Acao::Ler)?;
+    
+    let data_base = match data_base {
+        Some(data) => NaiveDate::parse_from_str(&data, "%Y-%m-%d")
+            .map_err(|_| AppError::Validation(format!("Data inválida: '{}'", data)))?,
+        None => Utc::now().date_naive(),
+    };
+    
+    let conn = db.reader()?;
+    let condicao = parcelamento::condicao(&conn, id)?;
+    
+    Plano::da_condicao(&condicao).parcelar(valor, data_base)
+}
+
+fn validar(input: &CondicaoPagamentoInput) -> AppResult<()> {
+    if input.nome.trim().is_empty() {
+        return Err(AppError::Validation("Nome da condição de pagamento é obrigatório".into()));
+    }
+    if !(1..=120).contains(&input.parcelas) {
+        return Err(AppError::Validation("Número de parcelas deve ficar entre 1 e 120".into()));
+    }
+    if input.intervalo_dias.is_some_and(|d| d < 0) || input.primeiro_vencimento_dias.is_some_and(|d| d < 0) {
+        return Err(AppError::Validation("Prazos não podem ser negativos".into()));
+    }
+    if input.intervalo_dias.is_some_and(|d| d > PRAZO_MAXIMO_DIAS)
+        || input.primeiro_vencimento_dias.is_some_and(|d| d > PRAZO_MAXIMO_DIAS)
+    {
+        return Err(AppError::Validation(format!("Prazos não podem passar de {} dias", PRAZO_MAXIMO_DIAS)));
+    }
+    if input.entrada_percentual.is_some_and(|p| !(0.0..100.0).contains(&p)) {
+        return Err(AppError::Validation("Entrada deve ficar entre 0% e 100% (exclusive)".into()));
+    }
+    if input.ajuste_percentual.is_some_and(|p| !p.is_finite() || p <= -100.0) {
+        return Err(AppError::Validation("Desconto não pode chegar a 100%".into()));
+    }
+    Ok(())
+}
+
+fn exigir_nome_livre(conn: &Connection, nome: &str, id: Option<i64>) -> AppResult<()> {
+    let existentes: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM condicoes_pagamento WHERE nome = ?1 AND id <> ?2",
+        params![nome.trim(), id.unwrap_or(0)],
+        |row| row.get(0),
+    )?;
+    if existentes > 0 {
+        return Err(AppError::Validation(format!("Já existe uma condição de pagamento '{}'", nome.trim())));
+    }
+    Ok(())
+}
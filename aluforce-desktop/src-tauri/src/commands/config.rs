@@ -1,17 +1,22 @@
 //! Comandos de Configuração
 
-use tauri::AppHandle;
+use tauri::State;
 use rusqlite::{Connection, params};
 use log::info;
 
-use crate::error::AppError;
-use crate::models::{Empresa, Config};
-use crate::database::get_database_path;
+use crate::error::{AppError, AppResult};
+use crate::models::{Empresa, Config, Sequencia, SequenciaInput, Modulo, Acao};
+use crate::database::DatabaseState;
+use crate::context::RequestContext;
+use crate::auditoria;
+use crate::sequencias;
 
 #[tauri::command]
-pub async fn get_config(app: AppHandle, chave: String) -> Result<Option<serde_json::Value>, AppError> {
-    let db_path = get_database_path(&app)?;
-    let conn = Connection::open(&db_path)?;
+pub async fn get_config(db: State<'_, DatabaseState>, token: String, chave: String) -> Result<Option<serde_json::Value>, AppError> {
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require(Modulo::Config, Acao::Ler)?;
+    
+    let conn = db.reader()?;
     
     let result: Result<String, _> = conn.query_row(
         "SELECT valor FROM configuracoes WHERE chave = ?1",
@@ -32,30 +37,43 @@ pub async fn get_config(app: AppHandle, chave: String) -> Result<Option<serde_js
 
 #[tauri::command]
 pub async fn save_config(
-    app: AppHandle,
+    db: State<'_, DatabaseState>,
+    token: String,
     chave: String,
     valor: serde_json::Value,
 ) -> Result<bool, AppError> {
-    let db_path = get_database_path(&app)?;
-    let conn = Connection::open(&db_path)?;
-    
-    let valor_str = serde_json::to_string(&valor)?;
-    
-    conn.execute(
-        "INSERT OR REPLACE INTO configuracoes (chave, valor, updated_at) VALUES (?1, ?2, datetime('now'))",
-        params![chave, valor_str],
-    )?;
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require(Modulo::Config, Acao::Editar)?;
     
-    info!("✅ Configuração salva: {}", chave);
-    
-    Ok(true)
+    db.with_transaction(|tx| {
+        let captura = auditoria::capturar_por(tx, "configuracoes", "chave", &chave)?;
+        
+        let valor_str = serde_json::to_string(&valor)?;
+        
+        tx.execute(
+            "INSERT OR REPLACE INTO configuracoes (chave, valor, updated_at) VALUES (?1, ?2, datetime('now'))",
+            params![chave, valor_str],
+        )?;
+        
+        captura.registrar(tx, &ctx, Modulo::Config, "editar")?;
+        
+        info!("✅ Configuração salva: {}", chave);
+        
+        Ok(true)
+    })
 }
 
 #[tauri::command]
-pub async fn get_empresa(app: AppHandle) -> Result<Option<Empresa>, AppError> {
-    let db_path = get_database_path(&app)?;
-    let conn = Connection::open(&db_path)?;
+pub async fn get_empresa(db: State<'_, DatabaseState>, token: String) -> Result<Option<Empresa>, AppError> {
+    // Dados da empresa aparecem em todas as telas: basta sessão válida
+    RequestContext::resolve(&db, &token)?;
     
+    let conn = db.reader()?;
+    fetch_empresa(&conn)
+}
+
+/// Carrega os dados da empresa na conexão informada
+pub(crate) fn fetch_empresa(conn: &Connection) -> AppResult<Option<Empresa>> {
     let result = conn.query_row(
         "SELECT id, razao_social, nome_fantasia, cnpj, inscricao_estadual, inscricao_municipal,
                 telefone, email, cep, endereco, numero, complemento, bairro, cidade, estado, logo_path
@@ -90,7 +108,8 @@ pub async fn get_empresa(app: AppHandle) -> Result<Option<Empresa>, AppError> {
 
 #[tauri::command]
 pub async fn save_empresa(
-    app: AppHandle,
+    db: State<'_, DatabaseState>,
+    token: String,
     razao_social: String,
     nome_fantasia: Option<String>,
     cnpj: Option<String>,
@@ -106,32 +125,143 @@ pub async fn save_empresa(
     cidade: Option<String>,
     estado: Option<String>,
 ) -> Result<Empresa, AppError> {
-    let db_path = get_database_path(&app)?;
-    let conn = Connection::open(&db_path)?;
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require(Modulo::Config, Acao::Editar)?;
     
-    // Verificar se já existe empresa
-    let count: i64 = conn.query_row("SELECT COUNT(*) FROM empresas", [], |row| row.get(0))?;
+    db.with_transaction(|tx| {
+        // Verificar se já existe empresa
+        let count: i64 = tx.query_row("SELECT COUNT(*) FROM empresas", [], |row| row.get(0))?;
+        
+        if count == 0 {
+            tx.execute(
+                "INSERT INTO empresas (razao_social, nome_fantasia, cnpj, inscricao_estadual, inscricao_municipal,
+                 telefone, email, cep, endereco, numero, complemento, bairro, cidade, estado)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)",
+                params![razao_social, nome_fantasia, cnpj, inscricao_estadual, inscricao_municipal,
+                        telefone, email, cep, endereco, numero, complemento, bairro, cidade, estado],
+            )?;
+            auditoria::novo("empresas", tx.last_insert_rowid()).registrar(tx, &ctx, Modulo::Config, "criar")?;
+        } else {
+            let captura = auditoria::capturar(tx, "empresas", 1)?;
+            tx.execute(
+                "UPDATE empresas SET razao_social = ?1, nome_fantasia = ?2, cnpj = ?3, inscricao_estadual = ?4,
+                 inscricao_municipal = ?5, telefone = ?6, email = ?7, cep = ?8, endereco = ?9, numero = ?10,
+                 complemento = ?11, bairro = ?12, cidade = ?13, estado = ?14, updated_at = datetime('now')
+                 WHERE id = 1",
+                params![razao_social, nome_fantasia, cnpj, inscricao_estadual, inscricao_municipal,
+                        telefone, email, cep, endereco, numero, complemento, bairro, cidade, estado],
+            )?;
+            captura.registrar(tx, &ctx, Modulo::Config, "editar")?;
+        }
+        
+        info!("✅ Dados da empresa salvos");
+        
+        fetch_empresa(tx)?.ok_or(AppError::NotFound("Empresa não encontrada".into()))
+    })
+}
+
+/// Sequências de numeração de documentos
+#[tauri::command]
+pub async fn get_sequencias(db: State<'_, DatabaseState>, token: String) -> Result<Vec<Sequencia>, AppError> {
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require(Modulo::Config, Acao::Ler)?;
     
-    if count == 0 {
-        conn.execute(
-            "INSERT INTO empresas (razao_social, nome_fantasia, cnpj, inscricao_estadual, inscricao_municipal,
-             telefone, email, cep, endereco, numero, complemento, bairro, cidade, estado)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)",
-            params![razao_social, nome_fantasia, cnpj, inscricao_estadual, inscricao_municipal,
-                    telefone, email, cep, endereco, numero, complemento, bairro, cidade, estado],
-        )?;
-    } else {
-        conn.execute(
-            "UPDATE empresas SET razao_social = ?1, nome_fantasia = ?2, cnpj = ?3, inscricao_estadual = ?4,
-             inscricao_municipal = ?5, telefone = ?6, email = ?7, cep = ?8, endereco = ?9, numero = ?10,
-             complemento = ?11, bairro = ?12, cidade = ?13, estado = ?14, updated_at = datetime('now')
-             WHERE id = 1",
-            params![razao_social, nome_fantasia, cnpj, inscricao_estadual, inscricao_municipal,
-                    telefone, email, cep, endereco, numero, complemento, bairro, cidade, estado],
+    let conn = db.reader()?;
+    
+    let mut stmt = conn.prepare(
+        "SELECT id, tipo, serie, prefixo, formato, largura, proximo, updated_at
+         FROM sequencias ORDER BY tipo, serie"
+    )?;
+    
+    let lista = stmt.query_map([], map_sequencia)?
+        .filter_map(|r| r.ok())
+        .collect();
+    
+    Ok(lista)
+}
+
+/// Cria ou altera a sequência de um tipo de documento (ou série de NFe)
+#[tauri::command]
+pub async fn save_sequencia(db: State<'_, DatabaseState>, token: String, input: SequenciaInput) -> Result<Sequencia, AppError> {
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require(Modulo::Config, Acao::Editar)?;
+    
+    let serie = input.serie.as_deref().map(str::trim).unwrap_or("").to_string();
+    let formato = input.formato.clone().unwrap_or_else(|| sequencias::FORMATO_PADRAO.to_string());
+    sequencias::validar(input.tipo, &serie, &formato, input.largura)?;
+    
+    db.with_transaction(|tx| {
+        let atual = fetch_sequencia(tx, input.tipo.chave(), &serie)?;
+        
+        // Recuar a sequência repetiria números já emitidos
+        let proximo = match (&atual, input.proximo) {
+            (Some(a), Some(p)) if p < a.proximo => {
+                return Err(AppError::Validation(format!(
+                    "Próximo número não pode ser menor que {}", a.proximo
+                )));
+            }
+            (Some(a), None) => a.proximo,
+            (_, Some(p)) if p < 1 => return Err(AppError::Validation("Próximo número deve ser maior que zero".into())),
+            (_, Some(p)) => p,
+            (None, None) => 1,
+        };
+        
+        let captura = match &atual {
+            Some(a) => auditoria::capturar(tx, "sequencias", a.id)?,
+            None => {
+                tx.execute(
+                    "INSERT INTO sequencias (tipo, serie) VALUES (?1, ?2)",
+                    params![input.tipo.chave(), serie],
+                )?;
+                auditoria::novo("sequencias", tx.last_insert_rowid())
+            }
+        };
+        
+        tx.execute(
+            "UPDATE sequencias SET prefixo = ?1, formato = ?2, largura = ?3, proximo = ?4, updated_at = datetime('now')
+             WHERE tipo = ?5 AND serie = ?6",
+            params![input.prefixo.trim(), formato, input.largura, proximo, input.tipo.chave(), serie],
         )?;
-    }
+        
+        captura.registrar(tx, &ctx, Modulo::Config, if atual.is_some() { "editar" } else { "criar" })?;
+        
+        info!("✅ Sequência salva: {} {}", input.tipo, serie);
+        
+        fetch_sequencia(tx, input.tipo.chave(), &serie)?
+            .ok_or(AppError::NotFound("Sequência não encontrada".into()))
+    })
+}
+
+fn fetch_sequencia(conn: &Connection, tipo: &str, serie: &str) -> AppResult<Option<Sequencia>> {
+    let result = conn.query_row(
+        "SELECT id, tipo, serie, prefixo, formato, largura, proximo, updated_at
+         FROM sequencias WHERE tipo = ?1 AND serie = ?2",
+        params![tipo, serie],
+        map_sequencia,
+    );
     
-    info!("✅ Dados da empresa salvos");
+    match result {
+        Ok(s) => Ok(Some(s)),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+        Err(e) => Err(AppError::Database(e.to_string())),
+    }
+}
+
+fn map_sequencia(row: &rusqlite::Row) -> rusqlite::Result<Sequencia> {
+    let prefixo: String = row.get(3)?;
+    let formato: String = row.get(4)?;
+    let largura: i64 = row.get(5)?;
+    let proximo: i64 = row.get(6)?;
     
-    get_empresa(app).await?.ok_or(AppError::NotFound("Empresa não encontrada".into()))
+    Ok(Sequencia {
+        id: row.get(0)?,
+        tipo: row.get(1)?,
+        serie: row.get(2)?,
+        exemplo: sequencias::formatar(&formato, &prefixo, largura, proximo, chrono::Utc::now().date_naive()),
+        prefixo,
+        formato,
+        largura,
+        proximo,
+        updated_at: row.get(7)?,
+    })
 }
@@ -1,47 +1,61 @@
 //! Comandos de banco de dados
 
-use tauri::AppHandle;
-use rusqlite::Connection;
+use tauri::State;
 use base64::Engine;
 
 use crate::error::AppError;
-use crate::database::{self, get_database_path};
+use crate::database::{self, DatabaseState};
+use crate::context::RequestContext;
+use crate::models::{Modulo, Acao};
 
 /// Inicializa banco de dados
 #[tauri::command]
-pub async fn init_database(app: AppHandle) -> Result<bool, AppError> {
-    database::init(&app).await?;
+pub async fn init_database(db: State<'_, DatabaseState>, token: String) -> Result<bool, AppError> {
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require(Modulo::Config, Acao::Editar)?;
+    
+    db.migrate()?;
     Ok(true)
 }
 
 /// Realiza backup do banco
 #[tauri::command]
-pub async fn backup_database(app: AppHandle, path: String) -> Result<bool, AppError> {
-    database::backup(&app, &path).await?;
+pub async fn backup_database(db: State<'_, DatabaseState>, token: String, path: String) -> Result<bool, AppError> {
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require(Modulo::Config, Acao::Editar)?;
+    
+    database::backup(&db, &path).await?;
     Ok(true)
 }
 
 /// Restaura banco de backup
 #[tauri::command]
-pub async fn restore_database(app: AppHandle, path: String) -> Result<bool, AppError> {
-    database::restore(&app, &path).await?;
+pub async fn restore_database(db: State<'_, DatabaseState>, token: String, path: String) -> Result<bool, AppError> {
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require_admin()?;
+    
+    database::restore(&db, &path).await?;
     Ok(true)
 }
 
 /// Executa query genérica (apenas SELECT para segurança)
 #[tauri::command]
 pub async fn execute_query(
-    app: AppHandle, 
+    db: State<'_, DatabaseState>,
+    token: String,
     query: String
 ) -> Result<Vec<serde_json::Value>, AppError> {
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require_admin()?;
+    
     // Validar que é apenas SELECT
     let query_upper = query.to_uppercase();
     if !query_upper.trim().starts_with("SELECT") {
         return Err(AppError::Validation("Apenas queries SELECT são permitidas".into()));
     }
     
-    let db_path = get_database_path(&app)?;
-    let conn = Connection::open(&db_path)?;
+    // Conexão somente leitura do pool: escrita é recusada pelo próprio SQLite
+    let conn = db.reader()?;
     
     let mut stmt = conn.prepare(&query)?;
     let column_count = stmt.column_count();
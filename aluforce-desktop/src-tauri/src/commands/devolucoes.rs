@@ -0,0 +1,403 @@
+//! Comandos de Devoluções de venda
+//!
+//! O cliente devolve parte ou todo o pedido faturado. A devolução:
+//!
+//! - registra as quantidades devolvidas de cada item, limitadas ao que
+//!   ainda não foi devolvido;
+//! - devolve os produtos com estoque controlado ao saldo físico;
+//! - prepara a NFe de entrada (finalidade devolução) referenciando a chave
+//!   da NFe de saída do pedido;
+//! - lança o valor devolvido como crédito do cliente (conta a receber
+//!   negativa, que abate da exposição de crédito) ou como reembolso (conta
+//!   a pagar ao cliente);
+//! - ajusta a comissão já apurada do pedido ao valor não devolvido.
+//!
+//! O valor de cada item é o total do item proporcional à quantidade, com o
+//! desconto/acréscimo do pedido rateado.
+
+use tauri::State;
+use rusqlite::{Connection, params};
+use log::info;
+use chrono::{NaiveDate, Utc};
+
+use crate::error::{AppError, AppResult};
+use crate::models::{
+    DevolucaoVenda, DevolucaoItem, DevolucaoInput, TratamentoDevolucao, Modulo, Acao, TipoDocumento, Dinheiro, StatusPedido,
+};
+use crate::database::DatabaseState;
+use crate::context::RequestContext;
+use crate::auditoria;
+use crate::sequencias;
+use crate::estoque;
+use crate::comissoes;
+use crate::commands::{nfe, vendas};
+
+/// Natureza de operação da NFe de devolução
+const NATUREZA_DEVOLUCAO: &str = "Devolução de venda";
+
+const SELECT_DEVOLUCAO: &str =
+    "SELECT d.id, d.numero, d.pedido_id, p.numero, p.cliente_id, c.nome, d.nota_origem_id, d.nota_fiscal_id,
+            nf.chave, d.conta_receber_id, d.data_devolucao, d.tratamento, d.motivo, d.valor, d.usuario_id,
+            d.created_at, d.conta_pagar_id
+     FROM devolucoes_venda d
+     LEFT JOIN pedidos_venda p ON p.id = d.pedido_id
+     LEFT JOIN clientes c ON c.id = p.cliente_id
+     LEFT JOIN notas_fiscais nf ON nf.id = d.nota_fiscal_id";
+
+/// Devoluções, de todos os pedidos ou de um só
+#[tauri::command]
+pub async fn get_devolucoes(
+    db: State<'_, DatabaseState>,
+    token: String,
+    pedido_id: Option<i64>,
+) -> Result<Vec<DevolucaoVenda>, AppError> {
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require(Modulo::Vendas, Acao::Ler)?;
+    
+    let conn = db.reader()?;
+    
+    let mut stmt = conn.prepare(&format!(
+        "{} WHERE (?1 IS NULL OR d.pedido_id = ?1) ORDER BY d.data_devolucao DESC, d.id DESC",
+        SELECT_DEVOLUCAO
+    ))?;
+    let mut devolucoes: Vec<DevolucaoVenda> = stmt.query_map(params![pedido_id], map_devolucao)?
+        .collect::<Result<_, _>>()?;
+    
+    for devolucao in &mut devolucoes {
+        devolucao.itens = itens(&conn, devolucao.id)?;
+    }
+    
+    Ok(devolucoes)
+}
+
+#[tauri::command]
+pub async fn get_devolucao(db: State<'_, DatabaseState>, token: String, id: i64) -> Result<DevolucaoVenda, AppError> {
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require(Modulo::Vendas, Acao::Ler)?;
+    
+    let conn = db.reader()?;
+    fetch_devolucao(&conn, id)
+}
+
+/// Registra a devolução de itens de um pedido faturado ou entregue
+///
+/// O pedido precisa ter NFe de saída não cancelada; a NFe de devolução usa
+/// a mesma série e fica pendente de transmissão.
+#[tauri::command]
+pub async fn create_devolucao(
+    db: State<'_, DatabaseState>,
+    token: String,
+    input: DevolucaoInput,
+) -> Result<DevolucaoVenda, AppError> {
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require(Modulo::Vendas, Acao::Criar)?;
+    ctx.require(Modulo::Nfe, Acao::Criar)?;
+    
+    let motivo = input.motivo.trim();
+    if motivo.is_empty() {
+        return Err(AppError::Validation("Informe o motivo da devolução".into()));
+    }
+    if input.itens.is_empty() {
+        return Err(AppError::Validation("Devolução sem itens".into()));
+    }
+    if input.itens.iter().any(|i| !i.quantidade.is_finite() || i.quantidade <= 0.0) {
+        return Err(AppError::Validation("Quantidade devolvida deve ser maior que zero".into()));
+    }
+    let data = match &input.data_devolucao {
+        Some(data) => NaiveDate::parse_from_str(data, "%Y-%m-%d")
+            .map_err(|_| AppError::Validation(format!("Data da devolução inválida: '{}'", data)))?,
+        None => Utc::now().date_naive(),
+    };
+    let tratamento = input.tratamento.unwrap_or_default();
+    
+    db.with_transaction(|tx| {
+        let status = vendas::status_pedido(tx, input.pedido_id)?;
+        if !matches!(status, StatusPedido::Faturado | StatusPedido::Entregue) {
+            return Err(AppError::Validation(format!(
+                "Pedido na situação '{}' não pode ter devolução; só pedidos faturados ou entregues", status.chave()
+            )));
+        }
+        
+        let (pedido_numero, cliente_id, subtotal, total): (String, Option<i64>, Dinheiro, Dinheiro) = tx.query_row(
+            "SELECT numero, cliente_id, subtotal, total FROM pedidos_venda WHERE id = ?1",
+            params![input.pedido_id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+        )?;
+        
+        let (nota_origem_id, serie, chave_origem): (i64, Option<String>, Option<String>) = match tx.query_row(
+            "SELECT id, serie, chave FROM notas_fiscais
+             WHERE pedido_id = ?1 AND tipo = 'saida' AND finalidade = ?2 AND status <> 'cancelada'
+             ORDER BY id DESC LIMIT 1",
+            params![input.pedido_id, nfe::FINALIDADE_NORMAL],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        ) {
+            Ok(nota) => nota,
+            Err(rusqlite::Error::QueryReturnedNoRows) => {
+                return Err(AppError::Validation(format!("Pedido {} não tem NFe de saída a devolver", pedido_numero)));
+            }
+            Err(e) => return Err(AppError::Database(e.to_string())),
+        };
+        
+        let numero = sequencias::proximo(tx, TipoDocumento::DevolucaoVenda, "")?.formatado;
+        tx.execute(
+            "INSERT INTO devolucoes_venda (numero, pedido_id, nota_origem_id, data_devolucao, tratamento, motivo, usuario_id)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                numero, input.pedido_id, nota_origem_id, data.format("%Y-%m-%d").to_string(), tratamento.chave(),
+                motivo, ctx.usuario.id
+            ],
+        )?;
+        let id = tx.last_insert_rowid();
+        
+        // Desconto e acréscimo do pedido rateados pelos itens
+        let fator = if subtotal.is_zero() { 1.0 } else { total.em_centavos() as f64 / subtotal.em_centavos() as f64 };
+        
+        let mut valor = Dinheiro::ZERO;
+        for item in &input.itens {
+            valor += devolver_item(tx, id, input.pedido_id, item.pedido_item_id, item.quantidade, fator)?;
+        }
+        
+        // NFe de entrada, na série da nota de origem
+        let serie = serie.unwrap_or_else(|| sequencias::SERIE_NFE_PADRAO.to_string());
+        let numero_nfe = sequencias::proximo(tx, TipoDocumento::Nfe, &serie)?;
+        tx.execute(
+            "INSERT INTO notas_fiscais (numero, serie, chave, tipo, natureza_operacao, data_emissao, cliente_id,
+                                        pedido_id, valor_produtos, valor_total, status, finalidade, chave_referenciada)
+             VALUES (?1, ?2, ?3, 'entrada', ?4, ?5, ?6, ?7, ?8, ?8, 'pendente', ?9, ?10)",
+            params![
+                numero_nfe.formatado, serie, nfe::gerar_chave(&serie, numero_nfe.valor), NATUREZA_DEVOLUCAO,
+                Utc::now().format("%Y-%m-%d %H:%M:%S").to_string(), cliente_id, input.pedido_id, valor,
+                nfe::FINALIDADE_DEVOLUCAO, chave_origem
+            ],
+        )?;
+        let nota_fiscal_id = tx.last_insert_rowid();
+        auditoria::novo("notas_fiscais", nota_fiscal_id).registrar(tx, &ctx, Modulo::Nfe, "criar")?;
+        
+        let vencimento = data.format("%Y-%m-%d").to_string();
+        let (conta_receber_id, conta_pagar_id) = match tratamento {
+            TratamentoDevolucao::Credito => {
+                tx.execute(
+                    "INSERT INTO contas_receber (descricao, cliente_id, pedido_id, nota_fiscal_id, valor, data_vencimento,
+                                                 categoria, status, observacoes)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, 'devolucoes', 'pendente', ?7)",
+                    params![
+                        format!("Crédito da devolução {} (pedido {})", numero, pedido_numero), cliente_id,
+                        input.pedido_id, nota_fiscal_id, -valor, vencimento, motivo
+                    ],
+                )?;
+                let conta_id = tx.last_insert_rowid();
+                auditoria::novo("contas_receber", conta_id).registrar(tx, &ctx, Modulo::Financeiro, "criar")?;
+                (Some(conta_id), None)
+            }
+            TratamentoDevolucao::Reembolso => {
+                tx.execute(
+                    "INSERT INTO contas_pagar (descricao, cliente_id, valor, data_vencimento, categoria, status, observacoes)
+                     VALUES (?1, ?2, ?3, ?4, 'devolucoes', 'pendente', ?5)",
+                    params![
+                        format!("Reembolso da devolução {} (pedido {})", numero, pedido_numero), cliente_id, valor,
+                        vencimento, motivo
+                    ],
+                )?;
+                let conta_id = tx.last_insert_rowid();
+                auditoria::novo("contas_pagar", conta_id).registrar(tx, &ctx, Modulo::Financeiro, "criar")?;
+                (None, Some(conta_id))
+            }
+        };
+        
+        tx.execute(
+            "UPDATE devolucoes_venda SET nota_fiscal_id = ?1, conta_receber_id = ?2, conta_pagar_id = ?3, valor = ?4
+             WHERE id = ?5",
+            params![nota_fiscal_id, conta_receber_id, conta_pagar_id, valor, id],
+        )?;
+        comissoes::ajustar_devolucao(tx, &ctx, input.pedido_id, data, &numero)?;
+        auditoria::novo("devolucoes_venda", id).registrar(tx, &ctx, Modulo::Vendas, "criar")?;
+        
+        info!("↩️ Devolução {} do pedido {}: R$ {} ({})", numero, pedido_numero, valor, tratamento.chave());
+        
+        fetch_devolucao(tx, id)
+    })
+}
+
+/// Grava o item devolvido, devolve o estoque e retorna o valor do item
+fn devolver_item(
+    tx: &Connection,
+    devolucao_id: i64,
+    pedido_id: i64,
+    pedido_item_id: i64,
+    quantidade: f64,
+    fator: f64,
+) -> AppResult<Dinheiro> {
+    let (produto_id, vendida, total, codigo): (i64, f64, Dinheiro, Option<String>) = match tx.query_row(
+        "SELECT i.produto_id, i.quantidade, i.total, p.codigo FROM pedido_itens i
+         LEFT JOIN produtos p ON p.id = i.produto_id
+         WHERE i.id = ?1 AND i.pedido_id = ?2",
+        params![pedido_item_id, pedido_id],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+    ) {
+        Ok(item) => item,
+        Err(rusqlite::Error::QueryReturnedNoRows) => {
+            return Err(AppError::Validation(format!("Item {} não pertence ao pedido", pedido_item_id)));
+        }
+        Err(e) => return Err(AppError::Database(e.to_string())),
+    };
+    
+    // Inclui o que já foi gravado nesta devolução, se o item vier repetido
+    let devolvida: f64 = tx.query_row(
+        "SELECT COALESCE(SUM(quantidade), 0) FROM devolucao_itens WHERE pedido_item_id = ?1",
+        params![pedido_item_id],
+        |row| row.get(0),
+    )?;
+    if quantidade > vendida - devolvida + estoque::TOLERANCIA_QUANTIDADE {
+        return Err(AppError::Validation(format!(
+            "Item {}: quantidade devolvida ({}) maior que a disponível para devolução ({})",
+            pedido_item_id, quantidade, vendida - devolvida
+        )));
+    }
+    
+    let valor = total.vezes(quantidade / vendida * fator)?;
+    tx.execute(
+        "INSERT INTO devolucao_itens (devolucao_id, pedido_item_id, produto_id, quantidade, valor)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![devolucao_id, pedido_item_id, produto_id, quantidade, valor],
+    )?;
+    
+    if let Some(codigo) = codigo.filter(|c| !c.is_empty()) {
+        estoque::entrada_devolucao(tx, &codigo, quantidade)?;
+    }
+    
+    Ok(valor)
+}
+
+fn fetch_devolucao(conn: &Connection, id: i64) -> AppResult<DevolucaoVenda> {
+    let result = conn.query_row(&format!("{} WHERE d.id = ?1", SELECT_DEVOLUCAO), params![id], map_devolucao);
+    
+    let mut devolucao = match result {
+        Ok(devolucao) => devolucao,
+        Err(rusqlite::Error::QueryReturnedNoRows) => return Err(AppError::NotFound("Devolução não encontrada".into())),
+        Err(e) => return Err(AppError::Database(e.to_string())),
+    };
+    
+    devolucao.itens = itens(conn, id)?;
+    Ok(devolucao)
+}
+
+fn map_devolucao(row: &rusqlite::Row) -> rusqlite::Result<DevolucaoVenda> {
+    Ok(DevolucaoVenda {
+        id: row.get(0)?,
+        numero: row.get(1)?,
+        pedido_id: row.get(2)?,
+        pedido_numero: row.get(3)?,
+        cliente_id: row.get(4)?,
+        cliente_nome: row.get(5)?,
+        nota_origem_id: row.get(6)?,
+        nota_fiscal_id: row.get(7)?,
+        nota_fiscal_chave: row.get(8)?,
+        conta_receber_id: row.get(9)?,
+        conta_pagar_id: row.get(16)?,
+        data_devolucao: row.get(10)?,
+        tratamento: row.get(11)?,
+        motivo: row.get(12)?,
+        valor: row.get(13)?,
+        usuario_id: row.get(14)?,
+        itens: Vec::new(),
+        created_at: row.get(15)?,
+    })
+}
+
+fn itens(conn: &Connection, devolucao_id: i64) -> AppResult<Vec<DevolucaoItem>> {
+    let mut stmt = conn.prepare(
+        "SELECT i.id, i.pedido_item_id, i.produto_id, p.nome, i.quantidade, i.valor
+         FROM devolucao_itens i
+         LEFT JOIN produtos p ON p.id = i.produto_id
+         WHERE i.devolucao_id = ?1
+         ORDER BY i.id ASC"
+    )?;
+    let itens = stmt.query_map(params![devolucao_id], |row| {
+        Ok(DevolucaoItem {
+            id: row.get(0)?,
+            pedido_item_id: row.get(1)?,
+            produto_id: row.get(2)?,
+            produto_nome: row.get(3)?,
+            quantidade: row.get(4)?,
+            valor: row.get(5)?,
+        })
+    })?.collect::<Result<_, _>>()?;
+    
+    Ok(itens)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testes::*;
+    
+    #[test]
+    fn devolucao_parcial_volta_ao_estoque_e_gera_credito() {
+        use crate::commands::nfe::emitir_nfe;
+        
+        let db = banco_teste(TOKEN);
+        let pedido = pedido_aprovavel(&db);
+        estoque(&db, 5.0);
+        
+        faturar(&db, pedido.id);
+        let nfe = executar(emitir_nfe(db.state(), TOKEN.into(), pedido.id, "Venda".into(), None)).unwrap();
+        assert_eq!(saldo(&db), (3.0, 0.0));
+        
+        let devolver = |quantidade: f64| executar(create_devolucao(db.state(), TOKEN.into(), serde_json::from_value(serde_json::json!({
+            "pedido_id": pedido.id,
+            "motivo": "Perfil amassado",
+            "itens": [{ "pedido_item_id": pedido.itens[0].id, "quantidade": quantidade }],
+        })).unwrap()));
+        
+        let devolucao = devolver(1.0).unwrap();
+        assert_eq!(devolucao.valor, Dinheiro::centavos(1000));
+        assert_eq!(devolucao.tratamento, "credito");
+        assert_eq!(saldo(&db), (4.0, 0.0));
+        
+        let (tipo, finalidade, referenciada): (String, i64, Option<String>) = db.reader().unwrap().query_row(
+            "SELECT tipo, finalidade, chave_referenciada FROM notas_fiscais WHERE id = ?1",
+            params![devolucao.nota_fiscal_id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        ).unwrap();
+        assert_eq!((tipo.as_str(), finalidade, referenciada), ("entrada", 4, nfe.chave));
+        
+        let credito: Dinheiro = db.reader().unwrap().query_row(
+            "SELECT valor FROM contas_receber WHERE id = ?1",
+            params![devolucao.conta_receber_id],
+            |row| row.get(0),
+        ).unwrap();
+        assert_eq!(credito, Dinheiro::centavos(-1000));
+        
+        // Só resta uma unidade a devolver
+        assert!(devolver(2.0).is_err());
+        assert_eq!(contar(&db, "SELECT COUNT(*) FROM devolucoes_venda"), 1);
+    }
+    
+    #[test]
+    fn reembolso_vai_para_contas_a_pagar() {
+        use crate::commands::nfe::emitir_nfe;
+        
+        let db = banco_teste(TOKEN);
+        let pedido = pedido_aprovavel(&db);
+        estoque(&db, 5.0);
+        faturar(&db, pedido.id);
+        executar(emitir_nfe(db.state(), TOKEN.into(), pedido.id, "Venda".into(), None)).unwrap();
+        
+        let devolucao = executar(create_devolucao(db.state(), TOKEN.into(), serde_json::from_value(serde_json::json!({
+            "pedido_id": pedido.id,
+            "motivo": "Pedido em duplicidade",
+            "tratamento": "reembolso",
+            "itens": [{ "pedido_item_id": pedido.itens[0].id, "quantidade": 2 }],
+        })).unwrap())).unwrap();
+        assert!(devolucao.conta_receber_id.is_none());
+        
+        let (valor, cliente_id): (Dinheiro, Option<i64>) = db.reader().unwrap().query_row(
+            "SELECT valor, cliente_id FROM contas_pagar WHERE id = ?1",
+            params![devolucao.conta_pagar_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        ).unwrap();
+        assert_eq!(valor, Dinheiro::centavos(2000));
+        assert_eq!(cliente_id, pedido.cliente_id);
+        assert_eq!(contar(&db, "SELECT COUNT(*) FROM contas_receber WHERE categoria = 'devolucoes'"), 0);
+    }
+}
@@ -1,22 +1,31 @@
 //! Comandos do Financeiro
 
-use tauri::AppHandle;
+use tauri::State;
 use rusqlite::{Connection, params};
 use log::info;
+use chrono::{NaiveDate, Utc};
 
-use crate::error::AppError;
-use crate::models::{ContaPagar, ContaReceber, ContaBancaria, DashboardFinanceiro, FluxoCaixaDia, PaginatedResponse};
-use crate::database::get_database_path;
+use crate::error::{AppError, AppResult};
+use crate::models::{ContaPagar, ContaReceber, ContaBancaria, DashboardFinanceiro, FluxoCaixaDia, PaginatedResponse, Modulo, Acao, Dinheiro, ExposicaoCredito, AprovacaoCredito};
+use crate::database::DatabaseState;
+use crate::context::RequestContext;
+use crate::auditoria;
+use crate::credito;
+use crate::comissoes;
+use crate::parcelamento::{self, Plano};
 
 #[tauri::command]
 pub async fn get_contas_pagar(
-    app: AppHandle,
+    db: State<'_, DatabaseState>,
+    token: String,
     page: Option<i64>,
     per_page: Option<i64>,
     status: Option<String>,
 ) -> Result<PaginatedResponse<ContaPagar>, AppError> {
-    let db_path = get_database_path(&app)?;
-    let conn = Connection::open(&db_path)?;
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require(Modulo::Financeiro, Acao::Ler)?;
+    
+    let conn = db.reader()?;
     
     let page = page.unwrap_or(1);
     let per_page = per_page.unwrap_or(20);
@@ -31,7 +40,7 @@ pub async fn get_contas_pagar(
         let mut stmt = conn.prepare(
             "SELECT cp.id, cp.descricao, cp.fornecedor_id, f.nome, cp.valor, cp.data_vencimento,
                     cp.data_pagamento, cp.status, cp.categoria, cp.forma_pagamento, cp.observacoes,
-                    cp.created_at, cp.updated_at
+                    cp.created_at, cp.updated_at, cp.parcela, cp.total_parcelas
              FROM contas_pagar cp
              LEFT JOIN fornecedores f ON f.id = cp.fornecedor_id
              WHERE cp.status = ?1
@@ -45,6 +54,7 @@ pub async fn get_contas_pagar(
                 data_pagamento: row.get(6)?, status: row.get(7)?, categoria: row.get(8)?,
                 forma_pagamento: row.get(9)?, observacoes: row.get(10)?,
                 created_at: row.get(11)?, updated_at: row.get(12)?,
+                parcela: row.get(13)?, total_parcelas: row.get(14)?,
             })
         })?.filter_map(|r| r.ok()).collect();
         
@@ -55,7 +65,7 @@ pub async fn get_contas_pagar(
         let mut stmt = conn.prepare(
             "SELECT cp.id, cp.descricao, cp.fornecedor_id, f.nome, cp.valor, cp.data_vencimento,
                     cp.data_pagamento, cp.status, cp.categoria, cp.forma_pagamento, cp.observacoes,
-                    cp.created_at, cp.updated_at
+                    cp.created_at, cp.updated_at, cp.parcela, cp.total_parcelas
              FROM contas_pagar cp
              LEFT JOIN fornecedores f ON f.id = cp.fornecedor_id
              ORDER BY cp.data_vencimento ASC LIMIT ?1 OFFSET ?2"
@@ -68,6 +78,7 @@ pub async fn get_contas_pagar(
                 data_pagamento: row.get(6)?, status: row.get(7)?, categoria: row.get(8)?,
                 forma_pagamento: row.get(9)?, observacoes: row.get(10)?,
                 created_at: row.get(11)?, updated_at: row.get(12)?,
+                parcela: row.get(13)?, total_parcelas: row.get(14)?,
             })
         })?.filter_map(|r| r.ok()).collect();
         
@@ -79,13 +90,16 @@ pub async fn get_contas_pagar(
 
 #[tauri::command]
 pub async fn get_contas_receber(
-    app: AppHandle,
+    db: State<'_, DatabaseState>,
+    token: String,
     page: Option<i64>,
     per_page: Option<i64>,
     status: Option<String>,
 ) -> Result<PaginatedResponse<ContaReceber>, AppError> {
-    let db_path = get_database_path(&app)?;
-    let conn = Connection::open(&db_path)?;
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require(Modulo::Financeiro, Acao::Ler)?;
+    
+    let conn = db.reader()?;
     
     let page = page.unwrap_or(1);
     let per_page = per_page.unwrap_or(20);
@@ -96,7 +110,7 @@ pub async fn get_contas_receber(
     let mut stmt = conn.prepare(
         "SELECT cr.id, cr.descricao, cr.cliente_id, c.nome, cr.pedido_id, cr.valor, cr.data_vencimento,
                 cr.data_recebimento, cr.status, cr.categoria, cr.forma_recebimento, cr.observacoes,
-                cr.created_at, cr.updated_at
+                cr.created_at, cr.updated_at, cr.nota_fiscal_id, cr.parcela, cr.total_parcelas
          FROM contas_receber cr
          LEFT JOIN clientes c ON c.id = cr.cliente_id
          ORDER BY cr.data_vencimento ASC LIMIT ?1 OFFSET ?2"
@@ -108,17 +122,73 @@ pub async fn get_contas_receber(
             cliente_nome: row.get(3)?, pedido_id: row.get(4)?, valor: row.get(5)?,
             data_vencimento: row.get(6)?, data_recebimento: row.get(7)?, status: row.get(8)?,
             categoria: row.get(9)?, forma_recebimento: row.get(10)?, observacoes: row.get(11)?,
-            created_at: row.get(12)?, updated_at: row.get(13)?,
+            created_at: row.get(12)?, updated_at: row.get(13)?, nota_fiscal_id: row.get(14)?,
+            parcela: row.get(15)?, total_parcelas: row.get(16)?,
         })
     })?.filter_map(|r| r.ok()).collect();
     
     Ok(PaginatedResponse::new(contas, total, page, per_page))
 }
 
+/// Baixa uma conta a receber em `data_recebimento` (hoje, se omitida)
+/// 
+/// Com comissão apurada no recebimento, lança a comissão da parcela.
 #[tauri::command]
-pub async fn get_contas_bancarias(app: AppHandle) -> Result<Vec<ContaBancaria>, AppError> {
-    let db_path = get_database_path(&app)?;
-    let conn = Connection::open(&db_path)?;
+pub async fn baixar_conta_receber(
+    db: State<'_, DatabaseState>,
+    token: String,
+    id: i64,
+    data_recebimento: Option<String>,
+    forma_recebimento: Option<String>,
+) -> Result<bool, AppError> {
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require(Modulo::Financeiro, Acao::Editar)?;
+    
+    let data = match data_recebimento {
+        Some(data) => NaiveDate::parse_from_str(&data, "%Y-%m-%d")
+            .map_err(|_| AppError::Validation(format!("Data de recebimento inválida: '{}'", data)))?,
+        None => Utc::now().date_naive(),
+    };
+    
+    db.with_transaction(|tx| {
+        let status = match tx.query_row(
+            "SELECT status FROM contas_receber WHERE id = ?1",
+            params![id],
+            |row| row.get::<_, Option<String>>(0),
+        ) {
+            Ok(status) => status.unwrap_or_default(),
+            Err(rusqlite::Error::QueryReturnedNoRows) => return Err(AppError::NotFound("Conta a receber não encontrada".into())),
+            Err(e) => return Err(AppError::Database(e.to_string())),
+        };
+        if status != "pendente" {
+            return Err(AppError::Validation(format!("Conta a receber na situação '{}' não pode ser baixada", status)));
+        }
+        
+        let captura = auditoria::capturar(tx, "contas_receber", id)?;
+        
+        tx.execute(
+            "UPDATE contas_receber SET status = 'recebido', data_recebimento = ?1,
+                    forma_recebimento = COALESCE(?2, forma_recebimento), updated_at = datetime('now')
+             WHERE id = ?3",
+            params![data.format("%Y-%m-%d").to_string(), forma_recebimento, id],
+        )?;
+        
+        captura.registrar(tx, &ctx, Modulo::Financeiro, "baixar")?;
+        
+        comissoes::apurar_recebimento(tx, &ctx, id, data)?;
+        
+        info!("💰 Conta a receber baixada: ID {}", id);
+        
+        Ok(true)
+    })
+}
+
+#[tauri::command]
+pub async fn get_contas_bancarias(db: State<'_, DatabaseState>, token: String) -> Result<Vec<ContaBancaria>, AppError> {
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require(Modulo::Financeiro, Acao::Ler)?;
+    
+    let conn = db.reader()?;
     
     let mut stmt = conn.prepare(
         "SELECT id, nome, banco, agencia, conta, tipo, saldo_inicial, saldo_atual, ativo
@@ -136,47 +206,75 @@ pub async fn get_contas_bancarias(app: AppHandle) -> Result<Vec<ContaBancaria>,
     Ok(contas)
 }
 
+/// Lança uma conta a pagar ou a receber
+/// 
+/// Com `condicao_pagamento_id`, o valor é parcelado pela condição a partir
+/// de `data_vencimento` (AAAA-MM-DD), uma conta por parcela.
 #[tauri::command]
 pub async fn create_lancamento(
-    app: AppHandle,
+    db: State<'_, DatabaseState>,
+    token: String,
     tipo: String, // 'pagar' ou 'receber'
     descricao: String,
-    valor: f64,
+    valor: Dinheiro,
     data_vencimento: String,
     fornecedor_id: Option<i64>,
     cliente_id: Option<i64>,
     categoria: Option<String>,
+    condicao_pagamento_id: Option<i64>,
 ) -> Result<bool, AppError> {
-    let db_path = get_database_path(&app)?;
-    let conn = Connection::open(&db_path)?;
-    
-    if tipo == "pagar" {
-        conn.execute(
-            "INSERT INTO contas_pagar (descricao, fornecedor_id, valor, data_vencimento, categoria, status)
-             VALUES (?1, ?2, ?3, ?4, ?5, 'pendente')",
-            params![descricao, fornecedor_id, valor, data_vencimento, categoria],
-        )?;
-    } else {
-        conn.execute(
-            "INSERT INTO contas_receber (descricao, cliente_id, valor, data_vencimento, categoria, status)
-             VALUES (?1, ?2, ?3, ?4, ?5, 'pendente')",
-            params![descricao, cliente_id, valor, data_vencimento, categoria],
-        )?;
-    }
-    
-    info!("✅ Lançamento criado: {} - R$ {:.2}", descricao, valor);
-    
-    Ok(true)
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require(Modulo::Financeiro, Acao::Criar)?;
+    
+    db.with_transaction(|tx| {
+        let data_base = NaiveDate::parse_from_str(&data_vencimento, "%Y-%m-%d")
+            .map_err(|_| AppError::Validation(format!("Data de vencimento inválida: '{}'", data_vencimento)))?;
+        let plano = match condicao_pagamento_id {
+            Some(id) => Plano::da_condicao(&parcelamento::condicao_ativa(tx, id)?),
+            None => Plano::de_texto("")?,
+        };
+        let parcelas = plano.parcelar(valor, data_base)?;
+        let total_parcelas = parcelas.len();
+        
+        for parcela in &parcelas {
+            let descricao = parcela.descricao(&descricao, total_parcelas);
+            let vencimento = parcela.vencimento.format("%Y-%m-%d").to_string();
+            let tabela = if tipo == "pagar" {
+                tx.execute(
+                    "INSERT INTO contas_pagar (descricao, fornecedor_id, valor, data_vencimento, categoria, status, parcela, total_parcelas)
+                     VALUES (?1, ?2, ?3, ?4, ?5, 'pendente', ?6, ?7)",
+                    params![descricao, fornecedor_id, parcela.valor, vencimento, categoria, parcela.numero, total_parcelas as i64],
+                )?;
+                "contas_pagar"
+            } else {
+                tx.execute(
+                    "INSERT INTO contas_receber (descricao, cliente_id, valor, data_vencimento, categoria, status, parcela, total_parcelas)
+                     VALUES (?1, ?2, ?3, ?4, ?5, 'pendente', ?6, ?7)",
+                    params![descricao, cliente_id, parcela.valor, vencimento, categoria, parcela.numero, total_parcelas as i64],
+                )?;
+                "contas_receber"
+            };
+            
+            auditoria::novo(tabela, tx.last_insert_rowid()).registrar(tx, &ctx, Modulo::Financeiro, "criar")?;
+        }
+        
+        info!("✅ Lançamento criado: {} - R$ {} em {} parcela(s)", descricao, valor, total_parcelas);
+        
+        Ok(true)
+    })
 }
 
 #[tauri::command]
 pub async fn get_fluxo_caixa(
-    app: AppHandle,
+    db: State<'_, DatabaseState>,
+    token: String,
     data_inicio: String,
     data_fim: String,
 ) -> Result<Vec<FluxoCaixaDia>, AppError> {
-    let db_path = get_database_path(&app)?;
-    let conn = Connection::open(&db_path)?;
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require(Modulo::Financeiro, Acao::Ler)?;
+    
+    let conn = db.reader()?;
     
     // Entradas (recebimentos)
     let mut stmt_entradas = conn.prepare(
@@ -186,8 +284,8 @@ pub async fn get_fluxo_caixa(
          GROUP BY date(data_recebimento)"
     )?;
     
-    let entradas: std::collections::HashMap<String, f64> = stmt_entradas.query_map(params![data_inicio, data_fim], |row| {
-        Ok((row.get::<_, String>(0)?, row.get::<_, f64>(1)?))
+    let entradas: std::collections::HashMap<String, Dinheiro> = stmt_entradas.query_map(params![data_inicio, data_fim], |row| {
+        Ok((row.get::<_, String>(0)?, row.get::<_, Dinheiro>(1)?))
     })?.filter_map(|r| r.ok()).collect();
     
     // Saídas (pagamentos)
@@ -198,8 +296,8 @@ pub async fn get_fluxo_caixa(
          GROUP BY date(data_pagamento)"
     )?;
     
-    let saidas: std::collections::HashMap<String, f64> = stmt_saidas.query_map(params![data_inicio, data_fim], |row| {
-        Ok((row.get::<_, String>(0)?, row.get::<_, f64>(1)?))
+    let saidas: std::collections::HashMap<String, Dinheiro> = stmt_saidas.query_map(params![data_inicio, data_fim], |row| {
+        Ok((row.get::<_, String>(0)?, row.get::<_, Dinheiro>(1)?))
     })?.filter_map(|r| r.ok()).collect();
     
     // Combinar
@@ -208,8 +306,8 @@ pub async fn get_fluxo_caixa(
     datas.extend(saidas.keys().cloned());
     
     let mut fluxo: Vec<FluxoCaixaDia> = datas.into_iter().map(|data| {
-        let ent = *entradas.get(&data).unwrap_or(&0.0);
-        let sai = *saidas.get(&data).unwrap_or(&0.0);
+        let ent = *entradas.get(&data).unwrap_or(&Dinheiro::ZERO);
+        let sai = *saidas.get(&data).unwrap_or(&Dinheiro::ZERO);
         FluxoCaixaDia {
             data: data.clone(),
             entradas: ent,
@@ -224,36 +322,38 @@ pub async fn get_fluxo_caixa(
 }
 
 #[tauri::command]
-pub async fn get_dashboard_financeiro(app: AppHandle) -> Result<DashboardFinanceiro, AppError> {
-    let db_path = get_database_path(&app)?;
-    let conn = Connection::open(&db_path)?;
+pub async fn get_dashboard_financeiro(db: State<'_, DatabaseState>, token: String) -> Result<DashboardFinanceiro, AppError> {
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require(Modulo::Financeiro, Acao::Ler)?;
     
-    let saldo_total: f64 = conn.query_row(
+    let conn = db.reader()?;
+    
+    let saldo_total: Dinheiro = conn.query_row(
         "SELECT COALESCE(SUM(saldo_atual), 0) FROM contas_bancarias WHERE ativo = 1",
         [], |row| row.get(0)
-    ).unwrap_or(0.0);
+    ).unwrap_or_default();
     
-    let a_receber: f64 = conn.query_row(
+    let a_receber: Dinheiro = conn.query_row(
         "SELECT COALESCE(SUM(valor), 0) FROM contas_receber WHERE status = 'pendente'",
         [], |row| row.get(0)
-    ).unwrap_or(0.0);
+    ).unwrap_or_default();
     
-    let a_pagar: f64 = conn.query_row(
+    let a_pagar: Dinheiro = conn.query_row(
         "SELECT COALESCE(SUM(valor), 0) FROM contas_pagar WHERE status = 'pendente'",
         [], |row| row.get(0)
-    ).unwrap_or(0.0);
+    ).unwrap_or_default();
     
     let mes = chrono::Utc::now().format("%Y-%m").to_string();
     
-    let recebido_mes: f64 = conn.query_row(
+    let recebido_mes: Dinheiro = conn.query_row(
         "SELECT COALESCE(SUM(valor), 0) FROM contas_receber WHERE status = 'recebido' AND strftime('%Y-%m', data_recebimento) = ?1",
         params![mes], |row| row.get(0)
-    ).unwrap_or(0.0);
+    ).unwrap_or_default();
     
-    let pago_mes: f64 = conn.query_row(
+    let pago_mes: Dinheiro = conn.query_row(
         "SELECT COALESCE(SUM(valor), 0) FROM contas_pagar WHERE status = 'pago' AND strftime('%Y-%m', data_pagamento) = ?1",
         params![mes], |row| row.get(0)
-    ).unwrap_or(0.0);
+    ).unwrap_or_default();
     
     let hoje = chrono::Utc::now().format("%Y-%m-%d").to_string();
     
@@ -278,3 +378,213 @@ pub async fn get_dashboard_financeiro(app: AppHandle) -> Result<DashboardFinance
         fluxo_caixa: vec![],
     })
 }
+
+/// Gera as contas a receber do pedido, uma por parcela da condição de pagamento
+///
+/// Os prazos contam a partir de `data_base`. Pedido que já tem títulos (por
+/// exemplo, faturado antes da emissão da nota) não gera de novo; só recebe o
+/// vínculo com a nota. Títulos cancelados com a nota e o crédito de
+/// devolução não contam. Ao gerar, apura a comissão do faturamento. Devolve
+/// quantos títulos foram criados.
+pub(crate) fn gerar_contas_receber(
+    tx: &Connection,
+    ctx: &RequestContext,
+    pedido_id: i64,
+    data_base: NaiveDate,
+    nota_fiscal_id: Option<i64>,
+) -> AppResult<usize> {
+    let existentes: i64 = tx.query_row(
+        "SELECT COUNT(*) FROM contas_receber
+         WHERE pedido_id = ?1 AND status <> 'cancelado' AND COALESCE(categoria, '') <> 'devolucoes'",
+        params![pedido_id],
+        |row| row.get(0),
+    )?;
+    if existentes > 0 {
+        if let Some(nota_fiscal_id) = nota_fiscal_id {
+            tx.execute(
+                "UPDATE contas_receber SET nota_fiscal_id = ?1, updated_at = datetime('now')
+                 WHERE pedido_id = ?2 AND nota_fiscal_id IS NULL",
+                params![nota_fiscal_id, pedido_id],
+            )?;
+        }
+        return Ok(0);
+    }
+    
+    let (numero, cliente_id, total, forma_pagamento, condicao_id, condicao): (String, Option<i64>, Dinheiro, Option<String>, Option<i64>, Option<String>) = tx.query_row(
+        "SELECT numero, cliente_id, total, forma_pagamento, condicao_pagamento_id, condicao_pagamento
+         FROM pedidos_venda WHERE id = ?1",
+        params![pedido_id],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?, row.get(5)?)),
+    )?;
+    if total.is_zero() || total.is_negative() {
+        return Ok(0);
+    }
+    
+    // A condição pode ter ganho juros/desconto depois de escolhida no pedido
+    let plano = parcelamento::plano(tx, condicao_id, condicao.as_deref())?;
+    plano.exigir_sem_ajuste()?;
+    let parcelas = plano.parcelar(total, data_base)?;
+    let total_parcelas = parcelas.len();
+    let documento = format!("Pedido {}", numero);
+    
+    for parcela in &parcelas {
+        tx.execute(
+            "INSERT INTO contas_receber (descricao, cliente_id, pedido_id, nota_fiscal_id, parcela, total_parcelas,
+                                         valor, data_vencimento, categoria, forma_recebimento, status)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, 'vendas', ?9, 'pendente')",
+            params![
+                parcela.descricao(&documento, total_parcelas), cliente_id, pedido_id, nota_fiscal_id,
+                parcela.numero, total_parcelas as i64, parcela.valor,
+                parcela.vencimento.format("%Y-%m-%d").to_string(), forma_pagamento
+            ],
+        )?;
+        auditoria::novo("contas_receber", tx.last_insert_rowid()).registrar(tx, ctx, Modulo::Financeiro, "criar")?;
+    }
+    
+    info!("✅ Contas a receber do pedido {}: {} parcela(s), total R$ {}", numero, total_parcelas, parcelas.iter().map(|p| p.valor).sum::<Dinheiro>());
+    
+    comissoes::apurar_faturamento(tx, ctx, pedido_id, Utc::now().date_naive())?;
+    
+    Ok(parcelas.len())
+}
+
+/// Exposição de crédito do cliente frente ao limite cadastrado
+#[tauri::command]
+pub async fn get_exposicao_credito(
+    db: State<'_, DatabaseState>,
+    token: String,
+    cliente_id: i64,
+) -> Result<ExposicaoCredito, AppError> {
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require_any(&[Modulo::Financeiro, Modulo::Vendas], Acao::Ler)?;
+    
+    let conn = db.reader()?;
+    credito::exposicao(&conn, cliente_id, None)
+}
+
+#[tauri::command]
+pub async fn get_aprovacoes_credito(
+    db: State<'_, DatabaseState>,
+    token: String,
+    status: Option<String>,
+) -> Result<Vec<AprovacaoCredito>, AppError> {
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require_any(&[Modulo::Financeiro, Modulo::Vendas], Acao::Ler)?;
+    
+    let conn = db.reader()?;
+    
+    let mut stmt = conn.prepare(
+        "SELECT a.id, a.pedido_id, p.numero, a.cliente_id, c.nome, a.valor_pedido, a.exposicao,
+                a.limite_credito, a.status, a.solicitado_por, us.nome, a.decidido_por, ud.nome,
+                a.decidido_em, a.observacao, a.created_at
+         FROM aprovacoes_credito a
+         LEFT JOIN pedidos_venda p ON p.id = a.pedido_id
+         LEFT JOIN clientes c ON c.id = a.cliente_id
+         LEFT JOIN usuarios us ON us.id = a.solicitado_por
+         LEFT JOIN usuarios ud ON ud.id = a.decidido_por
+         WHERE ?1 IS NULL OR a.status = ?1
+         ORDER BY a.created_at DESC, a.id DESC"
+    )?;
+    
+    let aprovacoes = stmt.query_map(params![status], map_aprovacao)?.collect::<Result<_, _>>()?;
+    
+    Ok(aprovacoes)
+}
+
+/// Aprova o crédito do pedido; o vendedor confirma o pedido em seguida
+#[tauri::command]
+pub async fn aprovar_credito(
+    db: State<'_, DatabaseState>,
+    token: String,
+    id: i64,
+    observacao: Option<String>,
+) -> Result<AprovacaoCredito, AppError> {
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require(Modulo::Financeiro, Acao::Aprovar)?;
+    
+    db.with_transaction(|tx| decidir_credito(tx, &ctx, id, "aprovada", observacao.as_deref()))
+}
+
+#[tauri::command]
+pub async fn rejeitar_credito(
+    db: State<'_, DatabaseState>,
+    token: String,
+    id: i64,
+    motivo: String,
+) -> Result<AprovacaoCredito, AppError> {
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require(Modulo::Financeiro, Acao::Aprovar)?;
+    
+    if motivo.trim().is_empty() {
+        return Err(AppError::Validation("Informe o motivo da rejeição".into()));
+    }
+    
+    db.with_transaction(|tx| decidir_credito(tx, &ctx, id, "rejeitada", Some(motivo.trim())))
+}
+
+/// Encerra uma solicitação pendente com a decisão `status`
+fn decidir_credito(
+    tx: &Connection,
+    ctx: &RequestContext,
+    id: i64,
+    status: &str,
+    observacao: Option<&str>,
+) -> AppResult<AprovacaoCredito> {
+    let atual = tx.query_row(
+        "SELECT status FROM aprovacoes_credito WHERE id = ?1",
+        params![id],
+        |row| row.get::<_, String>(0),
+    );
+    
+    match atual {
+        Ok(atual) if atual != "pendente" => {
+            return Err(AppError::Validation(format!("Solicitação de crédito já {}", atual)));
+        }
+        Ok(_) => {}
+        Err(rusqlite::Error::QueryReturnedNoRows) => {
+            return Err(AppError::NotFound("Solicitação de crédito não encontrada".into()));
+        }
+        Err(e) => return Err(AppError::Database(e.to_string())),
+    }
+    
+    let captura = auditoria::capturar(tx, "aprovacoes_credito", id)?;
+    
+    tx.execute(
+        "UPDATE aprovacoes_credito
+         SET status = ?1, decidido_por = ?2, decidido_em = datetime('now'), observacao = ?3
+         WHERE id = ?4",
+        params![status, ctx.usuario.id, observacao, id],
+    )?;
+    
+    let acao = if status == "aprovada" { "aprovar" } else { "rejeitar" };
+    captura.registrar(tx, ctx, Modulo::Financeiro, acao)?;
+    
+    info!("💳 Solicitação de crédito {} {}", id, status);
+    
+    let aprovacao = tx.query_row(
+        "SELECT a.id, a.pedido_id, p.numero, a.cliente_id, c.nome, a.valor_pedido, a.exposicao,
+                a.limite_credito, a.status, a.solicitado_por, us.nome, a.decidido_por, ud.nome,
+                a.decidido_em, a.observacao, a.created_at
+         FROM aprovacoes_credito a
+         LEFT JOIN pedidos_venda p ON p.id = a.pedido_id
+         LEFT JOIN clientes c ON c.id = a.cliente_id
+         LEFT JOIN usuarios us ON us.id = a.solicitado_por
+         LEFT JOIN usuarios ud ON ud.id = a.decidido_por
+         WHERE a.id = ?1",
+        params![id],
+        map_aprovacao,
+    )?;
+    
+    Ok(aprovacao)
+}
+
+fn map_aprovacao(row: &rusqlite::Row) -> rusqlite::Result<AprovacaoCredito> {
+    Ok(AprovacaoCredito {
+        id: row.get(0)?, pedido_id: row.get(1)?, pedido_numero: row.get(2)?,
+        cliente_id: row.get(3)?, cliente_nome: row.get(4)?, valor_pedido: row.get(5)?,
+        exposicao: row.get(6)?, limite_credito: row.get(7)?, status: row.get(8)?,
+        solicitado_por: row.get(9)?, solicitado_por_nome: row.get(10)?,
+        decidido_por: row.get(11)?, decidido_por_nome: row.get(12)?,
+        decidido_em: row.get(13)?, observacao: row.get(14)?, created_at: row.get(15)?,
+    })
+}
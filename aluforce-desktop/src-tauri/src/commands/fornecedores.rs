@@ -1,21 +1,26 @@
 //! Comandos de Fornecedores
 
-use tauri::AppHandle;
+use tauri::State;
 use rusqlite::{Connection, params};
 use log::info;
 
-use crate::error::AppError;
-use crate::models::{Fornecedor, PaginatedResponse};
-use crate::database::get_database_path;
+use crate::error::{AppError, AppResult};
+use crate::models::{Fornecedor, PaginatedResponse, Modulo, Acao};
+use crate::database::DatabaseState;
+use crate::context::RequestContext;
+use crate::auditoria;
 
 #[tauri::command]
 pub async fn get_fornecedores(
-    app: AppHandle,
+    db: State<'_, DatabaseState>,
+    token: String,
     page: Option<i64>,
     per_page: Option<i64>,
 ) -> Result<PaginatedResponse<Fornecedor>, AppError> {
-    let db_path = get_database_path(&app)?;
-    let conn = Connection::open(&db_path)?;
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require_any(&[Modulo::Compras, Modulo::Financeiro], Acao::Ler)?;
+    
+    let conn = db.reader()?;
     
     let page = page.unwrap_or(1);
     let per_page = per_page.unwrap_or(20);
@@ -65,10 +70,16 @@ pub async fn get_fornecedores(
 }
 
 #[tauri::command]
-pub async fn get_fornecedor_by_id(app: AppHandle, id: i64) -> Result<Option<Fornecedor>, AppError> {
-    let db_path = get_database_path(&app)?;
-    let conn = Connection::open(&db_path)?;
+pub async fn get_fornecedor_by_id(db: State<'_, DatabaseState>, token: String, id: i64) -> Result<Option<Fornecedor>, AppError> {
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require_any(&[Modulo::Compras, Modulo::Financeiro], Acao::Ler)?;
     
+    let conn = db.reader()?;
+    fetch_fornecedor(&conn, id)
+}
+
+/// Carrega fornecedor por ID na conexão informada
+pub(crate) fn fetch_fornecedor(conn: &Connection, id: i64) -> AppResult<Option<Fornecedor>> {
     let result = conn.query_row(
         "SELECT id, nome, razao_social, nome_fantasia, cnpj, cpf,
                 inscricao_estadual, email, telefone, cep, endereco, numero,
@@ -107,52 +118,70 @@ pub async fn get_fornecedor_by_id(app: AppHandle, id: i64) -> Result<Option<Forn
 
 #[tauri::command]
 pub async fn create_fornecedor(
-    app: AppHandle,
+    db: State<'_, DatabaseState>,
+    token: String,
     nome: String,
     cnpj: Option<String>,
     email: Option<String>,
     telefone: Option<String>,
 ) -> Result<Fornecedor, AppError> {
-    let db_path = get_database_path(&app)?;
-    let conn = Connection::open(&db_path)?;
-    
-    conn.execute(
-        "INSERT INTO fornecedores (nome, cnpj, email, telefone) VALUES (?1, ?2, ?3, ?4)",
-        params![nome, cnpj, email, telefone],
-    )?;
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require(Modulo::Compras, Acao::Criar)?;
     
-    let id = conn.last_insert_rowid();
-    info!("✅ Fornecedor criado: {} (ID: {})", nome, id);
-    
-    get_fornecedor_by_id(app, id).await?.ok_or(AppError::NotFound("Fornecedor não encontrado".into()))
+    db.with_transaction(|tx| {
+        tx.execute(
+            "INSERT INTO fornecedores (nome, cnpj, email, telefone) VALUES (?1, ?2, ?3, ?4)",
+            params![nome, cnpj, email, telefone],
+        )?;
+        
+        let id = tx.last_insert_rowid();
+        auditoria::novo("fornecedores", id).registrar(tx, &ctx, Modulo::Compras, "criar")?;
+        
+        info!("✅ Fornecedor criado: {} (ID: {})", nome, id);
+        
+        fetch_fornecedor(tx, id)?.ok_or(AppError::NotFound("Fornecedor não encontrado".into()))
+    })
 }
 
 #[tauri::command]
 pub async fn update_fornecedor(
-    app: AppHandle,
+    db: State<'_, DatabaseState>,
+    token: String,
     id: i64,
     nome: String,
     cnpj: Option<String>,
     email: Option<String>,
     telefone: Option<String>,
 ) -> Result<Fornecedor, AppError> {
-    let db_path = get_database_path(&app)?;
-    let conn = Connection::open(&db_path)?;
-    
-    conn.execute(
-        "UPDATE fornecedores SET nome = ?1, cnpj = ?2, email = ?3, telefone = ?4, updated_at = datetime('now') WHERE id = ?5",
-        params![nome, cnpj, email, telefone, id],
-    )?;
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require(Modulo::Compras, Acao::Editar)?;
     
-    get_fornecedor_by_id(app, id).await?.ok_or(AppError::NotFound("Fornecedor não encontrado".into()))
+    db.with_transaction(|tx| {
+        let captura = auditoria::capturar(tx, "fornecedores", id)?;
+        
+        tx.execute(
+            "UPDATE fornecedores SET nome = ?1, cnpj = ?2, email = ?3, telefone = ?4, updated_at = datetime('now') WHERE id = ?5",
+            params![nome, cnpj, email, telefone, id],
+        )?;
+        
+        captura.registrar(tx, &ctx, Modulo::Compras, "editar")?;
+        
+        fetch_fornecedor(tx, id)?.ok_or(AppError::NotFound("Fornecedor não encontrado".into()))
+    })
 }
 
 #[tauri::command]
-pub async fn delete_fornecedor(app: AppHandle, id: i64) -> Result<bool, AppError> {
-    let db_path = get_database_path(&app)?;
-    let conn = Connection::open(&db_path)?;
-    
-    conn.execute("UPDATE fornecedores SET ativo = 0, updated_at = datetime('now') WHERE id = ?1", params![id])?;
+pub async fn delete_fornecedor(db: State<'_, DatabaseState>, token: String, id: i64) -> Result<bool, AppError> {
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require(Modulo::Compras, Acao::Excluir)?;
     
-    Ok(true)
+    db.with_transaction(|tx| {
+        let captura = auditoria::capturar(tx, "fornecedores", id)?;
+        
+        tx.execute("UPDATE fornecedores SET ativo = 0, updated_at = datetime('now') WHERE id = ?1", params![id])?;
+        
+        captura.registrar(tx, &ctx, Modulo::Compras, "excluir")?;
+        
+        Ok(true)
+    })
 }
@@ -3,15 +3,24 @@
 //! Expõe funções Rust para o frontend React via invoke.
 
 pub mod auth;
+pub mod setup;
+pub mod papeis;
+pub mod usuarios;
 pub mod database;
 pub mod clientes;
 pub mod fornecedores;
 pub mod produtos;
 pub mod vendas;
+pub mod orcamentos;
+pub mod devolucoes;
 pub mod compras;
 pub mod financeiro;
+pub mod condicoes_pagamento;
+pub mod tabelas_preco;
+pub mod comissoes;
 pub mod pcp;
 pub mod rh;
 pub mod nfe;
 pub mod config;
 pub mod system;
+pub mod auditoria;
@@ -1,23 +1,37 @@
 //! Comandos de NFe (Nota Fiscal Eletrônica)
 
-use tauri::AppHandle;
-use rusqlite::{Connection, params};
+use tauri::State;
+use rusqlite::params;
 use log::info;
 
 use crate::error::AppError;
-use crate::models::{NotaFiscal, PaginatedResponse};
-use crate::database::get_database_path;
+use crate::models::{NotaFiscal, PaginatedResponse, Modulo, Acao, TipoDocumento, Dinheiro, StatusPedido};
+use crate::database::DatabaseState;
+use crate::context::RequestContext;
+use crate::auditoria;
+use crate::sequencias;
+use crate::commands::{financeiro, vendas};
+use crate::comissoes;
+
+/// finNFe da nota normal
+pub const FINALIDADE_NORMAL: i64 = 1;
+
+/// finNFe da nota de devolução
+pub const FINALIDADE_DEVOLUCAO: i64 = 4;
 
 #[tauri::command]
 pub async fn get_nfes(
-    app: AppHandle,
+    db: State<'_, DatabaseState>,
+    token: String,
     page: Option<i64>,
     per_page: Option<i64>,
     status: Option<String>,
     tipo: Option<String>,
 ) -> Result<PaginatedResponse<NotaFiscal>, AppError> {
-    let db_path = get_database_path(&app)?;
-    let conn = Connection::open(&db_path)?;
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require(Modulo::Nfe, Acao::Ler)?;
+    
+    let conn = db.reader()?;
     
     let page = page.unwrap_or(1);
     let per_page = per_page.unwrap_or(20);
@@ -29,7 +43,8 @@ pub async fn get_nfes(
         "SELECT nf.id, nf.numero, nf.serie, nf.chave, nf.tipo, nf.natureza_operacao,
                 nf.data_emissao, nf.data_saida, nf.cliente_id, c.nome, nf.fornecedor_id,
                 nf.pedido_id, nf.valor_produtos, nf.valor_frete, nf.valor_seguro,
-                nf.valor_desconto, nf.valor_total, nf.status, nf.protocolo, nf.observacoes
+                nf.valor_desconto, nf.valor_total, nf.status, nf.protocolo, nf.observacoes,
+                nf.finalidade, nf.chave_referenciada
          FROM notas_fiscais nf
          LEFT JOIN clientes c ON c.id = nf.cliente_id
          ORDER BY nf.data_emissao DESC LIMIT ?1 OFFSET ?2"
@@ -43,83 +58,106 @@ pub async fn get_nfes(
             fornecedor_id: row.get(10)?, pedido_id: row.get(11)?, valor_produtos: row.get(12)?,
             valor_frete: row.get(13)?, valor_seguro: row.get(14)?, valor_desconto: row.get(15)?,
             valor_total: row.get(16)?, status: row.get(17)?, protocolo: row.get(18)?,
-            observacoes: row.get(19)?,
+            observacoes: row.get(19)?, finalidade: row.get(20)?, chave_referenciada: row.get(21)?,
         })
     })?.filter_map(|r| r.ok()).collect();
     
     Ok(PaginatedResponse::new(nfes, total, page, per_page))
 }
 
+/// Emite a NFe do pedido e gera as contas a receber, se o pedido ainda não tiver
+/// 
+/// Só pedidos faturados ou entregues: a baixa do estoque reservado acontece
+/// no faturamento, não aqui.
 #[tauri::command]
 pub async fn emitir_nfe(
-    app: AppHandle,
+    db: State<'_, DatabaseState>,
+    token: String,
     pedido_id: i64,
     natureza_operacao: String,
+    serie: Option<String>,
 ) -> Result<NotaFiscal, AppError> {
-    let db_path = get_database_path(&app)?;
-    let conn = Connection::open(&db_path)?;
-    
-    // Buscar dados do pedido
-    let (cliente_id, valor_total): (Option<i64>, f64) = conn.query_row(
-        "SELECT cliente_id, total FROM pedidos_venda WHERE id = ?1",
-        params![pedido_id],
-        |row| Ok((row.get(0)?, row.get(1)?))
-    )?;
-    
-    // Gerar número da NFe
-    let count: i64 = conn.query_row("SELECT COUNT(*) + 1 FROM notas_fiscais WHERE tipo = 'saida'", [], |row| row.get(0))?;
-    let numero = format!("{}", count);
-    let serie = "1".to_string();
-    let data_emissao = chrono::Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
-    
-    // Gerar chave (simplificada - em produção usar algoritmo real)
-    let chave = format!("35{}00000000000100550010000{:09}1{:09}0", 
-        chrono::Utc::now().format("%y%m"), count, count);
-    
-    conn.execute(
-        "INSERT INTO notas_fiscais (numero, serie, chave, tipo, natureza_operacao, data_emissao,
-         cliente_id, pedido_id, valor_produtos, valor_total, status)
-         VALUES (?1, ?2, ?3, 'saida', ?4, ?5, ?6, ?7, ?8, ?8, 'pendente')",
-        params![numero, serie, chave, natureza_operacao, data_emissao, cliente_id, pedido_id, valor_total],
-    )?;
-    
-    let id = conn.last_insert_rowid();
-    info!("✅ NFe criada: {} (ID: {})", numero, id);
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require(Modulo::Nfe, Acao::Criar)?;
     
-    // Buscar NFe criada
-    let nfe = conn.query_row(
-        "SELECT nf.id, nf.numero, nf.serie, nf.chave, nf.tipo, nf.natureza_operacao,
-                nf.data_emissao, nf.data_saida, nf.cliente_id, c.nome, nf.fornecedor_id,
-                nf.pedido_id, nf.valor_produtos, nf.valor_frete, nf.valor_seguro,
-                nf.valor_desconto, nf.valor_total, nf.status, nf.protocolo, nf.observacoes
-         FROM notas_fiscais nf
-         LEFT JOIN clientes c ON c.id = nf.cliente_id
-         WHERE nf.id = ?1",
-        params![id],
-        |row| Ok(NotaFiscal {
-            id: row.get(0)?, numero: row.get(1)?, serie: row.get(2)?, chave: row.get(3)?,
-            tipo: row.get(4)?, natureza_operacao: row.get(5)?, data_emissao: row.get(6)?,
-            data_saida: row.get(7)?, cliente_id: row.get(8)?, cliente_nome: row.get(9)?,
-            fornecedor_id: row.get(10)?, pedido_id: row.get(11)?, valor_produtos: row.get(12)?,
-            valor_frete: row.get(13)?, valor_seguro: row.get(14)?, valor_desconto: row.get(15)?,
-            valor_total: row.get(16)?, status: row.get(17)?, protocolo: row.get(18)?,
-            observacoes: row.get(19)?,
-        })
-    )?;
+    let serie = serie.unwrap_or_else(|| sequencias::SERIE_NFE_PADRAO.to_string());
+    sequencias::validar(TipoDocumento::Nfe, &serie, sequencias::FORMATO_PADRAO, 0)?;
     
-    Ok(nfe)
+    db.with_transaction(|tx| {
+        let status = vendas::status_pedido(tx, pedido_id)?;
+        if !matches!(status, StatusPedido::Faturado | StatusPedido::Entregue) {
+            return Err(AppError::Validation(format!(
+                "Pedido na situação '{}' não pode ter NFe emitida; fature o pedido antes", status.chave()
+            )));
+        }
+        
+        // Buscar dados do pedido
+        let (cliente_id, valor_total): (Option<i64>, Dinheiro) = tx.query_row(
+            "SELECT cliente_id, total FROM pedidos_venda WHERE id = ?1",
+            params![pedido_id],
+            |row| Ok((row.get(0)?, row.get(1)?))
+        )?;
+        
+        // Número da NFe, sequencial dentro da série
+        let numero = sequencias::proximo(tx, TipoDocumento::Nfe, &serie)?;
+        let data_emissao = chrono::Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
+        
+        let chave = gerar_chave(&serie, numero.valor);
+        
+        tx.execute(
+            "INSERT INTO notas_fiscais (numero, serie, chave, tipo, natureza_operacao, data_emissao,
+             cliente_id, pedido_id, valor_produtos, valor_total, status)
+             VALUES (?1, ?2, ?3, 'saida', ?4, ?5, ?6, ?7, ?8, ?8, 'pendente')",
+            params![numero.formatado, serie, chave, natureza_operacao, data_emissao, cliente_id, pedido_id, valor_total],
+        )?;
+        
+        let id = tx.last_insert_rowid();
+        auditoria::novo("notas_fiscais", id).registrar(tx, &ctx, Modulo::Nfe, "criar")?;
+        
+        // Títulos a receber pela condição de pagamento do pedido, contados da emissão
+        financeiro::gerar_contas_receber(tx, &ctx, pedido_id, chrono::Utc::now().date_naive(), Some(id))?;
+        
+        info!("✅ NFe criada: {} série {} (ID: {})", numero.formatado, serie, id);
+        
+        // Buscar NFe criada
+        let nfe = tx.query_row(
+            "SELECT nf.id, nf.numero, nf.serie, nf.chave, nf.tipo, nf.natureza_operacao,
+                    nf.data_emissao, nf.data_saida, nf.cliente_id, c.nome, nf.fornecedor_id,
+                    nf.pedido_id, nf.valor_produtos, nf.valor_frete, nf.valor_seguro,
+                    nf.valor_desconto, nf.valor_total, nf.status, nf.protocolo, nf.observacoes,
+                    nf.finalidade, nf.chave_referenciada
+             FROM notas_fiscais nf
+             LEFT JOIN clientes c ON c.id = nf.cliente_id
+             WHERE nf.id = ?1",
+            params![id],
+            |row| Ok(NotaFiscal {
+                id: row.get(0)?, numero: row.get(1)?, serie: row.get(2)?, chave: row.get(3)?,
+                tipo: row.get(4)?, natureza_operacao: row.get(5)?, data_emissao: row.get(6)?,
+                data_saida: row.get(7)?, cliente_id: row.get(8)?, cliente_nome: row.get(9)?,
+                fornecedor_id: row.get(10)?, pedido_id: row.get(11)?, valor_produtos: row.get(12)?,
+                valor_frete: row.get(13)?, valor_seguro: row.get(14)?, valor_desconto: row.get(15)?,
+                valor_total: row.get(16)?, status: row.get(17)?, protocolo: row.get(18)?,
+                observacoes: row.get(19)?, finalidade: row.get(20)?, chave_referenciada: row.get(21)?,
+            })
+        )?;
+        
+        Ok(nfe)
+    })
 }
 
 #[tauri::command]
-pub async fn consultar_nfe(app: AppHandle, chave: String) -> Result<Option<NotaFiscal>, AppError> {
-    let db_path = get_database_path(&app)?;
-    let conn = Connection::open(&db_path)?;
+pub async fn consultar_nfe(db: State<'_, DatabaseState>, token: String, chave: String) -> Result<Option<NotaFiscal>, AppError> {
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require(Modulo::Nfe, Acao::Ler)?;
+    
+    let conn = db.reader()?;
     
     let result = conn.query_row(
         "SELECT nf.id, nf.numero, nf.serie, nf.chave, nf.tipo, nf.natureza_operacao,
                 nf.data_emissao, nf.data_saida, nf.cliente_id, c.nome, nf.fornecedor_id,
                 nf.pedido_id, nf.valor_produtos, nf.valor_frete, nf.valor_seguro,
-                nf.valor_desconto, nf.valor_total, nf.status, nf.protocolo, nf.observacoes
+                nf.valor_desconto, nf.valor_total, nf.status, nf.protocolo, nf.observacoes,
+                nf.finalidade, nf.chave_referenciada
          FROM notas_fiscais nf
          LEFT JOIN clientes c ON c.id = nf.cliente_id
          WHERE nf.chave = ?1",
@@ -131,7 +169,7 @@ pub async fn consultar_nfe(app: AppHandle, chave: String) -> Result<Option<NotaF
             fornecedor_id: row.get(10)?, pedido_id: row.get(11)?, valor_produtos: row.get(12)?,
             valor_frete: row.get(13)?, valor_seguro: row.get(14)?, valor_desconto: row.get(15)?,
             valor_total: row.get(16)?, status: row.get(17)?, protocolo: row.get(18)?,
-            observacoes: row.get(19)?,
+            observacoes: row.get(19)?, finalidade: row.get(20)?, chave_referenciada: row.get(21)?,
         })
     );
     
@@ -142,40 +180,94 @@ pub async fn consultar_nfe(app: AppHandle, chave: String) -> Result<Option<NotaF
     }
 }
 
+/// Cancela a NFe e estorna as comissões apuradas para o pedido
+/// 
+/// Os títulos pendentes da nota são cancelados junto; nota com título já
+/// recebido não pode ser cancelada.
 #[tauri::command]
 pub async fn cancelar_nfe(
-    app: AppHandle,
+    db: State<'_, DatabaseState>,
+    token: String,
     id: i64,
     justificativa: String,
 ) -> Result<bool, AppError> {
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require(Modulo::Nfe, Acao::Excluir)?;
+    
     if justificativa.len() < 15 {
         return Err(AppError::Validation("Justificativa deve ter no mínimo 15 caracteres".into()));
     }
     
-    let db_path = get_database_path(&app)?;
-    let conn = Connection::open(&db_path)?;
-    
-    conn.execute(
-        "UPDATE notas_fiscais SET status = 'cancelada', observacoes = ?1, updated_at = datetime('now') WHERE id = ?2",
-        params![justificativa, id],
-    )?;
-    
-    info!("❌ NFe cancelada: ID {}", id);
-    
-    Ok(true)
+    db.with_transaction(|tx| {
+        let (pedido_id, finalidade): (Option<i64>, i64) = match tx.query_row(
+            "SELECT pedido_id, finalidade FROM notas_fiscais WHERE id = ?1",
+            params![id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        ) {
+            Ok(nota) => nota,
+            Err(rusqlite::Error::QueryReturnedNoRows) => return Err(AppError::NotFound("NFe não encontrada".into())),
+            Err(e) => return Err(AppError::Database(e.to_string())),
+        };
+        
+        let recebidos: i64 = tx.query_row(
+            "SELECT COUNT(*) FROM contas_receber WHERE nota_fiscal_id = ?1 AND status = 'recebido'",
+            params![id],
+            |row| row.get(0),
+        )?;
+        if recebidos > 0 {
+            return Err(AppError::Validation(
+                "NFe com título já recebido não pode ser cancelada; estorne o recebimento antes".into()
+            ));
+        }
+        
+        let captura = auditoria::capturar(tx, "notas_fiscais", id)?;
+        
+        tx.execute(
+            "UPDATE notas_fiscais SET status = 'cancelada', observacoes = ?1, updated_at = datetime('now') WHERE id = ?2",
+            params![justificativa, id],
+        )?;
+        
+        captura.registrar(tx, &ctx, Modulo::Nfe, "cancelar")?;
+        
+        let titulos: Vec<i64> = {
+            let mut stmt = tx.prepare("SELECT id FROM contas_receber WHERE nota_fiscal_id = ?1 AND status = 'pendente'")?;
+            let ids = stmt.query_map(params![id], |row| row.get(0))?.collect::<Result<_, _>>()?;
+            ids
+        };
+        for titulo in titulos {
+            let captura = auditoria::capturar(tx, "contas_receber", titulo)?;
+            tx.execute(
+                "UPDATE contas_receber SET status = 'cancelado', updated_at = datetime('now') WHERE id = ?1",
+                params![titulo],
+            )?;
+            captura.registrar(tx, &ctx, Modulo::Financeiro, "cancelar")?;
+        }
+        
+        // A nota de devolução não estorna a comissão da venda
+        if let (Some(pedido_id), FINALIDADE_NORMAL) = (pedido_id, finalidade) {
+            comissoes::estornar_pedido(tx, &ctx, pedido_id, chrono::Utc::now().date_naive(), &justificativa)?;
+        }
+        
+        info!("❌ NFe cancelada: ID {}", id);
+        
+        Ok(true)
+    })
 }
 
 #[tauri::command]
-pub async fn gerar_danfe(app: AppHandle, id: i64) -> Result<String, AppError> {
-    let db_path = get_database_path(&app)?;
-    let conn = Connection::open(&db_path)?;
+pub async fn gerar_danfe(db: State<'_, DatabaseState>, token: String, id: i64) -> Result<String, AppError> {
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require(Modulo::Nfe, Acao::Ler)?;
+    
+    let conn = db.reader()?;
     
     // Buscar dados da NFe
     let nfe: NotaFiscal = conn.query_row(
         "SELECT nf.id, nf.numero, nf.serie, nf.chave, nf.tipo, nf.natureza_operacao,
                 nf.data_emissao, nf.data_saida, nf.cliente_id, c.nome, nf.fornecedor_id,
                 nf.pedido_id, nf.valor_produtos, nf.valor_frete, nf.valor_seguro,
-                nf.valor_desconto, nf.valor_total, nf.status, nf.protocolo, nf.observacoes
+                nf.valor_desconto, nf.valor_total, nf.status, nf.protocolo, nf.observacoes,
+                nf.finalidade, nf.chave_referenciada
          FROM notas_fiscais nf
          LEFT JOIN clientes c ON c.id = nf.cliente_id
          WHERE nf.id = ?1",
@@ -187,7 +279,7 @@ pub async fn gerar_danfe(app: AppHandle, id: i64) -> Result<String, AppError> {
             fornecedor_id: row.get(10)?, pedido_id: row.get(11)?, valor_produtos: row.get(12)?,
             valor_frete: row.get(13)?, valor_seguro: row.get(14)?, valor_desconto: row.get(15)?,
             valor_total: row.get(16)?, status: row.get(17)?, protocolo: row.get(18)?,
-            observacoes: row.get(19)?,
+            observacoes: row.get(19)?, finalidade: row.get(20)?, chave_referenciada: row.get(21)?,
         })
     )?;
     
@@ -199,7 +291,7 @@ pub async fn gerar_danfe(app: AppHandle, id: i64) -> Result<String, AppError> {
          Série: {}\n\
          Chave: {}\n\
          Cliente: {}\n\
-         Valor Total: R$ {:.2}\n\
+         Valor Total: R$ {}\n\
          Status: {}",
         nfe.numero.unwrap_or_default(),
         nfe.serie.unwrap_or_default(),
@@ -211,3 +303,56 @@ pub async fn gerar_danfe(app: AppHandle, id: i64) -> Result<String, AppError> {
     
     Ok(danfe_info)
 }
+
+/// Chave de acesso da NFe (simplificada - em produção usar algoritmo real)
+pub(crate) fn gerar_chave(serie: &str, numero: i64) -> String {
+    format!("35{}0000000000010055{:03}0000{:09}1{:09}0", 
+        chrono::Utc::now().format("%y%m"), serie.parse::<u16>().unwrap_or(1), numero, numero)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testes::*;
+    
+    #[test]
+    fn nfe_so_para_pedido_faturado() {
+        let db = banco_teste(TOKEN);
+        let id = pedido_aprovavel(&db).id;
+        estoque(&db, 5.0);
+        
+        let erro = executar(emitir_nfe(db.state(), TOKEN.into(), id, "Venda".into(), None)).unwrap_err();
+        assert!(matches!(erro, AppError::Validation(_)));
+        assert_eq!(contar(&db, "SELECT COUNT(*) FROM notas_fiscais"), 0);
+        assert_eq!(contar(&db, "SELECT COUNT(*) FROM contas_receber"), 0);
+        
+        faturar(&db, id);
+        let nfe = executar(emitir_nfe(db.state(), TOKEN.into(), id, "Venda".into(), None)).unwrap();
+        assert_eq!(nfe.pedido_id, Some(id));
+        assert_eq!(saldo(&db), (3.0, 0.0));
+    }
+    
+    #[test]
+    fn cancelamento_leva_os_titulos_pendentes_da_nota() {
+        use crate::commands::financeiro::baixar_conta_receber;
+        use crate::commands::vendas::update_pedido;
+        
+        let db = banco_teste(TOKEN);
+        let id = pedido_aprovavel(&db).id;
+        estoque(&db, 5.0);
+        executar(update_pedido(db.state(), TOKEN.into(), id, None, Some("30/60".into()), None, None, None, None)).unwrap();
+        faturar(&db, id);
+        let nfe = executar(emitir_nfe(db.state(), TOKEN.into(), id, "Venda".into(), None)).unwrap();
+        let titulo = contar(&db, "SELECT MIN(id) FROM contas_receber");
+        
+        executar(baixar_conta_receber(db.state(), TOKEN.into(), titulo, None, None)).unwrap();
+        let erro = executar(cancelar_nfe(db.state(), TOKEN.into(), nfe.id, "Erro no destinatário da nota".into())).unwrap_err();
+        assert!(matches!(erro, AppError::Validation(_)));
+        assert_eq!(contar(&db, "SELECT COUNT(*) FROM notas_fiscais WHERE status = 'cancelada'"), 0);
+        
+        db.writer().unwrap().execute("UPDATE contas_receber SET status = 'pendente', data_recebimento = NULL", []).unwrap();
+        executar(cancelar_nfe(db.state(), TOKEN.into(), nfe.id, "Erro no destinatário da nota".into())).unwrap();
+        assert_eq!(contar(&db, "SELECT COUNT(*) FROM contas_receber WHERE status = 'cancelado'"), 2);
+        assert_eq!(contar(&db, "SELECT COUNT(*) FROM contas_receber WHERE status = 'pendente'"), 0);
+    }
+}
@@ -0,0 +1,567 @@
+//! Comandos de Orçamentos de venda
+//!
+//! Um orçamento é enviado ao cliente em revisões numeradas: cada mudança de
+//! itens, preços, condições ou validade grava uma revisão nova e as
+//! anteriores ficam como histórico. Os itens de uma revisão podem ser
+//! divididos em alternativas; o cliente aceita uma delas.
+//!
+//! O orçamento termina ganho (convertido em pedido confirmado) ou perdido,
+//! sempre com o motivo registrado.
+
+use std::collections::BTreeMap;
+
+use tauri::State;
+use rusqlite::{Connection, params};
+use log::info;
+use chrono::{Duration, NaiveDate, Utc};
+
+use crate::error::{AppError, AppResult};
+use crate::models::{
+    Orcamento, OrcamentoAlternativa, OrcamentoRevisao, OrcamentoItem, OrcamentoInput, ConversaoOrcamento,
+    PedidoItemInput, PaginatedResponse, Modulo, Acao, TipoDocumento, Dinheiro, Ajuste,
+};
+use crate::database::DatabaseState;
+use crate::context::RequestContext;
+use crate::auditoria;
+use crate::sequencias;
+use crate::precos;
+use crate::commands::vendas::{self, NovoPedido};
+
+/// Validade de um orçamento quando nenhuma é informada
+pub const VALIDADE_PADRAO_DIAS: i64 = 15;
+
+const SELECT_ORCAMENTO: &str =
+    "SELECT o.id, o.numero, o.cliente_id, c.nome, o.vendedor_id, u.nome, o.data_emissao, o.revisao,
+            r.validade, (o.status = 'aberto' AND r.validade < date('now')), o.status, o.motivo,
+            o.alternativa_aceita, o.pedido_id, r.forma_pagamento, r.condicao_pagamento,
+            r.condicao_pagamento_id, r.observacoes, o.created_at, o.updated_at
+     FROM orcamentos o
+     INNER JOIN orcamento_revisoes r ON r.orcamento_id = o.id AND r.revisao = o.revisao
+     LEFT JOIN clientes c ON c.id = o.cliente_id
+     LEFT JOIN usuarios u ON u.id = o.vendedor_id";
+
+#[tauri::command]
+pub async fn get_orcamentos(
+    db: State<'_, DatabaseState>,
+    token: String,
+    page: Option<i64>,
+    per_page: Option<i64>,
+    status: Option<String>,
+    cliente_id: Option<i64>,
+) -> Result<PaginatedResponse<Orcamento>, AppError> {
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require(Modulo::Vendas, Acao::Ler)?;
+    
+    let conn = db.reader()?;
+    
+    let page = page.unwrap_or(1);
+    let per_page = per_page.unwrap_or(20);
+    let offset = (page - 1) * per_page;
+    
+    let total: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM orcamentos WHERE (?1 IS NULL OR status = ?1) AND (?2 IS NULL OR cliente_id = ?2)",
+        params![status, cliente_id],
+        |row| row.get(0),
+    )?;
+    
+    let mut stmt = conn.prepare(&format!(
+        "{} WHERE (?1 IS NULL OR o.status = ?1) AND (?2 IS NULL OR o.cliente_id = ?2)
+         ORDER BY o.created_at DESC LIMIT ?3 OFFSET ?4",
+        SELECT_ORCAMENTO
+    ))?;
+    let orcamentos = stmt.query_map(params![status, cliente_id, per_page, offset], map_orcamento)?
+        .collect::<Result<_, _>>()?;
+    
+    Ok(PaginatedResponse::new(orcamentos, total, page, per_page))
+}
+
+/// Orçamento com os itens e as alternativas da revisão atual
+#[tauri::command]
+pub async fn get_orcamento(db: State<'_, DatabaseState>, token: String, id: i64) -> Result<Orcamento, AppError> {
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require(Modulo::Vendas, Acao::Ler)?;
+    
+    let conn = db.reader()?;
+    fetch_orcamento(&conn, id)
+}
+
+/// Todas as revisões do orçamento, da primeira à atual
+#[tauri::command]
+pub async fn get_revisoes_orcamento(
+    db: State<'_, DatabaseState>,
+    token: String,
+    id: i64,
+) -> Result<Vec<OrcamentoRevisao>, AppError> {
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require(Modulo::Vendas, Acao::Ler)?;
+    
+    let conn = db.reader()?;
+    fetch_orcamento(&conn, id)?;
+    
+    let mut stmt = conn.prepare(
+        "SELECT r.id, r.orcamento_id, r.revisao, r.validade, r.forma_pagamento, r.condicao_pagamento,
+                r.condicao_pagamento_id, r.observacoes, r.motivo, r.usuario_id, u.nome, r.created_at
+         FROM orcamento_revisoes r
+         LEFT JOIN usuarios u ON u.id = r.usuario_id
+         WHERE r.orcamento_id = ?1
+         ORDER BY r.revisao ASC"
+    )?;
+    let mut revisoes: Vec<OrcamentoRevisao> = stmt.query_map(params![id], |row| {
+        Ok(OrcamentoRevisao {
+            id: row.get(0)?,
+            orcamento_id: row.get(1)?,
+            revisao: row.get(2)?,
+            validade: row.get(3)?,
+            forma_pagamento: row.get(4)?,
+            condicao_pagamento: row.get(5)?,
+            condicao_pagamento_id: row.get(6)?,
+            observacoes: row.get(7)?,
+            motivo: row.get(8)?,
+            usuario_id: row.get(9)?,
+            usuario_nome: row.get(10)?,
+            itens: Vec::new(),
+            created_at: row.get(11)?,
+        })
+    })?.collect::<Result<_, _>>()?;
+    
+    for revisao in &mut revisoes {
+        revisao.itens = itens(&conn, id, revisao.revisao)?;
+    }
+    
+    Ok(revisoes)
+}
+
+#[tauri::command]
+pub async fn create_orcamento(
+    db: State<'_, DatabaseState>,
+    token: String,
+    input: OrcamentoInput,
+) -> Result<Orcamento, AppError> {
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require(Modulo::Vendas, Acao::Criar)?;
+    
+    db.with_transaction(|tx| {
+        let clientes: i64 = tx.query_row(
+            "SELECT COUNT(*) FROM clientes WHERE id = ?1 AND ativo = 1",
+            params![input.cliente_id],
+            |row| row.get(0),
+        )?;
+        if clientes == 0 {
+            return Err(AppError::Validation(format!("Cliente {} não encontrado ou inativo", input.cliente_id)));
+        }
+        
+        let numero = sequencias::proximo(tx, TipoDocumento::OrcamentoVenda, "")?.formatado;
+        
+        tx.execute(
+            "INSERT INTO orcamentos (numero, cliente_id, vendedor_id, data_emissao, revisao)
+             VALUES (?1, ?2, ?3, ?4, 1)",
+            params![numero, input.cliente_id, input.vendedor_id, Utc::now().format("%Y-%m-%d").to_string()],
+        )?;
+        
+        let id = tx.last_insert_rowid();
+        gravar_revisao(tx, &ctx, id, 1, &input, None)?;
+        
+        auditoria::novo("orcamentos", id).registrar(tx, &ctx, Modulo::Vendas, "criar")?;
+        
+        info!("✅ Orçamento criado: {} (ID: {})", numero, id);
+        
+        fetch_orcamento(tx, id)
+    })
+}
+
+/// Grava uma nova revisão do orçamento em aberto
+///
+/// A revisão substitui itens, condições e validade; as anteriores ficam no
+/// histórico. O cliente não muda.
+#[tauri::command]
+pub async fn revisar_orcamento(
+    db: State<'_, DatabaseState>,
+    token: String,
+    id: i64,
+    input: OrcamentoInput,
+    motivo: Option<String>,
+) -> Result<Orcamento, AppError> {
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require(Modulo::Vendas, Acao::Editar)?;
+    
+    db.with_transaction(|tx| {
+        let atual = fetch_orcamento(tx, id)?;
+        exigir_aberto(&atual)?;
+        if input.cliente_id != atual.cliente_id {
+            return Err(AppError::Validation("O cliente do orçamento não muda; crie um novo orçamento".into()));
+        }
+        
+        let captura = auditoria::capturar(tx, "orcamentos", id)?;
+        
+        let revisao = atual.revisao + 1;
+        gravar_revisao(tx, &ctx, id, revisao, &input, motivo.as_deref())?;
+        tx.execute(
+            "UPDATE orcamentos SET revisao = ?1, vendedor_id = ?2, updated_at = datetime('now') WHERE id = ?3",
+            params![revisao, input.vendedor_id, id],
+        )?;
+        
+        captura.registrar(tx, &ctx, Modulo::Vendas, "revisar")?;
+        
+        info!("📝 Orçamento {} revisado: revisão {}", atual.numero, revisao);
+        
+        fetch_orcamento(tx, id)
+    })
+}
+
+/// Encerra o orçamento como perdido
+#[tauri::command]
+pub async fn perder_orcamento(
+    db: State<'_, DatabaseState>,
+    token: String,
+    id: i64,
+    motivo: String,
+) -> Result<Orcamento, AppError> {
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require(Modulo::Vendas, Acao::Editar)?;
+    
+    if motivo.trim().is_empty() {
+        return Err(AppError::Validation("Informe o motivo da perda".into()));
+    }
+    
+    db.with_transaction(|tx| {
+        let atual = fetch_orcamento(tx, id)?;
+        exigir_aberto(&atual)?;
+        
+        let captura = auditoria::capturar(tx, "orcamentos", id)?;
+        tx.execute(
+            "UPDATE orcamentos SET status = 'perdido', motivo = ?1, updated_at = datetime('now') WHERE id = ?2",
+            params![motivo.trim(), id],
+        )?;
+        captura.registrar(tx, &ctx, Modulo::Vendas, "perder")?;
+        
+        info!("❌ Orçamento {} perdido: {}", atual.numero, motivo.trim());
+        
+        fetch_orcamento(tx, id)
+    })
+}
+
+/// Converte o orçamento aceito em pedido confirmado
+///
+/// O pedido leva cliente, vendedor, condições e os itens da `alternativa`
+/// aceita (obrigatória se a revisão tiver mais de uma) com os preços e
+/// descontos orçados. A confirmação segue `confirmar_pedido`: reserva o
+/// estoque e, acima do limite de crédito, o pedido fica aprovado e entra
+/// na fila de aprovação de crédito, com o motivo em `pendencia_credito`.
+/// Nos dois casos o orçamento fica ganho.
+#[tauri::command]
+pub async fn converter_orcamento(
+    db: State<'_, DatabaseState>,
+    token: String,
+    id: i64,
+    alternativa: Option<i64>,
+    motivo: Option<String>,
+    permitir_encomenda: Option<bool>,
+) -> Result<ConversaoOrcamento, AppError> {
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require(Modulo::Vendas, Acao::Criar)?;
+    ctx.require(Modulo::Vendas, Acao::Aprovar)?;
+    
+    db.with_transaction(|tx| {
+        let orcamento = fetch_orcamento(tx, id)?;
+        exigir_aberto(&orcamento)?;
+        if orcamento.vencido {
+            return Err(AppError::Validation(format!(
+                "Orçamento vencido em {}; revise o orçamento para renovar a validade", orcamento.validade
+            )));
+        }
+        
+        let alternativa = match alternativa {
+            Some(alternativa) => alternativa,
+            None if orcamento.alternativas.len() == 1 => orcamento.alternativas[0].alternativa,
+            None => {
+                return Err(AppError::Validation(format!(
+                    "Orçamento tem {} alternativas; informe qual foi aceita", orcamento.alternativas.len()
+                )));
+            }
+        };
+        let itens: Vec<PedidoItemInput> = orcamento.itens.iter()
+            .filter(|i| i.alternativa == alternativa)
+            .map(|i| PedidoItemInput {
+                produto_id: i.produto_id,
+                quantidade: i.quantidade,
+                preco_unitario: Some(i.preco_unitario),
+                desconto: match i.desconto_percentual {
+                    Some(p) => Some(Ajuste::Percentual(p)),
+                    None if !i.desconto.is_zero() => Some(Ajuste::Valor(i.desconto)),
+                    None => None,
+                },
+            })
+            .collect();
+        if itens.is_empty() {
+            return Err(AppError::Validation(format!("Orçamento não tem a alternativa {}", alternativa)));
+        }
+        
+        let pedido_id = vendas::inserir_pedido(tx, &ctx, NovoPedido {
+            cliente_id: Some(orcamento.cliente_id),
+            vendedor_id: orcamento.vendedor_id,
+            desconto: None,
+            acrescimo: None,
+            forma_pagamento: orcamento.forma_pagamento.clone(),
+            condicao_pagamento: orcamento.condicao_pagamento.clone(),
+            condicao_pagamento_id: orcamento.condicao_pagamento_id,
+            observacoes: orcamento.observacoes.clone(),
+        }, &itens)?;
+        
+        let captura = auditoria::capturar(tx, "orcamentos", id)?;
+        tx.execute(
+            "UPDATE orcamentos SET status = 'ganho', motivo = ?1, alternativa_aceita = ?2, pedido_id = ?3,
+                    updated_at = datetime('now')
+             WHERE id = ?4",
+            params![motivo.as_deref().map(str::trim).filter(|m| !m.is_empty()), alternativa, pedido_id, id],
+        )?;
+        captura.registrar(tx, &ctx, Modulo::Vendas, "converter")?;
+        
+        let origem = format!("Orçamento {} rev. {}", orcamento.numero, orcamento.revisao);
+        vendas::aprovar(tx, &ctx, pedido_id, Some(&origem))?;
+        let confirmacao = vendas::confirmar(tx, &ctx, pedido_id, permitir_encomenda.unwrap_or(false), Some(&origem))?;
+        
+        info!("🤝 Orçamento {} convertido no pedido ID {}", orcamento.numero, pedido_id);
+        
+        let pedido = vendas::fetch_pedido(tx, pedido_id)?.ok_or(AppError::NotFound("Pedido não encontrado".into()))?;
+        Ok(ConversaoOrcamento { pedido, pendencia_credito: confirmacao.err() })
+    })
+}
+
+/// Carrega o orçamento com os itens da revisão atual
+fn fetch_orcamento(conn: &Connection, id: i64) -> AppResult<Orcamento> {
+    let result = conn.query_row(&format!("{} WHERE o.id = ?1", SELECT_ORCAMENTO), params![id], map_orcamento);
+    
+    let mut orcamento = match result {
+        Ok(orcamento) => orcamento,
+        Err(rusqlite::Error::QueryReturnedNoRows) => return Err(AppError::NotFound("Orçamento não encontrado".into())),
+        Err(e) => return Err(AppError::Database(e.to_string())),
+    };
+    
+    orcamento.itens = itens(conn, id, orcamento.revisao)?;
+    
+    let mut totais: BTreeMap<i64, Dinheiro> = BTreeMap::new();
+    for item in &orcamento.itens {
+        *totais.entry(item.alternativa).or_default() += item.total;
+    }
+    orcamento.alternativas = totais.into_iter()
+        .map(|(alternativa, total)| OrcamentoAlternativa { alternativa, total })
+        .collect();
+    
+    Ok(orcamento)
+}
+
+fn map_orcamento(row: &rusqlite::Row) -> rusqlite::Result<Orcamento> {
+    Ok(Orcamento {
+        id: row.get(0)?,
+        numero: row.get(1)?,
+        cliente_id: row.get(2)?,
+        cliente_nome: row.get(3)?,
+        vendedor_id: row.get(4)?,
+        vendedor_nome: row.get(5)?,
+        data_emissao: row.get(6)?,
+        revisao: row.get(7)?,
+        validade: row.get(8)?,
+        vencido: row.get(9)?,
+        status: row.get(10)?,
+        motivo: row.get(11)?,
+        alternativa_aceita: row.get(12)?,
+        pedido_id: row.get(13)?,
+        forma_pagamento: row.get(14)?,
+        condicao_pagamento: row.get(15)?,
+        condicao_pagamento_id: row.get(16)?,
+        observacoes: row.get(17)?,
+        alternativas: Vec::new(),
+        itens: Vec::new(),
+        created_at: row.get(18)?,
+        updated_at: row.get(19)?,
+    })
+}
+
+fn itens(conn: &Connection, orcamento_id: i64, revisao: i64) -> AppResult<Vec<OrcamentoItem>> {
+    let mut stmt = conn.prepare(
+        "SELECT i.id, i.alternativa, i.produto_id, p.nome, i.quantidade, i.preco_unitario, i.desconto,
+                i.desconto_percentual, i.total
+         FROM orcamento_itens i
+         INNER JOIN orcamento_revisoes r ON r.id = i.revisao_id
+         LEFT JOIN produtos p ON p.id = i.produto_id
+         WHERE r.orcamento_id = ?1 AND r.revisao = ?2
+         ORDER BY i.alternativa ASC, i.id ASC"
+    )?;
+    let itens = stmt.query_map(params![orcamento_id, revisao], |row| {
+        Ok(OrcamentoItem {
+            id: row.get(0)?,
+            alternativa: row.get(1)?,
+            produto_id: row.get(2)?,
+            produto_nome: row.get(3)?,
+            quantidade: row.get(4)?,
+            preco_unitario: row.get(5)?,
+            desconto: row.get(6)?,
+            desconto_percentual: row.get(7)?,
+            total: row.get(8)?,
+        })
+    })?.collect::<Result<_, _>>()?;
+    
+    Ok(itens)
+}
+
+/// Grava a revisão com seus itens, já com preços e totais calculados
+fn gravar_revisao(
+    tx: &Connection,
+    ctx: &RequestContext,
+    orcamento_id: i64,
+    revisao: i64,
+    input: &OrcamentoInput,
+    motivo: Option<&str>,
+) -> AppResult<()> {
+    if input.itens.is_empty() {
+        return Err(AppError::Validation("Orçamento sem itens".into()));
+    }
+    
+    let hoje = Utc::now().date_naive();
+    let validade = match &input.validade {
+        Some(data) => NaiveDate::parse_from_str(data, "%Y-%m-%d")
+            .map_err(|_| AppError::Validation(format!("Validade inválida: '{}'", data)))?,
+        None => hoje + Duration::days(VALIDADE_PADRAO_DIAS),
+    };
+    if validade < hoje {
+        return Err(AppError::Validation("Validade não pode ser anterior a hoje".into()));
+    }
+    
+    let condicao_pagamento = vendas::resolver_condicao(tx, input.condicao_pagamento_id, input.condicao_pagamento.clone())?;
+    
+    tx.execute(
+        "INSERT INTO orcamento_revisoes (orcamento_id, revisao, validade, forma_pagamento, condicao_pagamento,
+                                         condicao_pagamento_id, observacoes, motivo, usuario_id)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+        params![
+            orcamento_id, revisao, validade.format("%Y-%m-%d").to_string(), input.forma_pagamento,
+            condicao_pagamento, input.condicao_pagamento_id, input.observacoes, motivo, ctx.usuario.id
+        ],
+    )?;
+    let revisao_id = tx.last_insert_rowid();
+    
+    for item in &input.itens {
+        let alternativa = item.alternativa.unwrap_or(1);
+        if alternativa < 1 {
+            return Err(AppError::Validation("Alternativas são numeradas a partir de 1".into()));
+        }
+        if !item.quantidade.is_finite() || item.quantidade <= 0.0 {
+            return Err(AppError::Validation("Quantidade deve ser maior que zero".into()));
+        }
+        
+        let efetivo = precos::resolver(tx, Some(input.cliente_id), item.produto_id, item.quantidade, hoje)?;
+        let preco_unitario = item.preco_unitario.unwrap_or(efetivo.preco);
+        if preco_unitario.is_negative() {
+            return Err(AppError::Validation("Preço unitário não pode ser negativo".into()));
+        }
+        
+        let bruto = preco_unitario.vezes(item.quantidade)?;
+        let desconto = match item.desconto {
+            Some(Ajuste::Percentual(p)) if !(0.0..=100.0).contains(&p) => {
+                return Err(AppError::Validation("Desconto do item deve estar entre 0% e 100%".into()));
+            }
+            Some(Ajuste::Percentual(p)) => bruto.percentual(p)?,
+            Some(Ajuste::Valor(v)) => v,
+            None => Dinheiro::ZERO,
+        };
+        if desconto.is_negative() || desconto > bruto {
+            return Err(AppError::Validation("Desconto do item deve ficar entre zero e o valor do item".into()));
+        }
+        
+        tx.execute(
+            "INSERT INTO orcamento_itens (revisao_id, alternativa, produto_id, quantidade, preco_unitario,
+                                          desconto, desconto_percentual, total)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                revisao_id, alternativa, item.produto_id, item.quantidade, preco_unitario,
+                desconto, item.desconto.and_then(|d| d.percentual()), bruto - desconto
+            ],
+        )?;
+    }
+    
+    Ok(())
+}
+
+fn exigir_aberto(orcamento: &Orcamento) -> AppResult<()> {
+    if orcamento.status != "aberto" {
+        return Err(AppError::Validation(format!(
+            "Orçamento {} já foi encerrado como '{}'", orcamento.numero, orcamento.status
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testes::*;
+    
+    #[test]
+    fn orcamento_revisado_vira_pedido_confirmado() {
+        let db = banco_teste(TOKEN);
+        let produto_id = produto(&db);
+        let cliente_id = cliente(&db);
+        estoque(&db, 5.0);
+        
+        let input = |preco_alternativa_2: f64| serde_json::from_value(serde_json::json!({
+            "cliente_id": cliente_id,
+            "condicao_pagamento": "30/60",
+            "itens": [
+                { "produto_id": produto_id, "quantidade": 2 },
+                { "alternativa": 2, "produto_id": produto_id, "quantidade": 3, "preco_unitario": preco_alternativa_2,
+                  "desconto": { "percentual": 10 } }
+            ]
+        })).unwrap();
+        
+        let orcamento = executar(create_orcamento(db.state(), TOKEN.into(), input(9.0))).unwrap();
+        assert_eq!(orcamento.revisao, 1);
+        let totais: Vec<(i64, i64)> = orcamento.alternativas.iter().map(|a| (a.alternativa, a.total.em_centavos())).collect();
+        assert_eq!(totais, vec![(1, 2000), (2, 2430)]);
+        
+        let revisado = executar(revisar_orcamento(db.state(), TOKEN.into(), orcamento.id, input(8.0), Some("Contraproposta".into()))).unwrap();
+        assert_eq!(revisado.revisao, 2);
+        let revisoes = executar(get_revisoes_orcamento(db.state(), TOKEN.into(), orcamento.id)).unwrap();
+        assert_eq!(revisoes.len(), 2);
+        assert_eq!(revisoes[0].itens[1].preco_unitario, Dinheiro::centavos(900));
+        
+        // Com duas alternativas, a aceita precisa ser informada
+        assert!(executar(converter_orcamento(db.state(), TOKEN.into(), orcamento.id, None, None, None)).is_err());
+        
+        let conversao = executar(converter_orcamento(db.state(), TOKEN.into(), orcamento.id, Some(2), Some("Melhor preço".into()), None)).unwrap();
+        assert!(conversao.pendencia_credito.is_none());
+        let pedido = conversao.pedido;
+        assert_eq!(pedido.status, "confirmado");
+        assert_eq!(pedido.condicao_pagamento.as_deref(), Some("30/60"));
+        assert_eq!(pedido.itens.len(), 1);
+        assert_eq!(pedido.itens[0].preco_unitario, Dinheiro::centavos(800));
+        assert_eq!(pedido.total, Dinheiro::centavos(2160));
+        
+        let status: (String, Option<i64>, Option<i64>) = db.reader().unwrap().query_row(
+            "SELECT status, alternativa_aceita, pedido_id FROM orcamentos WHERE id = ?1",
+            params![orcamento.id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        ).unwrap();
+        assert_eq!(status, ("ganho".to_string(), Some(2), Some(pedido.id)));
+        assert!(executar(perder_orcamento(db.state(), TOKEN.into(), orcamento.id, "Preço".into())).is_err());
+    }
+    
+    #[test]
+    fn conversao_acima_do_limite_fica_aguardando_credito() {
+        let db = banco_teste(TOKEN);
+        let produto_id = produto(&db);
+        let cliente_id = cliente(&db);
+        estoque(&db, 5.0);
+        db.writer().unwrap().execute("UPDATE clientes SET limite_credito = 1000 WHERE id = ?1", params![cliente_id]).unwrap();
+        
+        let orcamento = executar(create_orcamento(db.state(), TOKEN.into(), serde_json::from_value(serde_json::json!({
+            "cliente_id": cliente_id,
+            "itens": [{ "produto_id": produto_id, "quantidade": 2 }]
+        })).unwrap())).unwrap();
+        
+        let conversao = executar(converter_orcamento(db.state(), TOKEN.into(), orcamento.id, None, None, None)).unwrap();
+        assert_eq!(conversao.pedido.status, "aprovado");
+        assert!(conversao.pendencia_credito.unwrap().contains("Limite de crédito excedido"));
+        assert_eq!(contar(&db, "SELECT COUNT(*) FROM aprovacoes_credito WHERE status = 'pendente'"), 1);
+        assert_eq!(contar(&db, "SELECT COUNT(*) FROM orcamentos WHERE status = 'ganho'"), 1);
+    }
+}
@@ -0,0 +1,312 @@
+//! Comandos de Papéis e Permissões
+//!
+//! Gestão de papéis (perfis de acesso) e sua atribuição a usuários.
+//! Todos os comandos de gestão exigem perfil de administrador.
+
+use tauri::State;
+use rusqlite::{Connection, params};
+use log::info;
+
+use crate::error::{AppError, AppResult};
+use crate::models::{Papel, PapelInput, Permissoes, Modulo};
+use crate::database::DatabaseState;
+use crate::context::{self, RequestContext};
+use crate::auditoria;
+
+#[tauri::command]
+pub async fn get_papeis(db: State<'_, DatabaseState>, token: String) -> Result<Vec<Papel>, AppError> {
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require_admin()?;
+    
+    let conn = db.reader()?;
+    
+    let mut stmt = conn.prepare(
+        "SELECT p.id, p.nome, p.descricao, p.permissoes,
+                (SELECT COUNT(*) FROM usuario_papeis up WHERE up.papel_id = p.id),
+                p.created_at, p.updated_at
+         FROM papeis p ORDER BY p.nome ASC"
+    )?;
+    
+    let papeis = stmt.query_map([], map_papel)?
+        .filter_map(|r| r.ok())
+        .collect();
+    
+    Ok(papeis)
+}
+
+/// Carrega papel por ID na conexão informada
+pub(crate) fn fetch_papel(conn: &Connection, id: i64) -> AppResult<Option<Papel>> {
+    let result = conn.query_row(
+        "SELECT p.id, p.nome, p.descricao, p.permissoes,
+                (SELECT COUNT(*) FROM usuario_papeis up WHERE up.papel_id = p.id),
+                p.created_at, p.updated_at
+         FROM papeis p WHERE p.id = ?1",
+        params![id],
+        map_papel,
+    );
+    
+    match result {
+        Ok(p) => Ok(Some(p)),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+        Err(e) => Err(AppError::Database(e.to_string())),
+    }
+}
+
+fn map_papel(row: &rusqlite::Row) -> rusqlite::Result<Papel> {
+    let permissoes_str: String = row.get(3)?;
+    let permissoes: serde_json::Value = serde_json::from_str(&permissoes_str)
+        .unwrap_or(serde_json::json!({}));
+    
+    Ok(Papel {
+        id: row.get(0)?,
+        nome: row.get(1)?,
+        descricao: row.get(2)?,
+        permissoes: Permissoes::from_json(&permissoes),
+        usuarios: row.get(4)?,
+        created_at: row.get(5)?,
+        updated_at: row.get(6)?,
+    })
+}
+
+fn validar_papel(input: &PapelInput) -> AppResult<()> {
+    if input.nome.trim().is_empty() {
+        return Err(AppError::Validation("Nome do papel é obrigatório".into()));
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn create_papel(db: State<'_, DatabaseState>, token: String, input: PapelInput) -> Result<Papel, AppError> {
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require_admin()?;
+    validar_papel(&input)?;
+    
+    db.with_transaction(|tx| {
+        let existe: i64 = tx.query_row(
+            "SELECT COUNT(*) FROM papeis WHERE nome = ?1",
+            params![input.nome.trim()],
+            |row| row.get(0),
+        )?;
+        if existe > 0 {
+            return Err(AppError::Validation(format!("Papel '{}' já existe", input.nome.trim())));
+        }
+        
+        tx.execute(
+            "INSERT INTO papeis (nome, descricao, permissoes) VALUES (?1, ?2, ?3)",
+            params![input.nome.trim(), input.descricao, serde_json::to_string(&input.permissoes)?],
+        )?;
+        
+        let id = tx.last_insert_rowid();
+        auditoria::novo("papeis", id).registrar(tx, &ctx, Modulo::Config, "criar")?;
+        
+        info!("✅ Papel criado: {} (ID: {})", input.nome, id);
+        
+        fetch_papel(tx, id)?.ok_or(AppError::NotFound("Papel não encontrado".into()))
+    })
+}
+
+#[tauri::command]
+pub async fn update_papel(db: State<'_, DatabaseState>, token: String, id: i64, input: PapelInput) -> Result<Papel, AppError> {
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require_admin()?;
+    validar_papel(&input)?;
+    
+    db.with_transaction(|tx| {
+        let captura = auditoria::capturar(tx, "papeis", id)?;
+        
+        let existe: i64 = tx.query_row(
+            "SELECT COUNT(*) FROM papeis WHERE nome = ?1 AND id <> ?2",
+            params![input.nome.trim(), id],
+            |row| row.get(0),
+        )?;
+        if existe > 0 {
+            return Err(AppError::Validation(format!("Papel '{}' já existe", input.nome.trim())));
+        }
+        
+        let alterados = tx.execute(
+            "UPDATE papeis SET nome = ?1, descricao = ?2, permissoes = ?3, updated_at = datetime('now') WHERE id = ?4",
+            params![input.nome.trim(), input.descricao, serde_json::to_string(&input.permissoes)?, id],
+        )?;
+        if alterados == 0 {
+            return Err(AppError::NotFound("Papel não encontrado".into()));
+        }
+        
+        captura.registrar(tx, &ctx, Modulo::Config, "editar")?;
+        
+        info!("✅ Papel atualizado: {} (ID: {})", input.nome, id);
+        
+        fetch_papel(tx, id)?.ok_or(AppError::NotFound("Papel não encontrado".into()))
+    })
+}
+
+#[tauri::command]
+pub async fn delete_papel(db: State<'_, DatabaseState>, token: String, id: i64) -> Result<bool, AppError> {
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require_admin()?;
+    
+    db.with_transaction(|tx| {
+        let captura = auditoria::capturar(tx, "papeis", id)?;
+        
+        let vinculados: i64 = tx.query_row(
+            "SELECT COUNT(*) FROM usuario_papeis WHERE papel_id = ?1",
+            params![id],
+            |row| row.get(0),
+        )?;
+        if vinculados > 0 {
+            return Err(AppError::Validation(format!(
+                "Papel atribuído a {} usuário(s); remova as atribuições antes de excluir", vinculados
+            )));
+        }
+        
+        let removidos = tx.execute("DELETE FROM papeis WHERE id = ?1", params![id])?;
+        captura.registrar(tx, &ctx, Modulo::Config, "excluir")?;
+        
+        Ok(removidos > 0)
+    })
+}
+
+#[tauri::command]
+pub async fn assign_papel(db: State<'_, DatabaseState>, token: String, usuario_id: i64, papel_id: i64) -> Result<Permissoes, AppError> {
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require_admin()?;
+    
+    db.with_transaction(|tx| {
+        if fetch_papel(tx, papel_id)?.is_none() {
+            return Err(AppError::NotFound("Papel não encontrado".into()));
+        }
+        
+        let inseridos = tx.execute(
+            "INSERT OR IGNORE INTO usuario_papeis (usuario_id, papel_id)
+             SELECT id, ?2 FROM usuarios WHERE id = ?1",
+            params![usuario_id, papel_id],
+        )?;
+        if inseridos > 0 {
+            auditoria::registrar_evento(
+                tx, &ctx, Modulo::Config, "usuario_papeis", &usuario_id.to_string(),
+                "atribuir_papel", serde_json::json!({ "papel_id": papel_id }),
+            )?;
+        }
+        
+        info!("✅ Papel {} atribuído ao usuário {}", papel_id, usuario_id);
+        
+        permissoes_usuario(tx, usuario_id)
+    })
+}
+
+#[tauri::command]
+pub async fn remove_papel(db: State<'_, DatabaseState>, token: String, usuario_id: i64, papel_id: i64) -> Result<Permissoes, AppError> {
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require_admin()?;
+    
+    db.with_transaction(|tx| {
+        let removidos = tx.execute(
+            "DELETE FROM usuario_papeis WHERE usuario_id = ?1 AND papel_id = ?2",
+            params![usuario_id, papel_id],
+        )?;
+        if removidos > 0 {
+            auditoria::registrar_evento(
+                tx, &ctx, Modulo::Config, "usuario_papeis", &usuario_id.to_string(),
+                "remover_papel", serde_json::json!({ "papel_id": papel_id }),
+            )?;
+        }
+        
+        info!("🔒 Papel {} removido do usuário {}", papel_id, usuario_id);
+        
+        permissoes_usuario(tx, usuario_id)
+    })
+}
+
+/// Permissões efetivas de qualquer usuário (visão do administrador)
+#[tauri::command]
+pub async fn get_permissoes_usuario(db: State<'_, DatabaseState>, token: String, usuario_id: i64) -> Result<Permissoes, AppError> {
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require_admin()?;
+    
+    let conn = db.reader()?;
+    permissoes_usuario(&conn, usuario_id)
+}
+
+/// Permissões efetivas do usuário da sessão (para montar menus no frontend)
+#[tauri::command]
+pub async fn get_minhas_permissoes(db: State<'_, DatabaseState>, token: String) -> Result<Permissoes, AppError> {
+    let ctx = RequestContext::resolve(&db, &token)?;
+    Ok(ctx.permissoes)
+}
+
+/// Calcula as permissões efetivas do usuário na conexão informada
+fn permissoes_usuario(conn: &Connection, usuario_id: i64) -> AppResult<Permissoes> {
+    let result = conn.query_row(
+        "SELECT permissoes FROM usuarios WHERE id = ?1",
+        params![usuario_id],
+        |row| row.get::<_, String>(0),
+    );
+    
+    let diretas = match result {
+        Ok(s) => serde_json::from_str(&s).unwrap_or(serde_json::json!({})),
+        Err(rusqlite::Error::QueryReturnedNoRows) => {
+            return Err(AppError::NotFound("Usuário não encontrado".into()));
+        }
+        Err(e) => return Err(AppError::Database(e.to_string())),
+    };
+    
+    context::load_permissoes(conn, usuario_id, &diretas)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::vendas::get_pedidos;
+    use crate::models::Acao;
+    use crate::testes::*;
+    
+    fn vendedor(db: &DatabaseState) -> i64 {
+        db.reader().unwrap().query_row("SELECT id FROM papeis WHERE nome = 'Vendedor'", [], |row| row.get(0)).unwrap()
+    }
+    
+    #[test]
+    fn sem_permissao_o_comando_e_recusado() {
+        let db = banco_teste(TOKEN);
+        let id = usuario(&db, "operador@teste", "{}");
+        sessao(&db, id, "operador");
+        
+        let ctx = RequestContext::resolve(&db, "operador").unwrap();
+        assert!(matches!(ctx.require(Modulo::Vendas, Acao::Ler), Err(AppError::Authorization(_))));
+        assert!(matches!(ctx.require_admin(), Err(AppError::Authorization(_))));
+        assert!(matches!(
+            executar(get_pedidos(db.state(), "operador".into(), None, None, None)),
+            Err(AppError::Authorization(_))
+        ));
+        assert!(matches!(executar(get_papeis(db.state(), "operador".into())), Err(AppError::Authorization(_))));
+    }
+    
+    #[test]
+    fn papel_atribuido_soma_as_permissoes_do_usuario() {
+        let db = banco_teste(TOKEN);
+        let id = usuario(&db, "operador@teste", r#"{"modulos": {"financeiro": ["ler"]}}"#);
+        sessao(&db, id, "operador");
+        
+        let permissoes = executar(assign_papel(db.state(), TOKEN.into(), id, vendedor(&db))).unwrap();
+        assert!(permissoes.permite(Modulo::Vendas, Acao::Criar));
+        assert!(permissoes.permite(Modulo::Financeiro, Acao::Ler));
+        
+        let ctx = RequestContext::resolve(&db, "operador").unwrap();
+        assert!(ctx.require(Modulo::Vendas, Acao::Editar).is_ok());
+        assert!(ctx.require(Modulo::Vendas, Acao::Excluir).is_err());
+        assert!(ctx.require_any(&[Modulo::Compras, Modulo::Vendas], Acao::Ler).is_ok());
+        assert!(executar(get_pedidos(db.state(), "operador".into(), None, None, None)).is_ok());
+        
+        // Papel em uso não pode ser excluído; sem ele, a permissão some
+        assert!(matches!(executar(delete_papel(db.state(), TOKEN.into(), vendedor(&db))), Err(AppError::Validation(_))));
+        executar(remove_papel(db.state(), TOKEN.into(), id, vendedor(&db))).unwrap();
+        let ctx = RequestContext::resolve(&db, "operador").unwrap();
+        assert!(ctx.require(Modulo::Vendas, Acao::Ler).is_err());
+    }
+    
+    #[test]
+    fn formato_legado_all_vale_como_administrador() {
+        let permissoes = Permissoes::from_json(&serde_json::json!({ "admin": false, "all": true }));
+        assert!(permissoes.admin);
+        assert!(permissoes.permite(Modulo::Rh, Acao::Excluir));
+    }
+}
+
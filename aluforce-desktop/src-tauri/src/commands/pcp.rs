@@ -1,12 +1,16 @@
 //! Comandos de PCP (Planejamento e Controle de Produção)
 
-use tauri::AppHandle;
-use rusqlite::{Connection, params};
+use tauri::State;
+use rusqlite::params;
+use rusqlite::types::Type;
 use log::info;
 
 use crate::error::AppError;
-use crate::models::{OrdemProducao, PaginatedResponse};
-use crate::database::get_database_path;
+use crate::models::{OrdemProducao, PaginatedResponse, Modulo, Acao, TipoDocumento, Dinheiro};
+use crate::database::DatabaseState;
+use crate::context::RequestContext;
+use crate::auditoria;
+use crate::sequencias;
 
 #[derive(serde::Serialize, serde::Deserialize)]
 pub struct EstoqueItem {
@@ -15,19 +19,22 @@ pub struct EstoqueItem {
     pub quantidade_fisica: f64,
     pub quantidade_reservada: f64,
     pub quantidade_disponivel: f64,
-    pub custo_medio: f64,
-    pub valor_estoque: f64,
+    pub custo_medio: Dinheiro,
+    pub valor_estoque: Dinheiro,
 }
 
 #[tauri::command]
 pub async fn get_ordens_producao(
-    app: AppHandle,
+    db: State<'_, DatabaseState>,
+    token: String,
     page: Option<i64>,
     per_page: Option<i64>,
     status: Option<String>,
 ) -> Result<PaginatedResponse<OrdemProducao>, AppError> {
-    let db_path = get_database_path(&app)?;
-    let conn = Connection::open(&db_path)?;
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require(Modulo::Pcp, Acao::Ler)?;
+    
+    let conn = db.reader()?;
     
     let page = page.unwrap_or(1);
     let per_page = per_page.unwrap_or(20);
@@ -92,7 +99,8 @@ pub async fn get_ordens_producao(
 
 #[tauri::command]
 pub async fn create_ordem_producao(
-    app: AppHandle,
+    db: State<'_, DatabaseState>,
+    token: String,
     produto_id: i64,
     quantidade: f64,
     data_prevista: Option<String>,
@@ -100,144 +108,120 @@ pub async fn create_ordem_producao(
     responsavel_id: Option<i64>,
     observacoes: Option<String>,
 ) -> Result<OrdemProducao, AppError> {
-    let db_path = get_database_path(&app)?;
-    let conn = Connection::open(&db_path)?;
-    
-    let count: i64 = conn.query_row("SELECT COUNT(*) + 1 FROM ordens_producao", [], |row| row.get(0))?;
-    let numero = format!("OP{:06}", count);
-    let data_inicio = chrono::Utc::now().format("%Y-%m-%d").to_string();
-    let prioridade_val = prioridade.unwrap_or("normal".into());
-    
-    conn.execute(
-        "INSERT INTO ordens_producao (numero, produto_id, quantidade, data_inicio, data_prevista, prioridade, responsavel_id, observacoes, status)
-         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, 'planejada')",
-        params![numero, produto_id, quantidade, data_inicio, data_prevista, &prioridade_val, responsavel_id, observacoes],
-    )?;
-    
-    let id = conn.last_insert_rowid();
-    
-    // Buscar nome do produto
-    let produto_nome: Option<String> = conn.query_row(
-        "SELECT nome FROM produtos WHERE id = ?1",
-        params![produto_id], |row| row.get(0)
-    ).ok();
-    
-    info!("✅ Ordem de produção criada: {} (ID: {})", numero, id);
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require(Modulo::Pcp, Acao::Criar)?;
     
-    Ok(OrdemProducao {
-        id,
-        numero,
-        produto_id: Some(produto_id),
-        produto_nome,
-        quantidade,
-        data_inicio: Some(data_inicio),
-        data_prevista,
-        data_conclusao: None,
-        status: "planejada".to_string(),
-        prioridade: prioridade_val,
-        responsavel_id,
-        responsavel_nome: None,
-        observacoes,
+    db.with_transaction(|tx| {
+        let numero = sequencias::proximo(tx, TipoDocumento::OrdemProducao, "")?.formatado;
+        let data_inicio = chrono::Utc::now().format("%Y-%m-%d").to_string();
+        let prioridade_val = prioridade.unwrap_or("normal".into());
+        
+        tx.execute(
+            "INSERT INTO ordens_producao (numero, produto_id, quantidade, data_inicio, data_prevista, prioridade, responsavel_id, observacoes, status)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, 'planejada')",
+            params![numero, produto_id, quantidade, data_inicio, data_prevista, &prioridade_val, responsavel_id, observacoes],
+        )?;
+        
+        let id = tx.last_insert_rowid();
+        auditoria::novo("ordens_producao", id).registrar(tx, &ctx, Modulo::Pcp, "criar")?;
+        
+        // Buscar nome do produto
+        let produto_nome: Option<String> = tx.query_row(
+            "SELECT nome FROM produtos WHERE id = ?1",
+            params![produto_id], |row| row.get(0)
+        ).ok();
+        
+        info!("✅ Ordem de produção criada: {} (ID: {})", numero, id);
+        
+        Ok(OrdemProducao {
+            id,
+            numero,
+            produto_id: Some(produto_id),
+            produto_nome,
+            quantidade,
+            data_inicio: Some(data_inicio),
+            data_prevista,
+            data_conclusao: None,
+            status: "planejada".to_string(),
+            prioridade: prioridade_val,
+            responsavel_id,
+            responsavel_nome: None,
+            observacoes,
+        })
     })
 }
 
 #[tauri::command]
 pub async fn update_ordem_producao(
-    app: AppHandle,
+    db: State<'_, DatabaseState>,
+    token: String,
     id: i64,
     status: Option<String>,
     data_conclusao: Option<String>,
     observacoes: Option<String>,
 ) -> Result<bool, AppError> {
-    let db_path = get_database_path(&app)?;
-    let conn = Connection::open(&db_path)?;
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require(Modulo::Pcp, Acao::Editar)?;
     
-    if let Some(st) = status {
-        conn.execute(
-            "UPDATE ordens_producao SET status = ?1, updated_at = datetime('now') WHERE id = ?2",
-            params![st, id]
-        )?;
+    db.with_transaction(|tx| {
+        let captura = auditoria::capturar(tx, "ordens_producao", id)?;
         
-        // Se concluída, atualizar data_conclusao
-        if st == "concluida" {
-            let data = chrono::Utc::now().format("%Y-%m-%d").to_string();
-            conn.execute(
-                "UPDATE ordens_producao SET data_conclusao = ?1 WHERE id = ?2",
-                params![data, id]
+        if let Some(st) = status {
+            tx.execute(
+                "UPDATE ordens_producao SET status = ?1, updated_at = datetime('now') WHERE id = ?2",
+                params![st, id]
             )?;
+            
+            // Se concluída, atualizar data_conclusao
+            if st == "concluida" {
+                let data = chrono::Utc::now().format("%Y-%m-%d").to_string();
+                tx.execute(
+                    "UPDATE ordens_producao SET data_conclusao = ?1 WHERE id = ?2",
+                    params![data, id]
+                )?;
+            }
         }
-    }
-    
-    if let Some(obs) = observacoes {
-        conn.execute(
-            "UPDATE ordens_producao SET observacoes = ?1, updated_at = datetime('now') WHERE id = ?2",
-            params![obs, id]
-        )?;
-    }
-    
-    Ok(true)
+        
+        if let Some(obs) = observacoes {
+            tx.execute(
+                "UPDATE ordens_producao SET observacoes = ?1, updated_at = datetime('now') WHERE id = ?2",
+                params![obs, id]
+            )?;
+        }
+        
+        captura.registrar(tx, &ctx, Modulo::Pcp, "editar")?;
+        
+        Ok(true)
+    })
 }
 
 #[tauri::command]
-pub async fn get_estoque(app: AppHandle, termo: Option<String>) -> Result<Vec<EstoqueItem>, AppError> {
-    let db_path = get_database_path(&app)?;
-    let conn = Connection::open(&db_path)?;
+pub async fn get_estoque(db: State<'_, DatabaseState>, token: String, termo: Option<String>) -> Result<Vec<EstoqueItem>, AppError> {
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require(Modulo::Pcp, Acao::Ler)?;
     
-    // Criar tabela de estoque se não existir
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS estoque_saldos (
-            codigo TEXT PRIMARY KEY,
-            descricao TEXT,
-            quantidade_fisica REAL DEFAULT 0,
-            quantidade_reservada REAL DEFAULT 0,
-            custo_medio REAL DEFAULT 0,
-            ultima_entrada TEXT,
-            ultima_saida TEXT
-        )", []
-    )?;
+    let conn = db.reader()?;
     
     let items = if let Some(t) = termo {
         let search = format!("%{}%", t);
         let mut stmt = conn.prepare(
             "SELECT codigo, descricao, quantidade_fisica, quantidade_reservada,
-                    (quantidade_fisica - quantidade_reservada) as disponivel, custo_medio,
-                    (quantidade_fisica * custo_medio) as valor
+                    (quantidade_fisica - quantidade_reservada) as disponivel, custo_medio
              FROM estoque_saldos
              WHERE codigo LIKE ?1 OR descricao LIKE ?1
              ORDER BY descricao ASC LIMIT 100"
         )?;
         
-        let result: Vec<EstoqueItem> = stmt.query_map(params![search], |row| {
-            Ok(EstoqueItem {
-                codigo: row.get(0)?,
-                descricao: row.get(1)?,
-                quantidade_fisica: row.get(2)?,
-                quantidade_reservada: row.get(3)?,
-                quantidade_disponivel: row.get(4)?,
-                custo_medio: row.get(5)?,
-                valor_estoque: row.get(6)?,
-            })
-        })?.filter_map(|r| r.ok()).collect();
+        let result: Vec<EstoqueItem> = stmt.query_map(params![search], map_estoque_item)?.filter_map(|r| r.ok()).collect();
         result
     } else {
         let mut stmt = conn.prepare(
             "SELECT codigo, descricao, quantidade_fisica, quantidade_reservada,
-                    (quantidade_fisica - quantidade_reservada) as disponivel, custo_medio,
-                    (quantidade_fisica * custo_medio) as valor
+                    (quantidade_fisica - quantidade_reservada) as disponivel, custo_medio
              FROM estoque_saldos ORDER BY descricao ASC LIMIT 100"
         )?;
         
-        let result: Vec<EstoqueItem> = stmt.query_map([], |row| {
-            Ok(EstoqueItem {
-                codigo: row.get(0)?,
-                descricao: row.get(1)?,
-                quantidade_fisica: row.get(2)?,
-                quantidade_reservada: row.get(3)?,
-                quantidade_disponivel: row.get(4)?,
-                custo_medio: row.get(5)?,
-                valor_estoque: row.get(6)?,
-            })
-        })?.filter_map(|r| r.ok()).collect();
+        let result: Vec<EstoqueItem> = stmt.query_map([], map_estoque_item)?.filter_map(|r| r.ok()).collect();
         result
     };
     
@@ -246,59 +230,76 @@ pub async fn get_estoque(app: AppHandle, termo: Option<String>) -> Result<Vec<Es
 
 #[tauri::command]
 pub async fn atualizar_estoque(
-    app: AppHandle,
+    db: State<'_, DatabaseState>,
+    token: String,
     codigo: String,
     quantidade: f64,
     tipo: String, // 'entrada' ou 'saida'
-    custo: Option<f64>,
+    custo: Option<Dinheiro>,
 ) -> Result<EstoqueItem, AppError> {
-    let db_path = get_database_path(&app)?;
-    let conn = Connection::open(&db_path)?;
-    
-    // Verificar se existe
-    let existe: i64 = conn.query_row(
-        "SELECT COUNT(*) FROM estoque_saldos WHERE codigo = ?1",
-        params![codigo], |row| row.get(0)
-    ).unwrap_or(0);
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require(Modulo::Pcp, Acao::Editar)?;
     
-    if existe == 0 {
-        // Criar novo registro
-        conn.execute(
-            "INSERT INTO estoque_saldos (codigo, quantidade_fisica, custo_medio) VALUES (?1, ?2, ?3)",
-            params![codigo, if tipo == "entrada" { quantidade } else { 0.0 }, custo.unwrap_or(0.0)],
-        )?;
-    } else {
-        // Atualizar existente
-        if tipo == "entrada" {
-            conn.execute(
-                "UPDATE estoque_saldos SET quantidade_fisica = quantidade_fisica + ?1, ultima_entrada = date('now') WHERE codigo = ?2",
-                params![quantidade, codigo],
+    db.with_transaction(|tx| {
+        let captura = auditoria::capturar_por(tx, "estoque_saldos", "codigo", &codigo)?;
+        
+        // Verificar se existe
+        let existe: i64 = tx.query_row(
+            "SELECT COUNT(*) FROM estoque_saldos WHERE codigo = ?1",
+            params![codigo], |row| row.get(0)
+        ).unwrap_or(0);
+        
+        if existe == 0 {
+            // Criar novo registro
+            tx.execute(
+                "INSERT INTO estoque_saldos (codigo, quantidade_fisica, custo_medio) VALUES (?1, ?2, ?3)",
+                params![codigo, if tipo == "entrada" { quantidade } else { 0.0 }, custo.unwrap_or_default()],
             )?;
         } else {
-            conn.execute(
-                "UPDATE estoque_saldos SET quantidade_fisica = quantidade_fisica - ?1, ultima_saida = date('now') WHERE codigo = ?2",
-                params![quantidade, codigo],
-            )?;
+            // Atualizar existente
+            if tipo == "entrada" {
+                tx.execute(
+                    "UPDATE estoque_saldos SET quantidade_fisica = quantidade_fisica + ?1, ultima_entrada = date('now') WHERE codigo = ?2",
+                    params![quantidade, codigo],
+                )?;
+            } else {
+                tx.execute(
+                    "UPDATE estoque_saldos SET quantidade_fisica = quantidade_fisica - ?1, ultima_saida = date('now') WHERE codigo = ?2",
+                    params![quantidade, codigo],
+                )?;
+            }
         }
-    }
-    
-    // Retornar item atualizado
-    let item = conn.query_row(
-        "SELECT codigo, descricao, quantidade_fisica, quantidade_reservada,
-                (quantidade_fisica - quantidade_reservada), custo_medio,
-                (quantidade_fisica * custo_medio)
-         FROM estoque_saldos WHERE codigo = ?1",
-        params![codigo],
-        |row| Ok(EstoqueItem {
-            codigo: row.get(0)?,
-            descricao: row.get(1)?,
-            quantidade_fisica: row.get(2)?,
-            quantidade_reservada: row.get(3)?,
-            quantidade_disponivel: row.get(4)?,
-            custo_medio: row.get(5)?,
-            valor_estoque: row.get(6)?,
-        })
-    )?;
-    
-    Ok(item)
+        
+        let acao = if tipo == "entrada" { "entrada" } else { "saida" };
+        captura.registrar(tx, &ctx, Modulo::Pcp, acao)?;
+        
+        // Retornar item atualizado
+        let item = tx.query_row(
+            "SELECT codigo, descricao, quantidade_fisica, quantidade_reservada,
+                    (quantidade_fisica - quantidade_reservada), custo_medio
+             FROM estoque_saldos WHERE codigo = ?1",
+            params![codigo],
+            map_estoque_item,
+        )?;
+        
+        Ok(item)
+    })
+}
+
+/// Valor do estoque calculado em centavos (custo médio × quantidade física)
+fn map_estoque_item(row: &rusqlite::Row) -> rusqlite::Result<EstoqueItem> {
+    let quantidade_fisica: f64 = row.get(2)?;
+    let custo_medio: Dinheiro = row.get(5)?;
+    let valor_estoque = custo_medio
+        .vezes(quantidade_fisica)
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(5, Type::Integer, Box::new(e)))?;
+    Ok(EstoqueItem {
+        codigo: row.get(0)?,
+        descricao: row.get(1)?,
+        quantidade_fisica,
+        quantidade_reservada: row.get(3)?,
+        quantidade_disponivel: row.get(4)?,
+        custo_medio,
+        valor_estoque,
+    })
 }
@@ -1,22 +1,27 @@
 //! Comandos de Produtos
 
-use tauri::AppHandle;
+use tauri::State;
 use rusqlite::{Connection, params};
 use log::info;
 
-use crate::error::AppError;
-use crate::models::{Produto, PaginatedResponse};
-use crate::database::get_database_path;
+use crate::error::{AppError, AppResult};
+use crate::models::{Produto, PaginatedResponse, Modulo, Acao, Dinheiro};
+use crate::database::DatabaseState;
+use crate::context::RequestContext;
+use crate::auditoria;
 
 #[tauri::command]
 pub async fn get_produtos(
-    app: AppHandle,
+    db: State<'_, DatabaseState>,
+    token: String,
     page: Option<i64>,
     per_page: Option<i64>,
     categoria: Option<String>,
 ) -> Result<PaginatedResponse<Produto>, AppError> {
-    let db_path = get_database_path(&app)?;
-    let conn = Connection::open(&db_path)?;
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require_any(&[Modulo::Vendas, Modulo::Compras, Modulo::Pcp], Acao::Ler)?;
+    
+    let conn = db.reader()?;
     
     let page = page.unwrap_or(1);
     let per_page = per_page.unwrap_or(20);
@@ -81,10 +86,16 @@ pub async fn get_produtos(
 }
 
 #[tauri::command]
-pub async fn get_produto_by_id(app: AppHandle, id: i64) -> Result<Option<Produto>, AppError> {
-    let db_path = get_database_path(&app)?;
-    let conn = Connection::open(&db_path)?;
+pub async fn get_produto_by_id(db: State<'_, DatabaseState>, token: String, id: i64) -> Result<Option<Produto>, AppError> {
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require_any(&[Modulo::Vendas, Modulo::Compras, Modulo::Pcp], Acao::Ler)?;
     
+    let conn = db.reader()?;
+    fetch_produto(&conn, id)
+}
+
+/// Carrega produto por ID na conexão informada
+pub(crate) fn fetch_produto(conn: &Connection, id: i64) -> AppResult<Option<Produto>> {
     let result = conn.query_row(
         "SELECT id, codigo, nome, descricao, categoria, unidade, preco_custo, preco_venda,
                 margem, estoque_minimo, estoque_atual, ncm, cest, origem, cfop,
@@ -111,73 +122,95 @@ pub async fn get_produto_by_id(app: AppHandle, id: i64) -> Result<Option<Produto
 
 #[tauri::command]
 pub async fn create_produto(
-    app: AppHandle,
+    db: State<'_, DatabaseState>,
+    token: String,
     codigo: Option<String>,
     nome: String,
     descricao: Option<String>,
     categoria: Option<String>,
     unidade: Option<String>,
-    preco_custo: Option<f64>,
-    preco_venda: Option<f64>,
+    preco_custo: Option<Dinheiro>,
+    preco_venda: Option<Dinheiro>,
 ) -> Result<Produto, AppError> {
-    let db_path = get_database_path(&app)?;
-    let conn = Connection::open(&db_path)?;
-    
-    let margem = match (preco_custo, preco_venda) {
-        (Some(custo), Some(venda)) if custo > 0.0 => ((venda - custo) / custo) * 100.0,
-        _ => 0.0,
-    };
-    
-    conn.execute(
-        "INSERT INTO produtos (codigo, nome, descricao, categoria, unidade, preco_custo, preco_venda, margem)
-         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
-        params![codigo, nome, descricao, categoria, unidade.unwrap_or("UN".into()),
-                preco_custo.unwrap_or(0.0), preco_venda.unwrap_or(0.0), margem],
-    )?;
-    
-    let id = conn.last_insert_rowid();
-    info!("✅ Produto criado: {} (ID: {})", nome, id);
-    
-    get_produto_by_id(app, id).await?.ok_or(AppError::NotFound("Produto não encontrado".into()))
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require(Modulo::Pcp, Acao::Criar)?;
+    
+    db.with_transaction(|tx| {
+        let margem = match (preco_custo, preco_venda) {
+            (Some(custo), Some(venda)) if custo > Dinheiro::ZERO => {
+                (venda - custo).em_centavos() as f64 / custo.em_centavos() as f64 * 100.0
+            }
+            _ => 0.0,
+        };
+        
+        tx.execute(
+            "INSERT INTO produtos (codigo, nome, descricao, categoria, unidade, preco_custo, preco_venda, margem)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![codigo, nome, descricao, categoria, unidade.unwrap_or("UN".into()),
+                    preco_custo.unwrap_or_default(), preco_venda.unwrap_or_default(), margem],
+        )?;
+        
+        let id = tx.last_insert_rowid();
+        auditoria::novo("produtos", id).registrar(tx, &ctx, Modulo::Pcp, "criar")?;
+        
+        info!("✅ Produto criado: {} (ID: {})", nome, id);
+        
+        fetch_produto(tx, id)?.ok_or(AppError::NotFound("Produto não encontrado".into()))
+    })
 }
 
 #[tauri::command]
 pub async fn update_produto(
-    app: AppHandle,
+    db: State<'_, DatabaseState>,
+    token: String,
     id: i64,
     codigo: Option<String>,
     nome: String,
     descricao: Option<String>,
     categoria: Option<String>,
-    preco_custo: Option<f64>,
-    preco_venda: Option<f64>,
+    preco_custo: Option<Dinheiro>,
+    preco_venda: Option<Dinheiro>,
 ) -> Result<Produto, AppError> {
-    let db_path = get_database_path(&app)?;
-    let conn = Connection::open(&db_path)?;
-    
-    conn.execute(
-        "UPDATE produtos SET codigo = ?1, nome = ?2, descricao = ?3, categoria = ?4,
-         preco_custo = ?5, preco_venda = ?6, updated_at = datetime('now') WHERE id = ?7",
-        params![codigo, nome, descricao, categoria, preco_custo.unwrap_or(0.0),
-                preco_venda.unwrap_or(0.0), id],
-    )?;
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require(Modulo::Pcp, Acao::Editar)?;
     
-    get_produto_by_id(app, id).await?.ok_or(AppError::NotFound("Produto não encontrado".into()))
+    db.with_transaction(|tx| {
+        let captura = auditoria::capturar(tx, "produtos", id)?;
+        
+        tx.execute(
+            "UPDATE produtos SET codigo = ?1, nome = ?2, descricao = ?3, categoria = ?4,
+             preco_custo = ?5, preco_venda = ?6, updated_at = datetime('now') WHERE id = ?7",
+            params![codigo, nome, descricao, categoria, preco_custo.unwrap_or_default(),
+                    preco_venda.unwrap_or_default(), id],
+        )?;
+        
+        captura.registrar(tx, &ctx, Modulo::Pcp, "editar")?;
+        
+        fetch_produto(tx, id)?.ok_or(AppError::NotFound("Produto não encontrado".into()))
+    })
 }
 
 #[tauri::command]
-pub async fn delete_produto(app: AppHandle, id: i64) -> Result<bool, AppError> {
-    let db_path = get_database_path(&app)?;
-    let conn = Connection::open(&db_path)?;
+pub async fn delete_produto(db: State<'_, DatabaseState>, token: String, id: i64) -> Result<bool, AppError> {
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require(Modulo::Pcp, Acao::Excluir)?;
     
-    conn.execute("UPDATE produtos SET ativo = 0, updated_at = datetime('now') WHERE id = ?1", params![id])?;
-    Ok(true)
+    db.with_transaction(|tx| {
+        let captura = auditoria::capturar(tx, "produtos", id)?;
+        
+        tx.execute("UPDATE produtos SET ativo = 0, updated_at = datetime('now') WHERE id = ?1", params![id])?;
+        
+        captura.registrar(tx, &ctx, Modulo::Pcp, "excluir")?;
+        Ok(true)
+    })
 }
 
 #[tauri::command]
-pub async fn search_produtos(app: AppHandle, termo: String, limit: Option<i64>) -> Result<Vec<Produto>, AppError> {
-    let db_path = get_database_path(&app)?;
-    let conn = Connection::open(&db_path)?;
+pub async fn search_produtos(db: State<'_, DatabaseState>, token: String, termo: String, limit: Option<i64>) -> Result<Vec<Produto>, AppError> {
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require_any(&[Modulo::Vendas, Modulo::Compras, Modulo::Pcp], Acao::Ler)?;
+    
+    let conn = db.reader()?;
     
     let search = format!("%{}%", termo);
     let limit = limit.unwrap_or(20);
@@ -1,22 +1,27 @@
 //! Comandos de RH (Recursos Humanos)
 
-use tauri::AppHandle;
+use tauri::State;
 use rusqlite::{Connection, params};
 use log::info;
 
-use crate::error::AppError;
-use crate::models::{Funcionario, RegistroPonto, PaginatedResponse};
-use crate::database::get_database_path;
+use crate::error::{AppError, AppResult};
+use crate::models::{Funcionario, RegistroPonto, PaginatedResponse, Modulo, Acao, Dinheiro};
+use crate::database::DatabaseState;
+use crate::context::RequestContext;
+use crate::auditoria;
 
 #[tauri::command]
 pub async fn get_funcionarios(
-    app: AppHandle,
+    db: State<'_, DatabaseState>,
+    token: String,
     page: Option<i64>,
     per_page: Option<i64>,
     ativo: Option<bool>,
 ) -> Result<PaginatedResponse<Funcionario>, AppError> {
-    let db_path = get_database_path(&app)?;
-    let conn = Connection::open(&db_path)?;
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require(Modulo::Rh, Acao::Ler)?;
+    
+    let conn = db.reader()?;
     
     let page = page.unwrap_or(1);
     let per_page = per_page.unwrap_or(20);
@@ -52,10 +57,16 @@ pub async fn get_funcionarios(
 }
 
 #[tauri::command]
-pub async fn get_funcionario_by_id(app: AppHandle, id: i64) -> Result<Option<Funcionario>, AppError> {
-    let db_path = get_database_path(&app)?;
-    let conn = Connection::open(&db_path)?;
+pub async fn get_funcionario_by_id(db: State<'_, DatabaseState>, token: String, id: i64) -> Result<Option<Funcionario>, AppError> {
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require(Modulo::Rh, Acao::Ler)?;
     
+    let conn = db.reader()?;
+    fetch_funcionario(&conn, id)
+}
+
+/// Carrega funcionário por ID na conexão informada
+pub(crate) fn fetch_funcionario(conn: &Connection, id: i64) -> AppResult<Option<Funcionario>> {
     let result = conn.query_row(
         "SELECT id, usuario_id, nome, cpf, rg, data_nascimento, data_admissao, data_demissao,
                 cargo, departamento, salario, email, telefone, cep, endereco, numero,
@@ -82,65 +93,80 @@ pub async fn get_funcionario_by_id(app: AppHandle, id: i64) -> Result<Option<Fun
 
 #[tauri::command]
 pub async fn create_funcionario(
-    app: AppHandle,
+    db: State<'_, DatabaseState>,
+    token: String,
     nome: String,
     cpf: Option<String>,
     cargo: Option<String>,
     departamento: Option<String>,
     data_admissao: Option<String>,
-    salario: Option<f64>,
+    salario: Option<Dinheiro>,
     email: Option<String>,
     telefone: Option<String>,
 ) -> Result<Funcionario, AppError> {
-    let db_path = get_database_path(&app)?;
-    let conn = Connection::open(&db_path)?;
-    
-    conn.execute(
-        "INSERT INTO funcionarios (nome, cpf, cargo, departamento, data_admissao, salario, email, telefone)
-         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
-        params![nome, cpf, cargo, departamento, data_admissao, salario.unwrap_or(0.0), email, telefone],
-    )?;
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require(Modulo::Rh, Acao::Criar)?;
     
-    let id = conn.last_insert_rowid();
-    info!("✅ Funcionário criado: {} (ID: {})", nome, id);
-    
-    get_funcionario_by_id(app, id).await?.ok_or(AppError::NotFound("Funcionário não encontrado".into()))
+    db.with_transaction(|tx| {
+        tx.execute(
+            "INSERT INTO funcionarios (nome, cpf, cargo, departamento, data_admissao, salario, email, telefone)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![nome, cpf, cargo, departamento, data_admissao, salario.unwrap_or_default(), email, telefone],
+        )?;
+        
+        let id = tx.last_insert_rowid();
+        auditoria::novo("funcionarios", id).registrar(tx, &ctx, Modulo::Rh, "criar")?;
+        
+        info!("✅ Funcionário criado: {} (ID: {})", nome, id);
+        
+        fetch_funcionario(tx, id)?.ok_or(AppError::NotFound("Funcionário não encontrado".into()))
+    })
 }
 
 #[tauri::command]
 pub async fn update_funcionario(
-    app: AppHandle,
+    db: State<'_, DatabaseState>,
+    token: String,
     id: i64,
     nome: String,
     cpf: Option<String>,
     cargo: Option<String>,
     departamento: Option<String>,
-    salario: Option<f64>,
+    salario: Option<Dinheiro>,
     email: Option<String>,
     telefone: Option<String>,
 ) -> Result<Funcionario, AppError> {
-    let db_path = get_database_path(&app)?;
-    let conn = Connection::open(&db_path)?;
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require(Modulo::Rh, Acao::Editar)?;
     
-    conn.execute(
-        "UPDATE funcionarios SET nome = ?1, cpf = ?2, cargo = ?3, departamento = ?4,
-         salario = ?5, email = ?6, telefone = ?7, updated_at = datetime('now')
-         WHERE id = ?8",
-        params![nome, cpf, cargo, departamento, salario.unwrap_or(0.0), email, telefone, id],
-    )?;
-    
-    get_funcionario_by_id(app, id).await?.ok_or(AppError::NotFound("Funcionário não encontrado".into()))
+    db.with_transaction(|tx| {
+        let captura = auditoria::capturar(tx, "funcionarios", id)?;
+        
+        tx.execute(
+            "UPDATE funcionarios SET nome = ?1, cpf = ?2, cargo = ?3, departamento = ?4,
+             salario = ?5, email = ?6, telefone = ?7, updated_at = datetime('now')
+             WHERE id = ?8",
+            params![nome, cpf, cargo, departamento, salario.unwrap_or_default(), email, telefone, id],
+        )?;
+        
+        captura.registrar(tx, &ctx, Modulo::Rh, "editar")?;
+        
+        fetch_funcionario(tx, id)?.ok_or(AppError::NotFound("Funcionário não encontrado".into()))
+    })
 }
 
 #[tauri::command]
 pub async fn get_controle_ponto(
-    app: AppHandle,
+    db: State<'_, DatabaseState>,
+    token: String,
     funcionario_id: Option<i64>,
     data_inicio: Option<String>,
     data_fim: Option<String>,
 ) -> Result<Vec<RegistroPonto>, AppError> {
-    let db_path = get_database_path(&app)?;
-    let conn = Connection::open(&db_path)?;
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require(Modulo::Rh, Acao::Ler)?;
+    
+    let conn = db.reader()?;
     
     let registros = if let Some(fid) = funcionario_id {
         let mut stmt = conn.prepare(
@@ -186,63 +212,74 @@ pub async fn get_controle_ponto(
 
 #[tauri::command]
 pub async fn registrar_ponto(
-    app: AppHandle,
+    db: State<'_, DatabaseState>,
+    token: String,
     funcionario_id: i64,
     tipo: String, // 'entrada', 'saida_almoco', 'retorno_almoco', 'saida'
 ) -> Result<RegistroPonto, AppError> {
-    let db_path = get_database_path(&app)?;
-    let conn = Connection::open(&db_path)?;
-    
-    let hoje = chrono::Utc::now().format("%Y-%m-%d").to_string();
-    let hora = chrono::Utc::now().format("%H:%M:%S").to_string();
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require(Modulo::Rh, Acao::Criar)?;
     
-    // Verificar se já existe registro para hoje
-    let existe: i64 = conn.query_row(
-        "SELECT COUNT(*) FROM controle_ponto WHERE funcionario_id = ?1 AND data = ?2",
-        params![funcionario_id, hoje], |row| row.get(0)
-    ).unwrap_or(0);
-    
-    if existe == 0 && tipo == "entrada" {
-        // Criar novo registro
-        conn.execute(
-            "INSERT INTO controle_ponto (funcionario_id, data, entrada) VALUES (?1, ?2, ?3)",
-            params![funcionario_id, hoje, hora],
-        )?;
-    } else if existe > 0 {
-        // Atualizar registro existente
-        let campo = match tipo.as_str() {
-            "entrada" => "entrada",
-            "saida_almoco" => "saida_almoco",
-            "retorno_almoco" => "retorno_almoco",
-            "saida" => "saida",
-            _ => return Err(AppError::Validation("Tipo de ponto inválido".into())),
-        };
+    db.with_transaction(|tx| {
+        let hoje = chrono::Utc::now().format("%Y-%m-%d").to_string();
+        let hora = chrono::Utc::now().format("%H:%M:%S").to_string();
         
-        conn.execute(
-            &format!("UPDATE controle_ponto SET {} = ?1 WHERE funcionario_id = ?2 AND data = ?3", campo),
-            params![hora, funcionario_id, hoje],
+        // Verificar se já existe registro para hoje
+        let existe: i64 = tx.query_row(
+            "SELECT COUNT(*) FROM controle_ponto WHERE funcionario_id = ?1 AND data = ?2",
+            params![funcionario_id, hoje], |row| row.get(0)
+        ).unwrap_or(0);
+        
+        if existe == 0 && tipo == "entrada" {
+            // Criar novo registro
+            tx.execute(
+                "INSERT INTO controle_ponto (funcionario_id, data, entrada) VALUES (?1, ?2, ?3)",
+                params![funcionario_id, hoje, hora],
+            )?;
+            auditoria::novo("controle_ponto", tx.last_insert_rowid()).registrar(tx, &ctx, Modulo::Rh, "criar")?;
+        } else if existe > 0 {
+            // Atualizar registro existente
+            let campo = match tipo.as_str() {
+                "entrada" => "entrada",
+                "saida_almoco" => "saida_almoco",
+                "retorno_almoco" => "retorno_almoco",
+                "saida" => "saida",
+                _ => return Err(AppError::Validation("Tipo de ponto inválido".into())),
+            };
+            
+            let ponto_id: i64 = tx.query_row(
+                "SELECT id FROM controle_ponto WHERE funcionario_id = ?1 AND data = ?2",
+                params![funcionario_id, hoje], |row| row.get(0)
+            )?;
+            let captura = auditoria::capturar(tx, "controle_ponto", ponto_id)?;
+            
+            tx.execute(
+                &format!("UPDATE controle_ponto SET {} = ?1 WHERE funcionario_id = ?2 AND data = ?3", campo),
+                params![hora, funcionario_id, hoje],
+            )?;
+            captura.registrar(tx, &ctx, Modulo::Rh, "editar")?;
+        } else {
+            return Err(AppError::Validation("Registre a entrada primeiro".into()));
+        }
+        
+        info!("✅ Ponto registrado: {} - {} às {}", funcionario_id, tipo, hora);
+        
+        // Retornar registro
+        let registro = tx.query_row(
+            "SELECT cp.id, cp.funcionario_id, f.nome, cp.data, cp.entrada, cp.saida_almoco,
+                    cp.retorno_almoco, cp.saida, cp.horas_trabalhadas, cp.observacoes
+             FROM controle_ponto cp
+             LEFT JOIN funcionarios f ON f.id = cp.funcionario_id
+             WHERE cp.funcionario_id = ?1 AND cp.data = ?2",
+            params![funcionario_id, hoje],
+            |row| Ok(RegistroPonto {
+                id: row.get(0)?, funcionario_id: row.get(1)?, funcionario_nome: row.get(2)?,
+                data: row.get(3)?, entrada: row.get(4)?, saida_almoco: row.get(5)?,
+                retorno_almoco: row.get(6)?, saida: row.get(7)?, horas_trabalhadas: row.get(8)?,
+                observacoes: row.get(9)?,
+            })
         )?;
-    } else {
-        return Err(AppError::Validation("Registre a entrada primeiro".into()));
-    }
-    
-    info!("✅ Ponto registrado: {} - {} às {}", funcionario_id, tipo, hora);
-    
-    // Retornar registro
-    let registro = conn.query_row(
-        "SELECT cp.id, cp.funcionario_id, f.nome, cp.data, cp.entrada, cp.saida_almoco,
-                cp.retorno_almoco, cp.saida, cp.horas_trabalhadas, cp.observacoes
-         FROM controle_ponto cp
-         LEFT JOIN funcionarios f ON f.id = cp.funcionario_id
-         WHERE cp.funcionario_id = ?1 AND cp.data = ?2",
-        params![funcionario_id, hoje],
-        |row| Ok(RegistroPonto {
-            id: row.get(0)?, funcionario_id: row.get(1)?, funcionario_nome: row.get(2)?,
-            data: row.get(3)?, entrada: row.get(4)?, saida_almoco: row.get(5)?,
-            retorno_almoco: row.get(6)?, saida: row.get(7)?, horas_trabalhadas: row.get(8)?,
-            observacoes: row.get(9)?,
-        })
-    )?;
-    
-    Ok(registro)
+        
+        Ok(registro)
+    })
 }
@@ -0,0 +1,184 @@
+//! Comandos do assistente de primeira execução
+//!
+//! Cadastra a empresa e o primeiro administrador em um banco novo.
+//! Não exigem sessão (ainda não há usuários), mas só funcionam enquanto
+//! a instalação não tiver sido concluída.
+
+use tauri::State;
+use rusqlite::{Connection, params};
+use log::info;
+
+use crate::error::{AppError, AppResult};
+use crate::models::{SetupInput, SetupStatus, UsuarioPublico};
+use crate::database::DatabaseState;
+use crate::commands::auth::validar_senha;
+use crate::commands::usuarios::{fetch_usuario, validar_usuario};
+
+/// Informa se o assistente de primeira execução já foi concluído
+#[tauri::command]
+pub async fn get_setup_status(db: State<'_, DatabaseState>) -> Result<SetupStatus, AppError> {
+    let conn = db.reader()?;
+    fetch_setup_status(&conn)
+}
+
+/// Carrega a situação da instalação na conexão informada
+pub(crate) fn fetch_setup_status(conn: &Connection) -> AppResult<SetupStatus> {
+    let result = conn.query_row(
+        "SELECT concluida_em FROM instalacao WHERE id = 1",
+        [],
+        |row| row.get::<_, String>(0),
+    );
+    
+    match result {
+        Ok(concluida_em) => Ok(SetupStatus { concluido: true, concluido_em: Some(concluida_em) }),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(SetupStatus { concluido: false, concluido_em: None }),
+        Err(e) => Err(AppError::Database(e.to_string())),
+    }
+}
+
+/// Cria a empresa e o primeiro administrador e bloqueia o assistente
+#[tauri::command]
+pub async fn concluir_setup(db: State<'_, DatabaseState>, input: SetupInput) -> Result<UsuarioPublico, AppError> {
+    let empresa = &input.empresa;
+    let admin = &input.administrador;
+    
+    if empresa.razao_social.trim().is_empty() {
+        return Err(AppError::Validation("Razão social é obrigatória".into()));
+    }
+    if empresa.cnpj.as_deref().map(str::trim).unwrap_or("").is_empty() {
+        return Err(AppError::Validation("CNPJ é obrigatório".into()));
+    }
+    validar_senha(&input.senha)?;
+    
+    let senha_hash = bcrypt::hash(&input.senha, bcrypt::DEFAULT_COST)?;
+    
+    db.with_transaction(|tx| {
+        // Verificação dentro da transação de escrita: dois assistentes abertos
+        // ao mesmo tempo não conseguem concluir ambos
+        let usuarios: i64 = tx.query_row("SELECT COUNT(*) FROM usuarios", [], |row| row.get(0))?;
+        if fetch_setup_status(tx)?.concluido || usuarios > 0 {
+            return Err(AppError::Authorization("Configuração inicial já foi concluída".into()));
+        }
+        
+        validar_usuario(tx, admin, None)?;
+        
+        tx.execute(
+            "INSERT INTO empresas (razao_social, nome_fantasia, cnpj, inscricao_estadual, inscricao_municipal,
+             telefone, email, cep, endereco, numero, complemento, bairro, cidade, estado)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)",
+            params![
+                empresa.razao_social.trim(), empresa.nome_fantasia, empresa.cnpj.as_deref().map(str::trim),
+                empresa.inscricao_estadual, empresa.inscricao_municipal, empresa.telefone, empresa.email,
+                empresa.cep, empresa.endereco, empresa.numero, empresa.complemento, empresa.bairro,
+                empresa.cidade, empresa.estado
+            ],
+        )?;
+        let empresa_id = tx.last_insert_rowid();
+        
+        tx.execute(
+            "INSERT INTO usuarios (nome, email, senha_hash, cargo, departamento, avatar, permissoes)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                admin.nome.trim(), admin.email.trim(), senha_hash,
+                admin.cargo, admin.departamento, admin.avatar, r#"{"admin": true}"#
+            ],
+        )?;
+        let usuario_id = tx.last_insert_rowid();
+        
+        tx.execute(
+            "INSERT INTO usuario_papeis (usuario_id, papel_id)
+             SELECT ?1, id FROM papeis WHERE nome = 'Administrador'",
+            params![usuario_id],
+        )?;
+        
+        tx.execute(
+            "INSERT INTO instalacao (id, empresa_id, usuario_id) VALUES (1, ?1, ?2)",
+            params![empresa_id, usuario_id],
+        )?;
+        
+        let usuario = fetch_usuario(tx, usuario_id)?
+            .ok_or(AppError::NotFound("Usuário não encontrado".into()))?;
+        
+        info!("✅ Configuração inicial concluída: {} / {}", empresa.razao_social.trim(), admin.email.trim());
+        
+        Ok(usuario.into())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::auth::login;
+    use crate::models::{EmpresaInput, UsuarioInput};
+    use crate::testes::*;
+    
+    fn banco_vazio() -> BancoTeste {
+        let dir = std::env::temp_dir().join(format!("aluforce-teste-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        BancoTeste::novo(DatabaseState::open(&dir.join("aluforce.db")).unwrap())
+    }
+    
+    fn input(cnpj: Option<&str>) -> SetupInput {
+        SetupInput {
+            empresa: EmpresaInput {
+                razao_social: "Alumínios Ltda".into(),
+                nome_fantasia: None,
+                cnpj: cnpj.map(Into::into),
+                inscricao_estadual: None,
+                inscricao_municipal: None,
+                telefone: None,
+                email: None,
+                cep: None,
+                endereco: None,
+                numero: None,
+                complemento: None,
+                bairro: None,
+                cidade: None,
+                estado: None,
+            },
+            administrador: UsuarioInput {
+                nome: "Administrador".into(),
+                email: "admin@empresa".into(),
+                cargo: None,
+                departamento: None,
+                avatar: None,
+                permissoes: None,
+            },
+            senha: "senha-escolhida".into(),
+        }
+    }
+    
+    #[test]
+    fn banco_novo_nao_tem_usuario_padrao() {
+        let db = banco_vazio();
+        assert!(!executar(get_setup_status(db.state())).unwrap().concluido);
+        assert_eq!(contar(&db, "SELECT COUNT(*) FROM usuarios"), 0);
+        assert_eq!(contar(&db, "SELECT COUNT(*) FROM empresas"), 0);
+    }
+    
+    #[test]
+    fn setup_cria_empresa_e_administrador_e_se_bloqueia() {
+        let db = banco_vazio();
+        
+        assert!(matches!(executar(concluir_setup(db.state(), input(None))), Err(AppError::Validation(_))));
+        assert_eq!(contar(&db, "SELECT COUNT(*) FROM empresas"), 0);
+        
+        let admin = executar(concluir_setup(db.state(), input(Some("11.222.333/0001-81")))).unwrap();
+        assert_eq!(admin.email, "admin@empresa");
+        assert!(!admin.trocar_senha);
+        assert!(executar(get_setup_status(db.state())).unwrap().concluido);
+        assert_eq!(contar(&db, "SELECT COUNT(*) FROM usuario_papeis"), 1);
+        
+        assert!(matches!(
+            executar(concluir_setup(db.state(), input(Some("11.222.333/0001-81")))),
+            Err(AppError::Authorization(_))
+        ));
+        assert_eq!(contar(&db, "SELECT COUNT(*) FROM usuarios"), 1);
+        
+        let resposta = executar(login(
+            db.state(), db.state(), "admin@empresa".into(), "senha-escolhida".into(),
+        )).unwrap();
+        assert!(resposta.success);
+    }
+}
+
@@ -1,10 +1,13 @@
 //! Comandos do Sistema
 
-use tauri::AppHandle;
+use tauri::State;
 use log::info;
 
 use crate::error::AppError;
-use crate::models::AppInfo;
+use crate::models::{AppInfo, Modulo, Acao, Dinheiro};
+use crate::database::DatabaseState;
+use crate::context::RequestContext;
+use crate::auditoria;
 
 #[tauri::command]
 pub async fn get_app_info() -> Result<AppInfo, AppError> {
@@ -26,25 +29,27 @@ pub async fn check_updates() -> Result<Option<String>, AppError> {
 
 #[tauri::command]
 pub async fn export_data(
-    app: AppHandle,
+    db: State<'_, DatabaseState>,
+    token: String,
     tabela: String,
     formato: String, // 'json' ou 'csv'
 ) -> Result<String, AppError> {
-    use crate::database::get_database_path;
-    use rusqlite::Connection;
-    
-    let db_path = get_database_path(&app)?;
-    let conn = Connection::open(&db_path)?;
-    
-    // Validar tabela (prevenir SQL injection)
-    let tabelas_permitidas = vec![
-        "clientes", "fornecedores", "produtos", "pedidos_venda", 
-        "contas_pagar", "contas_receber", "funcionarios", "notas_fiscais"
-    ];
+    let ctx = RequestContext::resolve(&db, &token)?;
+    
+    // Validar tabela (prevenir SQL injection) e exigir leitura no módulo dono
+    let modulos: &[Modulo] = match tabela.as_str() {
+        "clientes" => &[Modulo::Vendas, Modulo::Financeiro, Modulo::Nfe],
+        "fornecedores" => &[Modulo::Compras, Modulo::Financeiro],
+        "produtos" => &[Modulo::Vendas, Modulo::Compras, Modulo::Pcp],
+        "pedidos_venda" => &[Modulo::Vendas],
+        "contas_pagar" | "contas_receber" => &[Modulo::Financeiro],
+        "funcionarios" => &[Modulo::Rh],
+        "notas_fiscais" => &[Modulo::Nfe],
+        _ => return Err(AppError::Validation(format!("Tabela '{}' não permitida para exportação", tabela))),
+    };
+    ctx.require_any(modulos, Acao::Ler)?;
     
-    if !tabelas_permitidas.contains(&tabela.as_str()) {
-        return Err(AppError::Validation(format!("Tabela '{}' não permitida para exportação", tabela)));
-    }
+    let conn = db.reader()?;
     
     let mut stmt = conn.prepare(&format!("SELECT * FROM {} LIMIT 10000", tabela))?;
     let column_count = stmt.column_count();
@@ -90,67 +95,80 @@ pub async fn export_data(
     Ok(result)
 }
 
+/// Colunas importáveis que guardam dinheiro
+const COLUNAS_MONETARIAS: &[&str] = &["limite_credito", "preco_custo", "preco_venda"];
+
 #[tauri::command]
 pub async fn import_data(
-    app: AppHandle,
+    db: State<'_, DatabaseState>,
+    token: String,
     tabela: String,
     dados: String,
     formato: String,
 ) -> Result<i64, AppError> {
-    use crate::database::get_database_path;
-    use rusqlite::Connection;
-    
-    let db_path = get_database_path(&app)?;
-    let conn = Connection::open(&db_path)?;
-    
-    // Validar tabela
-    let tabelas_permitidas = vec!["clientes", "fornecedores", "produtos"];
-    
-    if !tabelas_permitidas.contains(&tabela.as_str()) {
-        return Err(AppError::Validation(format!("Tabela '{}' não permitida para importação", tabela)));
-    }
-    
-    let mut count = 0i64;
+    let ctx = RequestContext::resolve(&db, &token)?;
+    
+    // Validar tabela e exigir criação no módulo dono do cadastro
+    let (tabela, modulo) = match tabela.as_str() {
+        "clientes" => ("clientes", Modulo::Vendas),
+        "fornecedores" => ("fornecedores", Modulo::Compras),
+        "produtos" => ("produtos", Modulo::Pcp),
+        _ => return Err(AppError::Validation(format!("Tabela '{}' não permitida para importação", tabela))),
+    };
+    ctx.require(modulo, Acao::Criar)?;
     
-    if formato == "json" {
-        let registros: Vec<serde_json::Value> = serde_json::from_str(&dados)?;
+    db.with_transaction(|tx| {
+        let mut count = 0i64;
         
-        for registro in registros {
-            if let serde_json::Value::Object(obj) = registro {
-                // Construir INSERT dinâmico
-                let columns: Vec<&str> = obj.keys().map(|k| k.as_str()).collect();
-                let values: Vec<String> = obj.values().map(|v| {
-                    match v {
-                        serde_json::Value::String(s) => s.clone(),
-                        serde_json::Value::Number(n) => n.to_string(),
-                        serde_json::Value::Bool(b) => if *b { "1" } else { "0" }.to_string(),
-                        serde_json::Value::Null => "".to_string(),
-                        _ => v.to_string(),
-                    }
-                }).collect();
-                
-                if !columns.is_empty() {
-                    let placeholders: Vec<&str> = (0..columns.len()).map(|_| "?").collect();
-                    let sql = format!(
-                        "INSERT OR IGNORE INTO {} ({}) VALUES ({})",
-                        tabela,
-                        columns.join(", "),
-                        placeholders.join(", ")
-                    );
+        if formato == "json" {
+            let registros: Vec<serde_json::Value> = serde_json::from_str(&dados)?;
+            
+            for registro in registros {
+                if let serde_json::Value::Object(obj) = registro {
+                    // Construir INSERT dinâmico
+                    let columns: Vec<&str> = obj.keys().map(|k| k.as_str()).collect();
+                    let values: Vec<String> = obj.iter().map(|(coluna, v)| {
+                        // Valores monetários chegam em reais e são gravados em centavos
+                        if COLUNAS_MONETARIAS.contains(&coluna.as_str()) {
+                            if let Ok(valor) = serde_json::from_value::<Dinheiro>(v.clone()) {
+                                return valor.em_centavos().to_string();
+                            }
+                        }
+                        match v {
+                            serde_json::Value::String(s) => s.clone(),
+                            serde_json::Value::Number(n) => n.to_string(),
+                            serde_json::Value::Bool(b) => if *b { "1" } else { "0" }.to_string(),
+                            serde_json::Value::Null => "".to_string(),
+                            _ => v.to_string(),
+                        }
+                    }).collect();
                     
-                    let params: Vec<&dyn rusqlite::ToSql> = values.iter()
-                        .map(|v| v as &dyn rusqlite::ToSql)
-                        .collect();
-                    
-                    if conn.execute(&sql, params.as_slice()).is_ok() {
-                        count += 1;
+                    if !columns.is_empty() {
+                        let placeholders: Vec<&str> = (0..columns.len()).map(|_| "?").collect();
+                        let sql = format!(
+                            "INSERT OR IGNORE INTO {} ({}) VALUES ({})",
+                            tabela,
+                            columns.join(", "),
+                            placeholders.join(", ")
+                        );
+                        
+                        let params: Vec<&dyn rusqlite::ToSql> = values.iter()
+                            .map(|v| v as &dyn rusqlite::ToSql)
+                            .collect();
+                        
+                        if let Ok(inseridos) = tx.execute(&sql, params.as_slice()) {
+                            if inseridos > 0 {
+                                auditoria::novo(tabela, tx.last_insert_rowid()).registrar(tx, &ctx, modulo, "importar")?;
+                            }
+                            count += 1;
+                        }
                     }
                 }
             }
         }
-    }
-    
-    info!("📥 Dados importados: {} ({} registros)", tabela, count);
-    
-    Ok(count)
+        
+        info!("📥 Dados importados: {} ({} registros)", tabela, count);
+        
+        Ok(count)
+    })
 }
@@ -0,0 +1,296 @@
+//! Comandos de Tabelas de Preço
+//!
+//! A resolução do preço efetivo fica em `crate::precos`; aqui o cadastro
+//! das tabelas e dos seus itens.
+
+use tauri::State;
+use rusqlite::{Connection, params};
+use log::info;
+use chrono::{NaiveDate, Utc};
+
+use crate::error::{AppError, AppResult};
+use crate::models::{TabelaPreco, TabelaPrecoInput, TabelaPrecoItemInput, PrecoEfetivo, Modulo, Acao};
+use crate::database::DatabaseState;
+use crate::context::RequestContext;
+use crate::auditoria;
+use crate::precos;
+
+#[tauri::command]
+pub async fn get_tabelas_preco(
+    db: State<'_, DatabaseState>,
+    token: String,
+    incluir_inativas: Option<bool>,
+) -> Result<Vec<TabelaPreco>, AppError> {
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require(Modulo::Vendas, Acao::Ler)?;
+    
+    let conn = db.reader()?;
+    
+    let mut stmt = conn.prepare(
+        "SELECT id FROM tabelas_preco WHERE ativo = 1 OR ?1 ORDER BY nome ASC"
+    )?;
+    let ids: Vec<i64> = stmt.query_map(params![incluir_inativas.unwrap_or(false)], |row| row.get(0))?
+        .collect::<Result<_, _>>()?;
+    
+    ids.into_iter().map(|id| precos::tabela(&conn, id)).collect()
+}
+
+#[tauri::command]
+pub async fn get_tabela_preco(db: State<'_, DatabaseState>, token: String, id: i64) -> Result<TabelaPreco, AppError> {
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require(Modulo::Vendas, Acao::Ler)?;
+    
+    let conn = db.reader()?;
+    precos::tabela(&conn, id)
+}
+
+#[tauri::command]
+pub async fn create_tabela_preco(
+    db: State<'_, DatabaseState>,
+    token: String,
+    input: TabelaPrecoInput,
+) -> Result<TabelaPreco, AppError> {
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require(Modulo::Vendas, Acao::Criar)?;
+    
+    validar(&input)?;
+    
+    db.with_transaction(|tx| {
+        exigir_nome_livre(tx, &input.nome, None)?;
+        
+        tx.execute(
+            "INSERT INTO tabelas_preco (nome, descricao, ajuste_percentual, validade_inicio, validade_fim)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                input.nome.trim(), input.descricao, input.ajuste_percentual.unwrap_or(0.0),
+                input.validade_inicio, input.validade_fim
+            ],
+        )?;
+        
+        let id = tx.last_insert_rowid();
+        auditoria::novo("tabelas_preco", id).registrar(tx, &ctx, Modulo::Vendas, "criar")?;
+        
+        info!("✅ Tabela de preço criada: {} (ID: {})", input.nome.trim(), id);
+        
+        precos::tabela(tx, id)
+    })
+}
+
+#[tauri::command]
+pub async fn update_tabela_preco(
+    db: State<'_, DatabaseState>,
+    token: String,
+    id: i64,
+    input: TabelaPrecoInput,
+) -> Result<TabelaPreco, AppError> {
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require(Modulo::Vendas, Acao::Editar)?;
+    
+    validar(&input)?;
+    
+    db.with_transaction(|tx| {
+        precos::tabela(tx, id)?;
+        exigir_nome_livre(tx, &input.nome, Some(id))?;
+        
+        let captura = auditoria::capturar(tx, "tabelas_preco", id)?;
+        
+        tx.execute(
+            "UPDATE tabelas_preco SET
+                nome = ?1, descricao = ?2, ajuste_percentual = ?3, validade_inicio = ?4, validade_fim = ?5,
+                updated_at = datetime('now')
+             WHERE id = ?6",
+            params![
+                input.nome.trim(), input.descricao, input.ajuste_percentual.unwrap_or(0.0),
+                input.validade_inicio, input.validade_fim, id
+            ],
+        )?;
+        
+        captura.registrar(tx, &ctx, Modulo::Vendas, "editar")?;
+        
+        info!("✅ Tabela de preço atualizada: {} (ID: {})", input.nome.trim(), id);
+        
+        precos::tabela(tx, id)
+    })
+}
+
+/// Desativa a tabela (soft delete); os clientes passam a usar o preço do produto
+#[tauri::command]
+pub async fn delete_tabela_preco(db: State<'_, DatabaseState>, token: String, id: i64) -> Result<bool, AppError> {
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require(Modulo::Vendas, Acao::Excluir)?;
+    
+    db.with_transaction(|tx| {
+        precos::tabela(tx, id)?;
+        let captura = auditoria::capturar(tx, "tabelas_preco", id)?;
+        
+        tx.execute(
+            "UPDATE tabelas_preco SET ativo = 0, updated_at = datetime('now') WHERE id = ?1",
+            params![id],
+        )?;
+        
+        captura.registrar(tx, &ctx, Modulo::Vendas, "excluir")?;
+        
+        info!("🗑️ Tabela de preço desativada: ID {}", id);
+        
+        Ok(true)
+    })
+}
+
+/// Inclui ou substitui o preço do produto na faixa de quantidade
+#[tauri::command]
+pub async fn save_item_tabela_preco(
+    db: State<'_, DatabaseState>,
+    token: String,
+    tabela_id: i64,
+    item: TabelaPrecoItemInput,
+) -> Result<TabelaPreco, AppError> {
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require(Modulo::Vendas, Acao::Editar)?;
+    
+    let quantidade_minima = item.quantidade_minima.unwrap_or(0.0);
+    if !quantidade_minima.is_finite() || quantidade_minima < 0.0 {
+        return Err(AppError::Validation("Quantidade mínima não pode ser negativa".into()));
+    }
+    match (item.preco, item.ajuste_percentual) {
+        (Some(preco), None) if preco.is_negative() => {
+            return Err(AppError::Validation("Preço não pode ser negativo".into()));
+        }
+        (None, Some(ajuste)) if !ajuste.is_finite() || ajuste <= -100.0 => {
+            return Err(AppError::Validation("Desconto não pode chegar a 100%".into()));
+        }
+        (Some(_), None) | (None, Some(_)) => {}
+        _ => {
+            return Err(AppError::Validation("Informe o preço ou o percentual do item, não ambos".into()));
+        }
+    }
+    
+    db.with_transaction(|tx| {
+        precos::tabela(tx, tabela_id)?;
+        
+        let produtos: i64 = tx.query_row(
+            "SELECT COUNT(*) FROM produtos WHERE id = ?1 AND ativo = 1",
+            params![item.produto_id],
+            |row| row.get(0),
+        )?;
+        if produtos == 0 {
+            return Err(AppError::Validation(format!("Produto {} não encontrado ou inativo", item.produto_id)));
+        }
+        
+        let existente = tx.query_row(
+            "SELECT id FROM tabela_preco_itens WHERE tabela_id = ?1 AND produto_id = ?2 AND quantidade_minima = ?3",
+            params![tabela_id, item.produto_id, quantidade_minima],
+            |row| row.get::<_, i64>(0),
+        );
+        
+        match existente {
+            Ok(item_id) => {
+                let captura = auditoria::capturar(tx, "tabela_preco_itens", item_id)?;
+                tx.execute(
+                    "UPDATE tabela_preco_itens SET preco = ?1, ajuste_percentual = ?2 WHERE id = ?3",
+                    params![item.preco, item.ajuste_percentual, item_id],
+                )?;
+                captura.registrar(tx, &ctx, Modulo::Vendas, "editar")?;
+            }
+            Err(rusqlite::Error::QueryReturnedNoRows) => {
+                tx.execute(
+                    "INSERT INTO tabela_preco_itens (tabela_id, produto_id, quantidade_minima, preco, ajuste_percentual)
+                     VALUES (?1, ?2, ?3, ?4, ?5)",
+                    params![tabela_id, item.produto_id, quantidade_minima, item.preco, item.ajuste_percentual],
+                )?;
+                let item_id = tx.last_insert_rowid();
+                auditoria::novo("tabela_preco_itens", item_id).registrar(tx, &ctx, Modulo::Vendas, "criar")?;
+            }
+            Err(e) => return Err(AppError::Database(e.to_string())),
+        }
+        
+        info!("✅ Item da tabela de preço {} salvo: produto {}", tabela_id, item.produto_id);
+        
+        precos::tabela(tx, tabela_id)
+    })
+}
+
+#[tauri::command]
+pub async fn remove_item_tabela_preco(
+    db: State<'_, DatabaseState>,
+    token: String,
+    tabela_id: i64,
+    item_id: i64,
+) -> Result<TabelaPreco, AppError> {
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require(Modulo::Vendas, Acao::Editar)?;
+    
+    db.with_transaction(|tx| {
+        let captura = auditoria::capturar(tx, "tabela_preco_itens", item_id)?;
+        
+        let removidos = tx.execute(
+            "DELETE FROM tabela_preco_itens WHERE id = ?1 AND tabela_id = ?2",
+            params![item_id, tabela_id],
+        )?;
+        if removidos == 0 {
+            return Err(AppError::NotFound("Item da tabela de preço não encontrado".into()));
+        }
+        
+        captura.registrar(tx, &ctx, Modulo::Vendas, "excluir")?;
+        
+        info!("🗑️ Item {} removido da tabela de preço {}", item_id, tabela_id);
+        
+        precos::tabela(tx, tabela_id)
+    })
+}
+
+/// Preço efetivo do produto para o cliente, na quantidade e data (hoje, se omitida)
+#[tauri::command]
+pub async fn get_preco_efetivo(
+    db: State<'_, DatabaseState>,
+    token: String,
+    cliente_id: Option<i64>,
+    produto_id: i64,
+    quantidade: Option<f64>,
+    data: Option<String>,
+) -> Result<PrecoEfetivo, AppError> {
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require(Modulo::Vendas, Acao::Ler)?;
+    
+    let data = match data {
+        Some(data) => parse_data(&data)?,
+        None => Utc::now().date_naive(),
+    };
+    
+    let conn = db.reader()?;
+    precos::resolver(&conn, cliente_id, produto_id, quantidade.unwrap_or(1.0), data)
+}
+
+fn validar(input: &TabelaPrecoInput) -> AppResult<()> {
+    if input.nome.trim().is_empty() {
+        return Err(AppError::Validation("Nome da tabela de preço é obrigatório".into()));
+    }
+    if input.ajuste_percentual.is_some_and(|p| !p.is_finite() || p <= -100.0) {
+        return Err(AppError::Validation("Desconto não pode chegar a 100%".into()));
+    }
+    
+    let inicio = input.validade_inicio.as_deref().map(parse_data).transpose()?;
+    let fim = input.validade_fim.as_deref().map(parse_data).transpose()?;
+    if let (Some(inicio), Some(fim)) = (inicio, fim) {
+        if fim < inicio {
+            return Err(AppError::Validation("Fim da validade é anterior ao início".into()));
+        }
+    }
+    Ok(())
+}
+
+fn parse_data(data: &str) -> AppResult<NaiveDate> {
+    NaiveDate::parse_from_str(data, "%Y-%m-%d")
+        .map_err(|_| AppError::Validation(format!("Data inválida: '{}'", data)))
+}
+
+fn exigir_nome_livre(conn: &Connection, nome: &str, id: Option<i64>) -> AppResult<()> {
+    let existentes: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM tabelas_preco WHERE nome = ?1 AND id <> ?2",
+        params![nome.trim(), id.unwrap_or(0)],
+        |row| row.get(0),
+    )?;
+    if existentes > 0 {
+        return Err(AppError::Validation(format!("Já existe uma tabela de preço '{}'", nome.trim())));
+    }
+    Ok(())
+}
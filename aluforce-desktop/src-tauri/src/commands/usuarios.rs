@@ -0,0 +1,412 @@
+//! Comandos de Usuários
+//!
+//! Cadastro de usuários pelo administrador. Senhas definidas pelo
+//! administrador (criação e reset) exigem troca no próximo login.
+
+use tauri::State;
+use rusqlite::{Connection, params};
+use log::info;
+
+use crate::error::{AppError, AppResult};
+use crate::models::{Usuario, UsuarioInput, TentativaLogin, EventoBloqueio, Modulo};
+use crate::database::DatabaseState;
+use crate::context::RequestContext;
+use crate::auditoria;
+use crate::commands::auth::validar_senha;
+
+#[tauri::command]
+pub async fn list_usuarios(
+    db: State<'_, DatabaseState>,
+    token: String,
+    incluir_inativos: Option<bool>,
+) -> Result<Vec<Usuario>, AppError> {
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require_admin()?;
+    
+    let conn = db.reader()?;
+    
+    let mut stmt = conn.prepare(
+        "SELECT id, nome, email, senha_hash, cargo, departamento, avatar, ativo,
+                permissoes, trocar_senha, ultimo_acesso, created_at, updated_at
+         FROM usuarios WHERE ativo = 1 OR ?1 = 1
+         ORDER BY nome ASC"
+    )?;
+    
+    let usuarios = stmt.query_map(params![incluir_inativos.unwrap_or(false)], map_usuario)?
+        .filter_map(|r| r.ok())
+        .collect();
+    
+    Ok(usuarios)
+}
+
+/// Carrega usuário por ID na conexão informada
+pub(crate) fn fetch_usuario(conn: &Connection, id: i64) -> AppResult<Option<Usuario>> {
+    let result = conn.query_row(
+        "SELECT id, nome, email, senha_hash, cargo, departamento, avatar, ativo,
+                permissoes, trocar_senha, ultimo_acesso, created_at, updated_at
+         FROM usuarios WHERE id = ?1",
+        params![id],
+        map_usuario,
+    );
+    
+    match result {
+        Ok(u) => Ok(Some(u)),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+        Err(e) => Err(AppError::Database(e.to_string())),
+    }
+}
+
+fn map_usuario(row: &rusqlite::Row) -> rusqlite::Result<Usuario> {
+    let permissoes_str: String = row.get(8)?;
+    
+    Ok(Usuario {
+        id: row.get(0)?,
+        nome: row.get(1)?,
+        email: row.get(2)?,
+        senha_hash: row.get(3)?,
+        cargo: row.get(4)?,
+        departamento: row.get(5)?,
+        avatar: row.get(6)?,
+        ativo: row.get(7)?,
+        permissoes: serde_json::from_str(&permissoes_str).unwrap_or(serde_json::json!({})),
+        trocar_senha: row.get(9)?,
+        ultimo_acesso: row.get(10)?,
+        created_at: row.get(11)?,
+        updated_at: row.get(12)?,
+    })
+}
+
+/// Valida campos obrigatórios e e-mail único
+pub(crate) fn validar_usuario(conn: &Connection, input: &UsuarioInput, id: Option<i64>) -> AppResult<()> {
+    if input.nome.trim().is_empty() {
+        return Err(AppError::Validation("Nome é obrigatório".into()));
+    }
+    
+    let email = input.email.trim();
+    if email.is_empty() || !email.contains('@') {
+        return Err(AppError::Validation("E-mail inválido".into()));
+    }
+    
+    let existe: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM usuarios WHERE email = ?1 AND id <> ?2",
+        params![email, id.unwrap_or(0)],
+        |row| row.get(0),
+    )?;
+    if existe > 0 {
+        return Err(AppError::Validation(format!("E-mail '{}' já cadastrado", email)));
+    }
+    
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn create_usuario(
+    db: State<'_, DatabaseState>,
+    token: String,
+    input: UsuarioInput,
+    senha: String,
+) -> Result<Usuario, AppError> {
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require_admin()?;
+    validar_senha(&senha)?;
+    
+    let senha_hash = bcrypt::hash(&senha, bcrypt::DEFAULT_COST)?;
+    let permissoes = serde_json::to_string(&input.permissoes.clone().unwrap_or_default())?;
+    
+    db.with_transaction(|tx| {
+        validar_usuario(tx, &input, None)?;
+        
+        tx.execute(
+            "INSERT INTO usuarios (nome, email, senha_hash, cargo, departamento, avatar, permissoes, trocar_senha)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, 1)",
+            params![
+                input.nome.trim(), input.email.trim(), senha_hash,
+                input.cargo, input.departamento, input.avatar, permissoes
+            ],
+        )?;
+        
+        let id = tx.last_insert_rowid();
+        auditoria::novo("usuarios", id).registrar(tx, &ctx, Modulo::Config, "criar")?;
+        
+        info!("✅ Usuário criado: {} (ID: {})", input.email.trim(), id);
+        
+        fetch_usuario(tx, id)?.ok_or(AppError::NotFound("Usuário não encontrado".into()))
+    })
+}
+
+#[tauri::command]
+pub async fn update_usuario(
+    db: State<'_, DatabaseState>,
+    token: String,
+    id: i64,
+    input: UsuarioInput,
+) -> Result<Usuario, AppError> {
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require_admin()?;
+    
+    // Sem `permissoes` no input, mantém as permissões diretas atuais
+    let permissoes = input.permissoes.as_ref().map(serde_json::to_string).transpose()?;
+    
+    db.with_transaction(|tx| {
+        validar_usuario(tx, &input, Some(id))?;
+        let captura = auditoria::capturar(tx, "usuarios", id)?;
+        
+        let alterados = tx.execute(
+            "UPDATE usuarios SET nome = ?1, email = ?2, cargo = ?3, departamento = ?4, avatar = ?5,
+                    permissoes = COALESCE(?6, permissoes), updated_at = datetime('now')
+             WHERE id = ?7",
+            params![
+                input.nome.trim(), input.email.trim(),
+                input.cargo, input.departamento, input.avatar, permissoes, id
+            ],
+        )?;
+        if alterados == 0 {
+            return Err(AppError::NotFound("Usuário não encontrado".into()));
+        }
+        
+        captura.registrar(tx, &ctx, Modulo::Config, "editar")?;
+        
+        info!("✅ Usuário atualizado: {} (ID: {})", input.email.trim(), id);
+        
+        fetch_usuario(tx, id)?.ok_or(AppError::NotFound("Usuário não encontrado".into()))
+    })
+}
+
+/// Desativa usuário e encerra todas as suas sessões
+#[tauri::command]
+pub async fn deactivate_usuario(db: State<'_, DatabaseState>, token: String, id: i64) -> Result<bool, AppError> {
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require_admin()?;
+    
+    if id == ctx.usuario.id {
+        return Err(AppError::Validation("Não é possível desativar o próprio usuário".into()));
+    }
+    
+    db.with_transaction(|tx| {
+        let captura = auditoria::capturar(tx, "usuarios", id)?;
+        
+        let alterados = tx.execute(
+            "UPDATE usuarios SET ativo = 0, updated_at = datetime('now') WHERE id = ?1",
+            params![id],
+        )?;
+        if alterados == 0 {
+            return Err(AppError::NotFound("Usuário não encontrado".into()));
+        }
+        
+        let sessoes = tx.execute("DELETE FROM sessoes WHERE usuario_id = ?1", params![id])?;
+        captura.registrar(tx, &ctx, Modulo::Config, "desativar")?;
+        
+        info!("🔒 Usuário {} desativado ({} sessões revogadas)", id, sessoes);
+        
+        Ok(true)
+    })
+}
+
+/// Define nova senha pelo administrador e exige troca no próximo login
+#[tauri::command]
+pub async fn reset_password(
+    db: State<'_, DatabaseState>,
+    token: String,
+    usuario_id: i64,
+    nova_senha: String,
+) -> Result<bool, AppError> {
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require_admin()?;
+    validar_senha(&nova_senha)?;
+    
+    let nova_hash = bcrypt::hash(&nova_senha, bcrypt::DEFAULT_COST)?;
+    
+    db.with_transaction(|tx| {
+        let captura = auditoria::capturar(tx, "usuarios", usuario_id)?;
+        
+        let alterados = tx.execute(
+            "UPDATE usuarios SET senha_hash = ?1, trocar_senha = 1, updated_at = datetime('now') WHERE id = ?2",
+            params![nova_hash, usuario_id],
+        )?;
+        if alterados == 0 {
+            return Err(AppError::NotFound("Usuário não encontrado".into()));
+        }
+        
+        // Sessões abertas com a senha antiga deixam de valer
+        tx.execute("DELETE FROM sessoes WHERE usuario_id = ?1", params![usuario_id])?;
+        captura.registrar(tx, &ctx, Modulo::Config, "redefinir_senha")?;
+        
+        info!("🔐 Senha redefinida para usuário ID: {}", usuario_id);
+        
+        Ok(true)
+    })
+}
+
+/// E-mails com falhas de login pendentes ou bloqueio registrado
+#[tauri::command]
+pub async fn get_tentativas_login(db: State<'_, DatabaseState>, token: String) -> Result<Vec<TentativaLogin>, AppError> {
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require_admin()?;
+    
+    let conn = db.reader()?;
+    
+    let mut stmt = conn.prepare(
+        "SELECT email, falhas, ultima_falha, bloqueado_ate
+         FROM tentativas_login ORDER BY ultima_falha DESC"
+    )?;
+    
+    let tentativas = stmt.query_map([], |row| {
+        Ok(TentativaLogin {
+            email: row.get(0)?,
+            falhas: row.get(1)?,
+            ultima_falha: row.get(2)?,
+            bloqueado_ate: row.get(3)?,
+        })
+    })?
+    .filter_map(|r| r.ok())
+    .collect();
+    
+    Ok(tentativas)
+}
+
+/// Histórico de bloqueios e desbloqueios de login
+#[tauri::command]
+pub async fn get_eventos_bloqueio(
+    db: State<'_, DatabaseState>,
+    token: String,
+    email: Option<String>,
+    limit: Option<i64>,
+) -> Result<Vec<EventoBloqueio>, AppError> {
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require_admin()?;
+    
+    let conn = db.reader()?;
+    
+    let mut stmt = conn.prepare(
+        "SELECT e.id, e.email, e.tipo, e.falhas, e.bloqueado_ate, e.usuario_id, u.nome, e.created_at
+         FROM eventos_bloqueio e
+         LEFT JOIN usuarios u ON u.id = e.usuario_id
+         WHERE ?1 IS NULL OR e.email = ?1
+         ORDER BY e.id DESC LIMIT ?2"
+    )?;
+    
+    let eventos = stmt.query_map(params![email, limit.unwrap_or(100)], |row| {
+        Ok(EventoBloqueio {
+            id: row.get(0)?,
+            email: row.get(1)?,
+            tipo: row.get(2)?,
+            falhas: row.get(3)?,
+            bloqueado_ate: row.get(4)?,
+            usuario_id: row.get(5)?,
+            usuario_nome: row.get(6)?,
+            created_at: row.get(7)?,
+        })
+    })?
+    .filter_map(|r| r.ok())
+    .collect();
+    
+    Ok(eventos)
+}
+
+/// Libera o login do e-mail e zera as falhas acumuladas
+#[tauri::command]
+pub async fn desbloquear_login(db: State<'_, DatabaseState>, token: String, email: String) -> Result<bool, AppError> {
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require_admin()?;
+    
+    let email = email.trim();
+    
+    db.with_transaction(|tx| {
+        let removidos = tx.execute("DELETE FROM tentativas_login WHERE email = ?1", params![email])?;
+        if removidos == 0 {
+            return Ok(false);
+        }
+        
+        tx.execute(
+            "INSERT INTO eventos_bloqueio (email, tipo, usuario_id) VALUES (?1, 'desbloqueio', ?2)",
+            params![email, ctx.usuario.id],
+        )?;
+        
+        info!("🔓 Login desbloqueado: {} (por usuário ID: {})", email, ctx.usuario.id);
+        
+        Ok(true)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testes::*;
+    
+    fn input(email: &str) -> UsuarioInput {
+        UsuarioInput {
+            nome: "Operador".into(),
+            email: email.into(),
+            cargo: None,
+            departamento: None,
+            avatar: None,
+            permissoes: None,
+        }
+    }
+    
+    fn sessoes(db: &DatabaseState, id: i64) -> i64 {
+        contar(db, &format!("SELECT COUNT(*) FROM sessoes WHERE usuario_id = {}", id))
+    }
+    
+    #[test]
+    fn usuario_criado_troca_a_senha_e_email_e_unico() {
+        let db = banco_teste(TOKEN);
+        
+        assert!(matches!(
+            executar(create_usuario(db.state(), TOKEN.into(), input("operador@teste"), "curta".into())),
+            Err(AppError::Validation(_))
+        ));
+        let usuario = executar(create_usuario(db.state(), TOKEN.into(), input("operador@teste"), "senha-forte".into())).unwrap();
+        assert!(usuario.trocar_senha);
+        assert!(bcrypt::verify("senha-forte", &usuario.senha_hash).unwrap());
+        
+        assert!(matches!(
+            executar(create_usuario(db.state(), TOKEN.into(), input(" operador@teste "), "senha-forte".into())),
+            Err(AppError::Validation(_))
+        ));
+        
+        let alterado = executar(update_usuario(db.state(), TOKEN.into(), usuario.id, UsuarioInput {
+            cargo: Some("Vendas".into()),
+            ..input("operador@teste")
+        })).unwrap();
+        assert_eq!(alterado.cargo.as_deref(), Some("Vendas"));
+    }
+    
+    #[test]
+    fn reset_de_senha_exige_troca_e_encerra_as_sessoes() {
+        let db = banco_teste(TOKEN);
+        let id = usuario(&db, "operador@teste", "{}");
+        sessao(&db, id, "operador");
+        
+        assert!(executar(reset_password(db.state(), TOKEN.into(), id, "nova-senha".into())).unwrap());
+        assert_eq!(sessoes(&db, id), 0);
+        
+        let usuario = fetch_usuario(&db.reader().unwrap(), id).unwrap().unwrap();
+        assert!(usuario.trocar_senha);
+        assert!(bcrypt::verify("nova-senha", &usuario.senha_hash).unwrap());
+        assert!(matches!(
+            executar(reset_password(db.state(), TOKEN.into(), 99, "nova-senha".into())),
+            Err(AppError::NotFound(_))
+        ));
+    }
+    
+    #[test]
+    fn desativar_encerra_as_sessoes_e_some_da_lista() {
+        let db = banco_teste(TOKEN);
+        let id = usuario(&db, "operador@teste", "{}");
+        sessao(&db, id, "operador");
+        
+        assert!(matches!(executar(deactivate_usuario(db.state(), TOKEN.into(), 1)), Err(AppError::Validation(_))));
+        assert!(executar(deactivate_usuario(db.state(), TOKEN.into(), id)).unwrap());
+        assert_eq!(sessoes(&db, id), 0);
+        
+        let ativos = executar(list_usuarios(db.state(), TOKEN.into(), None)).unwrap();
+        assert_eq!(ativos.iter().map(|u| u.id).collect::<Vec<_>>(), vec![1]);
+        assert_eq!(executar(list_usuarios(db.state(), TOKEN.into(), Some(true))).unwrap().len(), 2);
+        
+        // Só o administrador gerencia usuários
+        sessao(&db, usuario(&db, "outro@teste", "{}"), "outro");
+        assert!(matches!(executar(list_usuarios(db.state(), "outro".into(), None)), Err(AppError::Authorization(_))));
+    }
+}
+
@@ -1,23 +1,36 @@
 //! Comandos de Vendas
 
-use tauri::AppHandle;
+use tauri::State;
 use rusqlite::{Connection, params};
 use log::info;
-use chrono::Utc;
+use std::collections::HashMap;
+use chrono::{Datelike, Duration, NaiveDate, Utc};
 
-use crate::error::AppError;
-use crate::models::{PedidoVenda, PedidoItem, DashboardVendas, VendasPorVendedor, VendasPorDia, TopProduto, TopCliente, PaginatedResponse};
-use crate::database::get_database_path;
+use crate::error::{AppError, AppResult};
+use crate::models::{PedidoVenda, PedidoItem, DashboardVendas, VendasPorVendedor, VendasPorDia, TopProduto, TopCliente, ResumoVendas, PaginatedResponse, Modulo, Acao, TipoDocumento, Dinheiro, StatusPedido, HistoricoPedido, PedidoItemInput, Ajuste, ReservaEstoque};
+use crate::database::DatabaseState;
+use crate::context::RequestContext;
+use crate::auditoria;
+use crate::sequencias;
+use crate::estoque;
+use crate::parcelamento::{self, Plano};
+use crate::precos;
+use crate::credito;
+use crate::comissoes;
+use crate::commands::financeiro;
 
 #[tauri::command]
 pub async fn get_pedidos(
-    app: AppHandle,
+    db: State<'_, DatabaseState>,
+    token: String,
     page: Option<i64>,
     per_page: Option<i64>,
     status: Option<String>,
 ) -> Result<PaginatedResponse<PedidoVenda>, AppError> {
-    let db_path = get_database_path(&app)?;
-    let conn = Connection::open(&db_path)?;
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require(Modulo::Vendas, Acao::Ler)?;
+    
+    let conn = db.reader()?;
     
     let page = page.unwrap_or(1);
     let per_page = per_page.unwrap_or(20);
@@ -33,7 +46,8 @@ pub async fn get_pedidos(
             "SELECT p.id, p.numero, p.cliente_id, c.nome, p.vendedor_id, u.nome,
                     p.data_pedido, p.data_entrega, p.status, p.subtotal, p.desconto,
                     p.acrescimo, p.total, p.forma_pagamento, p.condicao_pagamento,
-                    p.observacoes, p.created_at, p.updated_at
+                    p.observacoes, p.created_at, p.updated_at, p.desconto_percentual,
+                    p.acrescimo_percentual, p.condicao_pagamento_id
              FROM pedidos_venda p
              LEFT JOIN clientes c ON c.id = p.cliente_id
              LEFT JOIN usuarios u ON u.id = p.vendedor_id
@@ -47,7 +61,8 @@ pub async fn get_pedidos(
                 cliente_nome: row.get(3)?, vendedor_id: row.get(4)?, vendedor_nome: row.get(5)?,
                 data_pedido: row.get(6)?, data_entrega: row.get(7)?, status: row.get(8)?,
                 subtotal: row.get(9)?, desconto: row.get(10)?, acrescimo: row.get(11)?,
-                total: row.get(12)?, forma_pagamento: row.get(13)?, condicao_pagamento: row.get(14)?,
+                total: row.get(12)?, desconto_percentual: row.get(18)?, acrescimo_percentual: row.get(19)?,
+                forma_pagamento: row.get(13)?, condicao_pagamento: row.get(14)?, condicao_pagamento_id: row.get(20)?,
                 observacoes: row.get(15)?, itens: vec![], created_at: row.get(16)?, updated_at: row.get(17)?,
             })
         })?.filter_map(|r| r.ok()).collect();
@@ -60,7 +75,8 @@ pub async fn get_pedidos(
             "SELECT p.id, p.numero, p.cliente_id, c.nome, p.vendedor_id, u.nome,
                     p.data_pedido, p.data_entrega, p.status, p.subtotal, p.desconto,
                     p.acrescimo, p.total, p.forma_pagamento, p.condicao_pagamento,
-                    p.observacoes, p.created_at, p.updated_at
+                    p.observacoes, p.created_at, p.updated_at, p.desconto_percentual,
+                    p.acrescimo_percentual, p.condicao_pagamento_id
              FROM pedidos_venda p
              LEFT JOIN clientes c ON c.id = p.cliente_id
              LEFT JOIN usuarios u ON u.id = p.vendedor_id
@@ -73,7 +89,8 @@ pub async fn get_pedidos(
                 cliente_nome: row.get(3)?, vendedor_id: row.get(4)?, vendedor_nome: row.get(5)?,
                 data_pedido: row.get(6)?, data_entrega: row.get(7)?, status: row.get(8)?,
                 subtotal: row.get(9)?, desconto: row.get(10)?, acrescimo: row.get(11)?,
-                total: row.get(12)?, forma_pagamento: row.get(13)?, condicao_pagamento: row.get(14)?,
+                total: row.get(12)?, desconto_percentual: row.get(18)?, acrescimo_percentual: row.get(19)?,
+                forma_pagamento: row.get(13)?, condicao_pagamento: row.get(14)?, condicao_pagamento_id: row.get(20)?,
                 observacoes: row.get(15)?, itens: vec![], created_at: row.get(16)?, updated_at: row.get(17)?,
             })
         })?.filter_map(|r| r.ok()).collect();
@@ -85,15 +102,22 @@ pub async fn get_pedidos(
 }
 
 #[tauri::command]
-pub async fn get_pedido_by_id(app: AppHandle, id: i64) -> Result<Option<PedidoVenda>, AppError> {
-    let db_path = get_database_path(&app)?;
-    let conn = Connection::open(&db_path)?;
+pub async fn get_pedido_by_id(db: State<'_, DatabaseState>, token: String, id: i64) -> Result<Option<PedidoVenda>, AppError> {
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require(Modulo::Vendas, Acao::Ler)?;
     
+    let conn = db.reader()?;
+    fetch_pedido(&conn, id)
+}
+
+/// Carrega pedido por ID na conexão informada
+pub(crate) fn fetch_pedido(conn: &Connection, id: i64) -> AppResult<Option<PedidoVenda>> {
     let result = conn.query_row(
         "SELECT p.id, p.numero, p.cliente_id, c.nome, p.vendedor_id, u.nome,
                 p.data_pedido, p.data_entrega, p.status, p.subtotal, p.desconto,
                 p.acrescimo, p.total, p.forma_pagamento, p.condicao_pagamento,
-                p.observacoes, p.created_at, p.updated_at
+                p.observacoes, p.created_at, p.updated_at, p.desconto_percentual,
+                p.acrescimo_percentual, p.condicao_pagamento_id
          FROM pedidos_venda p
          LEFT JOIN clientes c ON c.id = p.cliente_id
          LEFT JOIN usuarios u ON u.id = p.vendedor_id
@@ -104,7 +128,8 @@ pub async fn get_pedido_by_id(app: AppHandle, id: i64) -> Result<Option<PedidoVe
             cliente_nome: row.get(3)?, vendedor_id: row.get(4)?, vendedor_nome: row.get(5)?,
             data_pedido: row.get(6)?, data_entrega: row.get(7)?, status: row.get(8)?,
             subtotal: row.get(9)?, desconto: row.get(10)?, acrescimo: row.get(11)?,
-            total: row.get(12)?, forma_pagamento: row.get(13)?, condicao_pagamento: row.get(14)?,
+            total: row.get(12)?, desconto_percentual: row.get(18)?, acrescimo_percentual: row.get(19)?,
+            forma_pagamento: row.get(13)?, condicao_pagamento: row.get(14)?, condicao_pagamento_id: row.get(20)?,
             observacoes: row.get(15)?, itens: vec![], created_at: row.get(16)?, updated_at: row.get(17)?,
         }),
     );
@@ -114,17 +139,19 @@ pub async fn get_pedido_by_id(app: AppHandle, id: i64) -> Result<Option<PedidoVe
             // Buscar itens
             let mut stmt = conn.prepare(
                 "SELECT i.id, i.pedido_id, i.produto_id, pr.nome, i.quantidade,
-                        i.preco_unitario, i.desconto, i.total
+                        i.preco_unitario, i.desconto, i.desconto_percentual, i.total
                  FROM pedido_itens i
                  LEFT JOIN produtos pr ON pr.id = i.produto_id
-                 WHERE i.pedido_id = ?1"
+                 WHERE i.pedido_id = ?1
+                 ORDER BY i.id"
             )?;
             
             pedido.itens = stmt.query_map(params![id], |row| {
                 Ok(PedidoItem {
                     id: row.get(0)?, pedido_id: row.get(1)?, produto_id: row.get(2)?,
                     produto_nome: row.get(3)?, quantidade: row.get(4)?,
-                    preco_unitario: row.get(5)?, desconto: row.get(6)?, total: row.get(7)?,
+                    preco_unitario: row.get(5)?, desconto: row.get(6)?, desconto_percentual: row.get(7)?,
+                    total: row.get(8)?,
                 })
             })?.filter_map(|r| r.ok()).collect();
             
@@ -137,117 +164,1445 @@ pub async fn get_pedido_by_id(app: AppHandle, id: i64) -> Result<Option<PedidoVe
 
 #[tauri::command]
 pub async fn create_pedido(
-    app: AppHandle,
+    db: State<'_, DatabaseState>,
+    token: String,
     cliente_id: Option<i64>,
     vendedor_id: Option<i64>,
-    itens: Vec<serde_json::Value>,
+    itens: Vec<PedidoItemInput>,
+    desconto: Option<Ajuste>,
+    acrescimo: Option<Ajuste>,
     forma_pagamento: Option<String>,
+    condicao_pagamento: Option<String>,
+    condicao_pagamento_id: Option<i64>,
     observacoes: Option<String>,
 ) -> Result<PedidoVenda, AppError> {
-    let db_path = get_database_path(&app)?;
-    let conn = Connection::open(&db_path)?;
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require(Modulo::Vendas, Acao::Criar)?;
+    
+    db.with_transaction(|tx| {
+        let novo = NovoPedido {
+            cliente_id,
+            vendedor_id,
+            desconto,
+            acrescimo,
+            forma_pagamento,
+            condicao_pagamento,
+            condicao_pagamento_id,
+            observacoes,
+        };
+        let pedido_id = inserir_pedido(tx, &ctx, novo, &itens)?;
+        
+        fetch_pedido(tx, pedido_id)?.ok_or(AppError::NotFound("Pedido não encontrado".into()))
+    })
+}
+
+/// Cabeçalho de um pedido novo
+pub(crate) struct NovoPedido {
+    pub cliente_id: Option<i64>,
+    pub vendedor_id: Option<i64>,
+    pub desconto: Option<Ajuste>,
+    pub acrescimo: Option<Ajuste>,
+    pub forma_pagamento: Option<String>,
+    pub condicao_pagamento: Option<String>,
+    pub condicao_pagamento_id: Option<i64>,
+    pub observacoes: Option<String>,
+}
+
+/// Grava o pedido em orçamento com seus itens e devolve o id
+pub(crate) fn inserir_pedido(tx: &Connection, ctx: &RequestContext, novo: NovoPedido, itens: &[PedidoItemInput]) -> AppResult<i64> {
+    let condicao_pagamento = resolver_condicao(tx, novo.condicao_pagamento_id, novo.condicao_pagamento)?;
     
     // Gerar número do pedido
-    let count: i64 = conn.query_row("SELECT COUNT(*) + 1 FROM pedidos_venda", [], |row| row.get(0))?;
-    let numero = format!("PV{:06}", count);
+    let numero = sequencias::proximo(tx, TipoDocumento::PedidoVenda, "")?.formatado;
     let data_pedido = Utc::now().format("%Y-%m-%d").to_string();
     
-    // Calcular totais
-    let mut subtotal = 0.0;
-    for item in &itens {
-        let qtd = item["quantidade"].as_f64().unwrap_or(1.0);
-        let preco = item["preco_unitario"].as_f64().unwrap_or(0.0);
-        subtotal += qtd * preco;
+    tx.execute(
+        "INSERT INTO pedidos_venda (numero, cliente_id, vendedor_id, data_pedido, status, forma_pagamento,
+                                    condicao_pagamento, condicao_pagamento_id, observacoes)
+         VALUES (?1, ?2, ?3, ?4, 'orcamento', ?5, ?6, ?7, ?8)",
+        params![
+            numero, novo.cliente_id, novo.vendedor_id, data_pedido, novo.forma_pagamento, condicao_pagamento,
+            novo.condicao_pagamento_id, novo.observacoes
+        ],
+    )?;
+    
+    let pedido_id = tx.last_insert_rowid();
+    tx.execute(
+        "INSERT INTO pedido_status_historico (pedido_id, status_novo, usuario_id) VALUES (?1, ?2, ?3)",
+        params![pedido_id, StatusPedido::Orcamento.chave(), ctx.usuario.id],
+    )?;
+    
+    for item in itens {
+        inserir_item(tx, pedido_id, item)?;
+    }
+    
+    gravar_ajustes(tx, pedido_id, novo.desconto, novo.acrescimo)?;
+    recalcular_pedido(tx, pedido_id)?;
+    
+    auditoria::novo("pedidos_venda", pedido_id).registrar(tx, ctx, Modulo::Vendas, "criar")?;
+    
+    info!("✅ Pedido criado: {} (ID: {})", numero, pedido_id);
+    
+    Ok(pedido_id)
+}
+
+/// Atualiza dados do cabeçalho
+/// 
+/// `desconto` e `acrescimo` só podem mudar enquanto o pedido está em
+/// orçamento; para zerar, envie `{"valor": 0}`. A condição de pagamento
+/// pode mudar até o faturamento; `condicao_pagamento_id` (cadastrada)
+/// prevalece sobre o texto livre em `condicao_pagamento`.
+#[tauri::command]
+pub async fn update_pedido(
+    db: State<'_, DatabaseState>,
+    token: String,
+    id: i64,
+    forma_pagamento: Option<String>,
+    condicao_pagamento: Option<String>,
+    condicao_pagamento_id: Option<i64>,
+    observacoes: Option<String>,
+    desconto: Option<Ajuste>,
+    acrescimo: Option<Ajuste>,
+) -> Result<PedidoVenda, AppError> {
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require(Modulo::Vendas, Acao::Editar)?;
+    
+    // A situação só muda pelos comandos de transição (aprovar_pedido etc.)
+    db.with_transaction(|tx| {
+        let captura = auditoria::capturar(tx, "pedidos_venda", id)?;
+        
+        if let Some(fp) = forma_pagamento {
+            tx.execute("UPDATE pedidos_venda SET forma_pagamento = ?1, updated_at = datetime('now') WHERE id = ?2", params![fp, id])?;
+        }
+        if condicao_pagamento.is_some() || condicao_pagamento_id.is_some() {
+            if matches!(status_pedido(tx, id)?, StatusPedido::Faturado | StatusPedido::Entregue | StatusPedido::Cancelado) {
+                return Err(AppError::Validation("Condição de pagamento não pode mudar depois do faturamento".into()));
+            }
+            let cp = resolver_condicao(tx, condicao_pagamento_id, condicao_pagamento)?;
+            tx.execute(
+                "UPDATE pedidos_venda SET condicao_pagamento = ?1, condicao_pagamento_id = ?2, updated_at = datetime('now') WHERE id = ?3",
+                params![cp, condicao_pagamento_id, id],
+            )?;
+        }
+        if let Some(obs) = observacoes {
+            tx.execute("UPDATE pedidos_venda SET observacoes = ?1, updated_at = datetime('now') WHERE id = ?2", params![obs, id])?;
+        }
+        if desconto.is_some() || acrescimo.is_some() {
+            exigir_aberto(tx, id)?;
+            gravar_ajustes(tx, id, desconto, acrescimo)?;
+            recalcular_pedido(tx, id)?;
+        }
+        
+        captura.registrar(tx, &ctx, Modulo::Vendas, "editar")?;
+        
+        fetch_pedido(tx, id)?.ok_or(AppError::NotFound("Pedido não encontrado".into()))
+    })
+}
+
+#[tauri::command]
+pub async fn add_item_pedido(
+    db: State<'_, DatabaseState>,
+    token: String,
+    id: i64,
+    item: PedidoItemInput,
+) -> Result<PedidoVenda, AppError> {
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require(Modulo::Vendas, Acao::Editar)?;
+    
+    db.with_transaction(|tx| {
+        exigir_aberto(tx, id)?;
+        
+        let item_id = inserir_item(tx, id, &item)?;
+        recalcular_pedido(tx, id)?;
+        
+        auditoria::novo("pedido_itens", item_id).registrar(tx, &ctx, Modulo::Vendas, "criar")?;
+        
+        fetch_pedido(tx, id)?.ok_or(AppError::NotFound("Pedido não encontrado".into()))
+    })
+}
+
+#[tauri::command]
+pub async fn update_item_pedido(
+    db: State<'_, DatabaseState>,
+    token: String,
+    id: i64,
+    item_id: i64,
+    item: PedidoItemInput,
+) -> Result<PedidoVenda, AppError> {
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require(Modulo::Vendas, Acao::Editar)?;
+    
+    db.with_transaction(|tx| {
+        exigir_aberto(tx, id)?;
+        
+        let captura = auditoria::capturar(tx, "pedido_itens", item_id)?;
+        let preco_unitario = validar_item(tx, id, &item)?;
+        
+        let alterados = tx.execute(
+            "UPDATE pedido_itens SET produto_id = ?1, quantidade = ?2, preco_unitario = ?3,
+                    desconto = ?4, desconto_percentual = ?5
+             WHERE id = ?6 AND pedido_id = ?7",
+            params![
+                item.produto_id, item.quantidade, preco_unitario,
+                item.desconto.map(|d| d.valor()).unwrap_or_default(),
+                item.desconto.and_then(|d| d.percentual()), item_id, id
+            ],
+        )?;
+        if alterados == 0 {
+            return Err(AppError::NotFound("Item não encontrado no pedido".into()));
+        }
+        
+        recalcular_pedido(tx, id)?;
+        captura.registrar(tx, &ctx, Modulo::Vendas, "editar")?;
+        
+        fetch_pedido(tx, id)?.ok_or(AppError::NotFound("Pedido não encontrado".into()))
+    })
+}
+
+#[tauri::command]
+pub async fn remove_item_pedido(
+    db: State<'_, DatabaseState>,
+    token: String,
+    id: i64,
+    item_id: i64,
+) -> Result<PedidoVenda, AppError> {
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require(Modulo::Vendas, Acao::Editar)?;
+    
+    db.with_transaction(|tx| {
+        exigir_aberto(tx, id)?;
+        
+        let captura = auditoria::capturar(tx, "pedido_itens", item_id)?;
+        let removidos = tx.execute(
+            "DELETE FROM pedido_itens WHERE id = ?1 AND pedido_id = ?2",
+            params![item_id, id],
+        )?;
+        if removidos == 0 {
+            return Err(AppError::NotFound("Item não encontrado no pedido".into()));
+        }
+        
+        recalcular_pedido(tx, id)?;
+        captura.registrar(tx, &ctx, Modulo::Vendas, "excluir")?;
+        
+        fetch_pedido(tx, id)?.ok_or(AppError::NotFound("Pedido não encontrado".into()))
+    })
+}
+
+/// Itens e ajustes só podem ser alterados em orçamento
+fn exigir_aberto(conn: &Connection, id: i64) -> AppResult<()> {
+    let status = status_pedido(conn, id)?;
+    if status != StatusPedido::Orcamento {
+        return Err(AppError::Validation(format!(
+            "Pedido na situação '{}' não pode ser alterado", status
+        )));
+    }
+    Ok(())
+}
+
+/// Valida o item e devolve o preço unitário
+/// 
+/// Sem preço informado, vale o preço efetivo do produto para o cliente do
+/// pedido (tabela de preço, faixa de quantidade ou `preco_venda`).
+fn validar_item(conn: &Connection, pedido_id: i64, item: &PedidoItemInput) -> AppResult<Dinheiro> {
+    if !item.quantidade.is_finite() || item.quantidade <= 0.0 {
+        return Err(AppError::Validation("Quantidade deve ser maior que zero".into()));
+    }
+    
+    let cliente_id: Option<i64> = conn.query_row(
+        "SELECT cliente_id FROM pedidos_venda WHERE id = ?1",
+        params![pedido_id],
+        |row| row.get(0),
+    )?;
+    let efetivo = precos::resolver(conn, cliente_id, item.produto_id, item.quantidade, Utc::now().date_naive())?;
+    
+    let preco_unitario = item.preco_unitario.unwrap_or(efetivo.preco);
+    if preco_unitario.is_negative() {
+        return Err(AppError::Validation("Preço unitário não pode ser negativo".into()));
+    }
+    if let Some(desconto) = item.desconto {
+        validar_ajuste(desconto, "Desconto do item")?;
+    }
+    
+    Ok(preco_unitario)
+}
+
+fn validar_ajuste(ajuste: Ajuste, nome: &str) -> AppResult<()> {
+    match ajuste {
+        Ajuste::Percentual(p) if !(0.0..=100.0).contains(&p) => {
+            Err(AppError::Validation(format!("{} deve estar entre 0% e 100%", nome)))
+        }
+        Ajuste::Valor(v) if v.is_negative() => {
+            Err(AppError::Validation(format!("{} não pode ser negativo", nome)))
+        }
+        _ => Ok(()),
     }
+}
+
+/// Insere o item; os totais são calculados por `recalcular_pedido`
+fn inserir_item(conn: &Connection, pedido_id: i64, item: &PedidoItemInput) -> AppResult<i64> {
+    let preco_unitario = validar_item(conn, pedido_id, item)?;
     
     conn.execute(
-        "INSERT INTO pedidos_venda (numero, cliente_id, vendedor_id, data_pedido, status, subtotal, total, forma_pagamento, observacoes)
-         VALUES (?1, ?2, ?3, ?4, 'orcamento', ?5, ?5, ?6, ?7)",
-        params![numero, cliente_id, vendedor_id, data_pedido, subtotal, forma_pagamento, observacoes],
+        "INSERT INTO pedido_itens (pedido_id, produto_id, quantidade, preco_unitario, desconto, desconto_percentual)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![
+            pedido_id, item.produto_id, item.quantidade, preco_unitario,
+            item.desconto.map(|d| d.valor()).unwrap_or_default(),
+            item.desconto.and_then(|d| d.percentual())
+        ],
     )?;
     
-    let pedido_id = conn.last_insert_rowid();
+    Ok(conn.last_insert_rowid())
+}
+
+/// Grava desconto e acréscimo do cabeçalho (`None` mantém o atual)
+fn gravar_ajustes(conn: &Connection, id: i64, desconto: Option<Ajuste>, acrescimo: Option<Ajuste>) -> AppResult<()> {
+    if let Some(d) = desconto {
+        validar_ajuste(d, "Desconto")?;
+        conn.execute(
+            "UPDATE pedidos_venda SET desconto = ?1, desconto_percentual = ?2 WHERE id = ?3",
+            params![d.valor(), d.percentual(), id],
+        )?;
+    }
+    if let Some(a) = acrescimo {
+        validar_ajuste(a, "Acréscimo")?;
+        conn.execute(
+            "UPDATE pedidos_venda SET acrescimo = ?1, acrescimo_percentual = ?2 WHERE id = ?3",
+            params![a.valor(), a.percentual(), id],
+        )?;
+    }
+    Ok(())
+}
+
+/// Recalcula itens e cabeçalho a partir de quantidades, preços e ajustes
+/// 
+/// Cada linha é arredondada ao centavo antes da soma; descontos em
+/// percentual são reaplicados sobre a base atual.
+pub(crate) fn recalcular_pedido(conn: &Connection, id: i64) -> AppResult<()> {
+    let itens: Vec<(i64, f64, Dinheiro, Dinheiro, Option<f64>)> = {
+        let mut stmt = conn.prepare(
+            "SELECT id, quantidade, preco_unitario, desconto, desconto_percentual
+             FROM pedido_itens WHERE pedido_id = ?1"
+        )?;
+        let linhas = stmt.query_map(params![id], |row| {
+            Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?))
+        })?;
+        linhas.collect::<Result<_, _>>()?
+    };
     
-    // Inserir itens
-    for item in &itens {
-        let produto_id = item["produto_id"].as_i64().unwrap_or(0);
-        let quantidade = item["quantidade"].as_f64().unwrap_or(1.0);
-        let preco_unitario = item["preco_unitario"].as_f64().unwrap_or(0.0);
-        let total = quantidade * preco_unitario;
+    let mut subtotal = Dinheiro::ZERO;
+    for (item_id, quantidade, preco_unitario, desconto, percentual) in itens {
+        let bruto = preco_unitario.vezes(quantidade)?;
+        let desconto = percentual.map(|p| bruto.percentual(p)).transpose()?.unwrap_or(desconto);
+        if desconto > bruto {
+            return Err(AppError::Validation("Desconto do item maior que o valor do item".into()));
+        }
         
+        let total = bruto.subtrair(desconto)?;
         conn.execute(
-            "INSERT INTO pedido_itens (pedido_id, produto_id, quantidade, preco_unitario, total)
-             VALUES (?1, ?2, ?3, ?4, ?5)",
-            params![pedido_id, produto_id, quantidade, preco_unitario, total],
+            "UPDATE pedido_itens SET desconto = ?1, total = ?2 WHERE id = ?3",
+            params![desconto, total, item_id],
         )?;
+        subtotal = subtotal.somar(total)?;
     }
     
-    info!("✅ Pedido criado: {} (ID: {})", numero, pedido_id);
+    let (desconto, desconto_percentual, acrescimo, acrescimo_percentual): (Dinheiro, Option<f64>, Dinheiro, Option<f64>) = conn.query_row(
+        "SELECT desconto, desconto_percentual, acrescimo, acrescimo_percentual FROM pedidos_venda WHERE id = ?1",
+        params![id],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+    )?;
     
-    get_pedido_by_id(app, pedido_id).await?.ok_or(AppError::NotFound("Pedido não encontrado".into()))
+    let desconto = desconto_percentual.map(|p| subtotal.percentual(p)).transpose()?.unwrap_or(desconto);
+    let acrescimo = acrescimo_percentual.map(|p| subtotal.percentual(p)).transpose()?.unwrap_or(acrescimo);
+    if desconto > subtotal {
+        return Err(AppError::Validation("Desconto maior que o subtotal do pedido".into()));
+    }
+    let total = subtotal.subtrair(desconto)?.somar(acrescimo)?;
+    
+    conn.execute(
+        "UPDATE pedidos_venda SET subtotal = ?1, desconto = ?2, acrescimo = ?3, total = ?4,
+                updated_at = datetime('now')
+         WHERE id = ?5",
+        params![subtotal, desconto, acrescimo, total, id],
+    )?;
+    
+    Ok(())
 }
 
+/// Tabelas com histórico do pedido; com registro em qualquer delas, o
+/// pedido não pode mais ser excluído
+const HISTORICO_PEDIDO: &[&str] = &[
+    "aprovacoes_credito", "comissoes", "orcamentos", "contas_receber", "notas_fiscais", "devolucoes_venda",
+];
+
 #[tauri::command]
-pub async fn update_pedido(
-    app: AppHandle,
+pub async fn delete_pedido(db: State<'_, DatabaseState>, token: String, id: i64) -> Result<bool, AppError> {
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require(Modulo::Vendas, Acao::Excluir)?;
+    
+    db.with_transaction(|tx| {
+        // Pedidos que já avançaram no fluxo são cancelados, não excluídos
+        let status = status_pedido(tx, id)?;
+        if !matches!(status, StatusPedido::Orcamento | StatusPedido::Cancelado) {
+            return Err(AppError::Validation(format!(
+                "Pedido na situação '{}' não pode ser excluído; cancele-o", status
+            )));
+        }
+        for tabela in HISTORICO_PEDIDO {
+            let existe: bool = tx.query_row(
+                &format!("SELECT EXISTS(SELECT 1 FROM {} WHERE pedido_id = ?1)", tabela),
+                params![id],
+                |row| row.get(0),
+            )?;
+            if existe {
+                return Err(AppError::Validation(
+                    "Pedido com histórico de crédito, comissão ou faturamento não pode ser excluído".into()
+                ));
+            }
+        }
+        
+        let captura = auditoria::capturar(tx, "pedidos_venda", id)?;
+        
+        tx.execute("DELETE FROM reservas_estoque WHERE pedido_id = ?1", params![id])?;
+        tx.execute("DELETE FROM pedido_status_historico WHERE pedido_id = ?1", params![id])?;
+        tx.execute("DELETE FROM pedido_itens WHERE pedido_id = ?1", params![id])?;
+        tx.execute("DELETE FROM pedidos_venda WHERE id = ?1", params![id])?;
+        
+        captura.registrar(tx, &ctx, Modulo::Vendas, "excluir")?;
+        
+        Ok(true)
+    })
+}
+
+#[tauri::command]
+pub async fn aprovar_pedido(
+    db: State<'_, DatabaseState>,
+    token: String,
     id: i64,
-    status: Option<String>,
-    forma_pagamento: Option<String>,
-    observacoes: Option<String>,
+    observacao: Option<String>,
 ) -> Result<PedidoVenda, AppError> {
-    let db_path = get_database_path(&app)?;
-    let conn = Connection::open(&db_path)?;
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require(Modulo::Vendas, Acao::Aprovar)?;
     
-    if let Some(st) = status {
-        conn.execute("UPDATE pedidos_venda SET status = ?1, updated_at = datetime('now') WHERE id = ?2", params![st, id])?;
-    }
-    if let Some(fp) = forma_pagamento {
-        conn.execute("UPDATE pedidos_venda SET forma_pagamento = ?1, updated_at = datetime('now') WHERE id = ?2", params![fp, id])?;
+    db.with_transaction(|tx| {
+        aprovar(tx, &ctx, id, observacao.as_deref())?;
+        
+        fetch_pedido(tx, id)?.ok_or(AppError::NotFound("Pedido não encontrado".into()))
+    })
+}
+
+/// Aprova o pedido; exige cliente e ao menos um item
+pub(crate) fn aprovar(tx: &Connection, ctx: &RequestContext, id: i64, observacao: Option<&str>) -> AppResult<()> {
+    transicionar(tx, ctx, id, StatusPedido::Aprovado, observacao, |_| {
+        let (cliente_id, itens): (Option<i64>, i64) = tx.query_row(
+            "SELECT cliente_id, (SELECT COUNT(*) FROM pedido_itens WHERE pedido_id = p.id)
+             FROM pedidos_venda p WHERE p.id = ?1",
+            params![id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+        
+        if cliente_id.is_none() {
+            return Err(AppError::Validation("Informe o cliente antes de aprovar o pedido".into()));
+        }
+        if itens == 0 {
+            return Err(AppError::Validation("Pedido sem itens não pode ser aprovado".into()));
+        }
+        Ok(())
+    })
+}
+
+/// Confirma o pedido reservando o estoque dos itens
+/// 
+/// Com `permitir_encomenda`, itens sem saldo suficiente ficam pendentes e
+/// precisam ter estoque até a separação; sem ele, a confirmação é recusada.
+///
+/// Pedido que excede o limite de crédito do cliente é recusado e entra na
+/// fila de aprovação de crédito; depois de aprovado, basta confirmar de novo.
+#[tauri::command]
+pub async fn confirmar_pedido(
+    db: State<'_, DatabaseState>,
+    token: String,
+    id: i64,
+    permitir_encomenda: Option<bool>,
+    observacao: Option<String>,
+) -> Result<PedidoVenda, AppError> {
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require(Modulo::Vendas, Acao::Editar)?;
+    
+    // A solicitação de crédito precisa ser gravada mesmo com a confirmação
+    // recusada, então a recusa sai da transação como `Err` interno
+    let confirmacao = db.with_transaction(|tx| {
+        if let Err(recusa) = confirmar(tx, &ctx, id, permitir_encomenda.unwrap_or(false), observacao.as_deref())? {
+            return Ok(Err(recusa));
+        }
+        
+        Ok(Ok(fetch_pedido(tx, id)?.ok_or(AppError::NotFound("Pedido não encontrado".into()))?))
+    })?;
+    
+    confirmacao.map_err(AppError::Validation)
+}
+
+/// Confirma o pedido aprovado, reservando o estoque
+/// 
+/// Acima do limite de crédito, o pedido entra na fila de aprovação e volta
+/// `Ok(Err(motivo))`: a solicitação deve ser gravada, então quem chama
+/// confirma a transação e devolve o motivo como erro de validação.
+pub(crate) fn confirmar(
+    tx: &Connection,
+    ctx: &RequestContext,
+    id: i64,
+    permitir_encomenda: bool,
+    observacao: Option<&str>,
+) -> AppResult<Result<(), String>> {
+    if status_pedido(tx, id)?.pode_ir_para(StatusPedido::Confirmado) {
+        let analise = credito::analisar_pedido(tx, id)?;
+        if let credito::Analise::Excedido { valor_pedido, exposicao, limite, .. } = &analise {
+            let solicitacao = credito::solicitar_aprovacao(tx, id, &analise, ctx.usuario.id)?;
+            auditoria::novo("aprovacoes_credito", solicitacao).registrar(tx, ctx, Modulo::Vendas, "solicitar")?;
+            
+            info!("💳 Pedido {} aguardando aprovação de crédito", id);
+            
+            return Ok(Err(format!(
+                "Limite de crédito excedido (limite {}, exposição {}, pedido {}). Pedido enviado para aprovação de crédito",
+                limite, exposicao, valor_pedido
+            )));
+        }
     }
-    if let Some(obs) = observacoes {
-        conn.execute("UPDATE pedidos_venda SET observacoes = ?1, updated_at = datetime('now') WHERE id = ?2", params![obs, id])?;
+    
+    transicionar(tx, ctx, id, StatusPedido::Confirmado, observacao, |_| {
+        estoque::reservar_pedido(tx, id, permitir_encomenda)
+    })?;
+    
+    Ok(Ok(()))
+}
+
+#[tauri::command]
+pub async fn separar_pedido(
+    db: State<'_, DatabaseState>,
+    token: String,
+    id: i64,
+    observacao: Option<String>,
+) -> Result<PedidoVenda, AppError> {
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require(Modulo::Vendas, Acao::Editar)?;
+    
+    db.with_transaction(|tx| {
+        transicionar(tx, &ctx, id, StatusPedido::Separado, observacao.as_deref(), |_| {
+            estoque::completar_reservas(tx, id)
+        })?;
+        
+        fetch_pedido(tx, id)?.ok_or(AppError::NotFound("Pedido não encontrado".into()))
+    })
+}
+
+/// Fatura o pedido: baixa o estoque reservado e gera as contas a receber
+/// 
+/// Os prazos da condição de pagamento contam a partir de `data_vencimento`
+/// (hoje, se omitida); pedido à vista vence nessa data.
+#[tauri::command]
+pub async fn faturar_pedido(
+    db: State<'_, DatabaseState>,
+    token: String,
+    id: i64,
+    data_vencimento: Option<String>,
+    observacao: Option<String>,
+) -> Result<PedidoVenda, AppError> {
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require(Modulo::Vendas, Acao::Editar)?;
+    
+    let data_base = match data_vencimento {
+        Some(data) => NaiveDate::parse_from_str(&data, "%Y-%m-%d")
+            .map_err(|_| AppError::Validation(format!("Data de vencimento inválida: '{}'", data)))?,
+        None => Utc::now().date_naive(),
+    };
+    
+    db.with_transaction(|tx| {
+        transicionar(tx, &ctx, id, StatusPedido::Faturado, observacao.as_deref(), |_| {
+            estoque::baixar_reservas(tx, id)?;
+            financeiro::gerar_contas_receber(tx, &ctx, id, data_base, None)?;
+            Ok(())
+        })?;
+        
+        fetch_pedido(tx, id)?.ok_or(AppError::NotFound("Pedido não encontrado".into()))
+    })
+}
+
+#[tauri::command]
+pub async fn entregar_pedido(
+    db: State<'_, DatabaseState>,
+    token: String,
+    id: i64,
+    data_entrega: Option<String>,
+    observacao: Option<String>,
+) -> Result<PedidoVenda, AppError> {
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require(Modulo::Vendas, Acao::Editar)?;
+    
+    let data_entrega = data_entrega.unwrap_or_else(|| Utc::now().format("%Y-%m-%d").to_string());
+    
+    db.with_transaction(|tx| {
+        transicionar(tx, &ctx, id, StatusPedido::Entregue, observacao.as_deref(), |_| {
+            tx.execute("UPDATE pedidos_venda SET data_entrega = ?1 WHERE id = ?2", params![data_entrega, id])?;
+            Ok(())
+        })?;
+        
+        fetch_pedido(tx, id)?.ok_or(AppError::NotFound("Pedido não encontrado".into()))
+    })
+}
+
+/// Cancela o pedido; pedidos faturados precisam ter a NFe cancelada antes
+#[tauri::command]
+pub async fn cancelar_pedido(
+    db: State<'_, DatabaseState>,
+    token: String,
+    id: i64,
+    motivo: String,
+) -> Result<PedidoVenda, AppError> {
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require(Modulo::Vendas, Acao::Editar)?;
+    
+    if motivo.trim().is_empty() {
+        return Err(AppError::Validation("Informe o motivo do cancelamento".into()));
     }
     
-    get_pedido_by_id(app, id).await?.ok_or(AppError::NotFound("Pedido não encontrado".into()))
+    db.with_transaction(|tx| {
+        transicionar(tx, &ctx, id, StatusPedido::Cancelado, Some(motivo.trim()), |atual| {
+            // Só pedidos confirmados ou separados têm estoque reservado
+            if matches!(atual, StatusPedido::Confirmado | StatusPedido::Separado) {
+                estoque::liberar_reservas(tx, id)?;
+            }
+            // Pedido com NFe emitida já pode ter comissão apurada
+            comissoes::estornar_pedido(tx, &ctx, id, Utc::now().date_naive(), motivo.trim())
+        })?;
+        
+        fetch_pedido(tx, id)?.ok_or(AppError::NotFound("Pedido não encontrado".into()))
+    })
 }
 
 #[tauri::command]
-pub async fn delete_pedido(app: AppHandle, id: i64) -> Result<bool, AppError> {
-    let db_path = get_database_path(&app)?;
-    let conn = Connection::open(&db_path)?;
+pub async fn get_historico_pedido(
+    db: State<'_, DatabaseState>,
+    token: String,
+    id: i64,
+) -> Result<Vec<HistoricoPedido>, AppError> {
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require(Modulo::Vendas, Acao::Ler)?;
+    
+    let conn = db.reader()?;
+    
+    let mut stmt = conn.prepare(
+        "SELECT h.id, h.pedido_id, h.status_anterior, h.status_novo, h.usuario_id, u.nome,
+                h.observacao, h.created_at
+         FROM pedido_status_historico h
+         LEFT JOIN usuarios u ON u.id = h.usuario_id
+         WHERE h.pedido_id = ?1
+         ORDER BY h.id ASC"
+    )?;
+    
+    let historico = stmt.query_map(params![id], |row| {
+        Ok(HistoricoPedido {
+            id: row.get(0)?, pedido_id: row.get(1)?, status_anterior: row.get(2)?,
+            status_novo: row.get(3)?, usuario_id: row.get(4)?, usuario_nome: row.get(5)?,
+            observacao: row.get(6)?, created_at: row.get(7)?,
+        })
+    })?.filter_map(|r| r.ok()).collect();
+    
+    Ok(historico)
+}
+
+#[tauri::command]
+pub async fn get_reservas_pedido(
+    db: State<'_, DatabaseState>,
+    token: String,
+    id: i64,
+) -> Result<Vec<ReservaEstoque>, AppError> {
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require_any(&[Modulo::Vendas, Modulo::Pcp], Acao::Ler)?;
+    
+    let conn = db.reader()?;
+    
+    let mut stmt = conn.prepare(
+        "SELECT id, pedido_id, item_id, codigo, quantidade, pendente, status, created_at, updated_at
+         FROM reservas_estoque WHERE pedido_id = ?1 ORDER BY id"
+    )?;
+    
+    let reservas = stmt.query_map(params![id], |row| {
+        Ok(ReservaEstoque {
+            id: row.get(0)?, pedido_id: row.get(1)?, item_id: row.get(2)?, codigo: row.get(3)?,
+            quantidade: row.get(4)?, pendente: row.get(5)?, status: row.get(6)?,
+            created_at: row.get(7)?, updated_at: row.get(8)?,
+        })
+    })?.filter_map(|r| r.ok()).collect();
+    
+    Ok(reservas)
+}
+
+/// Texto da condição de pagamento do pedido
+/// 
+/// Com condição cadastrada (ativa e sem juros/desconto, para que os títulos
+/// somem o total do pedido), o texto é o nome dela; sem ela, o texto livre
+/// precisa ser um prazo válido ("30/60/90").
+pub(crate) fn resolver_condicao(conn: &Connection, condicao_id: Option<i64>, texto: Option<String>) -> AppResult<Option<String>> {
+    match (condicao_id, texto) {
+        (Some(id), _) => {
+            let condicao = parcelamento::condicao_ativa(conn, id)?;
+            Plano::da_condicao(&condicao).exigir_sem_ajuste()?;
+            Ok(Some(condicao.nome))
+        }
+        (None, Some(texto)) => {
+            parcelamento::prazos(&texto)?;
+            Ok(Some(texto))
+        }
+        (None, None) => Ok(None),
+    }
+}
+
+/// Situação atual do pedido
+pub(crate) fn status_pedido(conn: &Connection, id: i64) -> AppResult<StatusPedido> {
+    let result = conn.query_row(
+        "SELECT status FROM pedidos_venda WHERE id = ?1",
+        params![id],
+        |row| row.get::<_, Option<String>>(0),
+    );
+    
+    match result {
+        Ok(status) => {
+            let status = status.unwrap_or_default();
+            StatusPedido::from_chave(&status)
+                .ok_or_else(|| AppError::Validation(format!("Pedido com situação desconhecida: '{}'", status)))
+        }
+        Err(rusqlite::Error::QueryReturnedNoRows) => Err(AppError::NotFound("Pedido não encontrado".into())),
+        Err(e) => Err(AppError::Database(e.to_string())),
+    }
+}
+
+/// Leva o pedido à situação `destino`
+/// 
+/// Recusa transições fora do fluxo de `StatusPedido::proximos`. `efeitos`
+/// recebe a situação atual e roda antes da mudança, na mesma transação;
+/// a auditoria registra o pedido antes e depois de tudo.
+fn transicionar<F>(
+    tx: &Connection,
+    ctx: &RequestContext,
+    id: i64,
+    destino: StatusPedido,
+    observacao: Option<&str>,
+    efeitos: F,
+) -> AppResult<()>
+where
+    F: FnOnce(StatusPedido) -> AppResult<()>,
+{
+    let atual = status_pedido(tx, id)?;
+    if !atual.pode_ir_para(destino) {
+        return Err(AppError::Validation(format!(
+            "Pedido na situação '{}' não pode passar para '{}'", atual, destino
+        )));
+    }
+    
+    let captura = auditoria::capturar(tx, "pedidos_venda", id)?;
     
-    conn.execute("DELETE FROM pedido_itens WHERE pedido_id = ?1", params![id])?;
-    conn.execute("DELETE FROM pedidos_venda WHERE id = ?1", params![id])?;
+    efeitos(atual)?;
     
-    Ok(true)
+    tx.execute(
+        "UPDATE pedidos_venda SET status = ?1, updated_at = datetime('now') WHERE id = ?2",
+        params![destino.chave(), id],
+    )?;
+    tx.execute(
+        "INSERT INTO pedido_status_historico (pedido_id, status_anterior, status_novo, usuario_id, observacao)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![id, atual.chave(), destino.chave(), ctx.usuario.id, observacao],
+    )?;
+    
+    let acao = match destino {
+        StatusPedido::Orcamento => "reabrir",
+        StatusPedido::Aprovado => "aprovar",
+        StatusPedido::Confirmado => "confirmar",
+        StatusPedido::Separado => "separar",
+        StatusPedido::Faturado => "faturar",
+        StatusPedido::Entregue => "entregar",
+        StatusPedido::Cancelado => "cancelar",
+    };
+    captura.registrar(tx, ctx, Modulo::Vendas, acao)?;
+    
+    info!("🔄 Pedido {}: {} → {}", id, atual, destino);
+    
+    Ok(())
 }
 
+/// Situações de pedido que contam como venda
+const SITUACOES_VENDIDAS: &str = "('confirmado', 'separado', 'faturado', 'entregue')";
+
+/// Maior período do dashboard: dois anos, com um bissexto
+const PERIODO_MAXIMO_DASHBOARD_DIAS: i64 = 731;
+
+/// Quantidade de produtos e clientes nos rankings do dashboard
+const LIMITE_RANKING: i64 = 10;
+
+/// Dashboard de vendas entre `data_inicio` e `data_fim` (AAAA-MM-DD)
+///
+/// Sem período, do primeiro dia do mês corrente até hoje.
 #[tauri::command]
-pub async fn get_dashboard_vendas(app: AppHandle) -> Result<DashboardVendas, AppError> {
-    let db_path = get_database_path(&app)?;
-    let conn = Connection::open(&db_path)?;
+pub async fn get_dashboard_vendas(
+    db: State<'_, DatabaseState>,
+    token: String,
+    data_inicio: Option<String>,
+    data_fim: Option<String>,
+) -> Result<DashboardVendas, AppError> {
+    let ctx = RequestContext::resolve(&db, &token)?;
+    ctx.require(Modulo::Vendas, Acao::Ler)?;
+    
+    let hoje = Utc::now().date_naive();
+    let fim = match data_fim {
+        Some(data) => parse_data(&data)?,
+        None => hoje,
+    };
+    let inicio = match data_inicio {
+        Some(data) => parse_data(&data)?,
+        None => fim.with_day(1).unwrap_or(fim),
+    };
+    if fim < inicio {
+        return Err(AppError::Validation("Fim do período é anterior ao início".into()));
+    }
+    if (fim - inicio).num_days() >= PERIODO_MAXIMO_DASHBOARD_DIAS {
+        return Err(AppError::Validation(format!(
+            "Período do dashboard limitado a {} dias", PERIODO_MAXIMO_DASHBOARD_DIAS
+        )));
+    }
+    
+    let conn = db.reader()?;
+    dashboard(&conn, inicio, fim)
+}
+
+fn dashboard(conn: &Connection, inicio: NaiveDate, fim: NaiveDate) -> AppResult<DashboardVendas> {
+    let atual = resumo(conn, inicio, fim)?;
+    
+    // Período anterior com o mesmo número de dias
+    let dias = (fim - inicio).num_days();
+    let (inicio_anterior, fim_anterior) = inicio.pred_opt()
+        .and_then(|fim_anterior| Some((fim_anterior.checked_sub_signed(Duration::try_days(dias)?)?, fim_anterior)))
+        .ok_or_else(|| AppError::Validation("Período anterior fora do calendário".into()))?;
+    let anterior = resumo(conn, inicio_anterior, fim_anterior)?;
+    
+    let variacao_vendas = if anterior.total_vendas.is_zero() {
+        None
+    } else {
+        let diferenca = (atual.total_vendas - anterior.total_vendas).em_centavos() as f64;
+        Some(diferenca / anterior.total_vendas.em_centavos() as f64 * 100.0)
+    };
+    
+    let (inicio_sql, fim_sql) = (data_sql(inicio), data_sql(fim));
     
-    let mes_atual = Utc::now().format("%Y-%m").to_string();
+    let (orcamentos_emitidos, orcamentos_ganhos, orcamentos_perdidos): (i64, i64, i64) = conn.query_row(
+        "SELECT COUNT(*),
+                COALESCE(SUM(CASE WHEN status = 'ganho' THEN 1 ELSE 0 END), 0),
+                COALESCE(SUM(CASE WHEN status = 'perdido' THEN 1 ELSE 0 END), 0)
+         FROM orcamentos WHERE date(data_emissao) BETWEEN ?1 AND ?2",
+        params![inicio_sql, fim_sql],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+    )?;
+    // Orçamentos ainda abertos não entram na taxa
+    let decididos = orcamentos_ganhos + orcamentos_perdidos;
+    let taxa_conversao = (decididos > 0).then(|| orcamentos_ganhos as f64 / decididos as f64 * 100.0);
     
-    let total_vendas_mes: f64 = conn.query_row(
-        "SELECT COALESCE(SUM(total), 0) FROM pedidos_venda WHERE strftime('%Y-%m', data_pedido) = ?1 AND status IN ('confirmado', 'faturado')",
-        params![mes_atual], |row| row.get(0)
-    ).unwrap_or(0.0);
+    let vendas_por_vendedor = {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT COALESCE(p.vendedor_id, c.vendedor_id) AS vendedor, u.nome, SUM(p.total), COUNT(*)
+             FROM pedidos_venda p
+             LEFT JOIN clientes c ON c.id = p.cliente_id
+             LEFT JOIN usuarios u ON u.id = COALESCE(p.vendedor_id, c.vendedor_id)
+             WHERE date(p.data_pedido) BETWEEN ?1 AND ?2 AND p.status IN {}
+             GROUP BY vendedor
+             ORDER BY SUM(p.total) DESC",
+            SITUACOES_VENDIDAS
+        ))?;
+        let linhas = stmt.query_map(params![inicio_sql, fim_sql], |row| {
+            Ok(VendasPorVendedor {
+                vendedor_id: row.get(0)?,
+                vendedor: row.get::<_, Option<String>>(1)?.unwrap_or_else(|| "Sem vendedor".into()),
+                total: row.get(2)?,
+                quantidade: row.get(3)?,
+            })
+        })?;
+        linhas.collect::<Result<Vec<_>, _>>()?
+    };
     
-    let total_pedidos_mes: i64 = conn.query_row(
-        "SELECT COUNT(*) FROM pedidos_venda WHERE strftime('%Y-%m', data_pedido) = ?1",
-        params![mes_atual], |row| row.get(0)
-    ).unwrap_or(0);
+    let vendas_por_dia = {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT date(data_pedido), SUM(total), COUNT(*) FROM pedidos_venda
+             WHERE date(data_pedido) BETWEEN ?1 AND ?2 AND status IN {}
+             GROUP BY date(data_pedido)",
+            SITUACOES_VENDIDAS
+        ))?;
+        let linhas = stmt.query_map(params![inicio_sql, fim_sql], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, Dinheiro>(1)?, row.get::<_, i64>(2)?))
+        })?;
+        let vendidos: HashMap<String, (Dinheiro, i64)> = linhas
+            .map(|linha| linha.map(|(data, total, pedidos)| (data, (total, pedidos))))
+            .collect::<Result<_, _>>()?;
+        
+        // Dias sem venda entram zerados para o gráfico não pular datas
+        inicio.iter_days().take_while(|dia| *dia <= fim).map(|dia| {
+            let data = data_sql(dia);
+            let (total, pedidos) = vendidos.get(&data).copied().unwrap_or_default();
+            VendasPorDia { data, total, pedidos }
+        }).collect()
+    };
     
-    let ticket_medio = if total_pedidos_mes > 0 { total_vendas_mes / total_pedidos_mes as f64 } else { 0.0 };
+    let top_produtos = {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT i.produto_id, pr.nome, SUM(i.quantidade), SUM(i.total)
+             FROM pedido_itens i
+             JOIN pedidos_venda p ON p.id = i.pedido_id
+             LEFT JOIN produtos pr ON pr.id = i.produto_id
+             WHERE date(p.data_pedido) BETWEEN ?1 AND ?2 AND p.status IN {}
+             GROUP BY i.produto_id
+             ORDER BY SUM(i.total) DESC
+             LIMIT ?3",
+            SITUACOES_VENDIDAS
+        ))?;
+        let linhas = stmt.query_map(params![inicio_sql, fim_sql, LIMITE_RANKING], |row| {
+            Ok(TopProduto {
+                produto_id: row.get(0)?,
+                produto: row.get::<_, Option<String>>(1)?.unwrap_or_default(),
+                quantidade: row.get(2)?,
+                total: row.get(3)?,
+            })
+        })?;
+        linhas.collect::<Result<Vec<_>, _>>()?
+    };
+    
+    let top_clientes = {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT p.cliente_id, c.nome, SUM(p.total), COUNT(*)
+             FROM pedidos_venda p
+             LEFT JOIN clientes c ON c.id = p.cliente_id
+             WHERE date(p.data_pedido) BETWEEN ?1 AND ?2 AND p.status IN {}
+             GROUP BY p.cliente_id
+             ORDER BY SUM(p.total) DESC
+             LIMIT ?3",
+            SITUACOES_VENDIDAS
+        ))?;
+        let linhas = stmt.query_map(params![inicio_sql, fim_sql, LIMITE_RANKING], |row| {
+            Ok(TopCliente {
+                cliente_id: row.get(0)?,
+                cliente: row.get::<_, Option<String>>(1)?.unwrap_or_else(|| "Sem cliente".into()),
+                total: row.get(2)?,
+                pedidos: row.get(3)?,
+            })
+        })?;
+        linhas.collect::<Result<Vec<_>, _>>()?
+    };
     
     Ok(DashboardVendas {
-        total_vendas_mes,
-        total_pedidos_mes,
-        ticket_medio,
-        vendas_por_vendedor: vec![],
-        vendas_por_dia: vec![],
-        top_produtos: vec![],
-        top_clientes: vec![],
+        data_inicio: atual.data_inicio,
+        data_fim: atual.data_fim,
+        total_vendas: atual.total_vendas,
+        total_pedidos: atual.total_pedidos,
+        pedidos_vendidos: atual.pedidos_vendidos,
+        ticket_medio: atual.ticket_medio,
+        periodo_anterior: anterior,
+        variacao_vendas,
+        orcamentos_emitidos,
+        orcamentos_ganhos,
+        orcamentos_perdidos,
+        taxa_conversao,
+        vendas_por_vendedor,
+        vendas_por_dia,
+        top_produtos,
+        top_clientes,
     })
 }
+
+fn resumo(conn: &Connection, inicio: NaiveDate, fim: NaiveDate) -> AppResult<ResumoVendas> {
+    let (data_inicio, data_fim) = (data_sql(inicio), data_sql(fim));
+    
+    let (total_pedidos, pedidos_vendidos, total_vendas): (i64, i64, Dinheiro) = conn.query_row(
+        &format!(
+            "SELECT COUNT(*),
+                    COALESCE(SUM(CASE WHEN status IN {0} THEN 1 ELSE 0 END), 0),
+                    COALESCE(SUM(CASE WHEN status IN {0} THEN total ELSE 0 END), 0)
+             FROM pedidos_venda WHERE date(data_pedido) BETWEEN ?1 AND ?2",
+            SITUACOES_VENDIDAS
+        ),
+        params![data_inicio, data_fim],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+    )?;
+    
+    Ok(ResumoVendas {
+        data_inicio,
+        data_fim,
+        total_vendas,
+        total_pedidos,
+        pedidos_vendidos,
+        ticket_medio: total_vendas.dividido(pedidos_vendidos)?,
+    })
+}
+
+fn parse_data(data: &str) -> AppResult<NaiveDate> {
+    NaiveDate::parse_from_str(data, "%Y-%m-%d")
+        .map_err(|_| AppError::Validation(format!("Data inválida: '{}'", data)))
+}
+
+fn data_sql(data: NaiveDate) -> String {
+    data.format("%Y-%m-%d").to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testes::*;
+    
+    fn criar(db: &BancoTeste, valores: Vec<serde_json::Value>) -> AppResult<PedidoVenda> {
+        tauri::async_runtime::block_on(create_pedido(db.state(), TOKEN.into(), None, None, itens(valores), None, None, None, None, None, None))
+    }
+    
+    #[test]
+    fn create_pedido_desfaz_cabecalho_quando_item_falha() {
+        let db = banco_teste(TOKEN);
+        let produto_id = produto(&db);
+        
+        // O segundo item referencia um produto inexistente
+        let resultado = criar(&db, vec![
+            serde_json::json!({ "produto_id": produto_id, "quantidade": 2, "preco_unitario": 10 }),
+            serde_json::json!({ "produto_id": 9999, "quantidade": 1, "preco_unitario": 5 }),
+        ]);
+        
+        assert!(resultado.is_err());
+        assert_eq!(contar(&db, "SELECT COUNT(*) FROM pedidos_venda"), 0);
+        assert_eq!(contar(&db, "SELECT COUNT(*) FROM pedido_itens"), 0);
+        assert_eq!(contar(&db, "SELECT COUNT(*) FROM auditoria"), 0);
+    }
+    
+    #[test]
+    fn update_pedido_desfaz_alteracoes_anteriores_ao_erro() {
+        let db = banco_teste(TOKEN);
+        let produto_id = produto(&db);
+        let pedido = criar(&db, vec![
+            serde_json::json!({ "produto_id": produto_id, "quantidade": 1, "preco_unitario": 10 }),
+        ]).unwrap();
+        
+        // Força a falha da última das duas atualizações
+        db.writer().unwrap().execute_batch(
+            "CREATE TRIGGER falha_observacoes BEFORE UPDATE OF observacoes ON pedidos_venda
+             BEGIN SELECT RAISE(ABORT, 'falha simulada'); END;"
+        ).unwrap();
+        
+        let resultado = tauri::async_runtime::block_on(update_pedido(
+            db.state(), TOKEN.into(), pedido.id, Some("boleto".into()), None, None, Some("obs".into()), None, None,
+        ));
+        
+        assert!(resultado.is_err());
+        let forma: Option<String> = db.reader().unwrap().query_row(
+            "SELECT forma_pagamento FROM pedidos_venda WHERE id = ?1",
+            params![pedido.id],
+            |row| row.get(0),
+        ).unwrap();
+        assert_eq!(forma, None);
+    }
+    
+    #[test]
+    fn delete_pedido_mantem_itens_quando_cabecalho_falha() {
+        let db = banco_teste(TOKEN);
+        let produto_id = produto(&db);
+        let pedido = criar(&db, vec![
+            serde_json::json!({ "produto_id": produto_id, "quantidade": 1, "preco_unitario": 10 }),
+        ]).unwrap();
+        
+        db.writer().unwrap().execute_batch(
+            "CREATE TRIGGER falha_exclusao BEFORE DELETE ON pedidos_venda
+             BEGIN SELECT RAISE(ABORT, 'falha simulada'); END;"
+        ).unwrap();
+        
+        let resultado = tauri::async_runtime::block_on(delete_pedido(db.state(), TOKEN.into(), pedido.id));
+        
+        assert!(resultado.is_err());
+        assert_eq!(contar(&db, "SELECT COUNT(*) FROM pedido_itens"), 1);
+        assert_eq!(contar(&db, "SELECT COUNT(*) FROM pedidos_venda"), 1);
+    }
+    
+    #[test]
+    fn pedido_percorre_o_fluxo_e_guarda_historico() {
+        let db = banco_teste(TOKEN);
+        let id = pedido_aprovavel(&db).id;
+        
+        executar(aprovar_pedido(db.state(), TOKEN.into(), id, None)).unwrap();
+        estoque(&db, 5.0);
+        executar(confirmar_pedido(db.state(), TOKEN.into(), id, None, None)).unwrap();
+        executar(separar_pedido(db.state(), TOKEN.into(), id, None)).unwrap();
+        executar(faturar_pedido(db.state(), TOKEN.into(), id, Some("2026-12-01".into()), None)).unwrap();
+        let pedido = executar(entregar_pedido(db.state(), TOKEN.into(), id, Some("2026-11-20".into()), None)).unwrap();
+        
+        assert_eq!(pedido.status, "entregue");
+        assert_eq!(pedido.data_entrega.as_deref(), Some("2026-11-20"));
+        
+        let historico = executar(get_historico_pedido(db.state(), TOKEN.into(), id)).unwrap();
+        let situacoes: Vec<&str> = historico.iter().map(|h| h.status_novo.as_str()).collect();
+        assert_eq!(situacoes, ["orcamento", "aprovado", "confirmado", "separado", "faturado", "entregue"]);
+        
+        let (valor, vencimento): (i64, String) = db.reader().unwrap().query_row(
+            "SELECT valor, data_vencimento FROM contas_receber WHERE pedido_id = ?1",
+            params![id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        ).unwrap();
+        assert_eq!(valor, 2000);
+        assert_eq!(vencimento, "2026-12-01");
+    }
+    
+    #[test]
+    fn transicao_fora_do_fluxo_e_recusada() {
+        let db = banco_teste(TOKEN);
+        let id = pedido_aprovavel(&db).id;
+        
+        assert!(matches!(executar(faturar_pedido(db.state(), TOKEN.into(), id, None, None)), Err(AppError::Validation(_))));
+        
+        executar(cancelar_pedido(db.state(), TOKEN.into(), id, "Desistência".into())).unwrap();
+        assert!(matches!(executar(aprovar_pedido(db.state(), TOKEN.into(), id, None)), Err(AppError::Validation(_))));
+        
+        assert_eq!(contar(&db, "SELECT COUNT(*) FROM pedido_status_historico"), 2);
+        assert_eq!(contar(&db, "SELECT COUNT(*) FROM contas_receber"), 0);
+    }
+    
+    #[test]
+    fn descontos_recalculam_os_totais() {
+        let db = banco_teste(TOKEN);
+        let produto_id = produto(&db);
+        
+        // 3 × 10,00 com 10% = 27,00; 1 × 10,00 (preço do produto) com R$ 0,50 = 9,50
+        let pedido = executar(create_pedido(
+            db.state(), TOKEN.into(), None, None,
+            itens(vec![
+                serde_json::json!({ "produto_id": produto_id, "quantidade": 3, "preco_unitario": 10, "desconto": { "percentual": 10 } }),
+                serde_json::json!({ "produto_id": produto_id, "quantidade": 1, "desconto": { "valor": 0.5 } }),
+            ]),
+            Some(Ajuste::Percentual(5.0)), Some(Ajuste::Valor(Dinheiro::centavos(100))), None, None, None, None,
+        )).unwrap();
+        
+        assert_eq!(pedido.itens[0].total, Dinheiro::centavos(2700));
+        assert_eq!(pedido.itens[1].total, Dinheiro::centavos(950));
+        assert_eq!(pedido.subtotal, Dinheiro::centavos(3650));
+        // 5% de 36,50 = 1,825 → 1,82 (meio centavo, par)
+        assert_eq!(pedido.desconto, Dinheiro::centavos(182));
+        assert_eq!(pedido.total, Dinheiro::centavos(3650 - 182 + 100));
+        
+        // Remover um item reaplica o desconto percentual sobre o novo subtotal
+        let pedido = executar(remove_item_pedido(db.state(), TOKEN.into(), pedido.id, pedido.itens[1].id)).unwrap();
+        assert_eq!(pedido.subtotal, Dinheiro::centavos(2700));
+        assert_eq!(pedido.desconto, Dinheiro::centavos(135));
+        assert_eq!(pedido.total, Dinheiro::centavos(2700 - 135 + 100));
+        
+        let item = itens(vec![serde_json::json!({ "produto_id": produto_id, "quantidade": 1, "desconto": { "valor": 11 } })]).remove(0);
+        assert!(matches!(
+            executar(add_item_pedido(db.state(), TOKEN.into(), pedido.id, item)),
+            Err(AppError::Validation(_))
+        ));
+    }
+    
+    #[test]
+    fn itens_so_mudam_em_orcamento() {
+        let db = banco_teste(TOKEN);
+        let pedido = pedido_aprovavel(&db);
+        executar(aprovar_pedido(db.state(), TOKEN.into(), pedido.id, None)).unwrap();
+        
+        let item = itens(vec![serde_json::json!({ "produto_id": pedido.itens[0].produto_id, "quantidade": 5 })]).remove(0);
+        assert!(matches!(
+            executar(update_item_pedido(db.state(), TOKEN.into(), pedido.id, pedido.itens[0].id, item)),
+            Err(AppError::Validation(_))
+        ));
+        assert!(matches!(
+            executar(remove_item_pedido(db.state(), TOKEN.into(), pedido.id, pedido.itens[0].id)),
+            Err(AppError::Validation(_))
+        ));
+    }
+    
+    #[test]
+    fn confirmacao_reserva_e_faturamento_baixa_estoque() {
+        let db = banco_teste(TOKEN);
+        let id = pedido_aprovavel(&db).id;
+        estoque(&db, 5.0);
+        
+        executar(aprovar_pedido(db.state(), TOKEN.into(), id, None)).unwrap();
+        executar(confirmar_pedido(db.state(), TOKEN.into(), id, None, None)).unwrap();
+        assert_eq!(saldo(&db), (5.0, 2.0));
+        
+        executar(separar_pedido(db.state(), TOKEN.into(), id, None)).unwrap();
+        executar(faturar_pedido(db.state(), TOKEN.into(), id, None, None)).unwrap();
+        assert_eq!(saldo(&db), (3.0, 0.0));
+        assert_eq!(contar(&db, "SELECT COUNT(*) FROM reservas_estoque WHERE status = 'baixada'"), 1);
+    }
+    
+    #[test]
+    fn falta_de_estoque_recusa_ou_vira_encomenda() {
+        let db = banco_teste(TOKEN);
+        let id = pedido_aprovavel(&db).id;
+        estoque(&db, 1.0);
+        executar(aprovar_pedido(db.state(), TOKEN.into(), id, None)).unwrap();
+        
+        assert!(matches!(
+            executar(confirmar_pedido(db.state(), TOKEN.into(), id, None, None)),
+            Err(AppError::Validation(_))
+        ));
+        assert_eq!(saldo(&db), (1.0, 0.0));
+        
+        // Como encomenda: reserva 1, fica 1 pendente e a separação espera o saldo
+        executar(confirmar_pedido(db.state(), TOKEN.into(), id, Some(true), None)).unwrap();
+        assert_eq!(saldo(&db), (1.0, 1.0));
+        assert!(matches!(
+            executar(separar_pedido(db.state(), TOKEN.into(), id, None)),
+            Err(AppError::Validation(_))
+        ));
+        
+        estoque(&db, 2.0);
+        executar(separar_pedido(db.state(), TOKEN.into(), id, None)).unwrap();
+        assert_eq!(saldo(&db), (2.0, 2.0));
+        
+        executar(cancelar_pedido(db.state(), TOKEN.into(), id, "Cliente desistiu".into())).unwrap();
+        assert_eq!(saldo(&db), (2.0, 0.0));
+    }
+    
+    #[test]
+    fn limite_excedido_vai_para_aprovacao_de_credito() {
+        use crate::commands::financeiro::{aprovar_credito, get_exposicao_credito};
+        
+        let db = banco_teste(TOKEN);
+        let pedido = pedido_aprovavel(&db);
+        estoque(&db, 5.0);
+        
+        // Limite de R$ 25 com R$ 10 vencidos: o pedido de R$ 20 não cabe
+        db.writer().unwrap().execute_batch(&format!(
+            "UPDATE clientes SET limite_credito = 2500 WHERE id = {0};
+             INSERT INTO contas_receber (descricao, cliente_id, valor, data_vencimento, status)
+             VALUES ('Título antigo', {0}, 1000, '2020-01-10', 'pendente');",
+            pedido.cliente_id.unwrap()
+        )).unwrap();
+        
+        let exposicao = executar(get_exposicao_credito(db.state(), TOKEN.into(), pedido.cliente_id.unwrap())).unwrap();
+        assert_eq!(exposicao.vencido, Dinheiro::centavos(1000));
+        assert_eq!(exposicao.disponivel, Dinheiro::centavos(1500));
+        
+        executar(aprovar_pedido(db.state(), TOKEN.into(), pedido.id, None)).unwrap();
+        for _ in 0..2 {
+            assert!(matches!(
+                executar(confirmar_pedido(db.state(), TOKEN.into(), pedido.id, None, None)),
+                Err(AppError::Validation(_))
+            ));
+        }
+        assert_eq!(status_pedido(&db.reader().unwrap(), pedido.id).unwrap(), StatusPedido::Aprovado);
+        assert_eq!(contar(&db, "SELECT COUNT(*) FROM aprovacoes_credito WHERE status = 'pendente'"), 1);
+        assert_eq!(saldo(&db), (5.0, 0.0));
+        
+        let solicitacao = contar(&db, "SELECT id FROM aprovacoes_credito");
+        
+        // Cancelado, o pedido guarda a solicitação de crédito e não sai do banco
+        executar(cancelar_pedido(db.state(), TOKEN.into(), pedido.id, "Cliente desistiu".into())).unwrap();
+        assert!(matches!(
+            executar(delete_pedido(db.state(), TOKEN.into(), pedido.id)),
+            Err(AppError::Validation(_))
+        ));
+        db.writer().unwrap().execute("UPDATE pedidos_venda SET status = 'aprovado'", []).unwrap();
+        
+        let aprovacao = executar(aprovar_credito(db.state(), TOKEN.into(), solicitacao, None)).unwrap();
+        assert_eq!(aprovacao.status, "aprovada");
+        assert_eq!(aprovacao.valor_pedido, Dinheiro::centavos(2000));
+        
+        let confirmado = executar(confirmar_pedido(db.state(), TOKEN.into(), pedido.id, None, None)).unwrap();
+        assert_eq!(confirmado.status, "confirmado");
+    }
+    
+    #[test]
+    fn faturamento_gera_parcelas_da_condicao() {
+        use crate::commands::nfe::emitir_nfe;
+        
+        let db = banco_teste(TOKEN);
+        let id = pedido_aprovavel(&db).id;
+        estoque(&db, 5.0);
+        
+        executar(update_pedido(db.state(), TOKEN.into(), id, None, Some("30/60/90".into()), None, None, None, None)).unwrap();
+        executar(aprovar_pedido(db.state(), TOKEN.into(), id, None)).unwrap();
+        executar(confirmar_pedido(db.state(), TOKEN.into(), id, None, None)).unwrap();
+        executar(separar_pedido(db.state(), TOKEN.into(), id, None)).unwrap();
+        executar(faturar_pedido(db.state(), TOKEN.into(), id, Some("2026-01-31".into()), None)).unwrap();
+        
+        // Condição não muda depois do faturamento
+        assert!(executar(update_pedido(db.state(), TOKEN.into(), id, None, Some("à vista".into()), None, None, None, None)).is_err());
+        
+        // A nota emitida depois só se liga aos títulos já gerados
+        let nfe = executar(emitir_nfe(db.state(), TOKEN.into(), id, "Venda".into(), None)).unwrap();
+        
+        let conn = db.reader().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT parcela, total_parcelas, valor, data_vencimento, nota_fiscal_id FROM contas_receber
+             WHERE pedido_id = ?1 ORDER BY parcela"
+        ).unwrap();
+        let parcelas: Vec<(i64, i64, i64, String, Option<i64>)> = stmt
+            .query_map(params![id], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?)))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        
+        assert_eq!(parcelas, vec![
+            (1, 3, 668, "2026-03-02".to_string(), Some(nfe.id)),
+            (2, 3, 666, "2026-04-01".to_string(), Some(nfe.id)),
+            (3, 3, 666, "2026-05-01".to_string(), Some(nfe.id)),
+        ]);
+    }
+    
+    #[test]
+    fn condicao_cadastrada_prevalece_no_faturamento() {
+        use crate::commands::condicoes_pagamento::create_condicao_pagamento;
+        
+        let db = banco_teste(TOKEN);
+        let id = pedido_aprovavel(&db).id;
+        estoque(&db, 5.0);
+        
+        let condicao = executar(create_condicao_pagamento(db.state(), TOKEN.into(), serde_json::from_value(serde_json::json!({
+            "nome": "Entrada + 28/56", "parcelas": 2, "intervalo_dias": 28, "primeiro_vencimento_dias": 28,
+            "entrada_percentual": 10
+        })).unwrap())).unwrap();
+        
+        let pedido = executar(update_pedido(db.state(), TOKEN.into(), id, None, Some("30".into()), Some(condicao.id), None, None, None)).unwrap();
+        assert_eq!(pedido.condicao_pagamento.as_deref(), Some("Entrada + 28/56"));
+        
+        // Com juros, os títulos não somariam o total da nota
+        let com_juros = executar(create_condicao_pagamento(db.state(), TOKEN.into(), serde_json::from_value(serde_json::json!({
+            "nome": "3x com juros", "parcelas": 3, "ajuste_percentual": 3
+        })).unwrap())).unwrap();
+        assert!(matches!(
+            executar(update_pedido(db.state(), TOKEN.into(), id, None, None, Some(com_juros.id), None, None, None)),
+            Err(AppError::Validation(_))
+        ));
+        
+        executar(aprovar_pedido(db.state(), TOKEN.into(), id, None)).unwrap();
+        executar(confirmar_pedido(db.state(), TOKEN.into(), id, None, None)).unwrap();
+        executar(separar_pedido(db.state(), TOKEN.into(), id, None)).unwrap();
+        executar(faturar_pedido(db.state(), TOKEN.into(), id, Some("2026-02-01".into()), None)).unwrap();
+        
+        let conn = db.reader().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT descricao, valor, data_vencimento FROM contas_receber WHERE pedido_id = ?1 ORDER BY parcela"
+        ).unwrap();
+        let titulos: Vec<(String, i64, String)> = stmt
+            .query_map(params![id], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        
+        let numero = &pedido.numero;
+        assert_eq!(titulos, vec![
+            (format!("Pedido {} - entrada", numero), 200, "2026-02-01".to_string()),
+            (format!("Pedido {} - parcela 2/3", numero), 900, "2026-03-01".to_string()),
+            (format!("Pedido {} - parcela 3/3", numero), 900, "2026-03-29".to_string()),
+        ]);
+    }
+    
+    #[test]
+    fn item_sem_preco_usa_a_tabela_do_cliente() {
+        use crate::commands::tabelas_preco::{create_tabela_preco, save_item_tabela_preco};
+        
+        let db = banco_teste(TOKEN);
+        let produto_id = produto(&db);
+        let cliente_id = cliente(&db);
+        
+        // 10% de desconto geral; a partir de 10 unidades, 7,00 fixo
+        let tabela = executar(create_tabela_preco(db.state(), TOKEN.into(), serde_json::from_value(serde_json::json!({
+            "nome": "Revenda", "ajuste_percentual": -10
+        })).unwrap())).unwrap();
+        executar(save_item_tabela_preco(db.state(), TOKEN.into(), tabela.id, serde_json::from_value(serde_json::json!({
+            "produto_id": produto_id, "quantidade_minima": 10, "preco": 7
+        })).unwrap())).unwrap();
+        db.writer().unwrap().execute(
+            "UPDATE clientes SET tabela_preco_id = ?1 WHERE id = ?2",
+            params![tabela.id, cliente_id],
+        ).unwrap();
+        
+        let pedido = executar(create_pedido(
+            db.state(), TOKEN.into(), Some(cliente_id), None,
+            itens(vec![
+                serde_json::json!({ "produto_id": produto_id, "quantidade": 2 }),
+                serde_json::json!({ "produto_id": produto_id, "quantidade": 10 }),
+                serde_json::json!({ "produto_id": produto_id, "quantidade": 1, "preco_unitario": 12 }),
+            ]),
+            None, None, None, None, None, None,
+        )).unwrap();
+        
+        let precos: Vec<i64> = pedido.itens.iter().map(|i| i.preco_unitario.em_centavos()).collect();
+        assert_eq!(precos, vec![900, 700, 1200]);
+        
+        // Sem cliente, vale o preço de venda do produto
+        let avulso = criar(&db, vec![serde_json::json!({ "produto_id": produto_id, "quantidade": 10 })]).unwrap();
+        assert_eq!(avulso.itens[0].preco_unitario, Dinheiro::centavos(1000));
+    }
+    
+    #[test]
+    fn dashboard_compara_periodos_e_conta_apenas_vendidos() {
+        let db = banco_teste(TOKEN);
+        let produto_id = produto(&db);
+        let cliente_id = cliente(&db);
+        {
+            let conn = db.writer().unwrap();
+            for (numero, data, status, total) in [
+                ("PV-1", "2026-03-02", "confirmado", 10000),
+                ("PV-2", "2026-03-02", "faturado", 5000),
+                ("PV-3", "2026-03-05", "orcamento", 7000),
+                ("PV-4", "2026-03-06", "cancelado", 9000),
+                ("PV-5", "2026-02-25", "entregue", 10000),
+            ] {
+                conn.execute(
+                    "INSERT INTO pedidos_venda (numero, cliente_id, vendedor_id, data_pedido, status, subtotal, total)
+                     VALUES (?1, ?2, 1, ?3, ?4, ?5, ?5)",
+                    params![numero, cliente_id, data, status, total],
+                ).unwrap();
+                conn.execute(
+                    "INSERT INTO pedido_itens (pedido_id, produto_id, quantidade, preco_unitario, total) VALUES (?1, ?2, 1, ?3, ?3)",
+                    params![conn.last_insert_rowid(), produto_id, total],
+                ).unwrap();
+            }
+            for (numero, status) in [("ORC-1", "ganho"), ("ORC-2", "perdido"), ("ORC-3", "perdido"), ("ORC-4", "aberto")] {
+                conn.execute(
+                    "INSERT INTO orcamentos (numero, cliente_id, data_emissao, status) VALUES (?1, ?2, '2026-03-01', ?3)",
+                    params![numero, cliente_id, status],
+                ).unwrap();
+            }
+        }
+        
+        let dashboard = executar(get_dashboard_vendas(
+            db.state(), TOKEN.into(), Some("2026-03-01".into()), Some("2026-03-07".into()),
+        )).unwrap();
+        
+        assert_eq!(dashboard.total_vendas, Dinheiro::centavos(15000));
+        assert_eq!(dashboard.total_pedidos, 4);
+        assert_eq!(dashboard.pedidos_vendidos, 2);
+        assert_eq!(dashboard.ticket_medio, Dinheiro::centavos(7500));
+        
+        assert_eq!(dashboard.periodo_anterior.data_inicio, "2026-02-22");
+        assert_eq!(dashboard.periodo_anterior.data_fim, "2026-02-28");
+        assert_eq!(dashboard.periodo_anterior.total_vendas, Dinheiro::centavos(10000));
+        assert_eq!(dashboard.variacao_vendas, Some(50.0));
+        
+        assert_eq!((dashboard.orcamentos_emitidos, dashboard.orcamentos_ganhos, dashboard.orcamentos_perdidos), (4, 1, 2));
+        assert!((dashboard.taxa_conversao.unwrap() - 100.0 / 3.0).abs() < 1e-9);
+        
+        assert_eq!(dashboard.vendas_por_dia.len(), 7);
+        assert_eq!(dashboard.vendas_por_dia[1].data, "2026-03-02");
+        assert_eq!((dashboard.vendas_por_dia[1].total, dashboard.vendas_por_dia[1].pedidos), (Dinheiro::centavos(15000), 2));
+        assert!(dashboard.vendas_por_dia[4].total.is_zero());
+        
+        assert_eq!(dashboard.vendas_por_vendedor.len(), 1);
+        assert_eq!((dashboard.vendas_por_vendedor[0].vendedor_id, dashboard.vendas_por_vendedor[0].quantidade), (Some(1), 2));
+        assert_eq!(dashboard.top_produtos[0].quantidade, 2.0);
+        assert_eq!(dashboard.top_clientes[0].total, Dinheiro::centavos(15000));
+    }
+    
+    #[test]
+    fn dashboard_recusa_periodo_longo_ou_fora_do_calendario() {
+        let db = banco_teste(TOKEN);
+        let dashboard = |inicio: &str, fim: &str| executar(get_dashboard_vendas(
+            db.state(), TOKEN.into(), Some(inicio.into()), Some(fim.into()),
+        ));
+        
+        assert!(dashboard("2024-01-01", "2025-12-31").is_ok());
+        assert!(matches!(dashboard("2024-01-01", "2026-01-01"), Err(AppError::Validation(_))));
+        
+        let primeiro_dia = NaiveDate::MIN.format("%Y-%m-%d").to_string();
+        assert!(matches!(dashboard(&primeiro_dia, &primeiro_dia), Err(AppError::Validation(_))));
+    }
+}
@@ -0,0 +1,281 @@
+//! Contexto de requisição
+//!
+//! Resolve o usuário autenticado a partir do token de sessão antes que
+//! qualquer comando de negócio seja executado, junto com suas permissões
+//! efetivas (permissões diretas do usuário somadas às dos seus papéis).
+//!
+//! Sessões expiram após um período de inatividade: cada uso renova o prazo.
+//! O token nunca é gravado em texto puro, apenas seu hash SHA-256.
+
+use rusqlite::{Connection, params};
+use chrono::{DateTime, Duration, SecondsFormat, Utc};
+use sha2::{Digest, Sha256};
+
+use crate::error::{AppError, AppResult};
+use crate::models::{UsuarioPublico, Permissoes, Modulo, Acao};
+use crate::database::DatabaseState;
+
+/// Horas de inatividade até a sessão expirar
+pub const SESSAO_INATIVIDADE_HORAS: i64 = 24;
+
+/// Intervalo mínimo entre renovações, para não gravar a cada comando
+const RENOVACAO_MINUTOS: i64 = 5;
+
+/// Hash do token de sessão, como gravado em `sessoes.token_hash`
+pub fn hash_token(token: &str) -> String {
+    Sha256::digest(token.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Data em RFC 3339 UTC com segundos, comparável como texto no SQLite
+pub fn carimbo(data: DateTime<Utc>) -> String {
+    data.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+/// Usuário autenticado da requisição atual
+#[derive(Debug, Clone)]
+pub struct RequestContext {
+    pub usuario: UsuarioPublico,
+    pub permissoes: Permissoes,
+    pub sessao_id: String,
+    pub ultimo_uso: DateTime<Utc>,
+}
+
+/// Sessão do token e o usuário dono, como gravados no banco
+struct Sessao {
+    id: String,
+    usuario: UsuarioPublico,
+    ativo: bool,
+    expires_at: String,
+    ultimo_uso: String,
+}
+
+impl RequestContext {
+    /// Resolve a sessão do token usando uma conexão do pool
+    /// 
+    /// Recusa usuários com troca de senha pendente: até trocarem a senha,
+    /// só os comandos de sessão (que usam `resolve_sessao`) ficam liberados.
+    pub fn resolve(db: &DatabaseState, token: &str) -> AppResult<Self> {
+        let ctx = Self::resolve_sessao(db, token)?;
+        if ctx.usuario.trocar_senha {
+            return Err(AppError::Authentication("Troca de senha obrigatória antes de continuar".into()));
+        }
+        Ok(ctx)
+    }
+    
+    /// Resolve a sessão sem exigir que a troca de senha pendente tenha sido feita
+    /// 
+    /// Renova a expiração da sessão (expiração deslizante).
+    pub fn resolve_sessao(db: &DatabaseState, token: &str) -> AppResult<Self> {
+        let ctx = {
+            let conn = db.reader()?;
+            Self::from_connection(&conn, token)?
+        };
+        
+        let agora = Utc::now();
+        if agora - ctx.ultimo_uso >= Duration::minutes(RENOVACAO_MINUTOS) {
+            db.writer()?.execute(
+                "UPDATE sessoes SET ultimo_uso = ?1, expires_at = ?2 WHERE id = ?3",
+                params![
+                    carimbo(agora),
+                    carimbo(agora + Duration::hours(SESSAO_INATIVIDADE_HORAS)),
+                    ctx.sessao_id
+                ],
+            )?;
+        }
+        
+        Ok(ctx)
+    }
+    
+    /// Resolve a sessão do token na conexão informada
+    pub fn from_connection(conn: &Connection, token: &str) -> AppResult<Self> {
+        if token.is_empty() {
+            return Err(AppError::Authentication("Sessão não informada".into()));
+        }
+        
+        let result = conn.query_row(
+            "SELECT u.id, u.nome, u.email, u.cargo, u.departamento, u.avatar, u.permissoes,
+                    u.trocar_senha, u.ativo, s.expires_at, s.id, s.ultimo_uso
+             FROM sessoes s
+             INNER JOIN usuarios u ON u.id = s.usuario_id
+             WHERE s.token_hash = ?1",
+            params![hash_token(token)],
+            |row| Ok(Sessao {
+                usuario: UsuarioPublico {
+                    id: row.get(0)?,
+                    nome: row.get(1)?,
+                    email: row.get(2)?,
+                    cargo: row.get(3)?,
+                    departamento: row.get(4)?,
+                    avatar: row.get(5)?,
+                    permissoes: serde_json::from_str(&row.get::<_, String>(6)?).unwrap_or(serde_json::json!({})),
+                    trocar_senha: row.get(7)?,
+                },
+                ativo: row.get(8)?,
+                expires_at: row.get(9)?,
+                id: row.get(10)?,
+                ultimo_uso: row.get(11)?,
+            }),
+        );
+        
+        let sessao = match result {
+            Ok(sessao) => sessao,
+            Err(rusqlite::Error::QueryReturnedNoRows) => {
+                return Err(AppError::Authentication("Sessão inválida".into()));
+            }
+            Err(e) => return Err(AppError::Database(e.to_string())),
+        };
+        
+        // expires_at e ultimo_uso são gravados em RFC 3339
+        let expira = parse_carimbo(&sessao.expires_at)?;
+        let ultimo_uso = parse_carimbo(&sessao.ultimo_uso)?;
+        
+        if expira <= Utc::now() {
+            return Err(AppError::Authentication("Sessão expirada, faça login novamente".into()));
+        }
+        
+        if !sessao.ativo {
+            return Err(AppError::Authentication("Usuário inativo".into()));
+        }
+        
+        let efetivas = load_permissoes(conn, sessao.usuario.id, &sessao.usuario.permissoes)?;
+        
+        Ok(Self {
+            usuario: sessao.usuario,
+            permissoes: efetivas,
+            sessao_id: sessao.id,
+            ultimo_uso,
+        })
+    }
+    
+    /// Exige permissão para a ação no módulo
+    pub fn require(&self, modulo: Modulo, acao: Acao) -> AppResult<()> {
+        if self.permissoes.permite(modulo, acao) {
+            Ok(())
+        } else {
+            Err(AppError::Authorization(format!(
+                "Sem permissão para {} em {}", acao, modulo
+            )))
+        }
+    }
+    
+    /// Exige permissão para a ação em pelo menos um dos módulos
+    /// 
+    /// Usado em cadastros compartilhados, como produtos e clientes.
+    pub fn require_any(&self, modulos: &[Modulo], acao: Acao) -> AppResult<()> {
+        if modulos.iter().any(|m| self.permissoes.permite(*m, acao)) {
+            Ok(())
+        } else {
+            Err(AppError::Authorization(format!("Sem permissão para {}", acao)))
+        }
+    }
+    
+    /// Exige perfil de administrador
+    pub fn require_admin(&self) -> AppResult<()> {
+        if self.permissoes.admin {
+            Ok(())
+        } else {
+            Err(AppError::Authorization("Acesso restrito a administradores".into()))
+        }
+    }
+}
+
+fn parse_carimbo(valor: &str) -> AppResult<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(valor)
+        .map(|d| d.with_timezone(&Utc))
+        .map_err(|_| AppError::Authentication("Sessão inválida".into()))
+}
+
+/// Calcula as permissões efetivas: diretas do usuário + papéis atribuídos
+pub fn load_permissoes(conn: &Connection, usuario_id: i64, diretas: &serde_json::Value) -> AppResult<Permissoes> {
+    let mut efetivas = Permissoes::from_json(diretas);
+    
+    let mut stmt = conn.prepare(
+        "SELECT p.permissoes FROM papeis p
+         INNER JOIN usuario_papeis up ON up.papel_id = p.id
+         WHERE up.usuario_id = ?1"
+    )?;
+    
+    let papeis: Vec<String> = stmt.query_map(params![usuario_id], |row| row.get(0))?
+        .filter_map(|r| r.ok())
+        .collect();
+    
+    for json in papeis {
+        let valor: serde_json::Value = serde_json::from_str(&json).unwrap_or(serde_json::json!({}));
+        efetivas.merge(&Permissoes::from_json(&valor));
+    }
+    
+    Ok(efetivas)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::clientes::get_clientes;
+    use crate::testes::*;
+    
+    fn erro_de_autenticacao(db: &DatabaseState, token: &str) -> bool {
+        matches!(RequestContext::resolve(db, token), Err(AppError::Authentication(_)))
+    }
+    
+    #[test]
+    fn resolve_recusa_sessao_ausente_invalida_ou_expirada() {
+        let db = banco_teste(TOKEN);
+        assert_eq!(RequestContext::resolve(&db, TOKEN).unwrap().usuario.id, 1);
+        
+        assert!(erro_de_autenticacao(&db, ""));
+        assert!(erro_de_autenticacao(&db, "token-desconhecido"));
+        
+        db.writer().unwrap().execute(
+            "UPDATE sessoes SET expires_at = ?1",
+            params![carimbo(Utc::now() - Duration::minutes(1))],
+        ).unwrap();
+        assert!(erro_de_autenticacao(&db, TOKEN));
+    }
+    
+    #[test]
+    fn resolve_recusa_usuario_inativo_e_troca_de_senha_pendente() {
+        let db = banco_teste(TOKEN);
+        let id = usuario(&db, "operador@teste", "{}");
+        sessao(&db, id, "operador");
+        
+        db.writer().unwrap().execute("UPDATE usuarios SET trocar_senha = 1 WHERE id = ?1", params![id]).unwrap();
+        assert!(erro_de_autenticacao(&db, "operador"));
+        // Os comandos de sessão continuam liberados para a troca da senha
+        assert!(RequestContext::resolve_sessao(&db, "operador").is_ok());
+        
+        db.writer().unwrap().execute("UPDATE usuarios SET trocar_senha = 0, ativo = 0 WHERE id = ?1", params![id]).unwrap();
+        assert!(erro_de_autenticacao(&db, "operador"));
+    }
+    
+    #[test]
+    fn comando_de_negocio_exige_sessao() {
+        let db = banco_teste(TOKEN);
+        let listar = |token: &str| executar(get_clientes(db.state(), token.into(), None, None, None));
+        
+        assert!(matches!(listar(""), Err(AppError::Authentication(_))));
+        assert!(listar(TOKEN).is_ok());
+    }
+    
+    #[test]
+    fn uso_da_sessao_renova_a_expiracao() {
+        let db = banco_teste(TOKEN);
+        let antes = Utc::now() - Duration::minutes(RENOVACAO_MINUTOS + 1);
+        db.writer().unwrap().execute(
+            "UPDATE sessoes SET ultimo_uso = ?1, expires_at = ?2",
+            params![carimbo(antes), carimbo(antes + Duration::hours(1))],
+        ).unwrap();
+        
+        RequestContext::resolve(&db, TOKEN).unwrap();
+        
+        let expira: String = db.reader().unwrap().query_row("SELECT expires_at FROM sessoes", [], |row| row.get(0)).unwrap();
+        let expira = parse_carimbo(&expira).unwrap();
+        assert!(expira > Utc::now() + Duration::hours(SESSAO_INATIVIDADE_HORAS - 1));
+        
+        // Logo em seguida não grava de novo
+        let ctx = RequestContext::resolve(&db, TOKEN).unwrap();
+        assert!(Utc::now() - ctx.ultimo_uso < Duration::minutes(RENOVACAO_MINUTOS));
+    }
+}
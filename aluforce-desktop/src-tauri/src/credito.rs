@@ -0,0 +1,149 @@
+//! Limite de crédito de clientes
+//!
+//! A exposição de um cliente soma o que ele já deve e o que está vendido
+//! mas ainda não faturado:
+//!
+//! - contas a receber pendentes ainda no prazo;
+//! - contas a receber pendentes vencidas;
+//! - pedidos confirmados ou separados ainda sem títulos a receber.
+//!
+//! Um pedido só é confirmado se exposição + total do pedido couber no
+//! limite, ou se houver uma aprovação de crédito para ele. Clientes com
+//! limite zero não têm limite configurado e não são verificados.
+
+use rusqlite::{Connection, params};
+use chrono::Utc;
+
+use crate::error::{AppError, AppResult};
+use crate::models::{Dinheiro, ExposicaoCredito};
+
+/// Resultado da análise de crédito de um pedido
+pub enum Analise {
+    /// Cabe no limite, cliente sem limite ou crédito já aprovado
+    Liberado,
+    /// Excede o limite; `exposicao` não inclui o próprio pedido
+    Excedido {
+        cliente_id: i64,
+        valor_pedido: Dinheiro,
+        exposicao: Dinheiro,
+        limite: Dinheiro,
+    },
+}
+
+/// Calcula a exposição de crédito do cliente
+///
+/// `ignorar_pedido` exclui um pedido da soma de pedidos em aberto (o que
+/// está sendo analisado).
+pub fn exposicao(conn: &Connection, cliente_id: i64, ignorar_pedido: Option<i64>) -> AppResult<ExposicaoCredito> {
+    let hoje = Utc::now().format("%Y-%m-%d").to_string();
+    
+    let limite_credito: Dinheiro = match conn.query_row(
+        "SELECT COALESCE(limite_credito, 0) FROM clientes WHERE id = ?1",
+        params![cliente_id],
+        |row| row.get(0),
+    ) {
+        Ok(limite) => limite,
+        Err(rusqlite::Error::QueryReturnedNoRows) => return Err(AppError::NotFound("Cliente não encontrado".into())),
+        Err(e) => return Err(AppError::Database(e.to_string())),
+    };
+    
+    let (a_receber, vencido): (Dinheiro, Dinheiro) = conn.query_row(
+        "SELECT COALESCE(SUM(CASE WHEN data_vencimento >= ?2 THEN valor ELSE 0 END), 0),
+                COALESCE(SUM(CASE WHEN data_vencimento < ?2 THEN valor ELSE 0 END), 0)
+         FROM contas_receber
+         WHERE cliente_id = ?1 AND status = 'pendente'",
+        params![cliente_id, hoje],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )?;
+    
+    let pedidos_em_aberto: Dinheiro = conn.query_row(
+        "SELECT COALESCE(SUM(total), 0) FROM pedidos_venda
+         WHERE cliente_id = ?1 AND status IN ('confirmado', 'separado') AND id <> ?2
+           AND NOT EXISTS (SELECT 1 FROM contas_receber cr WHERE cr.pedido_id = pedidos_venda.id)",
+        params![cliente_id, ignorar_pedido.unwrap_or(0)],
+        |row| row.get(0),
+    )?;
+    
+    let exposicao = a_receber + vencido + pedidos_em_aberto;
+    
+    Ok(ExposicaoCredito {
+        cliente_id,
+        limite_credito,
+        a_receber,
+        vencido,
+        pedidos_em_aberto,
+        exposicao,
+        disponivel: limite_credito - exposicao,
+    })
+}
+
+/// Verifica se o pedido cabe no limite de crédito do cliente
+pub fn analisar_pedido(conn: &Connection, pedido_id: i64) -> AppResult<Analise> {
+    let (cliente_id, total): (Option<i64>, Dinheiro) = conn.query_row(
+        "SELECT cliente_id, total FROM pedidos_venda WHERE id = ?1",
+        params![pedido_id],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )?;
+    
+    let Some(cliente_id) = cliente_id else {
+        return Ok(Analise::Liberado);
+    };
+    
+    let situacao = exposicao(conn, cliente_id, Some(pedido_id))?;
+    if situacao.limite_credito.is_zero() || situacao.exposicao + total <= situacao.limite_credito {
+        return Ok(Analise::Liberado);
+    }
+    
+    // Aprovação vale para o valor aprovado; se o pedido crescer, nova análise
+    let aprovado: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM aprovacoes_credito
+         WHERE pedido_id = ?1 AND status = 'aprovada' AND valor_pedido >= ?2",
+        params![pedido_id, total],
+        |row| row.get(0),
+    )?;
+    if aprovado > 0 {
+        return Ok(Analise::Liberado);
+    }
+    
+    Ok(Analise::Excedido {
+        cliente_id,
+        valor_pedido: total,
+        exposicao: situacao.exposicao,
+        limite: situacao.limite_credito,
+    })
+}
+
+/// Coloca o pedido na fila de aprovação de crédito
+///
+/// Se já houver uma solicitação pendente para o pedido, ela é atualizada
+/// com os valores atuais. Devolve o ID da solicitação.
+pub fn solicitar_aprovacao(conn: &Connection, pedido_id: i64, analise: &Analise, usuario_id: i64) -> AppResult<i64> {
+    let Analise::Excedido { cliente_id, valor_pedido, exposicao, limite } = analise else {
+        return Err(AppError::Internal("Pedido liberado não precisa de aprovação de crédito".into()));
+    };
+    
+    let pendente = conn.query_row(
+        "SELECT id FROM aprovacoes_credito WHERE pedido_id = ?1 AND status = 'pendente'",
+        params![pedido_id],
+        |row| row.get::<_, i64>(0),
+    );
+    
+    match pendente {
+        Ok(id) => {
+            conn.execute(
+                "UPDATE aprovacoes_credito SET valor_pedido = ?1, exposicao = ?2, limite_credito = ?3 WHERE id = ?4",
+                params![valor_pedido, exposicao, limite, id],
+            )?;
+            Ok(id)
+        }
+        Err(rusqlite::Error::QueryReturnedNoRows) => {
+            conn.execute(
+                "INSERT INTO aprovacoes_credito (pedido_id, cliente_id, valor_pedido, exposicao, limite_credito, solicitado_por)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![pedido_id, cliente_id, valor_pedido, exposicao, limite, usuario_id],
+            )?;
+            Ok(conn.last_insert_rowid())
+        }
+        Err(e) => Err(AppError::Database(e.to_string())),
+    }
+}
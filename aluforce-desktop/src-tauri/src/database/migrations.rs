@@ -0,0 +1,1340 @@
+//! Migrations versionadas do esquema SQLite
+//! 
+//! Cada migration é aplicada uma única vez, em ordem crescente de versão,
+//! e registrada na tabela `schema_migrations`. Nunca altere uma migration
+//! já publicada: crie uma nova com o próximo número.
+
+use rusqlite::Connection;
+use rusqlite::types::Value;
+
+use crate::error::{AppError, AppResult};
+use crate::models::Dinheiro;
+
+/// Migration de esquema
+pub struct Migration {
+    pub version: i64,
+    pub descricao: &'static str,
+    pub sql: &'static str,
+    /// Conversão de dados que não cabe em SQL, executada após `sql` na
+    /// mesma transação
+    pub rotina: Option<fn(&Connection) -> AppResult<()>>,
+}
+
+/// Lista ordenada de migrations conhecidas por este binário
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        descricao: "Esquema inicial",
+        sql: V001_ESQUEMA_INICIAL,
+        rotina: None,
+    },
+    Migration {
+        version: 2,
+        descricao: "Tabelas de compras e saldos de estoque",
+        sql: V002_COMPRAS_ESTOQUE,
+        rotina: None,
+    },
+    Migration {
+        version: 3,
+        descricao: "Papéis e permissões por módulo",
+        sql: V003_PAPEIS,
+        rotina: None,
+    },
+    Migration {
+        version: 4,
+        descricao: "Troca de senha obrigatória",
+        sql: V004_TROCAR_SENHA,
+        rotina: None,
+    },
+    Migration {
+        version: 5,
+        descricao: "Assistente de primeira execução",
+        sql: V005_INSTALACAO,
+        rotina: Some(v005_senha_padrao),
+    },
+    Migration {
+        version: 6,
+        descricao: "Controle de tentativas de login",
+        sql: V006_TENTATIVAS_LOGIN,
+        rotina: None,
+    },
+    Migration {
+        version: 7,
+        descricao: "Sessões com token em hash e expiração deslizante",
+        sql: V007_SESSOES,
+        rotina: None,
+    },
+    Migration {
+        version: 8,
+        descricao: "Trilha de auditoria",
+        sql: V008_AUDITORIA,
+        rotina: None,
+    },
+    Migration {
+        version: 9,
+        descricao: "Sequências de numeração de documentos",
+        sql: V009_SEQUENCIAS,
+        rotina: None,
+    },
+    Migration {
+        version: 10,
+        descricao: "Valores monetários em centavos",
+        sql: "",
+        rotina: Some(v010_dinheiro_em_centavos),
+    },
+    Migration {
+        version: 11,
+        descricao: "Histórico de situação dos pedidos de venda",
+        sql: V011_HISTORICO_PEDIDOS,
+        rotina: None,
+    },
+    Migration {
+        version: 12,
+        descricao: "Descontos e acréscimos percentuais nos pedidos",
+        sql: V012_AJUSTES_PEDIDO,
+        rotina: None,
+    },
+    Migration {
+        version: 13,
+        descricao: "Reservas de estoque dos pedidos de venda",
+        sql: V013_RESERVAS_ESTOQUE,
+        rotina: None,
+    },
+    Migration {
+        version: 14,
+        descricao: "Fila de aprovação de crédito",
+        sql: V014_APROVACOES_CREDITO,
+        rotina: None,
+    },
+    Migration {
+        version: 15,
+        descricao: "Parcelas de contas a receber",
+        sql: V015_PARCELAS_RECEBER,
+        rotina: None,
+    },
+    Migration {
+        version: 16,
+        descricao: "Cadastro de condições de pagamento",
+        sql: V016_CONDICOES_PAGAMENTO,
+        rotina: None,
+    },
+    Migration {
+        version: 17,
+        descricao: "Tabelas de preço por cliente",
+        sql: V017_TABELAS_PRECO,
+        rotina: None,
+    },
+    Migration {
+        version: 18,
+        descricao: "Regras e lançamentos de comissão",
+        sql: V018_COMISSOES,
+        rotina: None,
+    },
+    Migration {
+        version: 19,
+        descricao: "Orçamentos de venda com revisões",
+        sql: V019_ORCAMENTOS,
+        rotina: None,
+    },
+    Migration {
+        version: 20,
+        descricao: "Devoluções de venda e finalidade da NFe",
+        sql: V020_DEVOLUCOES,
+        rotina: None,
+    },
+    Migration {
+        version: 21,
+        descricao: "Itens e fluxo de aprovação de requisições, cotações e pedidos de compra",
+        sql: V021_FLUXO_COMPRAS,
+        rotina: None,
+    },
+    Migration {
+        version: 22,
+        descricao: "Frete nas cotações e pedidos de compra",
+        sql: V022_FRETE_COMPRAS,
+        rotina: None,
+    },
+    Migration {
+        version: 23,
+        descricao: "Reembolso de devolução em contas a pagar ao cliente",
+        sql: V023_REEMBOLSO_DEVOLUCAO,
+        rotina: None,
+    },
+];
+
+/// Versão mais recente do esquema suportada por este binário
+pub fn latest_version() -> i64 {
+    MIGRATIONS.last().map(|m| m.version).unwrap_or(0)
+}
+
+// Usa IF NOT EXISTS porque instalações anteriores ao controle de versão
+// já possuem estas tabelas criadas pelo antigo `create_tables`.
+const V001_ESQUEMA_INICIAL: &str = "
+    -- Tabela de usuários
+    CREATE TABLE IF NOT EXISTS usuarios (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        nome TEXT NOT NULL,
+        email TEXT UNIQUE NOT NULL,
+        senha_hash TEXT NOT NULL,
+        cargo TEXT,
+        departamento TEXT,
+        avatar TEXT,
+        ativo INTEGER DEFAULT 1,
+        permissoes TEXT DEFAULT '{}',
+        ultimo_acesso TEXT,
+        created_at TEXT DEFAULT (datetime('now')),
+        updated_at TEXT DEFAULT (datetime('now'))
+    );
+    
+    -- Tabela de sessões
+    CREATE TABLE IF NOT EXISTS sessoes (
+        id TEXT PRIMARY KEY,
+        usuario_id INTEGER NOT NULL,
+        token TEXT NOT NULL,
+        expires_at TEXT NOT NULL,
+        created_at TEXT DEFAULT (datetime('now')),
+        FOREIGN KEY (usuario_id) REFERENCES usuarios(id)
+    );
+    
+    -- Tabela de empresas
+    CREATE TABLE IF NOT EXISTS empresas (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        razao_social TEXT NOT NULL,
+        nome_fantasia TEXT,
+        cnpj TEXT UNIQUE,
+        inscricao_estadual TEXT,
+        inscricao_municipal TEXT,
+        telefone TEXT,
+        email TEXT,
+        cep TEXT,
+        endereco TEXT,
+        numero TEXT,
+        complemento TEXT,
+        bairro TEXT,
+        cidade TEXT,
+        estado TEXT,
+        logo_path TEXT,
+        created_at TEXT DEFAULT (datetime('now')),
+        updated_at TEXT DEFAULT (datetime('now'))
+    );
+    
+    -- Tabela de clientes
+    CREATE TABLE IF NOT EXISTS clientes (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        nome TEXT NOT NULL,
+        razao_social TEXT,
+        nome_fantasia TEXT,
+        cnpj TEXT,
+        cpf TEXT,
+        inscricao_estadual TEXT,
+        inscricao_municipal TEXT,
+        email TEXT,
+        telefone TEXT,
+        celular TEXT,
+        cep TEXT,
+        endereco TEXT,
+        numero TEXT,
+        complemento TEXT,
+        bairro TEXT,
+        cidade TEXT,
+        estado TEXT,
+        observacoes TEXT,
+        limite_credito REAL DEFAULT 0,
+        vendedor_id INTEGER,
+        ativo INTEGER DEFAULT 1,
+        created_at TEXT DEFAULT (datetime('now')),
+        updated_at TEXT DEFAULT (datetime('now'))
+    );
+    
+    -- Tabela de fornecedores
+    CREATE TABLE IF NOT EXISTS fornecedores (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        nome TEXT NOT NULL,
+        razao_social TEXT,
+        nome_fantasia TEXT,
+        cnpj TEXT,
+        cpf TEXT,
+        inscricao_estadual TEXT,
+        email TEXT,
+        telefone TEXT,
+        cep TEXT,
+        endereco TEXT,
+        numero TEXT,
+        bairro TEXT,
+        cidade TEXT,
+        estado TEXT,
+        observacoes TEXT,
+        ativo INTEGER DEFAULT 1,
+        created_at TEXT DEFAULT (datetime('now')),
+        updated_at TEXT DEFAULT (datetime('now'))
+    );
+    
+    -- Tabela de produtos
+    CREATE TABLE IF NOT EXISTS produtos (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        codigo TEXT UNIQUE,
+        nome TEXT NOT NULL,
+        descricao TEXT,
+        categoria TEXT,
+        unidade TEXT DEFAULT 'UN',
+        preco_custo REAL DEFAULT 0,
+        preco_venda REAL DEFAULT 0,
+        margem REAL DEFAULT 0,
+        estoque_minimo REAL DEFAULT 0,
+        estoque_atual REAL DEFAULT 0,
+        ncm TEXT,
+        cest TEXT,
+        origem TEXT,
+        cfop TEXT,
+        cst_icms TEXT,
+        cst_pis TEXT,
+        cst_cofins TEXT,
+        ativo INTEGER DEFAULT 1,
+        created_at TEXT DEFAULT (datetime('now')),
+        updated_at TEXT DEFAULT (datetime('now'))
+    );
+    
+    -- Tabela de pedidos de venda
+    CREATE TABLE IF NOT EXISTS pedidos_venda (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        numero TEXT UNIQUE,
+        cliente_id INTEGER,
+        vendedor_id INTEGER,
+        data_pedido TEXT,
+        data_entrega TEXT,
+        status TEXT DEFAULT 'orcamento',
+        subtotal REAL DEFAULT 0,
+        desconto REAL DEFAULT 0,
+        acrescimo REAL DEFAULT 0,
+        total REAL DEFAULT 0,
+        forma_pagamento TEXT,
+        condicao_pagamento TEXT,
+        observacoes TEXT,
+        created_at TEXT DEFAULT (datetime('now')),
+        updated_at TEXT DEFAULT (datetime('now')),
+        FOREIGN KEY (cliente_id) REFERENCES clientes(id),
+        FOREIGN KEY (vendedor_id) REFERENCES usuarios(id)
+    );
+    
+    -- Tabela de itens de pedido
+    CREATE TABLE IF NOT EXISTS pedido_itens (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        pedido_id INTEGER NOT NULL,
+        produto_id INTEGER NOT NULL,
+        quantidade REAL DEFAULT 1,
+        preco_unitario REAL DEFAULT 0,
+        desconto REAL DEFAULT 0,
+        total REAL DEFAULT 0,
+        FOREIGN KEY (pedido_id) REFERENCES pedidos_venda(id) ON DELETE CASCADE,
+        FOREIGN KEY (produto_id) REFERENCES produtos(id)
+    );
+    
+    -- Tabela de contas a pagar
+    CREATE TABLE IF NOT EXISTS contas_pagar (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        descricao TEXT NOT NULL,
+        fornecedor_id INTEGER,
+        valor REAL NOT NULL,
+        data_vencimento TEXT NOT NULL,
+        data_pagamento TEXT,
+        status TEXT DEFAULT 'pendente',
+        categoria TEXT,
+        forma_pagamento TEXT,
+        observacoes TEXT,
+        created_at TEXT DEFAULT (datetime('now')),
+        updated_at TEXT DEFAULT (datetime('now')),
+        FOREIGN KEY (fornecedor_id) REFERENCES fornecedores(id)
+    );
+    
+    -- Tabela de contas a receber
+    CREATE TABLE IF NOT EXISTS contas_receber (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        descricao TEXT NOT NULL,
+        cliente_id INTEGER,
+        pedido_id INTEGER,
+        valor REAL NOT NULL,
+        data_vencimento TEXT NOT NULL,
+        data_recebimento TEXT,
+        status TEXT DEFAULT 'pendente',
+        categoria TEXT,
+        forma_recebimento TEXT,
+        observacoes TEXT,
+        created_at TEXT DEFAULT (datetime('now')),
+        updated_at TEXT DEFAULT (datetime('now')),
+        FOREIGN KEY (cliente_id) REFERENCES clientes(id),
+        FOREIGN KEY (pedido_id) REFERENCES pedidos_venda(id)
+    );
+    
+    -- Tabela de contas bancárias
+    CREATE TABLE IF NOT EXISTS contas_bancarias (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        nome TEXT NOT NULL,
+        banco TEXT,
+        agencia TEXT,
+        conta TEXT,
+        tipo TEXT DEFAULT 'corrente',
+        saldo_inicial REAL DEFAULT 0,
+        saldo_atual REAL DEFAULT 0,
+        ativo INTEGER DEFAULT 1,
+        created_at TEXT DEFAULT (datetime('now')),
+        updated_at TEXT DEFAULT (datetime('now'))
+    );
+    
+    -- Tabela de funcionários
+    CREATE TABLE IF NOT EXISTS funcionarios (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        usuario_id INTEGER,
+        nome TEXT NOT NULL,
+        cpf TEXT UNIQUE,
+        rg TEXT,
+        data_nascimento TEXT,
+        data_admissao TEXT,
+        data_demissao TEXT,
+        cargo TEXT,
+        departamento TEXT,
+        salario REAL DEFAULT 0,
+        email TEXT,
+        telefone TEXT,
+        cep TEXT,
+        endereco TEXT,
+        numero TEXT,
+        bairro TEXT,
+        cidade TEXT,
+        estado TEXT,
+        ativo INTEGER DEFAULT 1,
+        created_at TEXT DEFAULT (datetime('now')),
+        updated_at TEXT DEFAULT (datetime('now')),
+        FOREIGN KEY (usuario_id) REFERENCES usuarios(id)
+    );
+    
+    -- Tabela de controle de ponto
+    CREATE TABLE IF NOT EXISTS controle_ponto (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        funcionario_id INTEGER NOT NULL,
+        data TEXT NOT NULL,
+        entrada TEXT,
+        saida_almoco TEXT,
+        retorno_almoco TEXT,
+        saida TEXT,
+        horas_trabalhadas REAL DEFAULT 0,
+        observacoes TEXT,
+        created_at TEXT DEFAULT (datetime('now')),
+        FOREIGN KEY (funcionario_id) REFERENCES funcionarios(id)
+    );
+    
+    -- Tabela de ordens de produção
+    CREATE TABLE IF NOT EXISTS ordens_producao (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        numero TEXT UNIQUE,
+        produto_id INTEGER,
+        quantidade REAL DEFAULT 1,
+        data_inicio TEXT,
+        data_prevista TEXT,
+        data_conclusao TEXT,
+        status TEXT DEFAULT 'planejada',
+        prioridade TEXT DEFAULT 'normal',
+        responsavel_id INTEGER,
+        observacoes TEXT,
+        created_at TEXT DEFAULT (datetime('now')),
+        updated_at TEXT DEFAULT (datetime('now')),
+        FOREIGN KEY (produto_id) REFERENCES produtos(id),
+        FOREIGN KEY (responsavel_id) REFERENCES funcionarios(id)
+    );
+    
+    -- Tabela de notas fiscais
+    CREATE TABLE IF NOT EXISTS notas_fiscais (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        numero TEXT,
+        serie TEXT,
+        chave TEXT UNIQUE,
+        tipo TEXT DEFAULT 'saida',
+        natureza_operacao TEXT,
+        data_emissao TEXT,
+        data_saida TEXT,
+        cliente_id INTEGER,
+        fornecedor_id INTEGER,
+        pedido_id INTEGER,
+        valor_produtos REAL DEFAULT 0,
+        valor_frete REAL DEFAULT 0,
+        valor_seguro REAL DEFAULT 0,
+        valor_desconto REAL DEFAULT 0,
+        valor_total REAL DEFAULT 0,
+        status TEXT DEFAULT 'pendente',
+        xml TEXT,
+        protocolo TEXT,
+        observacoes TEXT,
+        created_at TEXT DEFAULT (datetime('now')),
+        updated_at TEXT DEFAULT (datetime('now')),
+        FOREIGN KEY (cliente_id) REFERENCES clientes(id),
+        FOREIGN KEY (fornecedor_id) REFERENCES fornecedores(id),
+        FOREIGN KEY (pedido_id) REFERENCES pedidos_venda(id)
+    );
+    
+    -- Tabela de configurações
+    CREATE TABLE IF NOT EXISTS configuracoes (
+        chave TEXT PRIMARY KEY,
+        valor TEXT,
+        tipo TEXT DEFAULT 'string',
+        updated_at TEXT DEFAULT (datetime('now'))
+    );
+    
+    -- Índices para performance
+    CREATE INDEX IF NOT EXISTS idx_clientes_nome ON clientes(nome);
+    CREATE INDEX IF NOT EXISTS idx_clientes_cnpj ON clientes(cnpj);
+    CREATE INDEX IF NOT EXISTS idx_produtos_codigo ON produtos(codigo);
+    CREATE INDEX IF NOT EXISTS idx_produtos_nome ON produtos(nome);
+    CREATE INDEX IF NOT EXISTS idx_pedidos_numero ON pedidos_venda(numero);
+    CREATE INDEX IF NOT EXISTS idx_pedidos_cliente ON pedidos_venda(cliente_id);
+    CREATE INDEX IF NOT EXISTS idx_contas_pagar_vencimento ON contas_pagar(data_vencimento);
+    CREATE INDEX IF NOT EXISTS idx_contas_receber_vencimento ON contas_receber(data_vencimento);
+    CREATE INDEX IF NOT EXISTS idx_funcionarios_cpf ON funcionarios(cpf);
+    CREATE INDEX IF NOT EXISTS idx_nf_chave ON notas_fiscais(chave);
+
+";
+
+// Tabelas que antes eram criadas sob demanda pelos comandos de leitura
+const V002_COMPRAS_ESTOQUE: &str = "
+    -- Requisições de compra
+    CREATE TABLE IF NOT EXISTS requisicoes_compra (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        numero TEXT UNIQUE,
+        solicitante TEXT,
+        departamento TEXT,
+        data_solicitacao TEXT DEFAULT (date('now')),
+        data_necessidade TEXT,
+        status TEXT DEFAULT 'pendente',
+        observacoes TEXT,
+        created_at TEXT DEFAULT (datetime('now'))
+    );
+    
+    -- Cotações de compra
+    CREATE TABLE IF NOT EXISTS cotacoes_compra (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        numero TEXT UNIQUE,
+        requisicao_id INTEGER,
+        fornecedor_id INTEGER,
+        data_cotacao TEXT DEFAULT (date('now')),
+        validade TEXT,
+        valor_total REAL DEFAULT 0,
+        status TEXT DEFAULT 'pendente',
+        created_at TEXT DEFAULT (datetime('now'))
+    );
+    
+    -- Pedidos de compra
+    CREATE TABLE IF NOT EXISTS pedidos_compra (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        numero TEXT UNIQUE,
+        fornecedor_id INTEGER,
+        cotacao_id INTEGER,
+        data_pedido TEXT DEFAULT (date('now')),
+        data_entrega TEXT,
+        valor_total REAL DEFAULT 0,
+        status TEXT DEFAULT 'pendente',
+        observacoes TEXT,
+        created_at TEXT DEFAULT (datetime('now')),
+        FOREIGN KEY (fornecedor_id) REFERENCES fornecedores(id)
+    );
+    
+    -- Saldos de estoque
+    CREATE TABLE IF NOT EXISTS estoque_saldos (
+        codigo TEXT PRIMARY KEY,
+        descricao TEXT,
+        quantidade_fisica REAL DEFAULT 0,
+        quantidade_reservada REAL DEFAULT 0,
+        custo_medio REAL DEFAULT 0,
+        ultima_entrada TEXT,
+        ultima_saida TEXT
+    );
+    
+    CREATE INDEX IF NOT EXISTS idx_cotacoes_requisicao ON cotacoes_compra(requisicao_id);
+    CREATE INDEX IF NOT EXISTS idx_pedidos_compra_fornecedor ON pedidos_compra(fornecedor_id);
+";
+
+// Papéis de acesso; as permissões seguem o formato de `models::Permissoes`
+const V003_PAPEIS: &str = r#"
+    CREATE TABLE papeis (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        nome TEXT UNIQUE NOT NULL,
+        descricao TEXT,
+        permissoes TEXT NOT NULL DEFAULT '{}',
+        created_at TEXT DEFAULT (datetime('now')),
+        updated_at TEXT DEFAULT (datetime('now'))
+    );
+    
+    CREATE TABLE usuario_papeis (
+        usuario_id INTEGER NOT NULL,
+        papel_id INTEGER NOT NULL,
+        created_at TEXT DEFAULT (datetime('now')),
+        PRIMARY KEY (usuario_id, papel_id),
+        FOREIGN KEY (usuario_id) REFERENCES usuarios(id) ON DELETE CASCADE,
+        FOREIGN KEY (papel_id) REFERENCES papeis(id) ON DELETE CASCADE
+    );
+    
+    CREATE INDEX idx_usuario_papeis_papel ON usuario_papeis(papel_id);
+    
+    INSERT INTO papeis (nome, descricao, permissoes) VALUES
+        ('Administrador', 'Acesso total ao sistema', '{"admin": true}'),
+        ('Vendedor', 'Pedidos de venda e cadastro de clientes',
+         '{"modulos": {"vendas": ["ler", "criar", "editar"]}}'),
+        ('Comprador', 'Requisições, cotações e pedidos de compra',
+         '{"modulos": {"compras": ["ler", "criar", "editar"]}}'),
+        ('Financeiro', 'Contas a pagar e a receber',
+         '{"modulos": {"financeiro": ["ler", "criar", "editar", "aprovar"]}}');
+"#;
+
+// Marcado pelo reset de senha do administrador; limpo em `change_password`
+const V004_TROCAR_SENHA: &str = "
+    ALTER TABLE usuarios ADD COLUMN trocar_senha INTEGER NOT NULL DEFAULT 0;
+";
+
+// Registro único (id = 1) gravado ao concluir o assistente de primeira
+// execução; a PRIMARY KEY impede concluir o assistente duas vezes.
+// Bancos já em uso são dados como configurados; a troca de senha do
+// administrador do antigo seed fica em `v005_senha_padrao`.
+const V005_INSTALACAO: &str = "
+    CREATE TABLE instalacao (
+        id INTEGER PRIMARY KEY CHECK (id = 1),
+        empresa_id INTEGER,
+        usuario_id INTEGER,
+        concluida_em TEXT NOT NULL DEFAULT (datetime('now'))
+    );
+    
+    INSERT INTO instalacao (id, empresa_id, usuario_id)
+    SELECT 1, (SELECT MIN(id) FROM empresas), MIN(id) FROM usuarios
+    HAVING COUNT(*) > 0;
+";
+
+// O antigo seed criava o administrador com a senha `admin123`. Se o hash
+// ainda confere com ela, a troca de senha passa a ser obrigatória; datas
+// de alteração não dizem se a senha foi trocada.
+fn v005_senha_padrao(conn: &Connection) -> AppResult<()> {
+    let administradores: Vec<(i64, String)> = {
+        let mut stmt = conn.prepare("SELECT id, senha_hash FROM usuarios WHERE email = 'admin@aluforce.com.br'")?;
+        let linhas = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        linhas.collect::<Result<_, _>>()?
+    };
+    
+    for (id, senha_hash) in administradores {
+        if bcrypt::verify("admin123", &senha_hash).unwrap_or(false) {
+            conn.execute("UPDATE usuarios SET trocar_senha = 1 WHERE id = ?1", [id])?;
+        }
+    }
+    
+    Ok(())
+}
+
+// Falhas de login por e-mail (existente ou não) e histórico de bloqueios
+const V006_TENTATIVAS_LOGIN: &str = "
+    CREATE TABLE tentativas_login (
+        email TEXT PRIMARY KEY,
+        falhas INTEGER NOT NULL DEFAULT 0,
+        ultima_falha TEXT,
+        bloqueado_ate TEXT
+    );
+    
+    CREATE TABLE eventos_bloqueio (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        email TEXT NOT NULL,
+        tipo TEXT NOT NULL CHECK (tipo IN ('bloqueio', 'desbloqueio')),
+        falhas INTEGER NOT NULL DEFAULT 0,
+        bloqueado_ate TEXT,
+        usuario_id INTEGER,
+        created_at TEXT DEFAULT (datetime('now')),
+        FOREIGN KEY (usuario_id) REFERENCES usuarios(id)
+    );
+    
+    CREATE INDEX idx_eventos_bloqueio_email ON eventos_bloqueio(email, created_at);
+";
+
+// Tokens passam a ser guardados como SHA-256; como o hash não pode ser
+// calculado em SQL, as sessões existentes são descartadas (novo login).
+// Datas em RFC 3339 UTC com segundos, comparáveis como texto.
+const V007_SESSOES: &str = "
+    DROP TABLE sessoes;
+    
+    CREATE TABLE sessoes (
+        id TEXT PRIMARY KEY,
+        usuario_id INTEGER NOT NULL,
+        token_hash TEXT NOT NULL UNIQUE,
+        expires_at TEXT NOT NULL,
+        ultimo_uso TEXT NOT NULL,
+        created_at TEXT NOT NULL,
+        FOREIGN KEY (usuario_id) REFERENCES usuarios(id) ON DELETE CASCADE
+    );
+    
+    CREATE INDEX idx_sessoes_usuario ON sessoes(usuario_id);
+    CREATE INDEX idx_sessoes_expires ON sessoes(expires_at);
+";
+
+// Somente inserção: os gatilhos impedem alterar ou apagar registros
+const V008_AUDITORIA: &str = "
+    CREATE TABLE auditoria (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        usuario_id INTEGER,
+        usuario_nome TEXT,
+        modulo TEXT NOT NULL,
+        tabela TEXT NOT NULL,
+        registro_id TEXT NOT NULL,
+        acao TEXT NOT NULL,
+        antes TEXT,
+        depois TEXT,
+        created_at TEXT NOT NULL
+    );
+    
+    CREATE INDEX idx_auditoria_registro ON auditoria(tabela, registro_id);
+    CREATE INDEX idx_auditoria_usuario ON auditoria(usuario_id, created_at);
+    CREATE INDEX idx_auditoria_modulo ON auditoria(modulo, created_at);
+    CREATE INDEX idx_auditoria_data ON auditoria(created_at);
+    
+    CREATE TRIGGER auditoria_sem_update BEFORE UPDATE ON auditoria
+    BEGIN
+        SELECT RAISE(ABORT, 'Registros de auditoria não podem ser alterados');
+    END;
+    
+    CREATE TRIGGER auditoria_sem_delete BEFORE DELETE ON auditoria
+    BEGIN
+        SELECT RAISE(ABORT, 'Registros de auditoria não podem ser excluídos');
+    END;
+";
+
+// Cada sequência continua a partir do maior número já emitido, para que
+// bancos existentes não repitam números
+const V009_SEQUENCIAS: &str = "
+    CREATE TABLE sequencias (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        tipo TEXT NOT NULL,
+        serie TEXT NOT NULL DEFAULT '',
+        prefixo TEXT NOT NULL DEFAULT '',
+        formato TEXT NOT NULL DEFAULT '{prefixo}{numero}',
+        largura INTEGER NOT NULL DEFAULT 6,
+        proximo INTEGER NOT NULL DEFAULT 1 CHECK (proximo >= 1),
+        updated_at TEXT DEFAULT (datetime('now')),
+        UNIQUE (tipo, serie)
+    );
+    
+    INSERT INTO sequencias (tipo, prefixo, proximo)
+    SELECT 'pedido_venda', 'PV', COALESCE(MAX(CAST(substr(numero, 3) AS INTEGER)), 0) + 1
+    FROM pedidos_venda WHERE numero LIKE 'PV%';
+    
+    INSERT INTO sequencias (tipo, prefixo, proximo)
+    SELECT 'requisicao_compra', 'RC', COALESCE(MAX(CAST(substr(numero, 3) AS INTEGER)), 0) + 1
+    FROM requisicoes_compra WHERE numero LIKE 'RC%';
+    
+    INSERT INTO sequencias (tipo, prefixo, proximo)
+    SELECT 'cotacao_compra', 'COT', COALESCE(MAX(CAST(substr(numero, 4) AS INTEGER)), 0) + 1
+    FROM cotacoes_compra WHERE numero LIKE 'COT%';
+    
+    INSERT INTO sequencias (tipo, prefixo, proximo)
+    SELECT 'pedido_compra', 'PC', COALESCE(MAX(CAST(substr(numero, 3) AS INTEGER)), 0) + 1
+    FROM pedidos_compra WHERE numero LIKE 'PC%';
+    
+    INSERT INTO sequencias (tipo, prefixo, proximo)
+    SELECT 'ordem_producao', 'OP', COALESCE(MAX(CAST(substr(numero, 3) AS INTEGER)), 0) + 1
+    FROM ordens_producao WHERE numero LIKE 'OP%';
+    
+    -- NFe: uma sequência por série já usada, mais a série padrão
+    INSERT INTO sequencias (tipo, serie, prefixo, largura, proximo)
+    SELECT 'nfe', serie, '', 0, MAX(CAST(numero AS INTEGER)) + 1
+    FROM notas_fiscais
+    WHERE tipo = 'saida' AND serie IS NOT NULL AND serie <> ''
+    GROUP BY serie;
+    
+    INSERT OR IGNORE INTO sequencias (tipo, serie, prefixo, largura, proximo)
+    VALUES ('nfe', '1', '', 0, 1);
+";
+
+/// Colunas monetárias convertidas de REAL (reais) para INTEGER (centavos),
+/// com a definição da nova coluna
+const COLUNAS_MONETARIAS: &[(&str, &str, &str)] = &[
+    ("clientes", "limite_credito", "INTEGER DEFAULT 0"),
+    ("produtos", "preco_custo", "INTEGER DEFAULT 0"),
+    ("produtos", "preco_venda", "INTEGER DEFAULT 0"),
+    ("pedidos_venda", "subtotal", "INTEGER DEFAULT 0"),
+    ("pedidos_venda", "desconto", "INTEGER DEFAULT 0"),
+    ("pedidos_venda", "acrescimo", "INTEGER DEFAULT 0"),
+    ("pedidos_venda", "total", "INTEGER DEFAULT 0"),
+    ("pedido_itens", "preco_unitario", "INTEGER DEFAULT 0"),
+    ("pedido_itens", "desconto", "INTEGER DEFAULT 0"),
+    ("pedido_itens", "total", "INTEGER DEFAULT 0"),
+    ("contas_pagar", "valor", "INTEGER NOT NULL DEFAULT 0"),
+    ("contas_receber", "valor", "INTEGER NOT NULL DEFAULT 0"),
+    ("contas_bancarias", "saldo_inicial", "INTEGER DEFAULT 0"),
+    ("contas_bancarias", "saldo_atual", "INTEGER DEFAULT 0"),
+    ("funcionarios", "salario", "INTEGER DEFAULT 0"),
+    ("notas_fiscais", "valor_produtos", "INTEGER DEFAULT 0"),
+    ("notas_fiscais", "valor_frete", "INTEGER DEFAULT 0"),
+    ("notas_fiscais", "valor_seguro", "INTEGER DEFAULT 0"),
+    ("notas_fiscais", "valor_desconto", "INTEGER DEFAULT 0"),
+    ("notas_fiscais", "valor_total", "INTEGER DEFAULT 0"),
+    ("cotacoes_compra", "valor_total", "INTEGER DEFAULT 0"),
+    ("pedidos_compra", "valor_total", "INTEGER DEFAULT 0"),
+    ("estoque_saldos", "custo_medio", "INTEGER DEFAULT 0"),
+];
+
+// Cada coluna REAL é recriada como INTEGER e os valores existentes são
+// arredondados ao centavo pela mesma regra de `Dinheiro` (NBR 5891), o
+// que não dá para fazer em SQL. Colunas já em INTEGER são mantidas. Um
+// valor que não é número aborta a migration em vez de virar zero.
+fn v010_dinheiro_em_centavos(conn: &Connection) -> AppResult<()> {
+    for (tabela, coluna, definicao) in COLUNAS_MONETARIAS {
+        let tipo: Option<String> = conn.query_row(
+            "SELECT type FROM pragma_table_info(?1) WHERE name = ?2",
+            [tabela, coluna],
+            |row| row.get(0),
+        ).ok();
+        
+        match tipo.as_deref() {
+            Some(t) if t.eq_ignore_ascii_case("INTEGER") => continue,
+            None => {
+                conn.execute_batch(&format!("ALTER TABLE {} ADD COLUMN {} {}", tabela, coluna, definicao))?;
+                continue;
+            }
+            Some(_) => {}
+        }
+        
+        let nova = format!("{}_centavos", coluna);
+        conn.execute_batch(&format!("ALTER TABLE {} ADD COLUMN {} {}", tabela, nova, definicao))?;
+        
+        let valores: Vec<(i64, Value)> = {
+            let mut stmt = conn.prepare(&format!("SELECT rowid, {} FROM {}", coluna, tabela))?;
+            let linhas = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+            linhas.collect::<Result<_, _>>()?
+        };
+        
+        let mut update = conn.prepare(&format!("UPDATE {} SET {} = ?1 WHERE rowid = ?2", tabela, nova))?;
+        for (rowid, valor) in valores {
+            let invalido = |valor: String| AppError::Validation(format!(
+                "Valor inválido em {}.{} (rowid {}): {}", tabela, coluna, rowid, valor
+            ));
+            let valor = match valor {
+                Value::Null => None,
+                Value::Integer(reais) => Some(Dinheiro::reais(reais as f64).map_err(|_| invalido(reais.to_string()))?),
+                Value::Real(reais) => Some(Dinheiro::reais(reais).map_err(|_| invalido(reais.to_string()))?),
+                Value::Text(texto) => match texto.trim().replace(',', ".").parse::<f64>() {
+                    Ok(reais) if reais.is_finite() => Some(Dinheiro::reais(reais).map_err(|_| invalido(format!("'{}'", texto)))?),
+                    _ => return Err(invalido(format!("'{}'", texto))),
+                },
+                Value::Blob(bytes) => return Err(invalido(format!("BLOB de {} bytes", bytes.len()))),
+            };
+            update.execute(rusqlite::params![valor, rowid])?;
+        }
+        
+        conn.execute_batch(&format!(
+            "ALTER TABLE {tabela} DROP COLUMN {coluna};
+             ALTER TABLE {tabela} RENAME COLUMN {nova} TO {coluna};",
+        ))?;
+    }
+    
+    Ok(())
+}
+
+// Pedidos existentes recebem a situação atual como primeiro registro;
+// situações fora do fluxo (gravadas livremente pelo antigo `update_pedido`)
+// voltam para orçamento.
+const V011_HISTORICO_PEDIDOS: &str = "
+    CREATE TABLE pedido_status_historico (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        pedido_id INTEGER NOT NULL,
+        status_anterior TEXT,
+        status_novo TEXT NOT NULL,
+        usuario_id INTEGER,
+        observacao TEXT,
+        created_at TEXT DEFAULT (datetime('now')),
+        FOREIGN KEY (pedido_id) REFERENCES pedidos_venda(id) ON DELETE CASCADE,
+        FOREIGN KEY (usuario_id) REFERENCES usuarios(id)
+    );
+    
+    CREATE INDEX idx_pedido_status_historico_pedido ON pedido_status_historico(pedido_id, id);
+    
+    UPDATE pedidos_venda SET status = 'orcamento'
+    WHERE status IS NULL
+       OR status NOT IN ('orcamento', 'aprovado', 'confirmado', 'separado', 'faturado', 'entregue', 'cancelado');
+    
+    INSERT INTO pedido_status_historico (pedido_id, status_novo, observacao, created_at)
+    SELECT id, status, 'Situação na implantação do histórico', COALESCE(created_at, datetime('now'))
+    FROM pedidos_venda;
+";
+
+// Com percentual preenchido, o valor em `desconto`/`acrescimo` é recalculado
+// sempre que a base muda; sem ele, o valor é fixo
+const V012_AJUSTES_PEDIDO: &str = "
+    ALTER TABLE pedido_itens ADD COLUMN desconto_percentual REAL;
+    ALTER TABLE pedidos_venda ADD COLUMN desconto_percentual REAL;
+    ALTER TABLE pedidos_venda ADD COLUMN acrescimo_percentual REAL;
+";
+
+// Uma reserva por item de pedido com estoque controlado. `pendente` é a
+// parte em encomenda, ainda sem saldo reservado.
+const V013_RESERVAS_ESTOQUE: &str = "
+    CREATE TABLE reservas_estoque (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        pedido_id INTEGER NOT NULL,
+        item_id INTEGER NOT NULL,
+        codigo TEXT NOT NULL,
+        quantidade REAL NOT NULL DEFAULT 0,
+        pendente REAL NOT NULL DEFAULT 0,
+        status TEXT NOT NULL DEFAULT 'ativa' CHECK (status IN ('ativa', 'liberada', 'baixada')),
+        created_at TEXT DEFAULT (datetime('now')),
+        updated_at TEXT DEFAULT (datetime('now')),
+        FOREIGN KEY (pedido_id) REFERENCES pedidos_venda(id),
+        FOREIGN KEY (codigo) REFERENCES estoque_saldos(codigo)
+    );
+    
+    CREATE INDEX idx_reservas_estoque_pedido ON reservas_estoque(pedido_id, status);
+    CREATE INDEX idx_reservas_estoque_codigo ON reservas_estoque(codigo, status);
+";
+
+// Pedidos que excedem o limite de crédito do cliente. Só uma solicitação
+// pendente por pedido; `valor_pedido` é o valor aprovado.
+const V014_APROVACOES_CREDITO: &str = "
+    CREATE TABLE aprovacoes_credito (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        pedido_id INTEGER NOT NULL,
+        cliente_id INTEGER NOT NULL,
+        valor_pedido INTEGER NOT NULL DEFAULT 0,
+        exposicao INTEGER NOT NULL DEFAULT 0,
+        limite_credito INTEGER NOT NULL DEFAULT 0,
+        status TEXT NOT NULL DEFAULT 'pendente' CHECK (status IN ('pendente', 'aprovada', 'rejeitada')),
+        solicitado_por INTEGER,
+        decidido_por INTEGER,
+        decidido_em TEXT,
+        observacao TEXT,
+        created_at TEXT DEFAULT (datetime('now')),
+        FOREIGN KEY (pedido_id) REFERENCES pedidos_venda(id),
+        FOREIGN KEY (cliente_id) REFERENCES clientes(id),
+        FOREIGN KEY (solicitado_por) REFERENCES usuarios(id),
+        FOREIGN KEY (decidido_por) REFERENCES usuarios(id)
+    );
+    
+    CREATE UNIQUE INDEX idx_aprovacoes_credito_pendente ON aprovacoes_credito(pedido_id) WHERE status = 'pendente';
+    CREATE INDEX idx_aprovacoes_credito_status ON aprovacoes_credito(status, created_at);
+";
+
+// Títulos gerados no faturamento: uma linha por parcela da condição de
+// pagamento, ligada ao pedido e, quando houver, à nota fiscal
+const V015_PARCELAS_RECEBER: &str = "
+    ALTER TABLE contas_receber ADD COLUMN parcela INTEGER;
+    ALTER TABLE contas_receber ADD COLUMN total_parcelas INTEGER;
+    ALTER TABLE contas_receber ADD COLUMN nota_fiscal_id INTEGER REFERENCES notas_fiscais(id);
+    
+    CREATE INDEX idx_contas_receber_pedido ON contas_receber(pedido_id);
+";
+
+// Condições de pagamento usadas por pedidos de venda e de compra e pelos
+// geradores de contas a receber e a pagar. O texto livre de
+// `pedidos_venda.condicao_pagamento` continua valendo quando não há
+// condição cadastrada no pedido.
+const V016_CONDICOES_PAGAMENTO: &str = "
+    CREATE TABLE condicoes_pagamento (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        nome TEXT NOT NULL UNIQUE,
+        parcelas INTEGER NOT NULL DEFAULT 1 CHECK (parcelas >= 1),
+        intervalo_dias INTEGER NOT NULL DEFAULT 30 CHECK (intervalo_dias >= 0),
+        primeiro_vencimento_dias INTEGER NOT NULL DEFAULT 0 CHECK (primeiro_vencimento_dias >= 0),
+        entrada_percentual REAL CHECK (entrada_percentual IS NULL OR (entrada_percentual >= 0 AND entrada_percentual < 100)),
+        ajuste_percentual REAL NOT NULL DEFAULT 0 CHECK (ajuste_percentual > -100),
+        ativo INTEGER DEFAULT 1,
+        created_at TEXT DEFAULT (datetime('now')),
+        updated_at TEXT DEFAULT (datetime('now'))
+    );
+    
+    INSERT INTO condicoes_pagamento (nome, parcelas, intervalo_dias, primeiro_vencimento_dias) VALUES
+        ('À vista', 1, 0, 0),
+        ('30 dias', 1, 0, 30),
+        ('30/60/90', 3, 30, 30);
+    
+    ALTER TABLE pedidos_venda ADD COLUMN condicao_pagamento_id INTEGER REFERENCES condicoes_pagamento(id);
+    ALTER TABLE pedidos_compra ADD COLUMN condicao_pagamento_id INTEGER REFERENCES condicoes_pagamento(id);
+    ALTER TABLE contas_pagar ADD COLUMN parcela INTEGER;
+    ALTER TABLE contas_pagar ADD COLUMN total_parcelas INTEGER;
+";
+
+// Cada item da tabela tem preço fixo ou percentual sobre `preco_venda`; um
+// produto pode ter várias faixas por quantidade mínima. Produto sem item
+// usa o percentual geral da tabela.
+const V017_TABELAS_PRECO: &str = "
+    CREATE TABLE tabelas_preco (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        nome TEXT NOT NULL UNIQUE,
+        descricao TEXT,
+        ajuste_percentual REAL NOT NULL DEFAULT 0 CHECK (ajuste_percentual > -100),
+        validade_inicio TEXT,
+        validade_fim TEXT,
+        ativo INTEGER DEFAULT 1,
+        created_at TEXT DEFAULT (datetime('now')),
+        updated_at TEXT DEFAULT (datetime('now'))
+    );
+    
+    CREATE TABLE tabela_preco_itens (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        tabela_id INTEGER NOT NULL,
+        produto_id INTEGER NOT NULL,
+        quantidade_minima REAL NOT NULL DEFAULT 0,
+        preco INTEGER,
+        ajuste_percentual REAL,
+        created_at TEXT DEFAULT (datetime('now')),
+        updated_at TEXT DEFAULT (datetime('now')),
+        CHECK ((preco IS NULL) <> (ajuste_percentual IS NULL)),
+        UNIQUE (tabela_id, produto_id, quantidade_minima),
+        FOREIGN KEY (tabela_id) REFERENCES tabelas_preco(id) ON DELETE CASCADE,
+        FOREIGN KEY (produto_id) REFERENCES produtos(id)
+    );
+    
+    CREATE INDEX idx_tabela_preco_itens_produto ON tabela_preco_itens(produto_id);
+    
+    ALTER TABLE clientes ADD COLUMN tabela_preco_id INTEGER REFERENCES tabelas_preco(id);
+";
+
+// A regra mais específica vale (vendedor, categoria e tabela de preço, nessa
+// ordem); campo nulo vale para qualquer valor. Estornos são lançamentos
+// negativos, para que comissões já fechadas sejam descontadas no extrato
+// seguinte. `conta_pagar_id` marca os lançamentos já fechados.
+const V018_COMISSOES: &str = "
+    CREATE TABLE regras_comissao (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        vendedor_id INTEGER,
+        categoria TEXT,
+        tabela_preco_id INTEGER,
+        percentual REAL NOT NULL CHECK (percentual >= 0 AND percentual <= 100),
+        ativo INTEGER DEFAULT 1,
+        created_at TEXT DEFAULT (datetime('now')),
+        updated_at TEXT DEFAULT (datetime('now')),
+        FOREIGN KEY (vendedor_id) REFERENCES usuarios(id),
+        FOREIGN KEY (tabela_preco_id) REFERENCES tabelas_preco(id)
+    );
+    
+    CREATE TABLE comissoes (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        vendedor_id INTEGER NOT NULL,
+        pedido_id INTEGER NOT NULL,
+        conta_receber_id INTEGER,
+        tipo TEXT NOT NULL CHECK (tipo IN ('faturamento', 'recebimento', 'estorno')),
+        base INTEGER NOT NULL,
+        valor INTEGER NOT NULL,
+        data_competencia TEXT NOT NULL,
+        conta_pagar_id INTEGER,
+        observacao TEXT,
+        created_at TEXT DEFAULT (datetime('now')),
+        FOREIGN KEY (vendedor_id) REFERENCES usuarios(id),
+        FOREIGN KEY (pedido_id) REFERENCES pedidos_venda(id),
+        FOREIGN KEY (conta_receber_id) REFERENCES contas_receber(id),
+        FOREIGN KEY (conta_pagar_id) REFERENCES contas_pagar(id)
+    );
+    
+    CREATE INDEX idx_comissoes_vendedor ON comissoes(vendedor_id, data_competencia);
+    CREATE INDEX idx_comissoes_pedido ON comissoes(pedido_id);
+";
+
+// Cada revisão guarda as condições comerciais e os itens que o cliente
+// recebeu; revisões não mudam depois de gravadas. Os itens de uma revisão
+// se dividem em alternativas (1, 2, ...) e a conversão em pedido usa uma.
+const V019_ORCAMENTOS: &str = "
+    CREATE TABLE orcamentos (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        numero TEXT NOT NULL UNIQUE,
+        cliente_id INTEGER NOT NULL,
+        vendedor_id INTEGER,
+        data_emissao TEXT NOT NULL,
+        revisao INTEGER NOT NULL DEFAULT 1,
+        status TEXT NOT NULL DEFAULT 'aberto' CHECK (status IN ('aberto', 'ganho', 'perdido')),
+        motivo TEXT,
+        alternativa_aceita INTEGER,
+        pedido_id INTEGER,
+        created_at TEXT DEFAULT (datetime('now')),
+        updated_at TEXT DEFAULT (datetime('now')),
+        FOREIGN KEY (cliente_id) REFERENCES clientes(id),
+        FOREIGN KEY (vendedor_id) REFERENCES usuarios(id),
+        FOREIGN KEY (pedido_id) REFERENCES pedidos_venda(id)
+    );
+    
+    CREATE TABLE orcamento_revisoes (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        orcamento_id INTEGER NOT NULL,
+        revisao INTEGER NOT NULL,
+        validade TEXT NOT NULL,
+        forma_pagamento TEXT,
+        condicao_pagamento TEXT,
+        condicao_pagamento_id INTEGER,
+        observacoes TEXT,
+        motivo TEXT,
+        usuario_id INTEGER,
+        created_at TEXT DEFAULT (datetime('now')),
+        UNIQUE (orcamento_id, revisao),
+        FOREIGN KEY (orcamento_id) REFERENCES orcamentos(id),
+        FOREIGN KEY (condicao_pagamento_id) REFERENCES condicoes_pagamento(id),
+        FOREIGN KEY (usuario_id) REFERENCES usuarios(id)
+    );
+    
+    CREATE TABLE orcamento_itens (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        revisao_id INTEGER NOT NULL,
+        alternativa INTEGER NOT NULL DEFAULT 1 CHECK (alternativa >= 1),
+        produto_id INTEGER NOT NULL,
+        quantidade REAL NOT NULL,
+        preco_unitario INTEGER NOT NULL,
+        desconto INTEGER NOT NULL DEFAULT 0,
+        desconto_percentual REAL,
+        total INTEGER NOT NULL,
+        FOREIGN KEY (revisao_id) REFERENCES orcamento_revisoes(id) ON DELETE CASCADE,
+        FOREIGN KEY (produto_id) REFERENCES produtos(id)
+    );
+    
+    CREATE INDEX idx_orcamentos_cliente ON orcamentos(cliente_id);
+    CREATE INDEX idx_orcamento_itens_revisao ON orcamento_itens(revisao_id);
+";
+
+// `finalidade` segue o finNFe do leiaute (1 = normal, 4 = devolução); a nota
+// de devolução referencia a chave da nota de saída. Cada devolução gera uma
+// conta a receber negativa: crédito do cliente ou reembolso a pagar.
+const V020_DEVOLUCOES: &str = "
+    ALTER TABLE notas_fiscais ADD COLUMN finalidade INTEGER NOT NULL DEFAULT 1;
+    ALTER TABLE notas_fiscais ADD COLUMN chave_referenciada TEXT;
+    
+    CREATE TABLE devolucoes_venda (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        numero TEXT NOT NULL UNIQUE,
+        pedido_id INTEGER NOT NULL,
+        nota_origem_id INTEGER NOT NULL,
+        nota_fiscal_id INTEGER,
+        conta_receber_id INTEGER,
+        data_devolucao TEXT NOT NULL,
+        tratamento TEXT NOT NULL CHECK (tratamento IN ('credito', 'reembolso')),
+        motivo TEXT NOT NULL,
+        valor INTEGER NOT NULL DEFAULT 0,
+        usuario_id INTEGER,
+        created_at TEXT DEFAULT (datetime('now')),
+        FOREIGN KEY (pedido_id) REFERENCES pedidos_venda(id),
+        FOREIGN KEY (nota_origem_id) REFERENCES notas_fiscais(id),
+        FOREIGN KEY (nota_fiscal_id) REFERENCES notas_fiscais(id),
+        FOREIGN KEY (conta_receber_id) REFERENCES contas_receber(id),
+        FOREIGN KEY (usuario_id) REFERENCES usuarios(id)
+    );
+    
+    CREATE TABLE devolucao_itens (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        devolucao_id INTEGER NOT NULL,
+        pedido_item_id INTEGER NOT NULL,
+        produto_id INTEGER NOT NULL,
+        quantidade REAL NOT NULL CHECK (quantidade > 0),
+        valor INTEGER NOT NULL,
+        FOREIGN KEY (devolucao_id) REFERENCES devolucoes_venda(id) ON DELETE CASCADE,
+        FOREIGN KEY (pedido_item_id) REFERENCES pedido_itens(id),
+        FOREIGN KEY (produto_id) REFERENCES produtos(id)
+    );
+    
+    CREATE INDEX idx_devolucoes_venda_pedido ON devolucoes_venda(pedido_id);
+    CREATE INDEX idx_devolucao_itens_item ON devolucao_itens(pedido_item_id);
+";
+
+// Requisição → cotações (uma por fornecedor, com os itens da requisição) →
+// pedido de compra da cotação vencedora → recebimentos. `preco_unitario`
+// nulo na cotação é item que o fornecedor não cotou. Cada recebimento gera
+// as contas a pagar do valor recebido.
+const V021_FLUXO_COMPRAS: &str = "
+    ALTER TABLE requisicoes_compra ADD COLUMN motivo TEXT;
+    ALTER TABLE requisicoes_compra ADD COLUMN aprovado_por INTEGER REFERENCES usuarios(id);
+    ALTER TABLE requisicoes_compra ADD COLUMN aprovado_em TEXT;
+    ALTER TABLE requisicoes_compra ADD COLUMN updated_at TEXT;
+    
+    CREATE TABLE requisicao_itens (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        requisicao_id INTEGER NOT NULL,
+        produto_id INTEGER NOT NULL,
+        quantidade REAL NOT NULL CHECK (quantidade > 0),
+        observacoes TEXT,
+        FOREIGN KEY (requisicao_id) REFERENCES requisicoes_compra(id) ON DELETE CASCADE,
+        FOREIGN KEY (produto_id) REFERENCES produtos(id)
+    );
+    
+    ALTER TABLE cotacoes_compra ADD COLUMN condicao_pagamento_id INTEGER REFERENCES condicoes_pagamento(id);
+    ALTER TABLE cotacoes_compra ADD COLUMN prazo_entrega_dias INTEGER;
+    ALTER TABLE cotacoes_compra ADD COLUMN observacoes TEXT;
+    ALTER TABLE cotacoes_compra ADD COLUMN motivo TEXT;
+    ALTER TABLE cotacoes_compra ADD COLUMN aprovado_por INTEGER REFERENCES usuarios(id);
+    ALTER TABLE cotacoes_compra ADD COLUMN aprovado_em TEXT;
+    ALTER TABLE cotacoes_compra ADD COLUMN updated_at TEXT;
+    
+    CREATE TABLE cotacao_itens (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        cotacao_id INTEGER NOT NULL,
+        requisicao_item_id INTEGER NOT NULL,
+        produto_id INTEGER NOT NULL,
+        quantidade REAL NOT NULL,
+        preco_unitario INTEGER,
+        total INTEGER,
+        UNIQUE (cotacao_id, requisicao_item_id),
+        FOREIGN KEY (cotacao_id) REFERENCES cotacoes_compra(id) ON DELETE CASCADE,
+        FOREIGN KEY (requisicao_item_id) REFERENCES requisicao_itens(id),
+        FOREIGN KEY (produto_id) REFERENCES produtos(id)
+    );
+    
+    ALTER TABLE pedidos_compra ADD COLUMN requisicao_id INTEGER REFERENCES requisicoes_compra(id);
+    ALTER TABLE pedidos_compra ADD COLUMN aprovado_por INTEGER REFERENCES usuarios(id);
+    ALTER TABLE pedidos_compra ADD COLUMN aprovado_em TEXT;
+    ALTER TABLE pedidos_compra ADD COLUMN data_recebimento TEXT;
+    ALTER TABLE pedidos_compra ADD COLUMN motivo TEXT;
+    ALTER TABLE pedidos_compra ADD COLUMN updated_at TEXT;
+    
+    CREATE TABLE pedido_compra_itens (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        pedido_id INTEGER NOT NULL,
+        cotacao_item_id INTEGER,
+        produto_id INTEGER NOT NULL,
+        quantidade REAL NOT NULL CHECK (quantidade > 0),
+        quantidade_recebida REAL NOT NULL DEFAULT 0,
+        preco_unitario INTEGER NOT NULL,
+        total INTEGER NOT NULL,
+        FOREIGN KEY (pedido_id) REFERENCES pedidos_compra(id) ON DELETE CASCADE,
+        FOREIGN KEY (cotacao_item_id) REFERENCES cotacao_itens(id),
+        FOREIGN KEY (produto_id) REFERENCES produtos(id)
+    );
+    
+    ALTER TABLE contas_pagar ADD COLUMN pedido_compra_id INTEGER REFERENCES pedidos_compra(id);
+    
+    CREATE INDEX idx_requisicao_itens_requisicao ON requisicao_itens(requisicao_id);
+    CREATE INDEX idx_cotacao_itens_cotacao ON cotacao_itens(cotacao_id);
+    CREATE INDEX idx_pedido_compra_itens_pedido ON pedido_compra_itens(pedido_id);
+";
+
+// Frete cotado pelo fornecedor, em centavos. No pedido, entra nas contas a
+// pagar do primeiro recebimento.
+const V022_FRETE_COMPRAS: &str = "
+    ALTER TABLE cotacoes_compra ADD COLUMN frete INTEGER NOT NULL DEFAULT 0;
+    ALTER TABLE pedidos_compra ADD COLUMN frete INTEGER NOT NULL DEFAULT 0;
+";
+
+// O reembolso de uma devolução é dinheiro a devolver ao cliente: vai para
+// contas a pagar. O crédito continua como conta a receber negativa.
+const V023_REEMBOLSO_DEVOLUCAO: &str = "
+    ALTER TABLE contas_pagar ADD COLUMN cliente_id INTEGER REFERENCES clientes(id);
+    ALTER TABLE devolucoes_venda ADD COLUMN conta_pagar_id INTEGER REFERENCES contas_pagar(id);
+";
+
+#[cfg(test)]
+mod tests {
+    use rusqlite::params;
+    
+    use super::*;
+    use crate::database::run_migrations;
+    
+    /// Banco em memória com as migrations até `versao`
+    fn banco_na_versao(versao: i64) -> Connection {
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch("CREATE TABLE schema_migrations (version INTEGER PRIMARY KEY, descricao TEXT NOT NULL)").unwrap();
+        let tx = conn.transaction().unwrap();
+        for migration in MIGRATIONS.iter().filter(|m| m.version <= versao) {
+            tx.execute_batch(migration.sql).unwrap();
+            if let Some(rotina) = migration.rotina {
+                rotina(&tx).unwrap();
+            }
+            tx.execute(
+                "INSERT INTO schema_migrations (version, descricao) VALUES (?1, ?2)",
+                params![migration.version, migration.descricao],
+            ).unwrap();
+        }
+        tx.commit().unwrap();
+        conn
+    }
+    
+    fn versao(conn: &Connection) -> i64 {
+        conn.query_row("SELECT MAX(version) FROM schema_migrations", [], |row| row.get(0)).unwrap()
+    }
+    
+    #[test]
+    fn versoes_sao_sequenciais_a_partir_de_um() {
+        for (posicao, migration) in MIGRATIONS.iter().enumerate() {
+            assert_eq!(migration.version, posicao as i64 + 1, "{}", migration.descricao);
+        }
+        assert_eq!(latest_version(), MIGRATIONS.len() as i64);
+    }
+    
+    #[test]
+    fn reaplicar_nao_altera_o_banco() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        run_migrations(&mut conn).unwrap();
+        assert_eq!(versao(&conn), latest_version());
+        
+        conn.execute("INSERT INTO clientes (nome) VALUES ('A')", []).unwrap();
+        run_migrations(&mut conn).unwrap();
+        
+        let registros: i64 = conn.query_row("SELECT COUNT(*) FROM schema_migrations", [], |row| row.get(0)).unwrap();
+        assert_eq!(registros, latest_version());
+        let clientes: i64 = conn.query_row("SELECT COUNT(*) FROM clientes", [], |row| row.get(0)).unwrap();
+        assert_eq!(clientes, 1);
+    }
+    
+    #[test]
+    fn aplica_so_as_pendentes_em_ordem() {
+        let mut conn = banco_na_versao(3);
+        run_migrations(&mut conn).unwrap();
+        
+        let versoes: Vec<i64> = {
+            let mut stmt = conn.prepare("SELECT version FROM schema_migrations ORDER BY rowid").unwrap();
+            stmt.query_map([], |row| row.get(0)).unwrap().collect::<Result<_, _>>().unwrap()
+        };
+        assert_eq!(versoes, (1..=latest_version()).collect::<Vec<_>>());
+    }
+    
+    #[test]
+    fn recusa_banco_mais_novo_que_o_aplicativo() {
+        let mut conn = banco_na_versao(latest_version());
+        conn.execute(
+            "INSERT INTO schema_migrations (version, descricao) VALUES (?1, 'Futura')",
+            params![latest_version() + 1],
+        ).unwrap();
+        
+        assert!(matches!(run_migrations(&mut conn), Err(AppError::Database(_))));
+        assert_eq!(versao(&conn), latest_version() + 1);
+    }
+    
+    #[test]
+    fn v005_so_obriga_a_troca_da_senha_do_seed() {
+        for (senha, trocar) in [("admin123", true), ("trocada", false)] {
+            let mut conn = banco_na_versao(4);
+            conn.execute(
+                "INSERT INTO usuarios (nome, email, senha_hash) VALUES ('Administrador', 'admin@aluforce.com.br', ?1)",
+                params![bcrypt::hash(senha, 4).unwrap()],
+            ).unwrap();
+            
+            run_migrations(&mut conn).unwrap();
+            
+            let trocar_senha: bool = conn.query_row("SELECT trocar_senha FROM usuarios", [], |row| row.get(0)).unwrap();
+            assert_eq!(trocar_senha, trocar, "{}", senha);
+        }
+    }
+    
+    #[test]
+    fn v010_converte_reais_em_centavos() {
+        let mut conn = banco_na_versao(9);
+        conn.execute("INSERT INTO clientes (nome, limite_credito) VALUES ('A', 1234.5)", []).unwrap();
+        conn.execute("INSERT INTO clientes (nome, limite_credito) VALUES ('B', '10,25')", []).unwrap();
+        conn.execute("INSERT INTO clientes (nome, limite_credito) VALUES ('C', NULL)", []).unwrap();
+        
+        run_migrations(&mut conn).unwrap();
+        
+        let limites: Vec<Option<i64>> = {
+            let mut stmt = conn.prepare("SELECT limite_credito FROM clientes ORDER BY nome").unwrap();
+            stmt.query_map([], |row| row.get(0)).unwrap().collect::<Result<_, _>>().unwrap()
+        };
+        assert_eq!(limites, vec![Some(123450), Some(1025), None]);
+    }
+    
+    #[test]
+    fn v010_aborta_com_valor_que_nao_e_numero() {
+        for valor in [Value::Text("abc".into()), Value::Blob(vec![1, 2])] {
+            let mut conn = banco_na_versao(9);
+            conn.execute("INSERT INTO clientes (nome) VALUES ('A')", []).unwrap();
+            conn.execute("INSERT INTO clientes (nome, limite_credito) VALUES ('B', ?1)", params![valor]).unwrap();
+            
+            let erro = run_migrations(&mut conn).unwrap_err().to_string();
+            assert!(erro.contains("clientes.limite_credito (rowid 2)"), "{}", erro);
+            
+            // Nada da V010 fica gravado
+            assert_eq!(versao(&conn), 9);
+            let tipo: String = conn.query_row(
+                "SELECT type FROM pragma_table_info('clientes') WHERE name = 'limite_credito'", [], |row| row.get(0),
+            ).unwrap();
+            assert_eq!(tipo, "REAL");
+        }
+    }
+}
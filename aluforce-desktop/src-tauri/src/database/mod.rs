@@ -2,26 +2,126 @@
 //! 
 //! Gerencia conexões, migrations e operações com o banco local.
 
-use rusqlite::{Connection, params};
-use std::path::PathBuf;
-use std::sync::Mutex;
+use rusqlite::{Connection, OpenFlags, Transaction, params};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use tauri::AppHandle;
 use tauri::Manager;
 use log::info;
 
 use crate::error::{AppError, AppResult};
 
+mod migrations;
+
+use migrations::MIGRATIONS;
+
+/// Quantidade de conexões somente leitura mantidas no pool
+const POOL_LEITORES: usize = 4;
+
 /// Estado global do banco de dados
+/// 
+/// Mantém um pool fixo de conexões de leitura e uma única conexão de
+/// escrita. O SQLite só permite um escritor por vez, então serializar a
+/// escrita aqui evita erros de `SQLITE_BUSY` entre comandos concorrentes.
 pub struct DatabaseState {
-    pub connection: Mutex<Option<Connection>>,
+    path: PathBuf,
+    writer: Mutex<Connection>,
+    readers: Vec<Mutex<Connection>>,
+    proximo_leitor: AtomicUsize,
 }
 
-impl Default for DatabaseState {
-    fn default() -> Self {
-        Self {
-            connection: Mutex::new(None),
+impl DatabaseState {
+    /// Abre o banco, aplica as migrations e cria o pool de conexões
+    pub fn open(path: &Path) -> AppResult<Self> {
+        let mut writer = open_writer(path)?;
+        
+        // Aplicar migrations pendentes
+        run_migrations(&mut writer)?;
+        
+        let readers = (0..POOL_LEITORES)
+            .map(|_| open_reader(path).map(Mutex::new))
+            .collect::<AppResult<Vec<_>>>()?;
+        
+        Ok(Self {
+            path: path.to_path_buf(),
+            writer: Mutex::new(writer),
+            readers,
+            proximo_leitor: AtomicUsize::new(0),
+        })
+    }
+    
+    /// Obtém uma conexão somente leitura do pool
+    pub fn reader(&self) -> AppResult<MutexGuard<'_, Connection>> {
+        let inicio = self.proximo_leitor.fetch_add(1, Ordering::Relaxed) % self.readers.len();
+        
+        // Primeiro tenta uma conexão livre, depois aguarda a da vez
+        for i in 0..self.readers.len() {
+            if let Ok(guard) = self.readers[(inicio + i) % self.readers.len()].try_lock() {
+                return Ok(guard);
+            }
         }
+        
+        self.readers[inicio].lock().map_err(|_| pool_error())
+    }
+    
+    /// Obtém a conexão de escrita (serializada)
+    pub fn writer(&self) -> AppResult<MutexGuard<'_, Connection>> {
+        self.writer.lock().map_err(|_| pool_error())
     }
+    
+    /// Executa `f` dentro de uma transação na conexão de escrita
+    /// 
+    /// Confirma apenas se `f` retornar `Ok`. Qualquer erro (inclusive `?` no
+    /// meio do fechamento) descarta a transação e desfaz todas as escritas.
+    pub fn with_transaction<T, F>(&self, f: F) -> AppResult<T>
+    where
+        F: FnOnce(&Transaction<'_>) -> AppResult<T>,
+    {
+        let mut conn = self.writer()?;
+        let tx = conn.transaction()?;
+        let resultado = f(&tx)?;
+        tx.commit()?;
+        Ok(resultado)
+    }
+    
+    /// Reaplica migrations pendentes na conexão de escrita
+    pub fn migrate(&self) -> AppResult<()> {
+        let mut conn = self.writer()?;
+        run_migrations(&mut conn)
+    }
+}
+
+fn pool_error() -> AppError {
+    AppError::Internal("Pool de conexões do banco de dados indisponível".into())
+}
+
+/// Aplica as PRAGMAs comuns a todas as conexões
+fn apply_pragmas(conn: &Connection) -> AppResult<()> {
+    conn.execute_batch(
+        "PRAGMA busy_timeout = 5000;
+         PRAGMA synchronous = NORMAL;
+         PRAGMA cache_size = 10000;
+         PRAGMA temp_store = MEMORY;
+         PRAGMA foreign_keys = ON;"
+    )?;
+    Ok(())
+}
+
+fn open_writer(path: &Path) -> AppResult<Connection> {
+    let conn = Connection::open(path)?;
+    conn.execute_batch("PRAGMA journal_mode = WAL;")?;
+    apply_pragmas(&conn)?;
+    Ok(conn)
+}
+
+fn open_reader(path: &Path) -> AppResult<Connection> {
+    let conn = Connection::open_with_flags(
+        path,
+        OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX | OpenFlags::SQLITE_OPEN_URI,
+    )?;
+    apply_pragmas(&conn)?;
+    Ok(conn)
 }
 
 /// Obtém o caminho do banco de dados
@@ -35,481 +135,296 @@ pub fn get_database_path(app: &AppHandle) -> AppResult<PathBuf> {
 }
 
 /// Inicializa o banco de dados
-pub async fn init(app: &AppHandle) -> AppResult<()> {
+pub async fn init(app: &AppHandle) -> AppResult<DatabaseState> {
     let db_path = get_database_path(app)?;
     
     info!("📂 Inicializando banco de dados em: {:?}", db_path);
     
-    let conn = Connection::open(&db_path)?;
-    
-    // Configurações de performance
-    conn.execute_batch(
-        "PRAGMA journal_mode = WAL;
-         PRAGMA synchronous = NORMAL;
-         PRAGMA cache_size = 10000;
-         PRAGMA temp_store = MEMORY;
-         PRAGMA foreign_keys = ON;"
-    )?;
-    
-    // Criar tabelas
-    create_tables(&conn)?;
-    
-    // Inserir dados iniciais
-    seed_initial_data(&conn)?;
+    let state = DatabaseState::open(&db_path)?;
     
     info!("✅ Banco de dados inicializado com sucesso!");
     
-    Ok(())
+    Ok(state)
 }
 
-/// Cria todas as tabelas necessárias
-fn create_tables(conn: &Connection) -> AppResult<()> {
-    info!("📋 Criando tabelas...");
-    
-    // Tabela de usuários
+/// Aplica as migrations pendentes, cada uma em sua própria transação
+pub fn run_migrations(conn: &mut Connection) -> AppResult<()> {
     conn.execute(
-        "CREATE TABLE IF NOT EXISTS usuarios (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            nome TEXT NOT NULL,
-            email TEXT UNIQUE NOT NULL,
-            senha_hash TEXT NOT NULL,
-            cargo TEXT,
-            departamento TEXT,
-            avatar TEXT,
-            ativo INTEGER DEFAULT 1,
-            permissoes TEXT DEFAULT '{}',
-            ultimo_acesso TEXT,
-            created_at TEXT DEFAULT (datetime('now')),
-            updated_at TEXT DEFAULT (datetime('now'))
+        "CREATE TABLE IF NOT EXISTS schema_migrations (
+            version INTEGER PRIMARY KEY,
+            descricao TEXT NOT NULL,
+            applied_at TEXT DEFAULT (datetime('now'))
         )",
         [],
     )?;
     
-    // Tabela de sessões
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS sessoes (
-            id TEXT PRIMARY KEY,
-            usuario_id INTEGER NOT NULL,
-            token TEXT NOT NULL,
-            expires_at TEXT NOT NULL,
-            created_at TEXT DEFAULT (datetime('now')),
-            FOREIGN KEY (usuario_id) REFERENCES usuarios(id)
-        )",
+    let atual: i64 = conn.query_row(
+        "SELECT COALESCE(MAX(version), 0) FROM schema_migrations",
         [],
+        |row| row.get(0),
     )?;
     
-    // Tabela de empresas
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS empresas (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            razao_social TEXT NOT NULL,
-            nome_fantasia TEXT,
-            cnpj TEXT UNIQUE,
-            inscricao_estadual TEXT,
-            inscricao_municipal TEXT,
-            telefone TEXT,
-            email TEXT,
-            cep TEXT,
-            endereco TEXT,
-            numero TEXT,
-            complemento TEXT,
-            bairro TEXT,
-            cidade TEXT,
-            estado TEXT,
-            logo_path TEXT,
-            created_at TEXT DEFAULT (datetime('now')),
-            updated_at TEXT DEFAULT (datetime('now'))
-        )",
-        [],
-    )?;
+    let suportada = migrations::latest_version();
     
-    // Tabela de clientes
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS clientes (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            nome TEXT NOT NULL,
-            razao_social TEXT,
-            nome_fantasia TEXT,
-            cnpj TEXT,
-            cpf TEXT,
-            inscricao_estadual TEXT,
-            inscricao_municipal TEXT,
-            email TEXT,
-            telefone TEXT,
-            celular TEXT,
-            cep TEXT,
-            endereco TEXT,
-            numero TEXT,
-            complemento TEXT,
-            bairro TEXT,
-            cidade TEXT,
-            estado TEXT,
-            observacoes TEXT,
-            limite_credito REAL DEFAULT 0,
-            vendedor_id INTEGER,
-            ativo INTEGER DEFAULT 1,
-            created_at TEXT DEFAULT (datetime('now')),
-            updated_at TEXT DEFAULT (datetime('now'))
-        )",
-        [],
-    )?;
+    // Banco criado por uma versão mais nova do aplicativo
+    if atual > suportada {
+        return Err(AppError::Database(format!(
+            "Banco de dados na versão {} é mais recente que a suportada por este aplicativo ({}). Atualize o ALUFORCE.",
+            atual, suportada
+        )));
+    }
     
-    // Tabela de fornecedores
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS fornecedores (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            nome TEXT NOT NULL,
-            razao_social TEXT,
-            nome_fantasia TEXT,
-            cnpj TEXT,
-            cpf TEXT,
-            inscricao_estadual TEXT,
-            email TEXT,
-            telefone TEXT,
-            cep TEXT,
-            endereco TEXT,
-            numero TEXT,
-            bairro TEXT,
-            cidade TEXT,
-            estado TEXT,
-            observacoes TEXT,
-            ativo INTEGER DEFAULT 1,
-            created_at TEXT DEFAULT (datetime('now')),
-            updated_at TEXT DEFAULT (datetime('now'))
-        )",
-        [],
-    )?;
+    for migration in MIGRATIONS.iter().filter(|m| m.version > atual) {
+        info!("📋 Aplicando migration {:03}: {}", migration.version, migration.descricao);
+        
+        let tx = conn.transaction()?;
+        tx.execute_batch(migration.sql).map_err(|e| AppError::Database(format!(
+            "Falha na migration {:03} ({}): {}", migration.version, migration.descricao, e
+        )))?;
+        if let Some(rotina) = migration.rotina {
+            rotina(&tx).map_err(|e| AppError::Database(format!(
+                "Falha na migration {:03} ({}): {}", migration.version, migration.descricao, e
+            )))?;
+        }
+        tx.execute(
+            "INSERT INTO schema_migrations (version, descricao) VALUES (?1, ?2)",
+            params![migration.version, migration.descricao],
+        )?;
+        tx.commit()?;
+    }
     
-    // Tabela de produtos
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS produtos (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            codigo TEXT UNIQUE,
-            nome TEXT NOT NULL,
-            descricao TEXT,
-            categoria TEXT,
-            unidade TEXT DEFAULT 'UN',
-            preco_custo REAL DEFAULT 0,
-            preco_venda REAL DEFAULT 0,
-            margem REAL DEFAULT 0,
-            estoque_minimo REAL DEFAULT 0,
-            estoque_atual REAL DEFAULT 0,
-            ncm TEXT,
-            cest TEXT,
-            origem TEXT,
-            cfop TEXT,
-            cst_icms TEXT,
-            cst_pis TEXT,
-            cst_cofins TEXT,
-            ativo INTEGER DEFAULT 1,
-            created_at TEXT DEFAULT (datetime('now')),
-            updated_at TEXT DEFAULT (datetime('now'))
-        )",
-        [],
-    )?;
+    info!("✅ Esquema do banco na versão {}", suportada);
     
-    // Tabela de pedidos de venda
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS pedidos_venda (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            numero TEXT UNIQUE,
-            cliente_id INTEGER,
-            vendedor_id INTEGER,
-            data_pedido TEXT,
-            data_entrega TEXT,
-            status TEXT DEFAULT 'orcamento',
-            subtotal REAL DEFAULT 0,
-            desconto REAL DEFAULT 0,
-            acrescimo REAL DEFAULT 0,
-            total REAL DEFAULT 0,
-            forma_pagamento TEXT,
-            condicao_pagamento TEXT,
-            observacoes TEXT,
-            created_at TEXT DEFAULT (datetime('now')),
-            updated_at TEXT DEFAULT (datetime('now')),
-            FOREIGN KEY (cliente_id) REFERENCES clientes(id),
-            FOREIGN KEY (vendedor_id) REFERENCES usuarios(id)
-        )",
-        [],
-    )?;
+    Ok(())
+}
+
+/// Remove sessões expiradas; executada periodicamente pelo setup
+pub fn purge_expired_sessions(db: &DatabaseState) -> AppResult<usize> {
+    let agora = crate::context::carimbo(chrono::Utc::now());
+    let removidas = db.writer()?.execute("DELETE FROM sessoes WHERE expires_at <= ?1", params![agora])?;
     
-    // Tabela de itens de pedido
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS pedido_itens (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            pedido_id INTEGER NOT NULL,
-            produto_id INTEGER NOT NULL,
-            quantidade REAL DEFAULT 1,
-            preco_unitario REAL DEFAULT 0,
-            desconto REAL DEFAULT 0,
-            total REAL DEFAULT 0,
-            FOREIGN KEY (pedido_id) REFERENCES pedidos_venda(id) ON DELETE CASCADE,
-            FOREIGN KEY (produto_id) REFERENCES produtos(id)
-        )",
-        [],
-    )?;
+    if removidas > 0 {
+        info!("🧹 {} sessões expiradas removidas", removidas);
+    }
     
-    // Tabela de contas a pagar
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS contas_pagar (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            descricao TEXT NOT NULL,
-            fornecedor_id INTEGER,
-            valor REAL NOT NULL,
-            data_vencimento TEXT NOT NULL,
-            data_pagamento TEXT,
-            status TEXT DEFAULT 'pendente',
-            categoria TEXT,
-            forma_pagamento TEXT,
-            observacoes TEXT,
-            created_at TEXT DEFAULT (datetime('now')),
-            updated_at TEXT DEFAULT (datetime('now')),
-            FOREIGN KEY (fornecedor_id) REFERENCES fornecedores(id)
-        )",
-        [],
-    )?;
+    Ok(removidas)
+}
+
+/// Executa backup do banco de dados
+pub async fn backup(db: &DatabaseState, backup_path: &str) -> AppResult<()> {
+    // VACUUM INTO gera uma cópia consistente mesmo com o WAL ativo
+    if Path::new(backup_path).exists() {
+        std::fs::remove_file(backup_path)?;
+    }
     
-    // Tabela de contas a receber
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS contas_receber (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            descricao TEXT NOT NULL,
-            cliente_id INTEGER,
-            pedido_id INTEGER,
-            valor REAL NOT NULL,
-            data_vencimento TEXT NOT NULL,
-            data_recebimento TEXT,
-            status TEXT DEFAULT 'pendente',
-            categoria TEXT,
-            forma_recebimento TEXT,
-            observacoes TEXT,
-            created_at TEXT DEFAULT (datetime('now')),
-            updated_at TEXT DEFAULT (datetime('now')),
-            FOREIGN KEY (cliente_id) REFERENCES clientes(id),
-            FOREIGN KEY (pedido_id) REFERENCES pedidos_venda(id)
-        )",
-        [],
-    )?;
+    let conn = db.reader()?;
+    conn.execute("VACUUM INTO ?1", params![backup_path])?;
     
-    // Tabela de contas bancárias
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS contas_bancarias (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            nome TEXT NOT NULL,
-            banco TEXT,
-            agencia TEXT,
-            conta TEXT,
-            tipo TEXT DEFAULT 'corrente',
-            saldo_inicial REAL DEFAULT 0,
-            saldo_atual REAL DEFAULT 0,
-            ativo INTEGER DEFAULT 1,
-            created_at TEXT DEFAULT (datetime('now')),
-            updated_at TEXT DEFAULT (datetime('now'))
-        )",
-        [],
-    )?;
+    info!("💾 Backup realizado em: {}", backup_path);
     
-    // Tabela de funcionários
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS funcionarios (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            usuario_id INTEGER,
-            nome TEXT NOT NULL,
-            cpf TEXT UNIQUE,
-            rg TEXT,
-            data_nascimento TEXT,
-            data_admissao TEXT,
-            data_demissao TEXT,
-            cargo TEXT,
-            departamento TEXT,
-            salario REAL DEFAULT 0,
-            email TEXT,
-            telefone TEXT,
-            cep TEXT,
-            endereco TEXT,
-            numero TEXT,
-            bairro TEXT,
-            cidade TEXT,
-            estado TEXT,
-            ativo INTEGER DEFAULT 1,
-            created_at TEXT DEFAULT (datetime('now')),
-            updated_at TEXT DEFAULT (datetime('now')),
-            FOREIGN KEY (usuario_id) REFERENCES usuarios(id)
-        )",
-        [],
-    )?;
+    Ok(())
+}
+
+/// Restaura banco de dados de backup
+pub async fn restore(db: &DatabaseState, backup_path: &str) -> AppResult<()> {
+    // Verificar se backup existe
+    if !Path::new(backup_path).exists() {
+        return Err(AppError::NotFound("Arquivo de backup não encontrado".into()));
+    }
     
-    // Tabela de controle de ponto
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS controle_ponto (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            funcionario_id INTEGER NOT NULL,
-            data TEXT NOT NULL,
-            entrada TEXT,
-            saida_almoco TEXT,
-            retorno_almoco TEXT,
-            saida TEXT,
-            horas_trabalhadas REAL DEFAULT 0,
-            observacoes TEXT,
-            created_at TEXT DEFAULT (datetime('now')),
-            FOREIGN KEY (funcionario_id) REFERENCES funcionarios(id)
-        )",
-        [],
-    )?;
+    // Recusar backups gerados por uma versão mais nova do aplicativo
+    {
+        let origem = Connection::open_with_flags(backup_path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+        let versao: i64 = origem.query_row(
+            "SELECT COALESCE(MAX(version), 0) FROM schema_migrations",
+            [],
+            |row| row.get(0),
+        ).unwrap_or(0);
+        
+        if versao > migrations::latest_version() {
+            return Err(AppError::Validation(format!(
+                "Backup na versão {} é mais recente que a suportada por este aplicativo", versao
+            )));
+        }
+    }
     
-    // Tabela de ordens de produção
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS ordens_producao (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            numero TEXT UNIQUE,
-            produto_id INTEGER,
-            quantidade REAL DEFAULT 1,
-            data_inicio TEXT,
-            data_prevista TEXT,
-            data_conclusao TEXT,
-            status TEXT DEFAULT 'planejada',
-            prioridade TEXT DEFAULT 'normal',
-            responsavel_id INTEGER,
-            observacoes TEXT,
-            created_at TEXT DEFAULT (datetime('now')),
-            updated_at TEXT DEFAULT (datetime('now')),
-            FOREIGN KEY (produto_id) REFERENCES produtos(id),
-            FOREIGN KEY (responsavel_id) REFERENCES funcionarios(id)
-        )",
-        [],
-    )?;
+    // Bloquear todo o pool e fechar as conexões antes de substituir o arquivo
+    let mut writer = db.writer()?;
+    let mut readers = db.readers.iter()
+        .map(|r| r.lock().map_err(|_| pool_error()))
+        .collect::<AppResult<Vec<_>>>()?;
     
-    // Tabela de notas fiscais
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS notas_fiscais (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            numero TEXT,
-            serie TEXT,
-            chave TEXT UNIQUE,
-            tipo TEXT DEFAULT 'saida',
-            natureza_operacao TEXT,
-            data_emissao TEXT,
-            data_saida TEXT,
-            cliente_id INTEGER,
-            fornecedor_id INTEGER,
-            pedido_id INTEGER,
-            valor_produtos REAL DEFAULT 0,
-            valor_frete REAL DEFAULT 0,
-            valor_seguro REAL DEFAULT 0,
-            valor_desconto REAL DEFAULT 0,
-            valor_total REAL DEFAULT 0,
-            status TEXT DEFAULT 'pendente',
-            xml TEXT,
-            protocolo TEXT,
-            observacoes TEXT,
-            created_at TEXT DEFAULT (datetime('now')),
-            updated_at TEXT DEFAULT (datetime('now')),
-            FOREIGN KEY (cliente_id) REFERENCES clientes(id),
-            FOREIGN KEY (fornecedor_id) REFERENCES fornecedores(id),
-            FOREIGN KEY (pedido_id) REFERENCES pedidos_venda(id)
-        )",
-        [],
-    )?;
+    writer.execute_batch("PRAGMA wal_checkpoint(TRUNCATE);")?;
     
-    // Tabela de configurações
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS configuracoes (
-            chave TEXT PRIMARY KEY,
-            valor TEXT,
-            tipo TEXT DEFAULT 'string',
-            updated_at TEXT DEFAULT (datetime('now'))
-        )",
-        [],
-    )?;
+    *writer = Connection::open_in_memory()?;
+    for reader in readers.iter_mut() {
+        **reader = Connection::open_in_memory()?;
+    }
     
-    // Índices para performance
-    conn.execute_batch(
-        "CREATE INDEX IF NOT EXISTS idx_clientes_nome ON clientes(nome);
-         CREATE INDEX IF NOT EXISTS idx_clientes_cnpj ON clientes(cnpj);
-         CREATE INDEX IF NOT EXISTS idx_produtos_codigo ON produtos(codigo);
-         CREATE INDEX IF NOT EXISTS idx_produtos_nome ON produtos(nome);
-         CREATE INDEX IF NOT EXISTS idx_pedidos_numero ON pedidos_venda(numero);
-         CREATE INDEX IF NOT EXISTS idx_pedidos_cliente ON pedidos_venda(cliente_id);
-         CREATE INDEX IF NOT EXISTS idx_contas_pagar_vencimento ON contas_pagar(data_vencimento);
-         CREATE INDEX IF NOT EXISTS idx_contas_receber_vencimento ON contas_receber(data_vencimento);
-         CREATE INDEX IF NOT EXISTS idx_funcionarios_cpf ON funcionarios(cpf);
-         CREATE INDEX IF NOT EXISTS idx_nf_chave ON notas_fiscais(chave);"
-    )?;
+    let copia = std::fs::copy(backup_path, &db.path);
+    
+    // Reabrir o pool mesmo se a cópia falhar, para não deixar o app sem banco
+    *writer = open_writer(&db.path)?;
+    for reader in readers.iter_mut() {
+        **reader = open_reader(&db.path)?;
+    }
+    copia?;
     
-    info!("✅ Tabelas criadas com sucesso!");
+    // Backups antigos sobem para o esquema atual
+    run_migrations(&mut writer)?;
+    
+    info!("🔄 Banco restaurado de: {}", backup_path);
     
     Ok(())
 }
 
-/// Insere dados iniciais no banco
-fn seed_initial_data(conn: &Connection) -> AppResult<()> {
-    // Verificar se já existe usuário admin
-    let count: i32 = conn.query_row(
-        "SELECT COUNT(*) FROM usuarios WHERE email = 'admin@aluforce.com.br'",
+/// Banco temporário com um administrador e sessão `token`, para testes
+#[cfg(test)]
+pub(crate) fn banco_teste(token: &str) -> DatabaseState {
+    use crate::context::{carimbo, hash_token};
+    
+    let dir = std::env::temp_dir().join(format!("aluforce-teste-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let db = DatabaseState::open(&dir.join("aluforce.db")).unwrap();
+    
+    let agora = chrono::Utc::now();
+    let conn = db.writer().unwrap();
+    conn.execute(
+        "INSERT INTO usuarios (nome, email, senha_hash, permissoes) VALUES ('Admin', 'admin@teste', '', '{\"admin\": true}')",
         [],
-        |row| row.get(0),
-    ).unwrap_or(0);
+    ).unwrap();
+    conn.execute(
+        "INSERT INTO sessoes (id, usuario_id, token_hash, expires_at, ultimo_uso, created_at)
+         VALUES ('sessao-teste', ?1, ?2, ?3, ?4, ?4)",
+        params![
+            conn.last_insert_rowid(),
+            hash_token(token),
+            carimbo(agora + chrono::Duration::hours(1)),
+            carimbo(agora)
+        ],
+    ).unwrap();
+    drop(conn);
+    
+    db
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn contar(db: &DatabaseState, tabela: &str) -> i64 {
+        db.reader().unwrap()
+            .query_row(&format!("SELECT COUNT(*) FROM {}", tabela), [], |row| row.get(0))
+            .unwrap()
+    }
     
-    if count == 0 {
-        info!("🌱 Inserindo dados iniciais...");
+    #[test]
+    fn with_transaction_confirma_quando_ok() {
+        let db = banco_teste("t");
         
-        // Hash da senha 'admin123'
-        let senha_hash = bcrypt::hash("admin123", bcrypt::DEFAULT_COST)?;
+        let id = db.with_transaction(|tx| {
+            tx.execute("INSERT INTO clientes (nome) VALUES ('Cliente A')", [])?;
+            Ok(tx.last_insert_rowid())
+        }).unwrap();
         
-        // Criar usuário admin
-        conn.execute(
-            "INSERT INTO usuarios (nome, email, senha_hash, cargo, departamento, permissoes) 
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
-            params![
-                "Administrador",
-                "admin@aluforce.com.br",
-                senha_hash,
-                "Administrador",
-                "TI",
-                r#"{"admin": true, "all": true}"#
-            ],
-        )?;
+        assert!(id > 0);
+        assert_eq!(contar(&db, "clientes"), 1);
+    }
+    
+    #[test]
+    fn with_transaction_desfaz_tudo_em_erro() {
+        let db = banco_teste("t");
         
-        // Criar empresa padrão
-        conn.execute(
-            "INSERT INTO empresas (razao_social, nome_fantasia, cnpj) 
-             VALUES (?1, ?2, ?3)",
-            params![
-                "ALUFORCE LTDA",
-                "ALUFORCE",
-                "00.000.000/0001-00"
-            ],
-        )?;
+        let resultado: AppResult<()> = db.with_transaction(|tx| {
+            tx.execute("INSERT INTO clientes (nome) VALUES ('Cliente A')", [])?;
+            tx.execute("INSERT INTO clientes (nome) VALUES ('Cliente B')", [])?;
+            Err(AppError::Validation("falha no meio".into()))
+        });
         
-        info!("✅ Dados iniciais inseridos!");
+        assert!(matches!(resultado, Err(AppError::Validation(_))));
+        assert_eq!(contar(&db, "clientes"), 0);
     }
     
-    Ok(())
-}
-
-/// Executa backup do banco de dados
-pub async fn backup(app: &AppHandle, backup_path: &str) -> AppResult<()> {
-    let db_path = get_database_path(app)?;
-    
-    std::fs::copy(&db_path, backup_path)?;
-    
-    info!("💾 Backup realizado em: {}", backup_path);
-    
-    Ok(())
-}
-
-/// Restaura banco de dados de backup
-pub async fn restore(app: &AppHandle, backup_path: &str) -> AppResult<()> {
-    let db_path = get_database_path(app)?;
+    #[test]
+    fn with_transaction_desfaz_erro_de_sql() {
+        let db = banco_teste("t");
+        
+        let resultado = db.with_transaction(|tx| {
+            tx.execute("INSERT INTO clientes (nome) VALUES ('Cliente A')", [])?;
+            tx.execute("INSERT INTO tabela_inexistente (x) VALUES (1)", [])?;
+            Ok(())
+        });
+        
+        assert!(matches!(resultado, Err(AppError::Database(_))));
+        assert_eq!(contar(&db, "clientes"), 0);
+        
+        // A conexão de escrita continua utilizável depois do rollback
+        db.with_transaction(|tx| {
+            tx.execute("INSERT INTO clientes (nome) VALUES ('Cliente C')", [])?;
+            Ok(())
+        }).unwrap();
+        assert_eq!(contar(&db, "clientes"), 1);
+    }
     
-    // Verificar se backup existe
-    if !std::path::Path::new(backup_path).exists() {
-        return Err(AppError::NotFound("Arquivo de backup não encontrado".into()));
+    #[test]
+    fn conexoes_do_pool_aplicam_as_pragmas() {
+        let db = banco_teste("t");
+        
+        let chaves: i64 = db.writer().unwrap().query_row("PRAGMA foreign_keys", [], |row| row.get(0)).unwrap();
+        assert_eq!(chaves, 1);
+        
+        // Segura cada leitor para que o seguinte venha de outra conexão
+        let leitores: Vec<_> = (0..POOL_LEITORES).map(|_| db.reader().unwrap()).collect();
+        for leitor in &leitores {
+            let chaves: i64 = leitor.query_row("PRAGMA foreign_keys", [], |row| row.get(0)).unwrap();
+            assert_eq!(chaves, 1);
+        }
     }
     
-    std::fs::copy(backup_path, &db_path)?;
+    #[test]
+    fn escritor_respeita_chaves_estrangeiras_e_leitor_nao_escreve() {
+        let db = banco_teste("t");
+        
+        let orfao = db.writer().unwrap().execute(
+            "INSERT INTO pedido_itens (pedido_id, produto_id) VALUES (999, 999)", [],
+        );
+        assert!(orfao.is_err());
+        
+        db.writer().unwrap().execute("INSERT INTO clientes (nome) VALUES ('Cliente A')", []).unwrap();
+        assert_eq!(contar(&db, "clientes"), 1);
+        assert!(db.reader().unwrap().execute("DELETE FROM clientes", []).is_err());
+    }
     
-    info!("🔄 Banco restaurado de: {}", backup_path);
+    #[test]
+    fn restore_volta_ao_backup_e_mantem_o_pool() {
+        let db = banco_teste("t");
+        db.writer().unwrap().execute("INSERT INTO clientes (nome) VALUES ('Cliente A')", []).unwrap();
+        
+        let arquivo = db.path.with_file_name("backup.db");
+        let arquivo = arquivo.to_str().unwrap();
+        tauri::async_runtime::block_on(backup(&db, arquivo)).unwrap();
+        db.writer().unwrap().execute("INSERT INTO clientes (nome) VALUES ('Cliente B')", []).unwrap();
+        
+        tauri::async_runtime::block_on(restore(&db, arquivo)).unwrap();
+        assert_eq!(contar(&db, "clientes"), 1);
+        
+        db.writer().unwrap().execute("INSERT INTO clientes (nome) VALUES ('Cliente C')", []).unwrap();
+        assert_eq!(contar(&db, "clientes"), 2);
+    }
     
-    Ok(())
+    #[test]
+    fn limpeza_remove_so_sessoes_expiradas() {
+        let db = banco_teste("t");
+        let conn = db.writer().unwrap();
+        conn.execute(
+            "INSERT INTO sessoes (id, usuario_id, token_hash, expires_at, ultimo_uso, created_at)
+             VALUES ('expirada', 1, 'x', ?1, ?1, ?1)",
+            params![crate::context::carimbo(chrono::Utc::now() - chrono::Duration::minutes(1))],
+        ).unwrap();
+        drop(conn);
+        
+        assert_eq!(purge_expired_sessions(&db).unwrap(), 1);
+        assert_eq!(contar(&db, "sessoes"), 1);
+        assert_eq!(purge_expired_sessions(&db).unwrap(), 0);
+    }
 }
@@ -0,0 +1,236 @@
+//! Reservas de estoque dos pedidos de venda
+//!
+//! Ao confirmar um pedido, cada item reserva o que houver disponível
+//! (`quantidade_fisica - quantidade_reservada`) em `estoque_saldos`. O que
+//! faltar fica pendente (encomenda), se o pedido permitir, e precisa estar
+//! reservado até a separação. No faturamento a reserva vira baixa física;
+//! no cancelamento ela é devolvida ao disponível. Mercadoria devolvida
+//! pelo cliente volta ao saldo físico; a recebida de compras também, e
+//! recalcula o custo médio.
+//!
+//! O saldo é controlado pelo código do produto; produtos sem código não
+//! têm estoque controlado e são ignorados.
+
+use std::collections::BTreeMap;
+
+use rusqlite::{Connection, params};
+use log::info;
+
+use crate::error::{AppError, AppResult};
+use crate::models::Dinheiro;
+
+/// Diferença até a qual duas quantidades são consideradas iguais
+///
+/// Absorve o erro de ponto flutuante das somas (0,1 + 0,2 > 0,3).
+pub const TOLERANCIA_QUANTIDADE: f64 = 1e-6;
+
+/// Reserva de um item ainda não baixada nem liberada
+struct Reserva {
+    id: i64,
+    codigo: String,
+    quantidade: f64,
+    pendente: f64,
+}
+
+/// Reserva os itens do pedido
+///
+/// Sem `permitir_encomenda`, recusa a confirmação se algum produto não
+/// tiver saldo disponível para a quantidade total do pedido.
+pub fn reservar_pedido(conn: &Connection, pedido_id: i64, permitir_encomenda: bool) -> AppResult<()> {
+    let itens: Vec<(i64, String, f64)> = {
+        let mut stmt = conn.prepare(
+            "SELECT i.id, p.codigo, i.quantidade
+             FROM pedido_itens i
+             INNER JOIN produtos p ON p.id = i.produto_id
+             WHERE i.pedido_id = ?1 AND p.codigo IS NOT NULL AND p.codigo <> ''
+             ORDER BY i.id"
+        )?;
+        let linhas = stmt.query_map(params![pedido_id], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?;
+        linhas.collect::<Result<_, _>>()?
+    };
+    
+    // Disponível por código, consumido item a item
+    let mut disponivel: BTreeMap<String, f64> = BTreeMap::new();
+    for (_, codigo, _) in &itens {
+        if !disponivel.contains_key(codigo) {
+            disponivel.insert(codigo.clone(), saldo_disponivel(conn, codigo)?);
+        }
+    }
+    
+    let mut faltas: BTreeMap<&str, f64> = BTreeMap::new();
+    let mut reservas = Vec::with_capacity(itens.len());
+    for (item_id, codigo, quantidade) in &itens {
+        let saldo = disponivel.get_mut(codigo).expect("saldo carregado acima");
+        let reservada = quantidade.min(saldo.max(0.0));
+        *saldo -= reservada;
+        
+        let pendente = quantidade - reservada;
+        if pendente > 0.0 {
+            *faltas.entry(codigo.as_str()).or_default() += pendente;
+        }
+        reservas.push((*item_id, codigo, reservada, pendente));
+    }
+    
+    if !faltas.is_empty() && !permitir_encomenda {
+        let lista: Vec<String> = faltas.iter().map(|(codigo, falta)| format!("{} (faltam {})", codigo, falta)).collect();
+        return Err(AppError::Validation(format!("Estoque insuficiente: {}", lista.join(", "))));
+    }
+    
+    for (item_id, codigo, reservada, pendente) in reservas {
+        ajustar_reservado(conn, codigo, reservada)?;
+        conn.execute(
+            "INSERT INTO reservas_estoque (pedido_id, item_id, codigo, quantidade, pendente)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![pedido_id, item_id, codigo, reservada, pendente],
+        )?;
+    }
+    
+    info!("📦 Estoque reservado para o pedido {} ({} itens em encomenda)", pedido_id, faltas.len());
+    
+    Ok(())
+}
+
+/// Reserva o que ainda estava em encomenda; recusa se continuar faltando
+pub fn completar_reservas(conn: &Connection, pedido_id: i64) -> AppResult<()> {
+    let mut faltas = Vec::new();
+    
+    for reserva in reservas_ativas(conn, pedido_id)?.into_iter().filter(|r| r.pendente > 0.0) {
+        let saldo = saldo_disponivel(conn, &reserva.codigo)?;
+        if saldo < reserva.pendente {
+            faltas.push(format!("{} (faltam {})", reserva.codigo, reserva.pendente - saldo.max(0.0)));
+            continue;
+        }
+        
+        conn.execute(
+            "UPDATE reservas_estoque SET quantidade = quantidade + pendente, pendente = 0,
+                    updated_at = datetime('now')
+             WHERE id = ?1",
+            params![reserva.id],
+        )?;
+        ajustar_reservado(conn, &reserva.codigo, reserva.pendente)?;
+    }
+    
+    if !faltas.is_empty() {
+        return Err(AppError::Validation(format!("Itens em encomenda sem estoque: {}", faltas.join(", "))));
+    }
+    
+    Ok(())
+}
+
+/// Devolve as reservas do pedido ao disponível
+pub fn liberar_reservas(conn: &Connection, pedido_id: i64) -> AppResult<()> {
+    for reserva in reservas_ativas(conn, pedido_id)? {
+        ajustar_reservado(conn, &reserva.codigo, -reserva.quantidade)?;
+    }
+    
+    conn.execute(
+        "UPDATE reservas_estoque SET status = 'liberada', updated_at = datetime('now')
+         WHERE pedido_id = ?1 AND status = 'ativa'",
+        params![pedido_id],
+    )?;
+    
+    Ok(())
+}
+
+/// Transforma as reservas do pedido em saída física
+pub fn baixar_reservas(conn: &Connection, pedido_id: i64) -> AppResult<()> {
+    let reservas = reservas_ativas(conn, pedido_id)?;
+    if reservas.iter().any(|r| r.pendente > 0.0) {
+        return Err(AppError::Validation("Pedido com itens em encomenda não pode ser baixado".into()));
+    }
+    
+    for reserva in reservas {
+        conn.execute(
+            "UPDATE estoque_saldos
+             SET quantidade_fisica = quantidade_fisica - ?1,
+                 quantidade_reservada = MAX(quantidade_reservada - ?1, 0),
+                 ultima_saida = date('now')
+             WHERE codigo = ?2",
+            params![reserva.quantidade, reserva.codigo],
+        )?;
+    }
+    
+    conn.execute(
+        "UPDATE reservas_estoque SET status = 'baixada', updated_at = datetime('now')
+         WHERE pedido_id = ?1 AND status = 'ativa'",
+        params![pedido_id],
+    )?;
+    
+    Ok(())
+}
+
+/// Devolve ao saldo físico a quantidade que o cliente devolveu
+pub fn entrada_devolucao(conn: &Connection, codigo: &str, quantidade: f64) -> AppResult<()> {
+    conn.execute(
+        "INSERT INTO estoque_saldos (codigo, quantidade_fisica, ultima_entrada) VALUES (?1, ?2, date('now'))
+         ON CONFLICT(codigo) DO UPDATE SET quantidade_fisica = quantidade_fisica + ?2, ultima_entrada = date('now')",
+        params![codigo, quantidade],
+    )?;
+    Ok(())
+}
+
+/// Entrada de compra: soma ao saldo físico e pondera o custo médio
+pub fn entrada_compra(conn: &Connection, codigo: &str, quantidade: f64, custo_unitario: Dinheiro) -> AppResult<()> {
+    let (fisica, custo_medio): (f64, Dinheiro) = match conn.query_row(
+        "SELECT quantidade_fisica, custo_medio FROM estoque_saldos WHERE codigo = ?1",
+        params![codigo],
+        |row| Ok((row.get::<_, Option<f64>>(0)?.unwrap_or(0.0), row.get::<_, Option<Dinheiro>>(1)?.unwrap_or_default())),
+    ) {
+        Ok(saldo) => saldo,
+        Err(rusqlite::Error::QueryReturnedNoRows) => (0.0, Dinheiro::ZERO),
+        Err(e) => return Err(AppError::Database(e.to_string())),
+    };
+    
+    // Saldo negativo ou zerado não pesa no custo
+    let anterior = fisica.max(0.0);
+    let custo = if anterior + quantidade > 0.0 {
+        custo_medio.vezes(anterior)?.somar(custo_unitario.vezes(quantidade)?)?.vezes(1.0 / (anterior + quantidade))?
+    } else {
+        custo_unitario
+    };
+    
+    conn.execute(
+        "INSERT INTO estoque_saldos (codigo, quantidade_fisica, custo_medio, ultima_entrada) VALUES (?1, ?2, ?3, date('now'))
+         ON CONFLICT(codigo) DO UPDATE SET quantidade_fisica = quantidade_fisica + ?2, custo_medio = ?3,
+                                           ultima_entrada = date('now')",
+        params![codigo, quantidade, custo],
+    )?;
+    Ok(())
+}
+
+/// Quantidade física menos reservada (zero se o código não tem saldo)
+fn saldo_disponivel(conn: &Connection, codigo: &str) -> AppResult<f64> {
+    let result = conn.query_row(
+        "SELECT quantidade_fisica - quantidade_reservada FROM estoque_saldos WHERE codigo = ?1",
+        params![codigo],
+        |row| row.get::<_, Option<f64>>(0),
+    );
+    
+    match result {
+        Ok(saldo) => Ok(saldo.unwrap_or(0.0)),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(0.0),
+        Err(e) => Err(AppError::Database(e.to_string())),
+    }
+}
+
+fn ajustar_reservado(conn: &Connection, codigo: &str, quantidade: f64) -> AppResult<()> {
+    conn.execute(
+        "INSERT INTO estoque_saldos (codigo, quantidade_reservada) VALUES (?1, MAX(?2, 0))
+         ON CONFLICT(codigo) DO UPDATE SET quantidade_reservada = MAX(quantidade_reservada + ?2, 0)",
+        params![codigo, quantidade],
+    )?;
+    Ok(())
+}
+
+fn reservas_ativas(conn: &Connection, pedido_id: i64) -> AppResult<Vec<Reserva>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, codigo, quantidade, pendente FROM reservas_estoque
+         WHERE pedido_id = ?1 AND status = 'ativa'
+         ORDER BY id"
+    )?;
+    let reservas = stmt.query_map(params![pedido_id], |row| {
+        Ok(Reserva { id: row.get(0)?, codigo: row.get(1)?, quantidade: row.get(2)?, pendente: row.get(3)? })
+    })?.collect::<Result<_, _>>()?;
+    
+    Ok(reservas)
+}
@@ -6,3 +6,14 @@ pub mod commands;
 pub mod database;
 pub mod models;
 pub mod error;
+pub mod context;
+pub mod auditoria;
+pub mod sequencias;
+pub mod estoque;
+pub mod credito;
+pub mod parcelamento;
+pub mod precos;
+pub mod comissoes;
+
+#[cfg(test)]
+pub(crate) mod testes;
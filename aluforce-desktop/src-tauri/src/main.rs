@@ -10,6 +10,16 @@ mod commands;
 mod database;
 mod models;
 mod error;
+mod context;
+mod auditoria;
+mod sequencias;
+mod estoque;
+mod credito;
+mod parcelamento;
+mod precos;
+mod comissoes;
+#[cfg(test)]
+mod testes;
 
 use tauri::Manager;
 use log::info;
@@ -20,7 +30,7 @@ fn main() {
     env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
     
     info!("🚀 Iniciando ALUFORCE Desktop v2.5.0");
-
+    
     let mut builder = tauri::Builder::default()
         // Plugins
         .plugin(tauri_plugin_dialog::init())
@@ -29,7 +39,7 @@ fn main() {
         .plugin(tauri_plugin_shell::init())
         .plugin(tauri_plugin_store::Builder::default().build())
         .plugin(tauri_plugin_sql::Builder::default().build());
-        
+    
     // Single instance (apenas uma instância da aplicação) - desktop only
     #[cfg(not(any(target_os = "android", target_os = "ios")))]
     {
@@ -40,16 +50,46 @@ fn main() {
             }
         }));
     }
-        
+    
     builder
+        // Estado da sessão autenticada
+        .manage(commands::auth::AuthState::default())
+        
         // Comandos Rust expostos ao frontend
         .invoke_handler(tauri::generate_handler![
+            // Setup commands (primeira execução)
+            commands::setup::get_setup_status,
+            commands::setup::concluir_setup,
+            
             // Auth commands
             commands::auth::login,
             commands::auth::logout,
             commands::auth::get_current_user,
             commands::auth::change_password,
             commands::auth::validate_session,
+            commands::auth::list_sessoes,
+            commands::auth::revoke_sessao,
+            commands::auth::revoke_outras_sessoes,
+            
+            // Usuários commands
+            commands::usuarios::list_usuarios,
+            commands::usuarios::create_usuario,
+            commands::usuarios::update_usuario,
+            commands::usuarios::deactivate_usuario,
+            commands::usuarios::reset_password,
+            commands::usuarios::get_tentativas_login,
+            commands::usuarios::get_eventos_bloqueio,
+            commands::usuarios::desbloquear_login,
+            
+            // Papéis e permissões commands
+            commands::papeis::get_papeis,
+            commands::papeis::create_papel,
+            commands::papeis::update_papel,
+            commands::papeis::delete_papel,
+            commands::papeis::assign_papel,
+            commands::papeis::remove_papel,
+            commands::papeis::get_permissoes_usuario,
+            commands::papeis::get_minhas_permissoes,
             
             // Database commands
             commands::database::init_database,
@@ -86,23 +126,93 @@ fn main() {
             commands::vendas::create_pedido,
             commands::vendas::update_pedido,
             commands::vendas::delete_pedido,
+            commands::vendas::add_item_pedido,
+            commands::vendas::update_item_pedido,
+            commands::vendas::remove_item_pedido,
+            commands::vendas::aprovar_pedido,
+            commands::vendas::confirmar_pedido,
+            commands::vendas::separar_pedido,
+            commands::vendas::faturar_pedido,
+            commands::vendas::entregar_pedido,
+            commands::vendas::cancelar_pedido,
+            commands::vendas::get_historico_pedido,
+            commands::vendas::get_reservas_pedido,
             commands::vendas::get_dashboard_vendas,
             
+            // Orçamentos commands
+            commands::orcamentos::get_orcamentos,
+            commands::orcamentos::get_orcamento,
+            commands::orcamentos::get_revisoes_orcamento,
+            commands::orcamentos::create_orcamento,
+            commands::orcamentos::revisar_orcamento,
+            commands::orcamentos::perder_orcamento,
+            commands::orcamentos::converter_orcamento,
+            
+            // Devoluções commands
+            commands::devolucoes::get_devolucoes,
+            commands::devolucoes::get_devolucao,
+            commands::devolucoes::create_devolucao,
+            
             // Compras commands
             commands::compras::get_requisicoes,
+            commands::compras::get_requisicao,
             commands::compras::create_requisicao,
+            commands::compras::update_requisicao,
+            commands::compras::aprovar_requisicao,
+            commands::compras::rejeitar_requisicao,
+            commands::compras::cancelar_requisicao,
             commands::compras::get_cotacoes,
+            commands::compras::get_cotacao,
             commands::compras::create_cotacao,
+            commands::compras::responder_cotacao,
+            commands::compras::aprovar_cotacao,
+            commands::compras::cancelar_cotacao,
+            commands::compras::get_mapa_cotacao,
+            commands::compras::adjudicar_cotacoes,
             commands::compras::get_pedidos_compra,
+            commands::compras::get_pedido_compra,
             commands::compras::create_pedido_compra,
+            commands::compras::aprovar_pedido_compra,
+            commands::compras::receber_pedido_compra,
+            commands::compras::cancelar_pedido_compra,
             
             // Financeiro commands
             commands::financeiro::get_contas_pagar,
             commands::financeiro::get_contas_receber,
             commands::financeiro::get_contas_bancarias,
             commands::financeiro::create_lancamento,
+            commands::financeiro::baixar_conta_receber,
             commands::financeiro::get_fluxo_caixa,
             commands::financeiro::get_dashboard_financeiro,
+            commands::financeiro::get_exposicao_credito,
+            commands::financeiro::get_aprovacoes_credito,
+            commands::financeiro::aprovar_credito,
+            commands::financeiro::rejeitar_credito,
+            
+            // Condições de pagamento commands
+            commands::condicoes_pagamento::get_condicoes_pagamento,
+            commands::condicoes_pagamento::create_condicao_pagamento,
+            commands::condicoes_pagamento::update_condicao_pagamento,
+            commands::condicoes_pagamento::delete_condicao_pagamento,
+            commands::condicoes_pagamento::simular_condicao_pagamento,
+            
+            // Tabelas de preço commands
+            commands::tabelas_preco::get_tabelas_preco,
+            commands::tabelas_preco::get_tabela_preco,
+            commands::tabelas_preco::create_tabela_preco,
+            commands::tabelas_preco::update_tabela_preco,
+            commands::tabelas_preco::delete_tabela_preco,
+            commands::tabelas_preco::save_item_tabela_preco,
+            commands::tabelas_preco::remove_item_tabela_preco,
+            commands::tabelas_preco::get_preco_efetivo,
+            
+            // Comissões commands
+            commands::comissoes::get_regras_comissao,
+            commands::comissoes::create_regra_comissao,
+            commands::comissoes::update_regra_comissao,
+            commands::comissoes::delete_regra_comissao,
+            commands::comissoes::get_extrato_comissoes,
+            commands::comissoes::fechar_comissoes,
             
             // PCP commands
             commands::pcp::get_ordens_producao,
@@ -131,6 +241,11 @@ fn main() {
             commands::config::save_config,
             commands::config::get_empresa,
             commands::config::save_empresa,
+            commands::config::get_sequencias,
+            commands::config::save_sequencia,
+            
+            // Auditoria commands
+            commands::auditoria::get_auditoria,
             
             // System commands
             commands::system::get_app_info,
@@ -143,11 +258,28 @@ fn main() {
         .setup(|app| {
             info!("📦 Configurando aplicação...");
             
-            // Inicializar banco de dados
+            // Inicializar banco de dados (aborta se o esquema for incompatível)
             let app_handle = app.handle().clone();
-            tauri::async_runtime::spawn(async move {
-                if let Err(e) = database::init(&app_handle).await {
+            match tauri::async_runtime::block_on(database::init(&app_handle)) {
+                Ok(db) => {
+                    app.manage(db);
+                }
+                Err(e) => {
                     log::error!("Erro ao inicializar banco de dados: {}", e);
+                    return Err(Box::new(e));
+                }
+            }
+            
+            // Limpeza periódica de sessões expiradas
+            let purge_handle = app.handle().clone();
+            tauri::async_runtime::spawn(async move {
+                let mut intervalo = tokio::time::interval(std::time::Duration::from_secs(60 * 60));
+                loop {
+                    intervalo.tick().await;
+                    let db = purge_handle.state::<database::DatabaseState>();
+                    if let Err(e) = database::purge_expired_sessions(&db) {
+                        log::warn!("Falha ao remover sessões expiradas: {}", e);
+                    }
                 }
             });
             
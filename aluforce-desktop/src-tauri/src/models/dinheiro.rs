@@ -0,0 +1,337 @@
+//! Valores monetários
+//!
+//! `Dinheiro` guarda o valor em centavos inteiros, então somas e subtrações
+//! são exatas. Toda operação que gera frações de centavo (quantidade ×
+//! preço, percentuais, valores digitados com mais casas) arredonda pela
+//! ABNT NBR 5891: quando o que sobra é exatamente meio centavo, arredonda
+//! para o centavo par; caso contrário, para o mais próximo.
+//!
+//! No JSON o valor continua trafegando como número em reais (`10.5`); no
+//! SQLite é gravado como INTEGER em centavos.
+//!
+//! Conversões, multiplicações e percentuais cujo resultado não cabe em
+//! centavos `i64` são erro de validação; para somar valores vindos do
+//! usuário, `somar` e `subtrair` fazem o mesmo. Os operadores `+` e `-`
+//! ficam para valores já validados e param com pânico em vez de dar a volta.
+
+use std::fmt;
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Neg, Sub, SubAssign};
+
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ToSql, ToSqlOutput, ValueRef};
+use serde::de::{self, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::error::{AppError, AppResult};
+
+/// Valor monetário em centavos
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Dinheiro(i64);
+
+impl Dinheiro {
+    pub const ZERO: Dinheiro = Dinheiro(0);
+    
+    /// Valor a partir de centavos
+    pub const fn centavos(centavos: i64) -> Self {
+        Dinheiro(centavos)
+    }
+    
+    /// Valor em reais, arredondado ao centavo (NBR 5891)
+    ///
+    /// O `f64` é lido pela sua representação decimal mais curta, ou seja,
+    /// `10.005` é tratado como o decimal digitado, e não como o binário
+    /// aproximado logo abaixo dele.
+    pub fn reais(valor: f64) -> AppResult<Self> {
+        match decimal(valor).and_then(|(mantissa, escala)| Some((mantissa.checked_mul(100)?, escala))) {
+            Some((numerador, escala)) => arredondar(numerador, 10i128.pow(escala)),
+            None => de_f64(valor * 100.0),
+        }
+    }
+    
+    pub fn em_centavos(self) -> i64 {
+        self.0
+    }
+    
+    pub fn em_reais(self) -> f64 {
+        self.0 as f64 / 100.0
+    }
+    
+    pub fn is_zero(self) -> bool {
+        self.0 == 0
+    }
+    
+    pub fn is_negative(self) -> bool {
+        self.0 < 0
+    }
+    
+    /// Soma que recusa resultado fora do limite
+    pub fn somar(self, outro: Dinheiro) -> AppResult<Self> {
+        self.0.checked_add(outro.0).map(Dinheiro).ok_or_else(fora_do_limite)
+    }
+    
+    /// Subtração que recusa resultado fora do limite
+    pub fn subtrair(self, outro: Dinheiro) -> AppResult<Self> {
+        self.0.checked_sub(outro.0).map(Dinheiro).ok_or_else(fora_do_limite)
+    }
+    
+    /// Multiplica por um fator (ex.: quantidade) e arredonda ao centavo
+    pub fn vezes(self, fator: f64) -> AppResult<Self> {
+        match decimal(fator).and_then(|(mantissa, escala)| Some(((self.0 as i128).checked_mul(mantissa)?, escala))) {
+            Some((numerador, escala)) => arredondar(numerador, 10i128.pow(escala)),
+            None => de_f64(self.0 as f64 * fator),
+        }
+    }
+    
+    /// Divide em `partes` iguais, arredondando ao centavo
+    ///
+    /// Sem partes, o resultado é zero.
+    pub fn dividido(self, partes: i64) -> AppResult<Self> {
+        if partes == 0 {
+            return Ok(Dinheiro::ZERO);
+        }
+        arredondar(self.0 as i128, partes as i128)
+    }
+    
+    /// Percentual do valor (`percentual(12.5)` = 12,5%), arredondado ao centavo
+    pub fn percentual(self, percentual: f64) -> AppResult<Self> {
+        match decimal(percentual).and_then(|(mantissa, escala)| Some(((self.0 as i128).checked_mul(mantissa)?, escala))) {
+            Some((numerador, escala)) => arredondar(numerador, 10i128.pow(escala) * 100),
+            None => de_f64(self.0 as f64 * percentual / 100.0),
+        }
+    }
+}
+
+/// Maior escala decimal aceita nas conversões exatas
+const ESCALA_MAXIMA: u32 = 18;
+
+/// Representação decimal mais curta de um `f64`: `mantissa / 10^escala`
+fn decimal(valor: f64) -> Option<(i128, u32)> {
+    if !valor.is_finite() {
+        return None;
+    }
+    
+    // O Display de f64 nunca usa notação exponencial
+    let texto = valor.to_string();
+    let (inteira, fracao) = texto.split_once('.').unwrap_or((&texto, ""));
+    let escala = fracao.len() as u32;
+    if escala > ESCALA_MAXIMA || inteira.trim_start_matches('-').len() > 18 {
+        return None;
+    }
+    
+    let mantissa: i128 = format!("{}{}", inteira, fracao).parse().ok()?;
+    Some((mantissa, escala))
+}
+
+fn fora_do_limite() -> AppError {
+    AppError::Validation("Valor monetário fora do limite".into())
+}
+
+/// Centavos de um `f64` sem representação decimal exata, arredondados
+fn de_f64(centavos: f64) -> AppResult<Dinheiro> {
+    let centavos = centavos.round();
+    // i64::MAX não é representável em f64; o limite de cima é 2^63
+    if !centavos.is_finite() || centavos < i64::MIN as f64 || centavos >= i64::MAX as f64 {
+        return Err(fora_do_limite());
+    }
+    Ok(Dinheiro(centavos as i64))
+}
+
+/// Divisão inteira com arredondamento da NBR 5891 (meio exato vai para o par)
+fn arredondar(numerador: i128, denominador: i128) -> AppResult<Dinheiro> {
+    let quociente = numerador / denominador;
+    let resto = numerador % denominador;
+    
+    let dobro = resto.abs() * 2;
+    let ajuste = if dobro > denominador || (dobro == denominador && quociente % 2 != 0) {
+        numerador.signum()
+    } else {
+        0
+    };
+    
+    i64::try_from(quociente + ajuste).map(Dinheiro).map_err(|_| fora_do_limite())
+}
+
+impl Add for Dinheiro {
+    type Output = Dinheiro;
+    
+    fn add(self, outro: Dinheiro) -> Dinheiro {
+        self.somar(outro).expect("soma de valores monetários fora do limite")
+    }
+}
+
+impl Sub for Dinheiro {
+    type Output = Dinheiro;
+    
+    fn sub(self, outro: Dinheiro) -> Dinheiro {
+        self.subtrair(outro).expect("subtração de valores monetários fora do limite")
+    }
+}
+
+impl Neg for Dinheiro {
+    type Output = Dinheiro;
+    
+    fn neg(self) -> Dinheiro {
+        Dinheiro::ZERO - self
+    }
+}
+
+impl AddAssign for Dinheiro {
+    fn add_assign(&mut self, outro: Dinheiro) {
+        *self = *self + outro;
+    }
+}
+
+impl SubAssign for Dinheiro {
+    fn sub_assign(&mut self, outro: Dinheiro) {
+        *self = *self - outro;
+    }
+}
+
+impl Sum for Dinheiro {
+    fn sum<I: Iterator<Item = Dinheiro>>(iter: I) -> Dinheiro {
+        iter.fold(Dinheiro::ZERO, Add::add)
+    }
+}
+
+impl<'a> Sum<&'a Dinheiro> for Dinheiro {
+    fn sum<I: Iterator<Item = &'a Dinheiro>>(iter: I) -> Dinheiro {
+        iter.copied().sum()
+    }
+}
+
+/// Formato `1234.56`, para logs e mensagens
+impl fmt::Display for Dinheiro {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sinal = if self.0 < 0 { "-" } else { "" };
+        let absoluto = self.0.unsigned_abs();
+        write!(f, "{}{}.{:02}", sinal, absoluto / 100, absoluto % 100)
+    }
+}
+
+impl Serialize for Dinheiro {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_f64(self.em_reais())
+    }
+}
+
+impl<'de> Deserialize<'de> for Dinheiro {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(DinheiroVisitor)
+    }
+}
+
+struct DinheiroVisitor;
+
+impl Visitor<'_> for DinheiroVisitor {
+    type Value = Dinheiro;
+    
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "valor em reais (número ou texto como \"10,50\")")
+    }
+    
+    fn visit_f64<E: de::Error>(self, valor: f64) -> Result<Dinheiro, E> {
+        if !valor.is_finite() {
+            return Err(E::custom("valor monetário inválido"));
+        }
+        Dinheiro::reais(valor).map_err(|_| E::custom("valor monetário fora do limite"))
+    }
+    
+    fn visit_i64<E: de::Error>(self, valor: i64) -> Result<Dinheiro, E> {
+        valor.checked_mul(100).map(Dinheiro).ok_or_else(|| E::custom("valor monetário fora do limite"))
+    }
+    
+    fn visit_u64<E: de::Error>(self, valor: u64) -> Result<Dinheiro, E> {
+        i64::try_from(valor)
+            .ok()
+            .and_then(|v| v.checked_mul(100))
+            .map(Dinheiro)
+            .ok_or_else(|| E::custom("valor monetário fora do limite"))
+    }
+    
+    fn visit_str<E: de::Error>(self, valor: &str) -> Result<Dinheiro, E> {
+        let normalizado = valor.trim().replace(',', ".");
+        let numero: f64 = normalizado.parse().map_err(|_| E::custom(format!("valor monetário inválido: {}", valor)))?;
+        self.visit_f64(numero)
+    }
+}
+
+impl ToSql for Dinheiro {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(self.0))
+    }
+}
+
+impl FromSql for Dinheiro {
+    fn column_result(valor: ValueRef<'_>) -> FromSqlResult<Self> {
+        match valor {
+            ValueRef::Integer(centavos) => Ok(Dinheiro(centavos)),
+            // Valores gravados antes da conversão para centavos
+            ValueRef::Real(reais) => Dinheiro::reais(reais).map_err(|_| FromSqlError::OutOfRange(reais as i64)),
+            _ => Err(FromSqlError::InvalidType),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn arredonda_meio_centavo_para_o_par() {
+        assert_eq!(Dinheiro::reais(10.005).unwrap(), Dinheiro::centavos(1000));
+        assert_eq!(Dinheiro::reais(10.015).unwrap(), Dinheiro::centavos(1002));
+        assert_eq!(Dinheiro::reais(2.675).unwrap(), Dinheiro::centavos(268));
+        assert_eq!(Dinheiro::reais(-10.015).unwrap(), Dinheiro::centavos(-1002));
+        assert_eq!(Dinheiro::reais(10.0051).unwrap(), Dinheiro::centavos(1001));
+        assert_eq!(Dinheiro::reais(10.0049).unwrap(), Dinheiro::centavos(1000));
+    }
+    
+    #[test]
+    fn soma_sem_deriva() {
+        let total: Dinheiro = (0..10).map(|_| Dinheiro::reais(0.1).unwrap()).sum();
+        assert_eq!(total, Dinheiro::reais(1.0).unwrap());
+        assert_eq!(Dinheiro::reais(0.1 + 0.2).unwrap(), Dinheiro::centavos(30));
+    }
+    
+    #[test]
+    fn multiplicacao_e_percentual() {
+        // 0,335 vira 0,34 (meio centavo, par) antes de multiplicar
+        assert_eq!(Dinheiro::centavos(34).vezes(2.5).unwrap(), Dinheiro::centavos(85));
+        assert_eq!(Dinheiro::reais(0.335).unwrap().vezes(3.0).unwrap(), Dinheiro::centavos(102));
+        assert_eq!(Dinheiro::centavos(1001).vezes(0.5).unwrap(), Dinheiro::centavos(500));
+        assert_eq!(Dinheiro::centavos(1003).vezes(0.5).unwrap(), Dinheiro::centavos(502));
+        assert_eq!(Dinheiro::centavos(10000).percentual(12.5).unwrap(), Dinheiro::centavos(1250));
+        assert_eq!(Dinheiro::centavos(1010).percentual(5.0).unwrap(), Dinheiro::centavos(50));
+        assert_eq!(Dinheiro::centavos(1000).dividido(3).unwrap(), Dinheiro::centavos(333));
+        assert_eq!(Dinheiro::centavos(1000).dividido(0).unwrap(), Dinheiro::ZERO);
+    }
+    
+    #[test]
+    fn json_em_reais() {
+        assert_eq!(serde_json::to_string(&Dinheiro::centavos(1050)).unwrap(), "10.5");
+        assert_eq!(serde_json::from_str::<Dinheiro>("10.5").unwrap(), Dinheiro::centavos(1050));
+        assert_eq!(serde_json::from_str::<Dinheiro>("7").unwrap(), Dinheiro::centavos(700));
+        assert_eq!(serde_json::from_str::<Dinheiro>("\"1234,56\"").unwrap(), Dinheiro::centavos(123456));
+        assert!(serde_json::from_str::<Dinheiro>("\"abc\"").is_err());
+    }
+    
+    #[test]
+    fn exibicao() {
+        assert_eq!(Dinheiro::centavos(123456).to_string(), "1234.56");
+        assert_eq!(Dinheiro::centavos(-5).to_string(), "-0.05");
+    }
+    
+    #[test]
+    fn recusa_valor_fora_do_limite() {
+        let maximo = Dinheiro::centavos(i64::MAX);
+        assert!(matches!(maximo.somar(Dinheiro::centavos(1)), Err(AppError::Validation(_))));
+        assert!(matches!(Dinheiro::centavos(i64::MIN).subtrair(Dinheiro::centavos(1)), Err(AppError::Validation(_))));
+        assert!(maximo.vezes(2.0).is_err());
+        assert!(maximo.percentual(200.0).is_err());
+        assert!(Dinheiro::reais(1e300).is_err());
+        assert!(Dinheiro::reais(1e17).is_err());
+        assert_eq!(Dinheiro::reais(1e16).unwrap(), Dinheiro::centavos(1_000_000_000_000_000_000));
+        assert!(serde_json::from_str::<Dinheiro>("1e20").is_err());
+        assert!(serde_json::from_str::<Dinheiro>("\"1e18\"").is_err());
+    }
+}
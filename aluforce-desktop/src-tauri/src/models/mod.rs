@@ -2,6 +2,11 @@
 
 use serde::{Deserialize, Serialize};
 use chrono::{DateTime, Utc};
+use std::collections::{BTreeMap, BTreeSet};
+
+mod dinheiro;
+
+pub use dinheiro::Dinheiro;
 
 /// Usuário do sistema
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -16,11 +21,23 @@ pub struct Usuario {
     pub avatar: Option<String>,
     pub ativo: bool,
     pub permissoes: serde_json::Value,
+    pub trocar_senha: bool,
     pub ultimo_acesso: Option<String>,
     pub created_at: String,
     pub updated_at: String,
 }
 
+/// Dados para criar/atualizar usuário
+#[derive(Debug, Deserialize)]
+pub struct UsuarioInput {
+    pub nome: String,
+    pub email: String,
+    pub cargo: Option<String>,
+    pub departamento: Option<String>,
+    pub avatar: Option<String>,
+    pub permissoes: Option<Permissoes>,
+}
+
 /// Dados para login
 #[derive(Debug, Deserialize)]
 pub struct LoginRequest {
@@ -37,6 +54,38 @@ pub struct LoginResponse {
     pub message: Option<String>,
 }
 
+/// Sessão de login ativa
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Sessao {
+    pub id: String,
+    pub created_at: String,
+    pub ultimo_uso: String,
+    pub expires_at: String,
+    pub atual: bool,
+}
+
+/// Falhas de login acumuladas para um e-mail
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TentativaLogin {
+    pub email: String,
+    pub falhas: i64,
+    pub ultima_falha: Option<String>,
+    pub bloqueado_ate: Option<String>,
+}
+
+/// Registro de bloqueio ou desbloqueio de login
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EventoBloqueio {
+    pub id: i64,
+    pub email: String,
+    pub tipo: String,
+    pub falhas: i64,
+    pub bloqueado_ate: Option<String>,
+    pub usuario_id: Option<i64>,
+    pub usuario_nome: Option<String>,
+    pub created_at: String,
+}
+
 /// Dados públicos do usuário (sem senha)
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UsuarioPublico {
@@ -47,6 +96,7 @@ pub struct UsuarioPublico {
     pub departamento: Option<String>,
     pub avatar: Option<String>,
     pub permissoes: serde_json::Value,
+    pub trocar_senha: bool,
 }
 
 impl From<Usuario> for UsuarioPublico {
@@ -59,10 +109,212 @@ impl From<Usuario> for UsuarioPublico {
             departamento: u.departamento,
             avatar: u.avatar,
             permissoes: u.permissoes,
+            trocar_senha: u.trocar_senha,
         }
     }
 }
 
+/// Módulos do sistema sujeitos a controle de acesso
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Modulo {
+    Vendas,
+    Compras,
+    Financeiro,
+    Pcp,
+    Rh,
+    Nfe,
+    Config,
+}
+
+impl std::fmt::Display for Modulo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let nome = match self {
+            Modulo::Vendas => "vendas",
+            Modulo::Compras => "compras",
+            Modulo::Financeiro => "financeiro",
+            Modulo::Pcp => "pcp",
+            Modulo::Rh => "rh",
+            Modulo::Nfe => "nfe",
+            Modulo::Config => "config",
+        };
+        write!(f, "{}", nome)
+    }
+}
+
+/// Ações possíveis dentro de um módulo
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Acao {
+    Ler,
+    Criar,
+    Editar,
+    Excluir,
+    Aprovar,
+}
+
+impl std::fmt::Display for Acao {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let nome = match self {
+            Acao::Ler => "ler",
+            Acao::Criar => "criar",
+            Acao::Editar => "editar",
+            Acao::Excluir => "excluir",
+            Acao::Aprovar => "aprovar",
+        };
+        write!(f, "{}", nome)
+    }
+}
+
+/// Permissões por módulo e ação
+/// 
+/// Formato JSON: `{"admin": false, "modulos": {"vendas": ["ler", "criar"]}}`.
+/// O formato legado `{"admin": true, "all": true}` continua aceito.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Permissoes {
+    #[serde(default)]
+    pub admin: bool,
+    #[serde(default)]
+    pub modulos: BTreeMap<Modulo, BTreeSet<Acao>>,
+}
+
+impl Permissoes {
+    /// Interpreta o JSON gravado em `usuarios.permissoes` ou `papeis.permissoes`
+    pub fn from_json(valor: &serde_json::Value) -> Self {
+        let mut permissoes: Permissoes = serde_json::from_value(valor.clone()).unwrap_or_default();
+        if valor.get("all").and_then(|v| v.as_bool()).unwrap_or(false) {
+            permissoes.admin = true;
+        }
+        permissoes
+    }
+    
+    /// Verifica se a ação é permitida no módulo
+    pub fn permite(&self, modulo: Modulo, acao: Acao) -> bool {
+        self.admin || self.modulos.get(&modulo).map(|a| a.contains(&acao)).unwrap_or(false)
+    }
+    
+    /// Soma as permissões de outro conjunto (ex.: de um papel)
+    pub fn merge(&mut self, outras: &Permissoes) {
+        self.admin |= outras.admin;
+        for (modulo, acoes) in &outras.modulos {
+            self.modulos.entry(*modulo).or_default().extend(acoes.iter().copied());
+        }
+    }
+}
+
+/// Papel (perfil de acesso) atribuível a usuários
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Papel {
+    pub id: i64,
+    pub nome: String,
+    pub descricao: Option<String>,
+    pub permissoes: Permissoes,
+    pub usuarios: i64,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+/// Dados para criar/atualizar papel
+#[derive(Debug, Deserialize)]
+pub struct PapelInput {
+    pub nome: String,
+    pub descricao: Option<String>,
+    pub permissoes: Permissoes,
+}
+
+/// Registro da trilha de auditoria
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RegistroAuditoria {
+    pub id: i64,
+    pub usuario_id: Option<i64>,
+    pub usuario_nome: Option<String>,
+    pub modulo: String,
+    pub tabela: String,
+    pub registro_id: String,
+    pub acao: String,
+    pub antes: Option<serde_json::Value>,
+    pub depois: Option<serde_json::Value>,
+    pub created_at: String,
+}
+
+/// Filtros da consulta de auditoria (datas em `YYYY-MM-DD`, inclusivas)
+#[derive(Debug, Default, Deserialize)]
+pub struct AuditoriaFiltro {
+    pub modulo: Option<Modulo>,
+    pub usuario_id: Option<i64>,
+    pub tabela: Option<String>,
+    pub registro_id: Option<String>,
+    pub data_inicio: Option<String>,
+    pub data_fim: Option<String>,
+    pub page: Option<i64>,
+    pub per_page: Option<i64>,
+}
+
+/// Tipos de documento com numeração própria
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TipoDocumento {
+    OrcamentoVenda,
+    PedidoVenda,
+    DevolucaoVenda,
+    RequisicaoCompra,
+    CotacaoCompra,
+    PedidoCompra,
+    OrdemProducao,
+    Nfe,
+}
+
+impl TipoDocumento {
+    /// Valor gravado em `sequencias.tipo`
+    pub fn chave(&self) -> &'static str {
+        match self {
+            TipoDocumento::OrcamentoVenda => "orcamento_venda",
+            TipoDocumento::PedidoVenda => "pedido_venda",
+            TipoDocumento::DevolucaoVenda => "devolucao_venda",
+            TipoDocumento::RequisicaoCompra => "requisicao_compra",
+            TipoDocumento::CotacaoCompra => "cotacao_compra",
+            TipoDocumento::PedidoCompra => "pedido_compra",
+            TipoDocumento::OrdemProducao => "ordem_producao",
+            TipoDocumento::Nfe => "nfe",
+        }
+    }
+}
+
+impl std::fmt::Display for TipoDocumento {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.chave())
+    }
+}
+
+/// Sequência de numeração de um tipo de documento (por série, na NFe)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Sequencia {
+    pub id: i64,
+    pub tipo: String,
+    pub serie: String,
+    pub prefixo: String,
+    pub formato: String,
+    pub largura: i64,
+    pub proximo: i64,
+    /// Como o próximo número será exibido
+    pub exemplo: String,
+    pub updated_at: Option<String>,
+}
+
+/// Configuração de uma sequência
+/// 
+/// `formato` aceita `{prefixo}`, `{numero}`, `{ano}` e `{mes}`. `proximo` só
+/// pode avançar, para nunca repetir um número já emitido.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SequenciaInput {
+    pub tipo: TipoDocumento,
+    pub serie: Option<String>,
+    pub prefixo: String,
+    pub formato: Option<String>,
+    pub largura: i64,
+    pub proximo: Option<i64>,
+}
+
 /// Empresa
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Empresa {
@@ -84,6 +336,40 @@ pub struct Empresa {
     pub logo_path: Option<String>,
 }
 
+/// Dados da empresa informados no cadastro
+#[derive(Debug, Clone, Deserialize)]
+pub struct EmpresaInput {
+    pub razao_social: String,
+    pub nome_fantasia: Option<String>,
+    pub cnpj: Option<String>,
+    pub inscricao_estadual: Option<String>,
+    pub inscricao_municipal: Option<String>,
+    pub telefone: Option<String>,
+    pub email: Option<String>,
+    pub cep: Option<String>,
+    pub endereco: Option<String>,
+    pub numero: Option<String>,
+    pub complemento: Option<String>,
+    pub bairro: Option<String>,
+    pub cidade: Option<String>,
+    pub estado: Option<String>,
+}
+
+/// Dados do assistente de primeira execução
+#[derive(Debug, Deserialize)]
+pub struct SetupInput {
+    pub empresa: EmpresaInput,
+    pub administrador: UsuarioInput,
+    pub senha: String,
+}
+
+/// Situação da instalação
+#[derive(Debug, Serialize)]
+pub struct SetupStatus {
+    pub concluido: bool,
+    pub concluido_em: Option<String>,
+}
+
 /// Cliente
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Cliente {
@@ -106,8 +392,9 @@ pub struct Cliente {
     pub cidade: Option<String>,
     pub estado: Option<String>,
     pub observacoes: Option<String>,
-    pub limite_credito: f64,
+    pub limite_credito: Dinheiro,
     pub vendedor_id: Option<i64>,
+    pub tabela_preco_id: Option<i64>,
     pub ativo: bool,
     pub created_at: String,
     pub updated_at: String,
@@ -134,8 +421,9 @@ pub struct ClienteInput {
     pub cidade: Option<String>,
     pub estado: Option<String>,
     pub observacoes: Option<String>,
-    pub limite_credito: Option<f64>,
+    pub limite_credito: Option<Dinheiro>,
     pub vendedor_id: Option<i64>,
+    pub tabela_preco_id: Option<i64>,
 }
 
 /// Fornecedor
@@ -171,8 +459,8 @@ pub struct Produto {
     pub descricao: Option<String>,
     pub categoria: Option<String>,
     pub unidade: String,
-    pub preco_custo: f64,
-    pub preco_venda: f64,
+    pub preco_custo: Dinheiro,
+    pub preco_venda: Dinheiro,
     pub margem: f64,
     pub estoque_minimo: f64,
     pub estoque_atual: f64,
@@ -188,6 +476,180 @@ pub struct Produto {
     pub updated_at: String,
 }
 
+/// Tabela de preço
+///
+/// Vale entre `validade_inicio` e `validade_fim` (datas inclusivas; vazias
+/// não limitam). `ajuste_percentual` se aplica sobre o `preco_venda` dos
+/// produtos sem item na tabela; negativo é desconto.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TabelaPreco {
+    pub id: i64,
+    pub nome: String,
+    pub descricao: Option<String>,
+    pub ajuste_percentual: f64,
+    pub validade_inicio: Option<String>,
+    pub validade_fim: Option<String>,
+    pub ativo: bool,
+    pub itens: Vec<TabelaPrecoItem>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+/// Dados para criar/atualizar tabela de preço
+#[derive(Debug, Deserialize)]
+pub struct TabelaPrecoInput {
+    pub nome: String,
+    pub descricao: Option<String>,
+    pub ajuste_percentual: Option<f64>,
+    pub validade_inicio: Option<String>,
+    pub validade_fim: Option<String>,
+}
+
+/// Preço de um produto em uma tabela, a partir de uma quantidade mínima
+///
+/// Tem `preco` fixo ou `ajuste_percentual` sobre o `preco_venda`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TabelaPrecoItem {
+    pub id: i64,
+    pub tabela_id: i64,
+    pub produto_id: i64,
+    pub produto_nome: Option<String>,
+    pub quantidade_minima: f64,
+    pub preco: Option<Dinheiro>,
+    pub ajuste_percentual: Option<f64>,
+}
+
+/// Dados de um item de tabela de preço
+#[derive(Debug, Deserialize)]
+pub struct TabelaPrecoItemInput {
+    pub produto_id: i64,
+    pub quantidade_minima: Option<f64>,
+    pub preco: Option<Dinheiro>,
+    pub ajuste_percentual: Option<f64>,
+}
+
+/// Preço efetivo de um produto para um cliente
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PrecoEfetivo {
+    pub produto_id: i64,
+    pub cliente_id: Option<i64>,
+    pub quantidade: f64,
+    /// `preco_venda` do produto
+    pub preco_base: Dinheiro,
+    pub preco: Dinheiro,
+    pub tabela_preco_id: Option<i64>,
+    pub tabela_preco_nome: Option<String>,
+    /// "produto", "tabela" (percentual geral) ou "item" (item da tabela)
+    pub origem: String,
+}
+
+/// Orçamento de venda, com os dados da revisão atual
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Orcamento {
+    pub id: i64,
+    pub numero: String,
+    pub cliente_id: i64,
+    pub cliente_nome: Option<String>,
+    pub vendedor_id: Option<i64>,
+    pub vendedor_nome: Option<String>,
+    pub data_emissao: String,
+    pub revisao: i64,
+    pub validade: String,
+    /// Em aberto com a validade já passada
+    pub vencido: bool,
+    /// "aberto", "ganho" ou "perdido"
+    pub status: String,
+    /// Motivo do ganho ou da perda
+    pub motivo: Option<String>,
+    pub alternativa_aceita: Option<i64>,
+    pub pedido_id: Option<i64>,
+    pub forma_pagamento: Option<String>,
+    pub condicao_pagamento: Option<String>,
+    pub condicao_pagamento_id: Option<i64>,
+    pub observacoes: Option<String>,
+    pub alternativas: Vec<OrcamentoAlternativa>,
+    pub itens: Vec<OrcamentoItem>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+/// Total de uma alternativa do orçamento
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OrcamentoAlternativa {
+    pub alternativa: i64,
+    pub total: Dinheiro,
+}
+
+/// Revisão de um orçamento, como foi enviada ao cliente
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OrcamentoRevisao {
+    pub id: i64,
+    pub orcamento_id: i64,
+    pub revisao: i64,
+    pub validade: String,
+    pub forma_pagamento: Option<String>,
+    pub condicao_pagamento: Option<String>,
+    pub condicao_pagamento_id: Option<i64>,
+    pub observacoes: Option<String>,
+    /// Por que a revisão foi feita
+    pub motivo: Option<String>,
+    pub usuario_id: Option<i64>,
+    pub usuario_nome: Option<String>,
+    pub itens: Vec<OrcamentoItem>,
+    pub created_at: String,
+}
+
+/// Item de uma revisão de orçamento
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OrcamentoItem {
+    pub id: i64,
+    pub alternativa: i64,
+    pub produto_id: i64,
+    pub produto_nome: Option<String>,
+    pub quantidade: f64,
+    pub preco_unitario: Dinheiro,
+    pub desconto: Dinheiro,
+    pub desconto_percentual: Option<f64>,
+    pub total: Dinheiro,
+}
+
+/// Dados de um orçamento ou de uma nova revisão
+/// 
+/// Sem `validade`, vale por 15 dias a partir de hoje.
+#[derive(Debug, Deserialize)]
+pub struct OrcamentoInput {
+    pub cliente_id: i64,
+    pub vendedor_id: Option<i64>,
+    pub validade: Option<String>,
+    pub forma_pagamento: Option<String>,
+    pub condicao_pagamento: Option<String>,
+    pub condicao_pagamento_id: Option<i64>,
+    pub observacoes: Option<String>,
+    pub itens: Vec<OrcamentoItemInput>,
+}
+
+/// Item de orçamento; sem `alternativa`, vai para a alternativa 1
+/// 
+/// Sem `preco_unitario`, vale o preço efetivo do produto para o cliente.
+#[derive(Debug, Clone, Deserialize)]
+pub struct OrcamentoItemInput {
+    pub alternativa: Option<i64>,
+    pub produto_id: i64,
+    pub quantidade: f64,
+    pub preco_unitario: Option<Dinheiro>,
+    pub desconto: Option<Ajuste>,
+}
+
+/// Pedido gerado na conversão de um orçamento
+/// 
+/// Acima do limite de crédito, o pedido fica aprovado e `pendencia_credito`
+/// traz o motivo; a confirmação espera a aprovação de crédito.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConversaoOrcamento {
+    pub pedido: PedidoVenda,
+    pub pendencia_credito: Option<String>,
+}
+
 /// Pedido de Venda
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PedidoVenda {
@@ -200,18 +662,117 @@ pub struct PedidoVenda {
     pub data_pedido: String,
     pub data_entrega: Option<String>,
     pub status: String,
-    pub subtotal: f64,
-    pub desconto: f64,
-    pub acrescimo: f64,
-    pub total: f64,
+    pub subtotal: Dinheiro,
+    pub desconto: Dinheiro,
+    pub acrescimo: Dinheiro,
+    pub total: Dinheiro,
+    /// Preenchido quando o desconto foi dado em percentual sobre o subtotal
+    pub desconto_percentual: Option<f64>,
+    pub acrescimo_percentual: Option<f64>,
     pub forma_pagamento: Option<String>,
+    /// Texto da condição (nome da condição cadastrada ou prazos livres, ex.: "30/60/90")
     pub condicao_pagamento: Option<String>,
+    pub condicao_pagamento_id: Option<i64>,
     pub observacoes: Option<String>,
     pub itens: Vec<PedidoItem>,
     pub created_at: String,
     pub updated_at: String,
 }
 
+/// Situação do pedido de venda
+/// 
+/// Fluxo normal: orcamento → aprovado → confirmado → separado → faturado →
+/// entregue. Até ser faturado, o pedido pode ser cancelado.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StatusPedido {
+    Orcamento,
+    Aprovado,
+    Confirmado,
+    Separado,
+    Faturado,
+    Entregue,
+    Cancelado,
+}
+
+impl StatusPedido {
+    /// Valor gravado em `pedidos_venda.status`
+    pub fn chave(&self) -> &'static str {
+        match self {
+            StatusPedido::Orcamento => "orcamento",
+            StatusPedido::Aprovado => "aprovado",
+            StatusPedido::Confirmado => "confirmado",
+            StatusPedido::Separado => "separado",
+            StatusPedido::Faturado => "faturado",
+            StatusPedido::Entregue => "entregue",
+            StatusPedido::Cancelado => "cancelado",
+        }
+    }
+    
+    pub fn from_chave(chave: &str) -> Option<Self> {
+        match chave {
+            "orcamento" => Some(StatusPedido::Orcamento),
+            "aprovado" => Some(StatusPedido::Aprovado),
+            "confirmado" => Some(StatusPedido::Confirmado),
+            "separado" => Some(StatusPedido::Separado),
+            "faturado" => Some(StatusPedido::Faturado),
+            "entregue" => Some(StatusPedido::Entregue),
+            "cancelado" => Some(StatusPedido::Cancelado),
+            _ => None,
+        }
+    }
+    
+    /// Situações alcançáveis a partir desta
+    pub fn proximos(&self) -> &'static [StatusPedido] {
+        match self {
+            StatusPedido::Orcamento => &[StatusPedido::Aprovado, StatusPedido::Cancelado],
+            StatusPedido::Aprovado => &[StatusPedido::Confirmado, StatusPedido::Cancelado],
+            StatusPedido::Confirmado => &[StatusPedido::Separado, StatusPedido::Cancelado],
+            StatusPedido::Separado => &[StatusPedido::Faturado, StatusPedido::Cancelado],
+            StatusPedido::Faturado => &[StatusPedido::Entregue],
+            StatusPedido::Entregue | StatusPedido::Cancelado => &[],
+        }
+    }
+    
+    pub fn pode_ir_para(&self, destino: StatusPedido) -> bool {
+        self.proximos().contains(&destino)
+    }
+}
+
+impl std::fmt::Display for StatusPedido {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.chave())
+    }
+}
+
+/// Mudança de situação de um pedido de venda
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoricoPedido {
+    pub id: i64,
+    pub pedido_id: i64,
+    pub status_anterior: Option<String>,
+    pub status_novo: String,
+    pub usuario_id: Option<i64>,
+    pub usuario_nome: Option<String>,
+    pub observacao: Option<String>,
+    pub created_at: String,
+}
+
+/// Reserva de estoque de um item de pedido
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReservaEstoque {
+    pub id: i64,
+    pub pedido_id: i64,
+    pub item_id: i64,
+    pub codigo: String,
+    pub quantidade: f64,
+    /// Parte em encomenda, ainda sem saldo reservado
+    pub pendente: f64,
+    pub status: String,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
 /// Item de Pedido
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PedidoItem {
@@ -220,9 +781,126 @@ pub struct PedidoItem {
     pub produto_id: i64,
     pub produto_nome: Option<String>,
     pub quantidade: f64,
-    pub preco_unitario: f64,
-    pub desconto: f64,
-    pub total: f64,
+    pub preco_unitario: Dinheiro,
+    pub desconto: Dinheiro,
+    pub desconto_percentual: Option<f64>,
+    pub total: Dinheiro,
+}
+
+/// Dados de um item de pedido
+/// 
+/// Sem `preco_unitario`, vale o preço efetivo do produto para o cliente do
+/// pedido (ver `precos::resolver`).
+#[derive(Debug, Clone, Deserialize)]
+pub struct PedidoItemInput {
+    pub produto_id: i64,
+    pub quantidade: f64,
+    pub preco_unitario: Option<Dinheiro>,
+    pub desconto: Option<Ajuste>,
+}
+
+/// Desconto ou acréscimo, em percentual ou valor
+/// 
+/// JSON: `{"percentual": 10}` ou `{"valor": 15.9}`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Ajuste {
+    Percentual(f64),
+    Valor(Dinheiro),
+}
+
+impl Ajuste {
+    /// Percentual guardado junto do valor, para recalcular quando a base mudar
+    pub fn percentual(&self) -> Option<f64> {
+        match self {
+            Ajuste::Percentual(p) => Some(*p),
+            Ajuste::Valor(_) => None,
+        }
+    }
+    
+    /// Valor fixo; em percentual, o valor depende da base e é calculado depois
+    pub fn valor(&self) -> Dinheiro {
+        match self {
+            Ajuste::Percentual(_) => Dinheiro::ZERO,
+            Ajuste::Valor(v) => *v,
+        }
+    }
+}
+
+/// Devolução de venda
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DevolucaoVenda {
+    pub id: i64,
+    pub numero: String,
+    pub pedido_id: i64,
+    pub pedido_numero: Option<String>,
+    pub cliente_id: Option<i64>,
+    pub cliente_nome: Option<String>,
+    /// NFe de saída devolvida
+    pub nota_origem_id: i64,
+    /// NFe de entrada (devolução), referenciando a chave da nota de origem
+    pub nota_fiscal_id: Option<i64>,
+    pub nota_fiscal_chave: Option<String>,
+    /// Conta a receber negativa com o crédito do cliente
+    pub conta_receber_id: Option<i64>,
+    /// Conta a pagar ao cliente com o reembolso
+    pub conta_pagar_id: Option<i64>,
+    pub data_devolucao: String,
+    /// "credito" ou "reembolso"
+    pub tratamento: String,
+    pub motivo: String,
+    pub valor: Dinheiro,
+    pub usuario_id: Option<i64>,
+    pub itens: Vec<DevolucaoItem>,
+    pub created_at: String,
+}
+
+/// Item devolvido, com o valor líquido do desconto/acréscimo do pedido
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DevolucaoItem {
+    pub id: i64,
+    pub pedido_item_id: i64,
+    pub produto_id: i64,
+    pub produto_nome: Option<String>,
+    pub quantidade: f64,
+    pub valor: Dinheiro,
+}
+
+/// O que fazer com o valor devolvido
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TratamentoDevolucao {
+    /// Crédito do cliente, abatido de compras futuras
+    #[default]
+    Credito,
+    /// Valor a devolver ao cliente
+    Reembolso,
+}
+
+impl TratamentoDevolucao {
+    /// Valor gravado em `devolucoes_venda.tratamento`
+    pub fn chave(&self) -> &'static str {
+        match self {
+            TratamentoDevolucao::Credito => "credito",
+            TratamentoDevolucao::Reembolso => "reembolso",
+        }
+    }
+}
+
+/// Dados de uma devolução; sem `data_devolucao`, hoje
+#[derive(Debug, Deserialize)]
+pub struct DevolucaoInput {
+    pub pedido_id: i64,
+    pub motivo: String,
+    pub tratamento: Option<TratamentoDevolucao>,
+    pub data_devolucao: Option<String>,
+    pub itens: Vec<DevolucaoItemInput>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct DevolucaoItemInput {
+    pub pedido_item_id: i64,
+    pub quantidade: f64,
 }
 
 /// Conta a Pagar
@@ -232,13 +910,15 @@ pub struct ContaPagar {
     pub descricao: String,
     pub fornecedor_id: Option<i64>,
     pub fornecedor_nome: Option<String>,
-    pub valor: f64,
+    pub valor: Dinheiro,
     pub data_vencimento: String,
     pub data_pagamento: Option<String>,
     pub status: String,
     pub categoria: Option<String>,
     pub forma_pagamento: Option<String>,
     pub observacoes: Option<String>,
+    pub parcela: Option<i64>,
+    pub total_parcelas: Option<i64>,
     pub created_at: String,
     pub updated_at: String,
 }
@@ -251,7 +931,11 @@ pub struct ContaReceber {
     pub cliente_id: Option<i64>,
     pub cliente_nome: Option<String>,
     pub pedido_id: Option<i64>,
-    pub valor: f64,
+    pub nota_fiscal_id: Option<i64>,
+    /// Número da parcela e total de parcelas, para títulos gerados no faturamento
+    pub parcela: Option<i64>,
+    pub total_parcelas: Option<i64>,
+    pub valor: Dinheiro,
     pub data_vencimento: String,
     pub data_recebimento: Option<String>,
     pub status: String,
@@ -262,6 +946,132 @@ pub struct ContaReceber {
     pub updated_at: String,
 }
 
+/// Condição de pagamento cadastrada
+///
+/// As parcelas vencem em `primeiro_vencimento_dias`, depois a cada
+/// `intervalo_dias`. A entrada, se houver, vence na data-base e sai do total
+/// antes da divisão; `ajuste_percentual` positivo é juros, negativo é desconto.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CondicaoPagamento {
+    pub id: i64,
+    pub nome: String,
+    pub parcelas: i64,
+    pub intervalo_dias: i64,
+    pub primeiro_vencimento_dias: i64,
+    pub entrada_percentual: Option<f64>,
+    pub ajuste_percentual: f64,
+    pub ativo: bool,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+/// Dados para criar/atualizar condição de pagamento
+#[derive(Debug, Deserialize)]
+pub struct CondicaoPagamentoInput {
+    pub nome: String,
+    pub parcelas: i64,
+    pub intervalo_dias: Option<i64>,
+    pub primeiro_vencimento_dias: Option<i64>,
+    pub entrada_percentual: Option<f64>,
+    pub ajuste_percentual: Option<f64>,
+}
+
+/// Exposição de crédito de um cliente
+///
+/// `limite_credito` zero significa cliente sem limite configurado.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExposicaoCredito {
+    pub cliente_id: i64,
+    pub limite_credito: Dinheiro,
+    /// Contas a receber pendentes ainda no prazo
+    pub a_receber: Dinheiro,
+    /// Contas a receber pendentes vencidas
+    pub vencido: Dinheiro,
+    /// Pedidos confirmados ou separados, ainda sem títulos a receber
+    pub pedidos_em_aberto: Dinheiro,
+    pub exposicao: Dinheiro,
+    pub disponivel: Dinheiro,
+}
+
+/// Solicitação de aprovação de crédito de um pedido
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AprovacaoCredito {
+    pub id: i64,
+    pub pedido_id: i64,
+    pub pedido_numero: Option<String>,
+    pub cliente_id: i64,
+    pub cliente_nome: Option<String>,
+    pub valor_pedido: Dinheiro,
+    /// Exposição do cliente no momento da solicitação, sem o pedido
+    pub exposicao: Dinheiro,
+    pub limite_credito: Dinheiro,
+    pub status: String,
+    pub solicitado_por: Option<i64>,
+    pub solicitado_por_nome: Option<String>,
+    pub decidido_por: Option<i64>,
+    pub decidido_por_nome: Option<String>,
+    pub decidido_em: Option<String>,
+    pub observacao: Option<String>,
+    pub created_at: String,
+}
+
+/// Regra de comissão
+/// 
+/// Campos nulos valem para qualquer vendedor, categoria ou tabela de preço.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RegraComissao {
+    pub id: i64,
+    pub vendedor_id: Option<i64>,
+    pub vendedor_nome: Option<String>,
+    pub categoria: Option<String>,
+    pub tabela_preco_id: Option<i64>,
+    pub tabela_preco_nome: Option<String>,
+    pub percentual: f64,
+    pub ativo: bool,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+/// Dados para criar/atualizar regra de comissão
+#[derive(Debug, Deserialize)]
+pub struct RegraComissaoInput {
+    pub vendedor_id: Option<i64>,
+    pub categoria: Option<String>,
+    pub tabela_preco_id: Option<i64>,
+    pub percentual: f64,
+}
+
+/// Lançamento de comissão de um vendedor
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Comissao {
+    pub id: i64,
+    pub vendedor_id: i64,
+    pub pedido_id: i64,
+    pub pedido_numero: Option<String>,
+    pub conta_receber_id: Option<i64>,
+    /// "faturamento", "recebimento" ou "estorno" (valor negativo)
+    pub tipo: String,
+    pub base: Dinheiro,
+    pub valor: Dinheiro,
+    pub data_competencia: String,
+    pub conta_pagar_id: Option<i64>,
+    pub observacao: Option<String>,
+    pub created_at: String,
+}
+
+/// Extrato de comissões de um vendedor no período
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExtratoComissoes {
+    pub vendedor_id: i64,
+    pub vendedor_nome: Option<String>,
+    pub data_inicio: String,
+    pub data_fim: String,
+    pub lancamentos: Vec<Comissao>,
+    pub total: Dinheiro,
+    /// Soma dos lançamentos ainda não fechados em contas a pagar
+    pub em_aberto: Dinheiro,
+}
+
 /// Conta Bancária
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ContaBancaria {
@@ -271,8 +1081,8 @@ pub struct ContaBancaria {
     pub agencia: Option<String>,
     pub conta: Option<String>,
     pub tipo: String,
-    pub saldo_inicial: f64,
-    pub saldo_atual: f64,
+    pub saldo_inicial: Dinheiro,
+    pub saldo_atual: Dinheiro,
     pub ativo: bool,
 }
 
@@ -289,7 +1099,7 @@ pub struct Funcionario {
     pub data_demissao: Option<String>,
     pub cargo: Option<String>,
     pub departamento: Option<String>,
-    pub salario: f64,
+    pub salario: Dinheiro,
     pub email: Option<String>,
     pub telefone: Option<String>,
     pub cep: Option<String>,
@@ -349,63 +1159,99 @@ pub struct NotaFiscal {
     pub cliente_nome: Option<String>,
     pub fornecedor_id: Option<i64>,
     pub pedido_id: Option<i64>,
-    pub valor_produtos: f64,
-    pub valor_frete: f64,
-    pub valor_seguro: f64,
-    pub valor_desconto: f64,
-    pub valor_total: f64,
+    pub valor_produtos: Dinheiro,
+    pub valor_frete: Dinheiro,
+    pub valor_seguro: Dinheiro,
+    pub valor_desconto: Dinheiro,
+    pub valor_total: Dinheiro,
     pub status: String,
     pub protocolo: Option<String>,
     pub observacoes: Option<String>,
+    /// finNFe: 1 = normal, 4 = devolução
+    pub finalidade: i64,
+    /// Chave da nota de origem, na nota de devolução
+    pub chave_referenciada: Option<String>,
 }
 
 /// Dashboard de Vendas
+///
+/// Vendas são os pedidos confirmados em diante (confirmado, separado,
+/// faturado, entregue), pela data do pedido. O período anterior tem o
+/// mesmo número de dias e termina na véspera do início.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DashboardVendas {
-    pub total_vendas_mes: f64,
-    pub total_pedidos_mes: i64,
-    pub ticket_medio: f64,
+    pub data_inicio: String,
+    pub data_fim: String,
+    pub total_vendas: Dinheiro,
+    /// Pedidos lançados no período, em qualquer situação
+    pub total_pedidos: i64,
+    pub pedidos_vendidos: i64,
+    /// Total vendido dividido pelos pedidos vendidos
+    pub ticket_medio: Dinheiro,
+    pub periodo_anterior: ResumoVendas,
+    /// Variação percentual do total vendido sobre o período anterior; `None` se não houve vendas nele
+    pub variacao_vendas: Option<f64>,
+    pub orcamentos_emitidos: i64,
+    pub orcamentos_ganhos: i64,
+    pub orcamentos_perdidos: i64,
+    /// Percentual dos orçamentos emitidos no período e já decididos que viraram pedido
+    pub taxa_conversao: Option<f64>,
     pub vendas_por_vendedor: Vec<VendasPorVendedor>,
     pub vendas_por_dia: Vec<VendasPorDia>,
     pub top_produtos: Vec<TopProduto>,
     pub top_clientes: Vec<TopCliente>,
 }
 
+/// Totais de vendas de um período
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResumoVendas {
+    pub data_inicio: String,
+    pub data_fim: String,
+    pub total_vendas: Dinheiro,
+    pub total_pedidos: i64,
+    pub pedidos_vendidos: i64,
+    pub ticket_medio: Dinheiro,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct VendasPorVendedor {
+    pub vendedor_id: Option<i64>,
     pub vendedor: String,
-    pub total: f64,
+    pub total: Dinheiro,
     pub quantidade: i64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct VendasPorDia {
     pub data: String,
-    pub total: f64,
+    pub total: Dinheiro,
+    pub pedidos: i64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TopProduto {
+    pub produto_id: i64,
     pub produto: String,
     pub quantidade: f64,
-    pub total: f64,
+    pub total: Dinheiro,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TopCliente {
+    pub cliente_id: Option<i64>,
     pub cliente: String,
-    pub total: f64,
+    pub total: Dinheiro,
     pub pedidos: i64,
 }
 
 /// Dashboard Financeiro
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DashboardFinanceiro {
-    pub saldo_total: f64,
-    pub a_receber: f64,
-    pub a_pagar: f64,
-    pub recebido_mes: f64,
-    pub pago_mes: f64,
+    pub saldo_total: Dinheiro,
+    pub a_receber: Dinheiro,
+    pub a_pagar: Dinheiro,
+    pub recebido_mes: Dinheiro,
+    pub pago_mes: Dinheiro,
     pub vencidas: i64,
     pub vencer_hoje: i64,
     pub fluxo_caixa: Vec<FluxoCaixaDia>,
@@ -414,9 +1260,9 @@ pub struct DashboardFinanceiro {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FluxoCaixaDia {
     pub data: String,
-    pub entradas: f64,
-    pub saidas: f64,
-    pub saldo: f64,
+    pub entradas: Dinheiro,
+    pub saidas: Dinheiro,
+    pub saldo: Dinheiro,
 }
 
 /// Informações do App
@@ -0,0 +1,312 @@
+//! Parcelamento de valores
+//!
+//! Toda conta de parcelas (pedidos de venda e de compra, contas a receber e
+//! a pagar) passa por um `Plano`: os prazos em dias a partir da data-base
+//! (emissão ou faturamento), a entrada e o juros/desconto. O plano vem de
+//! uma condição cadastrada em `condicoes_pagamento` ou de um texto livre
+//! como "30/60/90".
+//!
+//! O valor é dividido em centavos inteiros e a diferença da divisão fica na
+//! primeira parcela, então a soma das parcelas é sempre igual ao total com o
+//! juros/desconto; parcela que ficaria zerada não é gerada. Pedidos de venda
+//! não aceitam condição com juros/desconto: os títulos precisam somar o
+//! total da nota.
+
+use chrono::{Duration, NaiveDate};
+use rusqlite::{Connection, params};
+use serde::Serialize;
+
+use crate::error::{AppError, AppResult};
+use crate::models::{CondicaoPagamento, Dinheiro};
+
+/// Maior prazo aceito, em dias, para uma parcela ou intervalo
+pub const PRAZO_MAXIMO_DIAS: i64 = 3650;
+
+/// Parcela calculada
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Parcela {
+    /// Número da parcela, a partir de 1 (a entrada, se houver, é a 1)
+    pub numero: i64,
+    pub entrada: bool,
+    pub vencimento: NaiveDate,
+    pub valor: Dinheiro,
+}
+
+impl Parcela {
+    /// Descrição do título gerado: "Pedido 1", "Pedido 1 - entrada" ou "Pedido 1 - parcela 2/3"
+    pub fn descricao(&self, documento: &str, total_parcelas: usize) -> String {
+        if self.entrada {
+            format!("{} - entrada", documento)
+        } else if total_parcelas == 1 {
+            documento.to_string()
+        } else {
+            format!("{} - parcela {}/{}", documento, self.numero, total_parcelas)
+        }
+    }
+}
+
+/// Regras de parcelamento de um documento
+#[derive(Debug, Clone, PartialEq)]
+pub struct Plano {
+    /// Prazo de cada parcela, em dias a partir da data-base
+    pub prazos: Vec<i64>,
+    /// Percentual do total pago na data-base, antes das parcelas
+    pub entrada_percentual: Option<f64>,
+    /// Positivo para juros, negativo para desconto, sobre o total
+    pub ajuste_percentual: f64,
+}
+
+impl Plano {
+    /// Plano de uma condição em texto livre (só prazos)
+    pub fn de_texto(condicao: &str) -> AppResult<Plano> {
+        Ok(Plano {
+            prazos: prazos(condicao)?,
+            entrada_percentual: None,
+            ajuste_percentual: 0.0,
+        })
+    }
+    
+    /// Plano de uma condição cadastrada
+    pub fn da_condicao(condicao: &CondicaoPagamento) -> Plano {
+        Plano {
+            prazos: (0..condicao.parcelas)
+                .map(|i| condicao.primeiro_vencimento_dias.saturating_add(i.saturating_mul(condicao.intervalo_dias)))
+                .collect(),
+            entrada_percentual: condicao.entrada_percentual.filter(|p| *p > 0.0),
+            ajuste_percentual: condicao.ajuste_percentual,
+        }
+    }
+    
+    /// Recusa o plano com juros/desconto, para documentos cujas parcelas
+    /// precisam somar o próprio total
+    pub fn exigir_sem_ajuste(&self) -> AppResult<()> {
+        if self.ajuste_percentual != 0.0 {
+            return Err(AppError::Validation(format!(
+                "Condição de pagamento com juros/desconto de {}% não pode ser usada em pedido de venda",
+                self.ajuste_percentual
+            )));
+        }
+        Ok(())
+    }
+    
+    /// Total com juros/desconto aplicado
+    pub fn total(&self, valor: Dinheiro) -> AppResult<Dinheiro> {
+        valor.somar(valor.percentual(self.ajuste_percentual)?)
+    }
+    
+    /// Parcelas de `valor` a partir de `data_base`
+    ///
+    /// A soma das parcelas é exatamente `self.total(valor)`; as que ficariam
+    /// zeradas (centavos divididos em mais parcelas do que há) são omitidas.
+    /// Prazo que leva o vencimento para fora do calendário é erro de validação.
+    pub fn parcelar(&self, valor: Dinheiro, data_base: NaiveDate) -> AppResult<Vec<Parcela>> {
+        let total = self.total(valor)?;
+        let entrada = self.entrada_percentual.map(|p| total.percentual(p)).transpose()?.unwrap_or_default();
+        
+        let mut parcelas = Vec::with_capacity(self.prazos.len() + 1);
+        if !entrada.is_zero() {
+            parcelas.push(Parcela { numero: 1, entrada: true, vencimento: data_base, valor: entrada });
+        }
+        let valores = dividir(total.subtrair(entrada)?, self.prazos.len());
+        for (valor, &dias) in valores.into_iter().zip(&self.prazos).filter(|(valor, _)| !valor.is_zero()) {
+            parcelas.push(Parcela {
+                numero: parcelas.len() as i64 + 1,
+                entrada: false,
+                vencimento: somar_dias(data_base, dias)?,
+                valor,
+            });
+        }
+        Ok(parcelas)
+    }
+}
+
+/// Data `dias` depois de `data_base`, sem estourar o calendário
+pub fn somar_dias(data_base: NaiveDate, dias: i64) -> AppResult<NaiveDate> {
+    Duration::try_days(dias)
+        .and_then(|prazo| data_base.checked_add_signed(prazo))
+        .ok_or_else(|| AppError::Validation(format!("Prazo de {} dias fora do calendário", dias)))
+}
+
+/// Plano de um documento: a condição cadastrada, se houver; senão o texto livre
+pub fn plano(conn: &Connection, condicao_id: Option<i64>, texto: Option<&str>) -> AppResult<Plano> {
+    match condicao_id {
+        Some(id) => Ok(Plano::da_condicao(&condicao(conn, id)?)),
+        None => Plano::de_texto(texto.unwrap_or("")),
+    }
+}
+
+/// Carrega uma condição cadastrada
+pub fn condicao(conn: &Connection, id: i64) -> AppResult<CondicaoPagamento> {
+    let result = conn.query_row(
+        "SELECT id, nome, parcelas, intervalo_dias, primeiro_vencimento_dias, entrada_percentual,
+                ajuste_percentual, ativo, created_at, updated_at
+         FROM condicoes_pagamento WHERE id = ?1",
+        params![id],
+        map_condicao,
+    );
+    
+    match result {
+        Ok(condicao) => Ok(condicao),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Err(AppError::NotFound("Condição de pagamento não encontrada".into())),
+        Err(e) => Err(AppError::Database(e.to_string())),
+    }
+}
+
+/// Condição cadastrada e ativa, para uso em um documento novo
+pub fn condicao_ativa(conn: &Connection, id: i64) -> AppResult<CondicaoPagamento> {
+    let condicao = condicao(conn, id)?;
+    if !condicao.ativo {
+        return Err(AppError::Validation(format!("Condição de pagamento '{}' está inativa", condicao.nome)));
+    }
+    Ok(condicao)
+}
+
+pub fn map_condicao(row: &rusqlite::Row) -> rusqlite::Result<CondicaoPagamento> {
+    Ok(CondicaoPagamento {
+        id: row.get(0)?,
+        nome: row.get(1)?,
+        parcelas: row.get(2)?,
+        intervalo_dias: row.get(3)?,
+        primeiro_vencimento_dias: row.get(4)?,
+        entrada_percentual: row.get(5)?,
+        ajuste_percentual: row.get(6)?,
+        ativo: row.get(7)?,
+        created_at: row.get(8)?,
+        updated_at: row.get(9)?,
+    })
+}
+
+/// Lê os prazos em dias de uma condição de pagamento
+///
+/// Aceita prazos separados por `/`, `,`, `;` ou espaços ("30/60/90",
+/// "30 60 90 dias", "28d/56d") e "à vista". Condição vazia é à vista.
+/// Nenhum prazo passa de `PRAZO_MAXIMO_DIAS`.
+pub fn prazos(condicao: &str) -> AppResult<Vec<i64>> {
+    let texto = condicao.trim().to_lowercase();
+    if texto.is_empty() || texto == "à vista" || texto == "a vista" || texto == "avista" {
+        return Ok(vec![0]);
+    }
+    
+    let texto = texto.trim_end_matches("dias").trim_end_matches("ddl");
+    let mut prazos = Vec::new();
+    for parte in texto.split(['/', ',', ';', ' ']).map(str::trim).filter(|p| !p.is_empty()) {
+        let dias: i64 = parte
+            .trim_end_matches('d')
+            .parse()
+            .map_err(|_| AppError::Validation(format!("Condição de pagamento inválida: '{}'", condicao)))?;
+        
+        if dias < 0 || prazos.last().is_some_and(|&anterior| dias < anterior) {
+            return Err(AppError::Validation(format!(
+                "Condição de pagamento inválida: '{}' (prazos devem ser crescentes e não negativos)", condicao
+            )));
+        }
+        if dias > PRAZO_MAXIMO_DIAS {
+            return Err(AppError::Validation(format!(
+                "Condição de pagamento inválida: '{}' (prazo máximo de {} dias)", condicao, PRAZO_MAXIMO_DIAS
+            )));
+        }
+        prazos.push(dias);
+    }
+    
+    if prazos.is_empty() {
+        return Err(AppError::Validation(format!("Condição de pagamento inválida: '{}'", condicao)));
+    }
+    
+    Ok(prazos)
+}
+
+/// Divide `total` em `partes` valores cuja soma é exatamente `total`
+///
+/// A diferença de centavos da divisão vai para a primeira parcela.
+pub fn dividir(total: Dinheiro, partes: usize) -> Vec<Dinheiro> {
+    if partes == 0 {
+        return Vec::new();
+    }
+    
+    let base = total.em_centavos() / partes as i64;
+    let mut valores = vec![Dinheiro::centavos(base); partes];
+    valores[0] = total - Dinheiro::centavos(base * (partes as i64 - 1));
+    valores
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn le_prazos() {
+        assert_eq!(prazos("30/60/90").unwrap(), vec![30, 60, 90]);
+        assert_eq!(prazos(" 28d, 56d ").unwrap(), vec![28, 56]);
+        assert_eq!(prazos("30 60 dias").unwrap(), vec![30, 60]);
+        assert_eq!(prazos("À vista").unwrap(), vec![0]);
+        assert_eq!(prazos("").unwrap(), vec![0]);
+        assert!(prazos("60/30").is_err());
+        assert!(prazos("30/x").is_err());
+        assert!(prazos("-10").is_err());
+    }
+    
+    #[test]
+    fn rejeita_prazo_acima_do_maximo() {
+        assert_eq!(prazos("30/3650").unwrap(), vec![30, 3650]);
+        assert!(matches!(prazos("30/3651"), Err(AppError::Validation(_))));
+        assert!(matches!(prazos("30/99999999999"), Err(AppError::Validation(_))));
+        assert!(Plano::de_texto("30/99999999999").is_err());
+    }
+    
+    #[test]
+    fn parcelas_somam_o_total() {
+        let base = NaiveDate::from_ymd_opt(2026, 1, 31).unwrap();
+        let parcelas = Plano::de_texto("30/60/90").unwrap().parcelar(Dinheiro::centavos(10000), base).unwrap();
+        
+        let valores: Vec<i64> = parcelas.iter().map(|p| p.valor.em_centavos()).collect();
+        assert_eq!(valores, vec![3334, 3333, 3333]);
+        assert_eq!(parcelas.iter().map(|p| p.valor).sum::<Dinheiro>(), Dinheiro::centavos(10000));
+        assert_eq!(parcelas[0].vencimento, NaiveDate::from_ymd_opt(2026, 3, 2).unwrap());
+        assert_eq!(parcelas[2].numero, 3);
+        
+        assert_eq!(dividir(Dinheiro::centavos(2), 3), vec![Dinheiro::centavos(2), Dinheiro::ZERO, Dinheiro::ZERO]);
+        
+        // Parcela zerada não é gerada
+        let parcelas = Plano::de_texto("30/60/90").unwrap().parcelar(Dinheiro::centavos(2), base).unwrap();
+        assert_eq!(parcelas.len(), 1);
+        assert_eq!((parcelas[0].numero, parcelas[0].valor), (1, Dinheiro::centavos(2)));
+    }
+    
+    #[test]
+    fn entrada_e_juros_da_condicao() {
+        let condicao = CondicaoPagamento {
+            id: 1,
+            nome: "Entrada + 2x".into(),
+            parcelas: 2,
+            intervalo_dias: 30,
+            primeiro_vencimento_dias: 30,
+            entrada_percentual: Some(20.0),
+            ajuste_percentual: 3.0,
+            ativo: true,
+            created_at: String::new(),
+            updated_at: String::new(),
+        };
+        let base = NaiveDate::from_ymd_opt(2026, 1, 1).unwrap();
+        let parcelas = Plano::da_condicao(&condicao).parcelar(Dinheiro::centavos(10001), base).unwrap();
+        
+        // 100,01 + 3% = 103,01; entrada de 20% = 20,60; restam 82,41 em 2x
+        let resumo: Vec<(i64, bool, i64, NaiveDate)> = parcelas
+            .iter()
+            .map(|p| (p.numero, p.entrada, p.valor.em_centavos(), p.vencimento))
+            .collect();
+        assert_eq!(resumo, vec![
+            (1, true, 2060, base),
+            (2, false, 4121, NaiveDate::from_ymd_opt(2026, 1, 31).unwrap()),
+            (3, false, 4120, NaiveDate::from_ymd_opt(2026, 3, 2).unwrap()),
+        ]);
+    }
+    
+    #[test]
+    fn prazo_fora_do_calendario_e_erro_de_validacao() {
+        let plano = Plano { prazos: vec![i64::MAX / 2], entrada_percentual: None, ajuste_percentual: 0.0 };
+        let base = NaiveDate::from_ymd_opt(2026, 1, 1).unwrap();
+        
+        assert!(matches!(plano.parcelar(Dinheiro::centavos(100), base), Err(AppError::Validation(_))));
+        assert!(somar_dias(NaiveDate::MAX, 1).is_err());
+    }
+}
@@ -0,0 +1,150 @@
+//! Preços de venda por tabela de preço
+//!
+//! O preço efetivo de um produto para um cliente sai da tabela de preço do
+//! cliente, se estiver ativa e vigente na data:
+//!
+//! 1. item da tabela para o produto, na faixa com a maior quantidade mínima
+//!    que a quantidade pedida atinge: preço fixo ou percentual sobre
+//!    `preco_venda`;
+//! 2. sem item, o percentual geral da tabela sobre `preco_venda`;
+//! 3. sem tabela (ou fora da vigência), o `preco_venda` do produto.
+
+use chrono::NaiveDate;
+use rusqlite::{Connection, params};
+
+use crate::error::{AppError, AppResult};
+use crate::models::{Dinheiro, PrecoEfetivo, TabelaPreco, TabelaPrecoItem};
+
+/// Preço efetivo do produto para o cliente na data
+pub fn resolver(
+    conn: &Connection,
+    cliente_id: Option<i64>,
+    produto_id: i64,
+    quantidade: f64,
+    data: NaiveDate,
+) -> AppResult<PrecoEfetivo> {
+    let preco_base: Dinheiro = match conn.query_row(
+        "SELECT preco_venda FROM produtos WHERE id = ?1 AND ativo = 1",
+        params![produto_id],
+        |row| row.get::<_, Option<Dinheiro>>(0),
+    ) {
+        Ok(preco) => preco.unwrap_or_default(),
+        Err(rusqlite::Error::QueryReturnedNoRows) => {
+            return Err(AppError::Validation(format!("Produto {} não encontrado ou inativo", produto_id)));
+        }
+        Err(e) => return Err(AppError::Database(e.to_string())),
+    };
+    
+    let mut efetivo = PrecoEfetivo {
+        produto_id,
+        cliente_id,
+        quantidade,
+        preco_base,
+        preco: preco_base,
+        tabela_preco_id: None,
+        tabela_preco_nome: None,
+        origem: "produto".into(),
+    };
+    
+    let Some(tabela) = tabela_do_cliente(conn, cliente_id, data)? else {
+        return Ok(efetivo);
+    };
+    
+    let item = conn.query_row(
+        "SELECT preco, ajuste_percentual FROM tabela_preco_itens
+         WHERE tabela_id = ?1 AND produto_id = ?2 AND quantidade_minima <= ?3
+         ORDER BY quantidade_minima DESC LIMIT 1",
+        params![tabela.id, produto_id, quantidade],
+        |row| Ok((row.get::<_, Option<Dinheiro>>(0)?, row.get::<_, Option<f64>>(1)?)),
+    );
+    
+    let (preco, origem) = match item {
+        Ok((Some(preco), _)) => (preco, "item"),
+        Ok((None, ajuste)) => (aplicar(preco_base, ajuste.unwrap_or(0.0))?, "item"),
+        Err(rusqlite::Error::QueryReturnedNoRows) => (aplicar(preco_base, tabela.ajuste_percentual)?, "tabela"),
+        Err(e) => return Err(AppError::Database(e.to_string())),
+    };
+    
+    efetivo.preco = preco;
+    efetivo.tabela_preco_id = Some(tabela.id);
+    efetivo.tabela_preco_nome = Some(tabela.nome);
+    efetivo.origem = origem.into();
+    
+    Ok(efetivo)
+}
+
+/// `preco_venda` ajustado pelo percentual (negativo é desconto)
+fn aplicar(preco: Dinheiro, percentual: f64) -> AppResult<Dinheiro> {
+    preco.somar(preco.percentual(percentual)?)
+}
+
+/// Tabela do cliente, se ativa e vigente na data
+fn tabela_do_cliente(conn: &Connection, cliente_id: Option<i64>, data: NaiveDate) -> AppResult<Option<TabelaPreco>> {
+    let Some(cliente_id) = cliente_id else {
+        return Ok(None);
+    };
+    
+    let result = conn.query_row(
+        "SELECT t.id FROM clientes c
+         INNER JOIN tabelas_preco t ON t.id = c.tabela_preco_id
+         WHERE c.id = ?1 AND t.ativo = 1
+           AND (t.validade_inicio IS NULL OR t.validade_inicio <= ?2)
+           AND (t.validade_fim IS NULL OR t.validade_fim >= ?2)",
+        params![cliente_id, data.format("%Y-%m-%d").to_string()],
+        |row| row.get::<_, i64>(0),
+    );
+    
+    match result {
+        Ok(id) => Ok(Some(tabela(conn, id)?)),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+        Err(e) => Err(AppError::Database(e.to_string())),
+    }
+}
+
+/// Carrega a tabela com seus itens
+pub fn tabela(conn: &Connection, id: i64) -> AppResult<TabelaPreco> {
+    let result = conn.query_row(
+        "SELECT id, nome, descricao, ajuste_percentual, validade_inicio, validade_fim, ativo, created_at, updated_at
+         FROM tabelas_preco WHERE id = ?1",
+        params![id],
+        |row| Ok(TabelaPreco {
+            id: row.get(0)?,
+            nome: row.get(1)?,
+            descricao: row.get(2)?,
+            ajuste_percentual: row.get(3)?,
+            validade_inicio: row.get(4)?,
+            validade_fim: row.get(5)?,
+            ativo: row.get(6)?,
+            itens: Vec::new(),
+            created_at: row.get(7)?,
+            updated_at: row.get(8)?,
+        }),
+    );
+    
+    let mut tabela = match result {
+        Ok(tabela) => tabela,
+        Err(rusqlite::Error::QueryReturnedNoRows) => return Err(AppError::NotFound("Tabela de preço não encontrada".into())),
+        Err(e) => return Err(AppError::Database(e.to_string())),
+    };
+    
+    let mut stmt = conn.prepare(
+        "SELECT i.id, i.tabela_id, i.produto_id, p.nome, i.quantidade_minima, i.preco, i.ajuste_percentual
+         FROM tabela_preco_itens i
+         LEFT JOIN produtos p ON p.id = i.produto_id
+         WHERE i.tabela_id = ?1
+         ORDER BY p.nome ASC, i.quantidade_minima ASC"
+    )?;
+    tabela.itens = stmt.query_map(params![id], |row| {
+        Ok(TabelaPrecoItem {
+            id: row.get(0)?,
+            tabela_id: row.get(1)?,
+            produto_id: row.get(2)?,
+            produto_nome: row.get(3)?,
+            quantidade_minima: row.get(4)?,
+            preco: row.get(5)?,
+            ajuste_percentual: row.get(6)?,
+        })
+    })?.collect::<Result<_, _>>()?;
+    
+    Ok(tabela)
+}
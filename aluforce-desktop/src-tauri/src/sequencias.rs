@@ -0,0 +1,167 @@
+//! Numeração de documentos
+//!
+//! Cada tipo de documento (e cada série, no caso da NFe) tem uma linha em
+//! `sequencias`. O número é reservado com `UPDATE ... RETURNING` dentro da
+//! mesma transação que grava o documento: se ela for desfeita, o número
+//! volta para a sequência, e como só existe um escritor, dois comandos
+//! nunca recebem o mesmo número.
+
+use rusqlite::{Connection, params};
+use chrono::{Datelike, NaiveDate, Utc};
+
+use crate::error::{AppError, AppResult};
+use crate::models::TipoDocumento;
+
+/// Formato usado quando a sequência não define outro
+pub const FORMATO_PADRAO: &str = "{prefixo}{numero}";
+
+/// Série usada pela NFe quando nenhuma é informada
+pub const SERIE_NFE_PADRAO: &str = "1";
+
+/// Número reservado para um documento
+#[derive(Debug, Clone)]
+pub struct NumeroDocumento {
+    pub valor: i64,
+    pub formatado: String,
+}
+
+/// Reserva o próximo número da sequência
+///
+/// Deve ser chamado na transação que grava o documento. Séries de NFe
+/// ainda não usadas são criadas no primeiro número.
+pub fn proximo(conn: &Connection, tipo: TipoDocumento, serie: &str) -> AppResult<NumeroDocumento> {
+    let (prefixo, largura) = padrao(tipo);
+    conn.execute(
+        "INSERT INTO sequencias (tipo, serie, prefixo, largura) VALUES (?1, ?2, ?3, ?4)
+         ON CONFLICT(tipo, serie) DO NOTHING",
+        params![tipo.chave(), serie, prefixo, largura],
+    )?;
+    
+    let (valor, prefixo, formato, largura): (i64, String, String, i64) = conn.query_row(
+        "UPDATE sequencias SET proximo = proximo + 1, updated_at = datetime('now')
+         WHERE tipo = ?1 AND serie = ?2
+         RETURNING proximo - 1, prefixo, formato, largura",
+        params![tipo.chave(), serie],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+    )?;
+    
+    Ok(NumeroDocumento {
+        valor,
+        formatado: formatar(&formato, &prefixo, largura, valor, Utc::now().date_naive()),
+    })
+}
+
+/// Monta o número exibido a partir do formato da sequência
+pub fn formatar(formato: &str, prefixo: &str, largura: i64, valor: i64, data: NaiveDate) -> String {
+    formato
+        .replace("{prefixo}", prefixo)
+        .replace("{ano}", &format!("{:04}", data.year()))
+        .replace("{mes}", &format!("{:02}", data.month()))
+        .replace("{numero}", &format!("{:0largura$}", valor, largura = largura.max(0) as usize))
+}
+
+/// Valida formato, largura e série de uma sequência
+pub fn validar(tipo: TipoDocumento, serie: &str, formato: &str, largura: i64) -> AppResult<()> {
+    if !formato.contains("{numero}") {
+        return Err(AppError::Validation("O formato deve conter {numero}".into()));
+    }
+    if !(0..=12).contains(&largura) {
+        return Err(AppError::Validation("Largura deve estar entre 0 e 12".into()));
+    }
+    
+    match tipo {
+        // Série da NFe: número de 0 a 999 (leiaute da SEFAZ)
+        TipoDocumento::Nfe => {
+            if serie.is_empty() || serie.len() > 3 || !serie.chars().all(|c| c.is_ascii_digit()) {
+                return Err(AppError::Validation("Série da NFe deve ser um número de 0 a 999".into()));
+            }
+        }
+        _ => {
+            if !serie.is_empty() {
+                return Err(AppError::Validation(format!("Documentos do tipo {} não usam série", tipo)));
+            }
+        }
+    }
+    
+    Ok(())
+}
+
+/// Prefixo e largura de uma sequência criada sob demanda
+fn padrao(tipo: TipoDocumento) -> (&'static str, i64) {
+    match tipo {
+        TipoDocumento::OrcamentoVenda => ("ORC", 6),
+        TipoDocumento::PedidoVenda => ("PV", 6),
+        TipoDocumento::DevolucaoVenda => ("DEV", 6),
+        TipoDocumento::RequisicaoCompra => ("RC", 6),
+        TipoDocumento::CotacaoCompra => ("COT", 6),
+        TipoDocumento::PedidoCompra => ("PC", 6),
+        TipoDocumento::OrdemProducao => ("OP", 6),
+        TipoDocumento::Nfe => ("", 0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::vendas::{create_pedido, delete_pedido};
+    use crate::testes::*;
+    
+    fn reservar(db: &crate::database::DatabaseState, tipo: TipoDocumento, serie: &str) -> String {
+        db.with_transaction(|tx| proximo(tx, tipo, serie)).unwrap().formatado
+    }
+    
+    #[test]
+    fn numeros_seguem_em_ordem_por_tipo_e_serie() {
+        let db = banco_teste(TOKEN);
+        
+        assert_eq!(reservar(&db, TipoDocumento::PedidoCompra, ""), "PC000001");
+        assert_eq!(reservar(&db, TipoDocumento::PedidoCompra, ""), "PC000002");
+        assert_eq!(reservar(&db, TipoDocumento::RequisicaoCompra, ""), "RC000001");
+        
+        // Cada série da NFe tem sua numeração, criada no primeiro uso
+        assert_eq!(reservar(&db, TipoDocumento::Nfe, SERIE_NFE_PADRAO), "1");
+        assert_eq!(reservar(&db, TipoDocumento::Nfe, "2"), "1");
+        assert_eq!(reservar(&db, TipoDocumento::Nfe, SERIE_NFE_PADRAO), "2");
+    }
+    
+    #[test]
+    fn transacao_desfeita_devolve_o_numero() {
+        let db = banco_teste(TOKEN);
+        
+        let resultado: AppResult<()> = db.with_transaction(|tx| {
+            proximo(tx, TipoDocumento::PedidoCompra, "")?;
+            Err(AppError::Validation("falha ao gravar o documento".into()))
+        });
+        assert!(resultado.is_err());
+        assert_eq!(reservar(&db, TipoDocumento::PedidoCompra, ""), "PC000001");
+    }
+    
+    #[test]
+    fn pedido_excluido_nao_libera_numero_repetido() {
+        let db = banco_teste(TOKEN);
+        let produto_id = produto(&db);
+        let criar = || executar(create_pedido(
+            db.state(), TOKEN.into(), None, None,
+            itens(vec![serde_json::json!({ "produto_id": produto_id, "quantidade": 1, "preco_unitario": 10 })]),
+            None, None, None, None, None, None,
+        )).unwrap();
+        
+        let primeiro = criar();
+        assert_eq!(criar().numero, "PV000002");
+        executar(delete_pedido(db.state(), TOKEN.into(), primeiro.id)).unwrap();
+        assert_eq!(criar().numero, "PV000003");
+    }
+    
+    #[test]
+    fn formato_e_serie_sao_validados() {
+        let data = NaiveDate::from_ymd_opt(2026, 3, 7).unwrap();
+        assert_eq!(formatar("{prefixo}{ano}/{mes}-{numero}", "V-", 4, 12, data), "V-2026/03-0012");
+        
+        assert!(validar(TipoDocumento::PedidoVenda, "", "{prefixo}{ano}", 6).is_err());
+        assert!(validar(TipoDocumento::PedidoVenda, "", FORMATO_PADRAO, 13).is_err());
+        assert!(validar(TipoDocumento::PedidoVenda, "2", FORMATO_PADRAO, 6).is_err());
+        assert!(validar(TipoDocumento::Nfe, "1000", FORMATO_PADRAO, 0).is_err());
+        assert!(validar(TipoDocumento::Nfe, "890", FORMATO_PADRAO, 0).is_ok());
+    }
+}
+
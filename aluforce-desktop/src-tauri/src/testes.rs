@@ -0,0 +1,146 @@
+//! Apoio aos testes de unidade
+//!
+//! Sessão de teste, execução dos comandos e o pedido de venda padrão usado
+//! nos cenários de vendas, comissões, orçamentos e devoluções.
+//!
+//! Os comandos recebem o banco como `State`, que só o Tauri constrói: o
+//! banco de teste fica gerenciado por um app simulado (`tauri::test`).
+
+use std::ops::Deref;
+
+use rusqlite::params;
+use tauri::{App, Manager, State};
+use tauri::test::{mock_app, MockRuntime};
+
+use crate::commands::auth::AuthState;
+use crate::commands::vendas::{aprovar_pedido, confirmar_pedido, create_pedido, faturar_pedido, separar_pedido};
+use crate::database::DatabaseState;
+use crate::models::{PedidoItemInput, PedidoVenda};
+
+/// Token da sessão criada por `banco_teste`
+pub(crate) const TOKEN: &str = "token-teste";
+
+/// Banco de teste gerenciado por um app simulado
+///
+/// Dá acesso ao banco como `DatabaseState` e, por `state()`, ao estado que
+/// os comandos recebem (banco e `AuthState`).
+pub(crate) struct BancoTeste(App<MockRuntime>);
+
+impl BancoTeste {
+    pub(crate) fn novo(db: DatabaseState) -> Self {
+        let app = mock_app();
+        app.manage(db);
+        app.manage(AuthState::default());
+        Self(app)
+    }
+
+    pub(crate) fn state<T: Send + Sync + 'static>(&self) -> State<'_, T> {
+        self.0.state()
+    }
+}
+
+impl Deref for BancoTeste {
+    type Target = DatabaseState;
+
+    fn deref(&self) -> &DatabaseState {
+        self.0.state::<DatabaseState>().inner()
+    }
+}
+
+/// Banco temporário com um administrador e sessão `token`
+pub(crate) fn banco_teste(token: &str) -> BancoTeste {
+    BancoTeste::novo(crate::database::banco_teste(token))
+}
+
+/// Roda um comando até o fim
+pub(crate) fn executar<F: std::future::Future>(comando: F) -> F::Output {
+    tauri::async_runtime::block_on(comando)
+}
+
+/// Primeira coluna da consulta, como inteiro
+pub(crate) fn contar(db: &DatabaseState, sql: &str) -> i64 {
+    db.reader().unwrap().query_row(sql, [], |row| row.get(0)).unwrap()
+}
+
+/// Executa o INSERT e devolve o id da linha
+pub(crate) fn inserir(db: &DatabaseState, sql: &str) -> i64 {
+    let conn = db.writer().unwrap();
+    conn.execute(sql, []).unwrap();
+    conn.last_insert_rowid()
+}
+
+/// Usuário ativo com as permissões diretas em JSON
+pub(crate) fn usuario(db: &DatabaseState, email: &str, permissoes: &str) -> i64 {
+    let conn = db.writer().unwrap();
+    conn.execute(
+        "INSERT INTO usuarios (nome, email, senha_hash, permissoes) VALUES (?1, ?1, '', ?2)",
+        params![email, permissoes],
+    ).unwrap();
+    conn.last_insert_rowid()
+}
+
+/// Sessão de uma hora do usuário com o token informado
+pub(crate) fn sessao(db: &DatabaseState, usuario_id: i64, token: &str) {
+    let agora = chrono::Utc::now();
+    db.writer().unwrap().execute(
+        "INSERT INTO sessoes (id, usuario_id, token_hash, expires_at, ultimo_uso, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?5)",
+        params![
+            uuid::Uuid::new_v4().to_string(),
+            usuario_id,
+            crate::context::hash_token(token),
+            crate::context::carimbo(agora + chrono::Duration::hours(1)),
+            crate::context::carimbo(agora)
+        ],
+    ).unwrap();
+}
+
+/// Produto 'P1' a R$ 10,00
+pub(crate) fn produto(db: &DatabaseState) -> i64 {
+    inserir(db, "INSERT INTO produtos (codigo, nome, preco_venda) VALUES ('P1', 'Perfil', 1000)")
+}
+
+pub(crate) fn cliente(db: &DatabaseState) -> i64 {
+    inserir(db, "INSERT INTO clientes (nome) VALUES ('Cliente')")
+}
+
+pub(crate) fn itens(valores: Vec<serde_json::Value>) -> Vec<PedidoItemInput> {
+    valores.into_iter().map(|v| serde_json::from_value(v).unwrap()).collect()
+}
+
+/// Saldo físico do produto 'P1'
+pub(crate) fn estoque(db: &DatabaseState, quantidade: f64) {
+    db.writer().unwrap().execute(
+        "INSERT INTO estoque_saldos (codigo, quantidade_fisica) VALUES ('P1', ?1)
+         ON CONFLICT(codigo) DO UPDATE SET quantidade_fisica = ?1",
+        params![quantidade],
+    ).unwrap();
+}
+
+/// Quantidades física e reservada do produto 'P1'
+pub(crate) fn saldo(db: &DatabaseState) -> (f64, f64) {
+    db.reader().unwrap().query_row(
+        "SELECT quantidade_fisica, quantidade_reservada FROM estoque_saldos WHERE codigo = 'P1'",
+        [],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    ).unwrap()
+}
+
+/// Pedido em orçamento de 2 × R$ 10,00 do produto 'P1' para um cliente novo
+pub(crate) fn pedido_aprovavel(db: &BancoTeste) -> PedidoVenda {
+    let produto_id = produto(db);
+    let cliente_id = cliente(db);
+    executar(create_pedido(
+        db.state(), TOKEN.into(), Some(cliente_id), None,
+        itens(vec![serde_json::json!({ "produto_id": produto_id, "quantidade": 2, "preco_unitario": 10 })]),
+        None, None, None, None, None, None,
+    )).unwrap()
+}
+
+/// Leva o pedido de aprovado a faturado; precisa de estoque
+pub(crate) fn faturar(db: &BancoTeste, id: i64) {
+    executar(aprovar_pedido(db.state(), TOKEN.into(), id, None)).unwrap();
+    executar(confirmar_pedido(db.state(), TOKEN.into(), id, None, None)).unwrap();
+    executar(separar_pedido(db.state(), TOKEN.into(), id, None)).unwrap();
+    executar(faturar_pedido(db.state(), TOKEN.into(), id, None, None)).unwrap();
+}
@@ -31,15 +31,26 @@ interface PedidoItem {
   total: number;
 }
 
-interface DashboardVendas {
+interface ResumoVendas {
+  data_inicio: string;
+  data_fim: string;
+  total_vendas: number;
   total_pedidos: number;
-  total_faturado: number;
+  pedidos_vendidos: number;
   ticket_medio: number;
-  pedidos_pendentes: number;
-  pedidos_mes: number;
-  faturamento_mes: number;
-  top_clientes: { cliente_id: number; nome: string; total: number }[];
-  top_produtos: { produto_id: number; nome: string; quantidade: number }[];
+}
+
+interface DashboardVendas extends ResumoVendas {
+  periodo_anterior: ResumoVendas;
+  variacao_vendas: number | null;
+  orcamentos_emitidos: number;
+  orcamentos_ganhos: number;
+  orcamentos_perdidos: number;
+  taxa_conversao: number | null;
+  vendas_por_vendedor: { vendedor_id: number | null; vendedor: string; total: number; quantidade: number }[];
+  vendas_por_dia: { data: string; total: number; pedidos: number }[];
+  top_produtos: { produto_id: number; produto: string; quantidade: number; total: number }[];
+  top_clientes: { cliente_id: number | null; cliente: string; total: number; pedidos: number }[];
 }
 
 interface VendasState {